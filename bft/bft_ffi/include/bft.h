@@ -0,0 +1,127 @@
+#ifndef BFT_H
+#define BFT_H
+
+/* Generated by cbindgen from bft_ffi, regenerate with UPDATE_HEADER=1 cargo test -p bft_ffi */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// Let the tape grow when the head runs off its right end, rather than stopping the program
+#define BFT_GROWABLE_TAPE (1 << 0)
+
+// Set the cell to zero when `,` finds the input exhausted, rather than stopping the program
+#define BFT_EOF_ZERO (1 << 1)
+
+// Set the cell to minus one when `,` finds the input exhausted
+#define BFT_EOF_MINUS_ONE (1 << 2)
+
+// Leave the cell unchanged when `,` finds the input exhausted
+#define BFT_EOF_UNCHANGED (1 << 3)
+
+// Fold runs of repeated instructions together and run multiplication loops as multiply-adds
+#define BFT_OPTIMIZE (1 << 4)
+
+// The kinds of problem the C interface reports
+typedef enum BftErrorCode {
+  // Nothing went wrong
+  BFT_ERROR_CODE_OK = 0,
+  // The program's source is malformed
+  BFT_ERROR_CODE_PARSE = 1,
+  // The program stopped with an error while running
+  BFT_ERROR_CODE_RUNTIME = 2,
+  // A callback failed to read or write
+  BFT_ERROR_CODE_IO = 3,
+  // An argument was null or otherwise invalid
+  BFT_ERROR_CODE_INVALID_ARGUMENT = 4,
+} BftErrorCode;
+
+// A virtual machine running a program
+typedef struct BftMachine BftMachine;
+
+// A parsed brainfuck program
+typedef struct BftProgram BftProgram;
+
+// A problem reported by the C interface
+//
+// Zero initialise it before use, once a message has been set it must be released with
+// [`bft_error_free`].
+typedef struct BftError {
+  // What kind of problem occurred
+  enum BftErrorCode code;
+  // A nul terminated description of the problem, null if there isn't one
+  char *message;
+} BftError;
+
+// Reads up to `len` bytes into `buf`, returning the number read, zero at the end of the input
+// or a negative number on failure
+typedef ptrdiff_t (*BftReadCallback)(void *user_data, uint8_t *buf, size_t len);
+
+// Writes up to `len` bytes from `buf`, returning the number written or a negative number on
+// failure
+typedef ptrdiff_t (*BftWriteCallback)(void *user_data, const uint8_t *buf, size_t len);
+
+// Parse a program, returning null and filling in `err` if it is malformed
+//
+// The returned program must be released with [`bft_program_free`].
+//
+// # Safety
+//
+// `src` must point to `len` readable bytes, which needn't be nul terminated, and `err` must be
+// null or point to a valid [`BftError`].
+struct BftProgram *bft_program_parse(const char *src, size_t len, struct BftError *err);
+
+// Release a program returned by [`bft_program_parse`], doing nothing if it is null
+//
+// # Safety
+//
+// `program` must be null or have come from [`bft_program_parse`] and not yet been freed.
+void bft_program_free(struct BftProgram *program);
+
+// Create a machine with `cells` 8-bit cells to run a copy of `program`
+//
+// `flags` combines the `BFT_*` flags, by default the tape is a fixed size and `,` stops the
+// program once the input is exhausted. Returns null if `program` is null, `cells` is zero or
+// the flags are unknown or ask for more than one behaviour at the end of the input. The
+// returned machine must be released with [`bft_machine_free`].
+//
+// # Safety
+//
+// `program` must be null or a live program from [`bft_program_parse`], which can be freed as
+// soon as this returns.
+struct BftMachine *bft_machine_new(const struct BftProgram *program, size_t cells, uint32_t flags);
+
+// Run a machine's program until it stops, returning [`BftErrorCode::Ok`] if it finished
+//
+// `,` reads through `read_cb` and `.` writes through `write_cb`, each being passed `user_data`.
+// A null `read_cb` gives the program no input and a null `write_cb` discards its output. If
+// the program stops with an error its code is returned and `err` is filled in.
+//
+// # Safety
+//
+// `machine` must be null or a live machine from [`bft_machine_new`], the callbacks must be
+// safe to call with `user_data` and buffers of the given lengths, and `err` must be null or
+// point to a valid [`BftError`].
+enum BftErrorCode bft_machine_run(struct BftMachine *machine,
+                                  BftReadCallback read_cb,
+                                  BftWriteCallback write_cb,
+                                  void *user_data,
+                                  struct BftError *err);
+
+// Release a machine returned by [`bft_machine_new`], doing nothing if it is null
+//
+// # Safety
+//
+// `machine` must be null or have come from [`bft_machine_new`] and not yet been freed.
+void bft_machine_free(struct BftMachine *machine);
+
+// Release the message held by an error and reset it to [`BftErrorCode::Ok`]
+//
+// # Safety
+//
+// `err` must be null or point to a valid [`BftError`].
+void bft_error_free(struct BftError *err);
+
+#endif  /* BFT_H */
@@ -0,0 +1,321 @@
+#![deny(missing_docs)]
+#![deny(clippy::missing_docs_in_private_items)]
+
+//! A C interface for embedding the brainfuck interpreter
+//!
+//! Programs are parsed with [`bft_program_parse`] and run by machines made with
+//! [`bft_machine_new`], which read and write through callbacks. Every object handed out must be
+//! released with the matching free function. The header is `include/bft.h`.
+
+use std::{
+    ffi::{c_char, c_void, CString},
+    io::{self, Read, Write},
+    path::Path,
+    ptr, slice,
+};
+
+use bft_interp::{EofBehaviour, InterpretError, Machine, TapeKind};
+use bft_types::Program;
+
+/// Let the tape grow when the head runs off its right end, rather than stopping the program
+pub const BFT_GROWABLE_TAPE: u32 = 1 << 0;
+
+/// Set the cell to zero when `,` finds the input exhausted, rather than stopping the program
+pub const BFT_EOF_ZERO: u32 = 1 << 1;
+
+/// Set the cell to minus one when `,` finds the input exhausted
+pub const BFT_EOF_MINUS_ONE: u32 = 1 << 2;
+
+/// Leave the cell unchanged when `,` finds the input exhausted
+pub const BFT_EOF_UNCHANGED: u32 = 1 << 3;
+
+/// Fold runs of repeated instructions together and run multiplication loops as multiply-adds
+pub const BFT_OPTIMIZE: u32 = 1 << 4;
+
+/// Every flag [`bft_machine_new`] understands
+const ALL_FLAGS: u32 =
+    BFT_GROWABLE_TAPE | BFT_EOF_ZERO | BFT_EOF_MINUS_ONE | BFT_EOF_UNCHANGED | BFT_OPTIMIZE;
+
+/// The name programs parsed through the C interface are referred to by in errors
+const PROGRAM_NAME: &str = "<ffi>";
+
+/// The kinds of problem the C interface reports
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BftErrorCode {
+    /// Nothing went wrong
+    Ok = 0,
+    /// The program's source is malformed
+    Parse = 1,
+    /// The program stopped with an error while running
+    Runtime = 2,
+    /// A callback failed to read or write
+    Io = 3,
+    /// An argument was null or otherwise invalid
+    InvalidArgument = 4,
+}
+
+/// A problem reported by the C interface
+///
+/// Zero initialise it before use, once a message has been set it must be released with
+/// [`bft_error_free`].
+#[repr(C)]
+#[derive(Debug)]
+pub struct BftError {
+    /// What kind of problem occurred
+    pub code: BftErrorCode,
+
+    /// A nul terminated description of the problem, null if there isn't one
+    pub message: *mut c_char,
+}
+
+/// A parsed brainfuck program
+#[derive(Debug)]
+pub struct BftProgram {
+    /// The parsed program
+    program: Program,
+}
+
+/// A virtual machine running a program
+#[derive(Debug)]
+pub struct BftMachine {
+    /// The virtual machine, which owns a copy of its program
+    machine: Machine<'static, u8>,
+}
+
+/// Reads up to `len` bytes into `buf`, returning the number read, zero at the end of the input
+/// or a negative number on failure
+pub type BftReadCallback =
+    Option<unsafe extern "C" fn(user_data: *mut c_void, buf: *mut u8, len: usize) -> isize>;
+
+/// Writes up to `len` bytes from `buf`, returning the number written or a negative number on
+/// failure
+pub type BftWriteCallback =
+    Option<unsafe extern "C" fn(user_data: *mut c_void, buf: *const u8, len: usize) -> isize>;
+
+/// Input read through a [`BftReadCallback`], empty if there is no callback
+struct CallbackReader {
+    /// The callback to read with
+    callback: BftReadCallback,
+    /// Passed to the callback untouched
+    user_data: *mut c_void,
+}
+
+impl Read for CallbackReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let Some(callback) = self.callback else {
+            return Ok(0);
+        };
+        // SAFETY: the caller of bft_machine_run promised the callback accepts these arguments
+        let read = unsafe { callback(self.user_data, buf.as_mut_ptr(), buf.len()) };
+        usize::try_from(read)
+            .ok()
+            .filter(|&read| read <= buf.len())
+            .ok_or_else(|| io::Error::other(format!("the read callback returned {read}")))
+    }
+}
+
+/// Output written through a [`BftWriteCallback`], discarded if there is no callback
+struct CallbackWriter {
+    /// The callback to write with
+    callback: BftWriteCallback,
+    /// Passed to the callback untouched
+    user_data: *mut c_void,
+}
+
+impl Write for CallbackWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let Some(callback) = self.callback else {
+            return Ok(buf.len());
+        };
+        // SAFETY: the caller of bft_machine_run promised the callback accepts these arguments
+        let written = unsafe { callback(self.user_data, buf.as_ptr(), buf.len()) };
+        usize::try_from(written)
+            .ok()
+            .filter(|&written| written <= buf.len())
+            .ok_or_else(|| io::Error::other(format!("the write callback returned {written}")))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Record a problem in `err`, if there is somewhere to record it, returning its code
+///
+/// # Safety
+///
+/// `err` must be null or point to a valid [`BftError`].
+unsafe fn report(err: *mut BftError, code: BftErrorCode, message: &str) -> BftErrorCode {
+    if let Some(err) = err.as_mut() {
+        bft_error_free(err);
+        err.code = code;
+        if code != BftErrorCode::Ok {
+            // messages come from Display impls, which never contain a nul
+            err.message = CString::new(message).map_or(ptr::null_mut(), CString::into_raw);
+        }
+    }
+    code
+}
+
+/// Parse a program, returning null and filling in `err` if it is malformed
+///
+/// The returned program must be released with [`bft_program_free`].
+///
+/// # Safety
+///
+/// `src` must point to `len` readable bytes, which needn't be nul terminated, and `err` must be
+/// null or point to a valid [`BftError`].
+#[no_mangle]
+pub unsafe extern "C" fn bft_program_parse(
+    src: *const c_char,
+    len: usize,
+    err: *mut BftError,
+) -> *mut BftProgram {
+    if src.is_null() {
+        report(err, BftErrorCode::InvalidArgument, "the source is null");
+        return ptr::null_mut();
+    }
+    let bytes = slice::from_raw_parts(src.cast::<u8>(), len);
+    let Ok(source) = std::str::from_utf8(bytes) else {
+        report(err, BftErrorCode::Parse, "the source isn't valid UTF-8");
+        return ptr::null_mut();
+    };
+    match Program::try_new(Path::new(PROGRAM_NAME), source) {
+        Ok(program) => {
+            report(err, BftErrorCode::Ok, "");
+            Box::into_raw(Box::new(BftProgram { program }))
+        }
+        Err(e) => {
+            report(err, BftErrorCode::Parse, &e.to_string());
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Release a program returned by [`bft_program_parse`], doing nothing if it is null
+///
+/// # Safety
+///
+/// `program` must be null or have come from [`bft_program_parse`] and not yet been freed.
+#[no_mangle]
+pub unsafe extern "C" fn bft_program_free(program: *mut BftProgram) {
+    if !program.is_null() {
+        drop(Box::from_raw(program));
+    }
+}
+
+/// Create a machine with `cells` 8-bit cells to run a copy of `program`
+///
+/// `flags` combines the `BFT_*` flags, by default the tape is a fixed size and `,` stops the
+/// program once the input is exhausted. Returns null if `program` is null, `cells` is zero or
+/// the flags are unknown or ask for more than one behaviour at the end of the input. The
+/// returned machine must be released with [`bft_machine_free`].
+///
+/// # Safety
+///
+/// `program` must be null or a live program from [`bft_program_parse`], which can be freed as
+/// soon as this returns.
+#[no_mangle]
+pub unsafe extern "C" fn bft_machine_new(
+    program: *const BftProgram,
+    cells: usize,
+    flags: u32,
+) -> *mut BftMachine {
+    let Some(program) = program.as_ref() else {
+        return ptr::null_mut();
+    };
+    let eof_flags = flags & (BFT_EOF_ZERO | BFT_EOF_MINUS_ONE | BFT_EOF_UNCHANGED);
+    if cells == 0 || flags & !ALL_FLAGS != 0 || eof_flags.count_ones() > 1 {
+        return ptr::null_mut();
+    }
+
+    let eof_behaviour = match eof_flags {
+        BFT_EOF_ZERO => EofBehaviour::Zero,
+        BFT_EOF_MINUS_ONE => EofBehaviour::MinusOne,
+        BFT_EOF_UNCHANGED => EofBehaviour::Unchanged,
+        _ => EofBehaviour::Error,
+    };
+    let tape_kind = if flags & BFT_GROWABLE_TAPE != 0 {
+        TapeKind::Growable {
+            growth: Default::default(),
+        }
+    } else {
+        TapeKind::FixedSize
+    };
+    let optimize = flags & BFT_OPTIMIZE != 0;
+    let program = if optimize {
+        program.program.optimized()
+    } else {
+        program.program.clone()
+    };
+    let machine = Machine::new_owned(cells, tape_kind, program)
+        .with_eof_behaviour(eof_behaviour)
+        .with_multiply_loops(optimize);
+    Box::into_raw(Box::new(BftMachine { machine }))
+}
+
+/// Run a machine's program until it stops, returning [`BftErrorCode::Ok`] if it finished
+///
+/// `,` reads through `read_cb` and `.` writes through `write_cb`, each being passed `user_data`.
+/// A null `read_cb` gives the program no input and a null `write_cb` discards its output. If
+/// the program stops with an error its code is returned and `err` is filled in.
+///
+/// # Safety
+///
+/// `machine` must be null or a live machine from [`bft_machine_new`], the callbacks must be
+/// safe to call with `user_data` and buffers of the given lengths, and `err` must be null or
+/// point to a valid [`BftError`].
+#[no_mangle]
+pub unsafe extern "C" fn bft_machine_run(
+    machine: *mut BftMachine,
+    read_cb: BftReadCallback,
+    write_cb: BftWriteCallback,
+    user_data: *mut c_void,
+    err: *mut BftError,
+) -> BftErrorCode {
+    let Some(machine) = machine.as_mut() else {
+        return report(err, BftErrorCode::InvalidArgument, "the machine is null");
+    };
+    let input = CallbackReader {
+        callback: read_cb,
+        user_data,
+    };
+    let output = CallbackWriter {
+        callback: write_cb,
+        user_data,
+    };
+    match machine.machine.run(input, output) {
+        Ok(()) => report(err, BftErrorCode::Ok, ""),
+        Err(e @ InterpretError::IoError { .. }) => report(err, BftErrorCode::Io, &e.to_string()),
+        Err(e) => report(err, BftErrorCode::Runtime, &e.to_string()),
+    }
+}
+
+/// Release a machine returned by [`bft_machine_new`], doing nothing if it is null
+///
+/// # Safety
+///
+/// `machine` must be null or have come from [`bft_machine_new`] and not yet been freed.
+#[no_mangle]
+pub unsafe extern "C" fn bft_machine_free(machine: *mut BftMachine) {
+    if !machine.is_null() {
+        drop(Box::from_raw(machine));
+    }
+}
+
+/// Release the message held by an error and reset it to [`BftErrorCode::Ok`]
+///
+/// # Safety
+///
+/// `err` must be null or point to a valid [`BftError`].
+#[no_mangle]
+pub unsafe extern "C" fn bft_error_free(err: *mut BftError) {
+    if let Some(err) = err.as_mut() {
+        if !err.message.is_null() {
+            drop(CString::from_raw(err.message));
+        }
+        err.code = BftErrorCode::Ok;
+        err.message = ptr::null_mut();
+    }
+}
@@ -0,0 +1,80 @@
+/* Runs programs through the C interface, exiting with a non-zero status on any mismatch */
+
+#include <stdio.h>
+#include <string.h>
+
+#include "bft.h"
+
+struct buffers {
+    const char *input;
+    size_t input_len;
+    char output[256];
+    size_t output_len;
+};
+
+static ptrdiff_t read_input(void *user_data, uint8_t *buf, size_t len)
+{
+    struct buffers *b = user_data;
+    size_t n = b->input_len < len ? b->input_len : len;
+    memcpy(buf, b->input, n);
+    b->input += n;
+    b->input_len -= n;
+    return (ptrdiff_t)n;
+}
+
+static ptrdiff_t write_output(void *user_data, const uint8_t *buf, size_t len)
+{
+    struct buffers *b = user_data;
+    if (len > sizeof(b->output) - b->output_len)
+        return -1;
+    memcpy(b->output + b->output_len, buf, len);
+    b->output_len += len;
+    return (ptrdiff_t)len;
+}
+
+static int run(const char *src, const char *input, unsigned flags, const char *expected,
+               enum BftErrorCode expected_code)
+{
+    BftError err = {0};
+    struct buffers b = {input, strlen(input), {0}, 0};
+    BftProgram *program = bft_program_parse(src, strlen(src), &err);
+    if (program == NULL) {
+        fprintf(stderr, "failed to parse %s: %s\n", src, err.message);
+        bft_error_free(&err);
+        return 1;
+    }
+    BftMachine *machine = bft_machine_new(program, 100, flags);
+    bft_program_free(program);
+    enum BftErrorCode code = bft_machine_run(machine, read_input, write_output, &b, &err);
+    bft_machine_free(machine);
+
+    int failed = code != expected_code || b.output_len != strlen(expected)
+        || memcmp(b.output, expected, b.output_len) != 0;
+    if (failed)
+        fprintf(stderr, "%s gave %d and wrote \"%.*s\": %s\n", src, code, (int)b.output_len,
+                b.output, err.message ? err.message : "");
+    bft_error_free(&err);
+    return failed;
+}
+
+int main(void)
+{
+    int failures = 0;
+    failures += run("++++++++[>++++++++<-]>+.+.", "", 0, "AB", BFT_ERROR_CODE_OK);
+    failures += run(",[.,]", "echo", BFT_EOF_ZERO | BFT_OPTIMIZE, "echo", BFT_ERROR_CODE_OK);
+    failures += run(",.,", "x", 0, "x", BFT_ERROR_CODE_IO);
+    failures += run("<", "", BFT_GROWABLE_TAPE, "", BFT_ERROR_CODE_RUNTIME);
+
+    BftError err = {0};
+    if (bft_program_parse("[", 1, &err) != NULL || err.code != BFT_ERROR_CODE_PARSE
+        || strstr(err.message, "<ffi>") == NULL) {
+        fprintf(stderr, "an unclosed loop parsed\n");
+        failures++;
+    }
+    bft_error_free(&err);
+    if (err.message != NULL || bft_machine_new(NULL, 100, 0) != NULL) {
+        fprintf(stderr, "invalid arguments were accepted\n");
+        failures++;
+    }
+    return failures != 0;
+}
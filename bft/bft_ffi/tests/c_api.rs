@@ -0,0 +1,38 @@
+//! Builds a C program against the library and header, skipped if there is no C compiler
+
+use std::{path::Path, process::Command};
+
+#[test]
+fn test_c_program() {
+    let crate_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+    // integration tests are built into target/<profile>/deps, beside the library
+    let exe = std::env::current_exe().unwrap();
+    let lib_dir = exe.parent().unwrap();
+    let smoke = Path::new(env!("CARGO_TARGET_TMPDIR")).join("bft_ffi_smoke");
+
+    let status = Command::new("cc")
+        .arg(crate_dir.join("tests/c/smoke.c"))
+        .arg("-I")
+        .arg(crate_dir.join("include"))
+        .arg("-L")
+        .arg(lib_dir)
+        .arg(format!("-Wl,-rpath,{}", lib_dir.display()))
+        .args(["-lbft_ffi", "-Wall", "-Werror", "-o"])
+        .arg(&smoke)
+        .status();
+    let status = match status {
+        Ok(status) => status,
+        Err(e) => {
+            eprintln!("skipping, cc couldn't be run: {e}");
+            return;
+        }
+    };
+    assert!(status.success(), "failed to build the C program");
+
+    let output = Command::new(&smoke).output().unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
@@ -0,0 +1,23 @@
+//! Checks the committed header matches the Rust interface
+
+use std::{fs, path::Path};
+
+#[test]
+fn test_header_up_to_date() {
+    let crate_dir = env!("CARGO_MANIFEST_DIR");
+    let config = cbindgen::Config::from_file(Path::new(crate_dir).join("cbindgen.toml")).unwrap();
+    let mut generated = Vec::new();
+    cbindgen::generate_with_config(crate_dir, config)
+        .unwrap()
+        .write(&mut generated);
+
+    let header = Path::new(crate_dir).join("include/bft.h");
+    if std::env::var_os("UPDATE_HEADER").is_some_and(|v| v == "1") {
+        fs::write(&header, &generated).unwrap();
+    }
+    let committed = fs::read(&header).unwrap_or_default();
+    assert!(
+        committed == generated,
+        "include/bft.h is out of date, regenerate it with UPDATE_HEADER=1 cargo test -p bft_ffi"
+    );
+}
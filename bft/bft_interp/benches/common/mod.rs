@@ -0,0 +1,63 @@
+//! Helpers shared by the interpreter benchmarks
+//!
+//! Programs read their input from and write their output to memory, so the benchmarks measure
+//! the interpreter rather than the terminal.
+
+// each benchmark only uses some of the helpers
+#![allow(dead_code)]
+
+use std::{
+    io::Cursor,
+    path::{Path, PathBuf},
+};
+
+use bft_interp::{EofBehaviour, Machine, TapeKind};
+use bft_types::Program;
+
+/// One mebibyte, the size of the larger inputs and outputs
+pub const MIB: usize = 1 << 20;
+
+/// Load one of the example programs by name, such as `"rot13"`
+pub fn example(name: &str) -> Program {
+    Program::from_file(example_path(name).with_extension("bf")).unwrap()
+}
+
+/// The path of an example program's files without their extension
+fn example_path(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("../programs")
+        .join(name)
+}
+
+/// Parse a program generated by a benchmark
+pub fn generated(source: impl AsRef<str>) -> Program {
+    Program::try_new(Path::new("<generated>"), source).unwrap()
+}
+
+/// `len` bytes of printable text cycling through the alphabet in both cases, with a newline
+/// every so often
+pub fn text(len: usize) -> Vec<u8> {
+    b"The Quick Brown Fox Jumps Over The Lazy Dog\n"
+        .iter()
+        .copied()
+        .cycle()
+        .take(len)
+        .collect()
+}
+
+/// Run `prog` on an 8-bit machine, returning everything it wrote
+///
+/// `output_capacity` is reserved up front so growing the output isn't measured.
+pub fn run(
+    prog: &Program,
+    tape_size: usize,
+    tape_kind: TapeKind,
+    input: &[u8],
+    output_capacity: usize,
+) -> Vec<u8> {
+    let mut machine =
+        Machine::<u8>::new(tape_size, tape_kind, prog).with_eof_behaviour(EofBehaviour::MinusOne);
+    let mut output = Vec::with_capacity(output_capacity);
+    machine.run(Cursor::new(input), &mut output).unwrap();
+    output
+}
@@ -0,0 +1,39 @@
+use bft_interp::{TapeKind, DEFAULT_TAPE_SIZE};
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+
+mod common;
+
+/// Run the hello world program from start to finish
+fn hello_world(c: &mut Criterion) {
+    let prog = common::example("hello_world");
+
+    c.bench_function("hello_world", |b| {
+        b.iter(|| common::run(&prog, DEFAULT_TAPE_SIZE, TapeKind::FixedSize, b"", 64))
+    });
+}
+
+/// Encode 1MiB of text with the rot13 program
+fn rot13(c: &mut Criterion) {
+    let prog = common::example("rot13");
+    let input = common::text(common::MIB);
+
+    let mut group = c.benchmark_group("rot13");
+    group.sample_size(10);
+    group.throughput(Throughput::Bytes(input.len() as u64));
+    group.bench_function("1mib", |b| {
+        b.iter(|| {
+            let output = common::run(
+                &prog,
+                DEFAULT_TAPE_SIZE,
+                TapeKind::FixedSize,
+                &input,
+                input.len(),
+            );
+            assert_eq!(output.len(), input.len());
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, hello_world, rot13);
+criterion_main!(benches);
@@ -0,0 +1,29 @@
+use std::{io, path::Path};
+
+use bft_interp::{Machine, TapeKind, DEFAULT_TAPE_SIZE};
+use bft_types::Program;
+use criterion::{criterion_group, criterion_main, Criterion};
+
+/// Nested loops whose bodies are dominated by long runs of `+`, `-`, `<` and `>`
+const SOURCE: &str = "-[>-[>+++++++++>>>>>>>>-----------<<<<<<<<<-]<-]";
+
+/// Compare running a program with and without its runs folded into counted instructions
+fn optimize(c: &mut Criterion) {
+    let prog = Program::try_new(Path::new("-"), SOURCE).unwrap();
+    let optimized = prog.optimized();
+
+    let mut group = c.benchmark_group("optimize");
+    group.sample_size(20);
+    for (name, prog) in [("unoptimized", &prog), ("optimized", &optimized)] {
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                let mut machine = Machine::<u8>::new(DEFAULT_TAPE_SIZE, TapeKind::FixedSize, prog);
+                machine.run(io::empty(), io::sink()).unwrap();
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, optimize);
+criterion_main!(benches);
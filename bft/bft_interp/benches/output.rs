@@ -0,0 +1,31 @@
+use std::{io, path::Path};
+
+use bft_interp::{Machine, OutputMode, TapeKind};
+use bft_types::Program;
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+
+/// The number of bytes of output the program writes
+const OUTPUT_BYTES: u64 = 1 << 20;
+
+/// Write 1MiB of output from 32-bit cells, four bytes at a time
+fn full_cell_output(c: &mut Criterion) {
+    // six nested loops of eight iterations each write 8^6 cells of four bytes
+    let source = format!("{}.{}", "++++++++[>".repeat(6), "<-]".repeat(6));
+    let prog = Program::try_new(Path::new("-"), source).unwrap();
+
+    let mut group = c.benchmark_group("output");
+    group.throughput(Throughput::Bytes(OUTPUT_BYTES));
+    group.bench_function("full_cell_1mib", |b| {
+        b.iter(|| {
+            let mut machine = Machine::<u32>::new(8, TapeKind::FixedSize, &prog)
+                .with_output_mode(OutputMode::FullCell);
+            let mut output = Vec::with_capacity(OUTPUT_BYTES as usize);
+            machine.run(io::empty(), &mut output).unwrap();
+            assert_eq!(output.len() as u64, OUTPUT_BYTES);
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, full_cell_output);
+criterion_main!(benches);
@@ -0,0 +1,32 @@
+use std::{io, path::Path};
+
+use bft_interp::{Machine, SparseTape, Tape, TapeKind};
+use bft_types::Program;
+use criterion::{criterion_group, criterion_main, Criterion};
+
+/// Run `prog` on a machine storing its tape in a `T`
+fn run<T: Tape<u8>>(prog: &Program, tape_kind: TapeKind) {
+    let mut machine = Machine::<u8, T>::new(1, tape_kind, prog);
+    machine.run(io::empty(), io::sink()).unwrap();
+}
+
+/// Move the head a long way to the right, touching only the cells at either end
+fn sparse_tape(c: &mut Criterion) {
+    let source = format!("+{}+", ">".repeat(10_000_000));
+    let prog = Program::try_new(Path::new("-"), source)
+        .unwrap()
+        .optimized();
+    let growable = TapeKind::Growable {
+        growth: Default::default(),
+    };
+
+    let mut group = c.benchmark_group("wide_jump");
+    group.bench_function("dense", |b| b.iter(|| run::<Vec<u8>>(&prog, growable)));
+    group.bench_function("sparse", |b| {
+        b.iter(|| run::<SparseTape<u8>>(&prog, TapeKind::Sparse))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, sparse_tape);
+criterion_main!(benches);
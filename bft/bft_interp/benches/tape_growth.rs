@@ -0,0 +1,33 @@
+use std::{io, path::Path};
+
+use bft_interp::{GrowthStrategy, Machine, TapeKind};
+use bft_types::Program;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+
+/// Move the head right far enough that a tape starting with a single cell must grow many times
+fn tape_growth(c: &mut Criterion) {
+    let source = ">".repeat(100_000);
+    let prog = Program::try_new(Path::new("-"), source).unwrap();
+
+    let mut group = c.benchmark_group("tape_growth");
+    for growth in [
+        GrowthStrategy::Doubling,
+        GrowthStrategy::FixedIncrement(1),
+        GrowthStrategy::FixedIncrement(1024),
+    ] {
+        group.bench_with_input(
+            BenchmarkId::from_parameter(format!("{growth:?}")),
+            &growth,
+            |b, &growth| {
+                b.iter(|| {
+                    let mut machine = Machine::<u8>::new(1, TapeKind::Growable { growth }, &prog);
+                    machine.run(io::empty(), io::sink()).unwrap();
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, tape_growth);
+criterion_main!(benches);
@@ -0,0 +1,43 @@
+use bft_interp::TapeKind;
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+
+mod common;
+
+/// How far right the tape growth program moves the head
+const DISTANCE: usize = 100_000;
+
+/// Move the head right across a tape allocated up front and one grown as it goes
+fn tape_growth(c: &mut Criterion) {
+    let prog = common::generated(">+".repeat(DISTANCE));
+
+    let mut group = c.benchmark_group("head_movement");
+    group.bench_function("fixed_size", |b| {
+        b.iter(|| common::run(&prog, DISTANCE + 1, TapeKind::FixedSize, b"", 0))
+    });
+    group.bench_function("growable", |b| {
+        b.iter(|| {
+            let growth = Default::default();
+            common::run(&prog, 1, TapeKind::Growable { growth }, b"", 0)
+        })
+    });
+    group.finish();
+}
+
+/// Write 1MiB of output a byte at a time
+fn output_heavy(c: &mut Criterion) {
+    // six nested loops of eight iterations each write four bytes 8^6 times
+    let prog = common::generated(format!("{}....{}", "++++++++[>".repeat(6), "<-]".repeat(6)));
+
+    let mut group = c.benchmark_group("output_heavy");
+    group.throughput(Throughput::Bytes(common::MIB as u64));
+    group.bench_function("1mib", |b| {
+        b.iter(|| {
+            let output = common::run(&prog, 8, TapeKind::FixedSize, b"", common::MIB);
+            assert_eq!(output.len(), common::MIB);
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, tape_growth, output_heavy);
+criterion_main!(benches);
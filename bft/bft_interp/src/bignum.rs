@@ -0,0 +1,115 @@
+use num_bigint::BigUint;
+
+use crate::CellKind;
+
+/// Cells which grow without bound, for programs which assume they never overflow
+///
+/// There is nothing to wrap around to below zero, so unless overflow is an error decrementing
+/// zero leaves it at zero.
+/// Full-cell output writes the shortest big-endian encoding of the value. The cells have no fixed
+/// width for full-cell input to read, so it reads `size_of::<BigUint>()` bytes, a detail of how
+/// the value is stored rather than of the value itself; `bft` rejects the combination.
+impl CellKind for BigUint {
+    fn wrapping_inc(&mut self) {
+        *self += 1_u8;
+    }
+
+    fn wrapping_dec(&mut self) {
+        self.wrapping_sub_by(1);
+    }
+
+    fn wrapping_add_by(&mut self, amount: u8) {
+        *self += amount;
+    }
+
+    fn wrapping_sub_by(&mut self, amount: u8) {
+        if *self >= BigUint::from(amount) {
+            *self -= amount;
+        } else {
+            *self = BigUint::ZERO;
+        }
+    }
+
+    fn checked_add_by(&mut self, amount: u8) -> bool {
+        *self += amount;
+        true
+    }
+
+    fn checked_sub_by(&mut self, amount: u8) -> bool {
+        let in_range = *self >= BigUint::from(amount);
+        if in_range {
+            *self -= amount;
+        }
+        in_range
+    }
+
+    fn saturating_add_by(&mut self, amount: u8) {
+        *self += amount;
+    }
+
+    fn saturating_sub_by(&mut self, amount: u8) {
+        self.wrapping_sub_by(amount);
+    }
+
+    fn wrapping_add_product(&mut self, counter: &Self, factor: i16) {
+        let product = counter * factor.unsigned_abs();
+        if factor >= 0 {
+            *self += product;
+        } else if *self >= product {
+            *self -= product;
+        } else {
+            *self = BigUint::ZERO;
+        }
+    }
+
+    fn is_zero(&self) -> bool {
+        *self == BigUint::ZERO
+    }
+
+    fn set_value(&mut self, value: u8) {
+        *self = BigUint::from(value);
+    }
+
+    fn as_bytes(&self) -> Box<[u8]> {
+        self.to_bytes_be().into_boxed_slice()
+    }
+
+    fn low_byte(&self) -> u8 {
+        self.to_bytes_le()[0]
+    }
+
+    fn set_from_be_bytes(&mut self, bytes: &[u8]) {
+        *self = BigUint::from_bytes_be(bytes);
+    }
+
+    fn to_decimal(&self) -> String {
+        self.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_big_cell_grows_past_u64() {
+        let mut cell = BigUint::default();
+        cell.set_from_be_bytes(&u64::MAX.to_be_bytes());
+        cell.wrapping_add_by(2);
+        assert_eq!(cell.to_decimal(), "18446744073709551617");
+        assert_eq!(&*cell.as_bytes(), [1, 0, 0, 0, 0, 0, 0, 0, 1]);
+        assert_eq!(cell.low_byte(), 1);
+    }
+
+    #[test]
+    fn test_big_cell_saturates_at_zero() {
+        let mut cell = BigUint::default();
+        cell.wrapping_dec();
+        assert!(cell.is_zero());
+        assert_eq!(&*cell.as_bytes(), [0]);
+
+        cell.set_value(3);
+        cell.wrapping_sub_by(5);
+        assert!(cell.is_zero());
+    }
+}
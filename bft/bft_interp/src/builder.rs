@@ -0,0 +1,323 @@
+use bft_types::Program;
+
+use crate::{
+    CellKind, CellOverflow, DebugSink, EofBehaviour, InputMode, Machine, MachineConfigError,
+    OutputMode, Replay, Tape, TapeKind, DEFAULT_TAPE_SIZE,
+};
+
+/// The options for a virtual machine, checked against each other when it is built
+///
+/// Anything not set keeps the default used by [`Machine::new`], with a fixed size tape of
+/// [`DEFAULT_TAPE_SIZE`] cells.
+///
+/// ```
+/// # use bft_interp::{EofBehaviour, Machine, MachineBuilder, TapeKind};
+/// # use bft_types::Program;
+/// # use std::{io, path::Path};
+/// let prog = Program::try_new(Path::new("-"), ",.").unwrap();
+/// let mut vm: Machine<u8> = MachineBuilder::new()
+///     .tape_size(10)
+///     .eof(EofBehaviour::Zero)
+///     .max_steps(100)
+///     .build(&prog)
+///     .unwrap();
+/// let mut output = Vec::new();
+/// vm.run(io::empty(), &mut output).unwrap();
+/// assert_eq!(output, [0]);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MachineBuilder {
+    /// The number of cells the tape starts with
+    tape_size: usize,
+    /// The kind of tape
+    tape_kind: TapeKind,
+    /// The most cells the tape can grow to, if limited
+    max_cells: Option<usize>,
+    /// The most instructions run executes, if limited
+    max_steps: Option<u64>,
+    /// What `,` does once the input is exhausted
+    eof_behaviour: EofBehaviour,
+    /// What `+` and `-` do when a cell would overflow
+    overflow: CellOverflow,
+    /// How much of a cell `.` writes
+    output_mode: OutputMode,
+    /// How much of a cell `,` reads
+    input_mode: InputMode,
+    /// Should multiplying loops run as multiply-adds
+    multiply_loops: bool,
+    /// Should the number of times each instruction is executed be counted
+    profiling: bool,
+    /// Should every byte read and written be recorded
+    recording: bool,
+    /// The recorded run to repeat, if any
+    replay: Option<Replay>,
+    /// Should a loop which never changes anything stop the program
+    detect_hangs: bool,
+    /// Where `#` reports the state of the machine
+    debug_sink: DebugSink,
+}
+
+impl Default for MachineBuilder {
+    fn default() -> Self {
+        Self {
+            tape_size: DEFAULT_TAPE_SIZE,
+            tape_kind: TapeKind::FixedSize,
+            max_cells: None,
+            max_steps: None,
+            eof_behaviour: EofBehaviour::default(),
+            overflow: CellOverflow::default(),
+            output_mode: OutputMode::default(),
+            input_mode: InputMode::default(),
+            multiply_loops: false,
+            profiling: false,
+            recording: false,
+            replay: None,
+            detect_hangs: false,
+            debug_sink: DebugSink::default(),
+        }
+    }
+}
+
+impl MachineBuilder {
+    /// Start from the default options
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the number of cells the tape starts with
+    pub fn tape_size(mut self, tape_size: usize) -> Self {
+        self.tape_size = tape_size;
+        self
+    }
+
+    /// Set the kind of tape, see [`TapeKind`]
+    pub fn tape_kind(mut self, tape_kind: TapeKind) -> Self {
+        self.tape_kind = tape_kind;
+        self
+    }
+
+    /// Limit the number of cells the tape can grow to, see [`Machine::with_max_cells`]
+    pub fn max_cells(mut self, max_cells: usize) -> Self {
+        self.max_cells = Some(max_cells);
+        self
+    }
+
+    /// Limit the number of instructions executed, see [`Machine::with_max_steps`]
+    pub fn max_steps(mut self, max_steps: u64) -> Self {
+        self.max_steps = Some(max_steps);
+        self
+    }
+
+    /// Set what `,` does once the input is exhausted, see [`Machine::with_eof_behaviour`]
+    pub fn eof(mut self, eof_behaviour: EofBehaviour) -> Self {
+        self.eof_behaviour = eof_behaviour;
+        self
+    }
+
+    /// Set what `+` and `-` do when a cell would overflow, see [`Machine::with_overflow`]
+    pub fn overflow(mut self, overflow: CellOverflow) -> Self {
+        self.overflow = overflow;
+        self
+    }
+
+    /// Set how much of a cell `.` writes, see [`Machine::with_output_mode`]
+    pub fn output_mode(mut self, output_mode: OutputMode) -> Self {
+        self.output_mode = output_mode;
+        self
+    }
+
+    /// Set how much of a cell `,` reads, see [`Machine::with_input_mode`]
+    pub fn input_mode(mut self, input_mode: InputMode) -> Self {
+        self.input_mode = input_mode;
+        self
+    }
+
+    /// Set whether multiplying loops run as multiply-adds, see
+    /// [`Machine::with_multiply_loops`]
+    pub fn multiply_loops(mut self, multiply_loops: bool) -> Self {
+        self.multiply_loops = multiply_loops;
+        self
+    }
+
+    /// Set whether to count how often each instruction is executed, see
+    /// [`Machine::with_profiling`]
+    pub fn profiling(mut self, profiling: bool) -> Self {
+        self.profiling = profiling;
+        self
+    }
+
+    /// Set whether to record every byte read and written, see [`Machine::with_recording`]
+    pub fn recording(mut self, recording: bool) -> Self {
+        self.recording = recording;
+        self
+    }
+
+    /// Repeat a recorded run, see [`Machine::with_replay`]
+    pub fn replay(mut self, replay: Replay) -> Self {
+        self.replay = Some(replay);
+        self
+    }
+
+    /// Set whether a loop which never changes anything stops the program, see
+    /// [`Machine::with_hang_detection`]
+    pub fn hang_detection(mut self, detect_hangs: bool) -> Self {
+        self.detect_hangs = detect_hangs;
+        self
+    }
+
+    /// Set where `#` reports the state of the machine, see [`Machine::with_debug_sink`]
+    pub fn debug_sink(mut self, debug_sink: DebugSink) -> Self {
+        self.debug_sink = debug_sink;
+        self
+    }
+
+    /// Create a virtual machine running `program` with these options
+    ///
+    /// Fails if the tape would be empty or too large to allocate, or if a limit on the number
+    /// of cells is given for a tape which can't grow or is below the size the tape starts at.
+    pub fn build<'a, Cell: CellKind, T: Tape<Cell>>(
+        &self,
+        program: &'a Program,
+    ) -> Result<Machine<'a, Cell, T>, MachineConfigError> {
+        if let Some(max_cells) = self.max_cells {
+            if matches!(self.tape_kind, TapeKind::FixedSize | TapeKind::Wrapping) {
+                return Err(MachineConfigError::MaxCellsWithoutGrowth {
+                    tape_kind: self.tape_kind,
+                });
+            }
+            if self.tape_size > max_cells {
+                return Err(MachineConfigError::TapeAboveMaxCells {
+                    tape_size: self.tape_size,
+                    max_cells,
+                });
+            }
+        }
+
+        let mut machine = Machine::try_new(self.tape_size, self.tape_kind, program)?
+            .with_eof_behaviour(self.eof_behaviour)
+            .with_overflow(self.overflow)
+            .with_output_mode(self.output_mode)
+            .with_input_mode(self.input_mode)
+            .with_multiply_loops(self.multiply_loops)
+            .with_profiling(self.profiling)
+            .with_recording(self.recording)
+            .with_hang_detection(self.detect_hangs)
+            .with_debug_sink(self.debug_sink);
+        if let Some(max_cells) = self.max_cells {
+            machine = machine.with_max_cells(max_cells);
+        }
+        if let Some(max_steps) = self.max_steps {
+            machine = machine.with_max_steps(max_steps);
+        }
+        if let Some(replay) = &self.replay {
+            machine = machine.with_replay(replay.clone());
+        }
+        Ok(machine)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io, path::Path};
+
+    use super::*;
+    use crate::{GrowthStrategy, InterpretError, SparseTape};
+    use rstest::rstest;
+
+    /// A program which runs off the right of the tape after three cells
+    const SOURCE: &str = "+[>+]";
+
+    fn growable() -> TapeKind {
+        TapeKind::Growable {
+            growth: GrowthStrategy::default(),
+        }
+    }
+
+    #[rstest]
+    #[case(MachineBuilder::new().tape_size(0), MachineConfigError::EmptyTape)]
+    #[case(
+        MachineBuilder::new().tape_size(usize::MAX),
+        MachineConfigError::TapeTooLarge { cells: usize::MAX, cell_bytes: 2 }
+    )]
+    #[case(
+        MachineBuilder::new().max_cells(100),
+        MachineConfigError::MaxCellsWithoutGrowth { tape_kind: TapeKind::FixedSize }
+    )]
+    #[case(
+        MachineBuilder::new().tape_kind(TapeKind::Wrapping).max_cells(100),
+        MachineConfigError::MaxCellsWithoutGrowth { tape_kind: TapeKind::Wrapping }
+    )]
+    #[case(
+        MachineBuilder::new().tape_kind(growable()).tape_size(10).max_cells(5),
+        MachineConfigError::TapeAboveMaxCells { tape_size: 10, max_cells: 5 }
+    )]
+    fn test_build_rejects(#[case] builder: MachineBuilder, #[case] expected: MachineConfigError) {
+        let prog = Program::try_new(Path::new("-"), SOURCE).unwrap();
+        let res = builder.build::<u16, Vec<u16>>(&prog);
+        assert_eq!(res.err(), Some(expected));
+    }
+
+    #[rstest]
+    #[case(
+        MachineConfigError::MaxCellsWithoutGrowth { tape_kind: TapeKind::FixedSize },
+        "a limit on the number of cells needs a tape which can grow, not FixedSize"
+    )]
+    #[case(
+        MachineConfigError::TapeAboveMaxCells { tape_size: 10, max_cells: 5 },
+        "the tape can't start with 10 cells when it is limited to 5"
+    )]
+    fn test_config_error_messages(#[case] err: MachineConfigError, #[case] expected: &str) {
+        assert_eq!(err.to_string(), expected);
+    }
+
+    #[rstest]
+    #[case(growable())]
+    #[case(TapeKind::Sparse)]
+    fn test_build_max_cells(#[case] tape_kind: TapeKind) {
+        let prog = Program::try_new(Path::new("-"), SOURCE).unwrap();
+        let builder = MachineBuilder::new()
+            .tape_kind(tape_kind)
+            .tape_size(1)
+            .max_cells(3);
+        let err = if tape_kind == TapeKind::Sparse {
+            let mut machine: Machine<u8, SparseTape<u8>> = builder.build(&prog).unwrap();
+            machine.run(io::empty(), io::sink()).unwrap_err()
+        } else {
+            let mut machine: Machine<u8> = builder.build(&prog).unwrap();
+            machine.run(io::empty(), io::sink()).unwrap_err()
+        };
+        assert!(matches!(
+            err,
+            InterpretError::TapeLimitExceeded { limit: 3, .. }
+        ));
+    }
+
+    #[test]
+    fn test_build_max_steps() {
+        let prog = Program::try_new(Path::new("-"), "+[]").unwrap();
+        let mut machine: Machine<u8> = MachineBuilder::new().max_steps(10).build(&prog).unwrap();
+        let err = machine.run(io::empty(), io::sink()).unwrap_err();
+        assert!(matches!(
+            err,
+            InterpretError::StepLimitExceeded { steps: 10, .. }
+        ));
+    }
+
+    #[test]
+    fn test_build_applies_options() {
+        let prog = Program::try_new(Path::new("-"), ",-.").unwrap();
+        let mut machine: Machine<u8> = MachineBuilder::new()
+            .tape_size(1)
+            .eof(EofBehaviour::Zero)
+            .overflow(CellOverflow::Saturating)
+            .output_mode(OutputMode::Decimal)
+            .recording(true)
+            .build(&prog)
+            .unwrap();
+        let mut output = Vec::new();
+        machine.run(io::empty(), &mut output).unwrap();
+        assert_eq!(output, b"0 ");
+        // the end of the input is recorded along with both bytes written
+        assert_eq!(machine.transcript().map(<[_]>::len), Some(3));
+    }
+}
@@ -0,0 +1,440 @@
+use std::{fmt::Write, num::NonZeroUsize};
+
+use bft_types::{Instruction, Program};
+
+use crate::{EofBehaviour, DEFAULT_TAPE_SIZE};
+
+/// The languages a program can be translated into
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Language {
+    /// A single Rust source file, buildable with `rustc`
+    Rust,
+
+    /// A single C99 source file, buildable with `cc`
+    C,
+}
+
+/// The cell widths a translated program can use
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CellType {
+    /// 8-bit cells
+    #[default]
+    U8,
+    /// 16-bit cells
+    U16,
+    /// 32-bit cells
+    U32,
+    /// 64-bit cells
+    U64,
+}
+
+impl CellType {
+    /// The cell type for cells of this many bits, if there is one
+    pub fn from_bits(bits: u32) -> Option<Self> {
+        match bits {
+            8 => Some(Self::U8),
+            16 => Some(Self::U16),
+            32 => Some(Self::U32),
+            64 => Some(Self::U64),
+            _ => None,
+        }
+    }
+
+    /// The name of the type in the given language
+    fn name(self, language: Language) -> &'static str {
+        match (language, self) {
+            (Language::Rust, Self::U8) => "u8",
+            (Language::Rust, Self::U16) => "u16",
+            (Language::Rust, Self::U32) => "u32",
+            (Language::Rust, Self::U64) => "u64",
+            (Language::C, Self::U8) => "uint8_t",
+            (Language::C, Self::U16) => "uint16_t",
+            (Language::C, Self::U32) => "uint32_t",
+            (Language::C, Self::U64) => "uint64_t",
+        }
+    }
+}
+
+/// Translates programs into standalone source code in another language
+///
+/// The translated program behaves like the interpreter with wrapping cells, writing the low byte
+/// of a cell on `.` and reading a single byte on `,`. Running off the tape, or running out of
+/// input with [`EofBehaviour::Error`], prints an error and exits with status 4. `#` is ignored.
+///
+/// ```
+/// # use bft_interp::{CellType, Codegen, Language};
+/// # use bft_types::Program;
+/// # use std::path::Path;
+/// let prog = Program::try_new(Path::new("-"), "+[-.]").unwrap();
+/// let source = Codegen::new(Language::Rust)
+///     .with_cell_type(CellType::U16)
+///     .generate(&prog);
+/// assert!(source.contains("type Cell = u16;"));
+/// assert!(source.contains("while tape[p] != 0 {"));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Codegen {
+    /// The language to translate into
+    language: Language,
+
+    /// The width of the tape's cells
+    cell_type: CellType,
+
+    /// The number of cells the tape starts with
+    cells: usize,
+
+    /// Does the tape grow when the head runs off its right end
+    growable: bool,
+
+    /// What `,` does once the input is exhausted
+    eof_behaviour: EofBehaviour,
+}
+
+impl Codegen {
+    /// Translate into `language`, with a fixed size tape of [`DEFAULT_TAPE_SIZE`] 8-bit cells
+    /// and `,` failing at the end of the input
+    pub fn new(language: Language) -> Self {
+        Self {
+            language,
+            cell_type: CellType::default(),
+            cells: DEFAULT_TAPE_SIZE,
+            growable: false,
+            eof_behaviour: EofBehaviour::Error,
+        }
+    }
+
+    /// Set the width of the tape's cells
+    pub fn with_cell_type(mut self, cell_type: CellType) -> Self {
+        self.cell_type = cell_type;
+        self
+    }
+
+    /// Set the number of cells the tape starts with
+    pub fn with_cells(mut self, cells: NonZeroUsize) -> Self {
+        self.cells = cells.get();
+        self
+    }
+
+    /// Set whether the tape grows when the head runs off its right end
+    pub fn with_growable_tape(mut self, growable: bool) -> Self {
+        self.growable = growable;
+        self
+    }
+
+    /// Set what `,` does once the input is exhausted
+    pub fn with_eof_behaviour(mut self, eof_behaviour: EofBehaviour) -> Self {
+        self.eof_behaviour = eof_behaviour;
+        self
+    }
+
+    /// Translate a program, returning the source of the translation
+    pub fn generate(&self, program: &Program) -> String {
+        let mut out = String::new();
+        // writing to a String can't fail
+        let _ = writeln!(
+            out,
+            "// Generated by bft from {}",
+            program.filename().display().to_string().escape_debug()
+        );
+        match self.language {
+            Language::Rust => self.rust_prelude(&mut out),
+            Language::C => self.c_prelude(&mut out),
+        }
+
+        let mut depth = 1;
+        for instruction in program.instructions() {
+            if matches!(instruction, Instruction::Jnz { .. }) {
+                depth -= 1;
+            }
+            if let Some(statement) = self.statement(instruction) {
+                let _ = writeln!(out, "{:indent$}{statement}", "", indent = depth * 4);
+            }
+            if matches!(instruction, Instruction::Jz { .. }) {
+                depth += 1;
+            }
+        }
+
+        out.push_str(match self.language {
+            Language::Rust => RUST_EPILOGUE,
+            Language::C => C_EPILOGUE,
+        });
+        out
+    }
+
+    /// The statement an instruction is translated into, None for instructions which do nothing
+    fn statement(&self, instruction: &Instruction) -> Option<String> {
+        let rust = self.language == Language::Rust;
+        let statement = match *instruction {
+            Instruction::Inc => Self::right(rust, 1),
+            Instruction::Right(n) => Self::right(rust, n),
+            Instruction::Dec => Self::left(rust, 1),
+            Instruction::Left(n) => Self::left(rust, n),
+            Instruction::Succ => Self::add(rust, 1),
+            Instruction::Add(n) => Self::add(rust, n),
+            Instruction::Pred => Self::sub(rust, 1),
+            Instruction::Sub(n) => Self::sub(rust, n),
+            Instruction::SetZero => "tape[p] = 0;".to_owned(),
+            Instruction::Out if rust => "write_byte(tape[p] as u8);".to_owned(),
+            Instruction::Out => "write_byte((unsigned char)tape[p]);".to_owned(),
+            Instruction::In => self.read(rust),
+            // the matching brackets of a parsed program always pair up
+            Instruction::Jz { .. } if rust => "while tape[p] != 0 {".to_owned(),
+            Instruction::Jz { .. } => "while (tape[p] != 0) {".to_owned(),
+            Instruction::Jnz { .. } => "}".to_owned(),
+            Instruction::Debug => return None,
+        };
+        Some(statement)
+    }
+
+    /// Move the head right by `n` cells
+    fn right(rust: bool, n: usize) -> String {
+        if rust {
+            format!("p = right(&mut tape, p, {n});")
+        } else {
+            format!("right({n});")
+        }
+    }
+
+    /// Move the head left by `n` cells
+    fn left(rust: bool, n: usize) -> String {
+        if rust {
+            format!("p = left(p, {n});")
+        } else {
+            format!("left({n});")
+        }
+    }
+
+    /// Add `n` to the current cell
+    fn add(rust: bool, n: u8) -> String {
+        if rust {
+            format!("tape[p] = tape[p].wrapping_add({n});")
+        } else {
+            format!("tape[p] += {n};")
+        }
+    }
+
+    /// Subtract `n` from the current cell
+    fn sub(rust: bool, n: u8) -> String {
+        if rust {
+            format!("tape[p] = tape[p].wrapping_sub({n});")
+        } else {
+            format!("tape[p] -= {n};")
+        }
+    }
+
+    /// Read a byte into the current cell
+    fn read(&self, rust: bool) -> String {
+        let on_eof = match (self.eof_behaviour, rust) {
+            (EofBehaviour::Error, true) => "fail(\"the input ran out\")",
+            (EofBehaviour::Error, false) => "fail(\"the input ran out\");",
+            (EofBehaviour::Zero, true) => "tape[p] = 0",
+            (EofBehaviour::Zero, false) => "tape[p] = 0;",
+            (EofBehaviour::MinusOne, true) => "tape[p] = Cell::MAX",
+            (EofBehaviour::MinusOne, false) => "tape[p] = (cell)-1;",
+            (EofBehaviour::Unchanged, true) => "{}",
+            (EofBehaviour::Unchanged, false) => ";",
+        };
+        if rust {
+            format!("match read_byte(&mut input) {{ Some(byte) => tape[p] = Cell::from(byte), None => {on_eof}, }}")
+        } else {
+            format!("c = read_byte(); if (c != EOF) tape[p] = (cell)c; else {on_eof}")
+        }
+    }
+
+    /// Write everything a Rust translation needs before the program itself
+    fn rust_prelude(&self, out: &mut String) {
+        let grow = if self.growable {
+            "tape.resize((p + 1).max(tape.len() * 2), 0);"
+        } else {
+            "fail(\"the head ran off the right end of the tape\");"
+        };
+        let _ = write!(
+            out,
+            r#"
+#![allow(unused)]
+
+use std::io::{{self, Read, Write}};
+use std::process;
+
+type Cell = {cell};
+
+const TAPE_SIZE: usize = {cells};
+
+fn fail(message: &str) -> ! {{
+    eprintln!("error: {{message}}");
+    process::exit(4);
+}}
+
+fn right(tape: &mut Vec<Cell>, p: usize, n: usize) -> usize {{
+    let p = p + n;
+    if p >= tape.len() {{
+        {grow}
+    }}
+    p
+}}
+
+fn left(p: usize, n: usize) -> usize {{
+    p.checked_sub(n)
+        .unwrap_or_else(|| fail("the head ran off the left end of the tape"))
+}}
+
+fn write_byte(byte: u8) {{
+    if io::stdout().write_all(&[byte]).is_err() {{
+        fail("failed to write output");
+    }}
+}}
+
+fn read_byte(input: &mut impl Read) -> Option<u8> {{
+    let _ = io::stdout().flush();
+    let mut byte = [0];
+    loop {{
+        match input.read(&mut byte) {{
+            Ok(0) => return None,
+            Ok(_) => return Some(byte[0]),
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {{}}
+            Err(_) => fail("failed to read input"),
+        }}
+    }}
+}}
+
+fn main() {{
+    let mut tape: Vec<Cell> = vec![0; TAPE_SIZE];
+    let mut p: usize = 0;
+    let mut input = io::stdin().lock();
+"#,
+            cell = self.cell_type.name(Language::Rust),
+            cells = self.cells,
+        );
+    }
+
+    /// Write everything a C translation needs before the program itself
+    fn c_prelude(&self, out: &mut String) {
+        let grow = if self.growable {
+            "size_t new_len = tape_len * 2 > p + n ? tape_len * 2 : p + n + 1;
+        cell *grown = realloc(tape, new_len * sizeof(cell));
+        if (grown == NULL)
+            fail(\"out of memory\");
+        memset(grown + tape_len, 0, (new_len - tape_len) * sizeof(cell));
+        tape = grown;
+        tape_len = new_len;"
+        } else {
+            "fail(\"the head ran off the right end of the tape\");"
+        };
+        let _ = write!(
+            out,
+            r#"
+#include <stdint.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+typedef {cell} cell;
+
+static cell *tape;
+static size_t tape_len = {cells};
+static size_t p;
+
+static void fail(const char *message)
+{{
+    fflush(stdout);
+    fprintf(stderr, "error: %s\n", message);
+    exit(4);
+}}
+
+static void right(size_t n)
+{{
+    if (n >= tape_len - p) {{
+        {grow}
+    }}
+    p += n;
+}}
+
+static void left(size_t n)
+{{
+    if (n > p)
+        fail("the head ran off the left end of the tape");
+    p -= n;
+}}
+
+static void write_byte(unsigned char byte)
+{{
+    if (putchar(byte) == EOF)
+        fail("failed to write output");
+}}
+
+static int read_byte(void)
+{{
+    fflush(stdout);
+    return getchar();
+}}
+
+int main(void)
+{{
+    int c;
+    tape = calloc(tape_len, sizeof(cell));
+    if (tape == NULL)
+        fail("out of memory");
+"#,
+            cell = self.cell_type.name(Language::C),
+            cells = self.cells,
+        );
+    }
+}
+
+/// The end of a Rust translation, after the program itself
+const RUST_EPILOGUE: &str = "    if io::stdout().flush().is_err() {
+        fail(\"failed to write output\");
+    }
+}
+";
+
+/// The end of a C translation, after the program itself
+const C_EPILOGUE: &str = "    if (fflush(stdout) != 0)
+        fail(\"failed to write output\");
+    return 0;
+}
+";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+    use std::path::Path;
+
+    #[rstest]
+    #[case::rust(Language::Rust, "    while tape[p] != 0 {\n        p = right(&mut tape, p, 2);\n        tape[p] = tape[p].wrapping_add(1);\n    }\n")]
+    #[case::c(
+        Language::C,
+        "    while (tape[p] != 0) {\n        right(2);\n        tape[p] += 1;\n    }\n"
+    )]
+    fn test_loops_become_while(#[case] language: Language, #[case] expected: &str) {
+        let prog = Program::try_new(Path::new("-"), "[>>+]")
+            .unwrap()
+            .optimized();
+        let source = Codegen::new(language).generate(&prog);
+        assert!(source.contains(expected), "{source}");
+    }
+
+    #[rstest]
+    #[case::rust(Language::Rust, "type Cell = u32;", "const TAPE_SIZE: usize = 7;")]
+    #[case::c(Language::C, "typedef uint32_t cell;", "static size_t tape_len = 7;")]
+    fn test_cell_type_and_tape_size(
+        #[case] language: Language,
+        #[case] cell: &str,
+        #[case] tape: &str,
+    ) {
+        let prog = Program::try_new(Path::new("-"), "+.").unwrap();
+        let source = Codegen::new(language)
+            .with_cell_type(CellType::from_bits(32).unwrap())
+            .with_cells(NonZeroUsize::new(7).unwrap())
+            .generate(&prog);
+        assert!(source.contains(cell), "{source}");
+        assert!(source.contains(tape), "{source}");
+        assert!(source.contains("the head ran off the right end of the tape"));
+
+        let growable = Codegen::new(language)
+            .with_growable_tape(true)
+            .generate(&prog);
+        assert!(!growable.contains("the head ran off the right end of the tape"));
+    }
+}
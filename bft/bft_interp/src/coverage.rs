@@ -0,0 +1,75 @@
+use std::{fmt, path::PathBuf};
+
+use bft_types::{Instruction, SourceLocation};
+
+/// Which of a program's instructions were never executed, see [`Machine::coverage`]
+///
+/// [`Machine::coverage`]: crate::Machine::coverage
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CoverageReport {
+    /// The file the program was loaded from
+    pub filename: PathBuf,
+
+    /// The number of instructions in the program
+    pub instructions: usize,
+
+    /// Every instruction which was never executed, in program order
+    pub unexecuted: Vec<UnexecutedInstruction>,
+}
+
+/// An instruction which was never executed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UnexecutedInstruction {
+    /// The index of the instruction in the program
+    pub ip: usize,
+
+    /// Where the instruction is in the source code
+    pub location: SourceLocation,
+
+    /// The instruction itself
+    pub instruction: Instruction,
+}
+
+impl CoverageReport {
+    /// Was every instruction executed at least once
+    pub fn is_complete(&self) -> bool {
+        self.unexecuted.is_empty()
+    }
+}
+
+/// Summarises the coverage then lists each run of adjacent unexecuted instructions on a line
+///
+/// ```text
+/// coverage: 3 of 8 instructions executed
+///   dead.bf:1:2: ->+<
+/// ```
+impl fmt::Display for CoverageReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "coverage: {} of {} instructions executed",
+            self.instructions - self.unexecuted.len(),
+            self.instructions
+        )?;
+
+        let mut unexecuted = self.unexecuted.iter().peekable();
+        while let Some(first) = unexecuted.next() {
+            let mut run = first.instruction.to_string();
+            let mut last = first;
+            while let Some(next) = unexecuted
+                .next_if(|next| next.ip == last.ip + 1 && next.location.line == last.location.line)
+            {
+                run.push_str(&next.instruction.to_string());
+                last = next;
+            }
+            write!(
+                f,
+                "\n  {}:{}:{}: {run}",
+                self.filename.display(),
+                first.location.line + 1,
+                first.location.column + 1
+            )?;
+        }
+        Ok(())
+    }
+}
@@ -2,10 +2,60 @@
 #![deny(clippy::missing_docs_in_private_items)]
 
 //! the brainfuck interpreter
+//!
+//! A snippet of brainfuck can be tested on its own by starting it with known memory and
+//! inspecting the tape once it finishes, with no IO involved:
+//!
+//! ```
+//! # use bft_interp::{Machine, TapeKind};
+//! # use bft_types::Program;
+//! # use std::{io, path::Path};
+//! // add the first cell to the second
+//! let prog = Program::try_new(Path::new("add.bf"), "[->+<]").unwrap();
+//! let mut vm = Machine::<u8>::new(2, TapeKind::FixedSize, &prog)
+//!     .with_initial_tape(&[3, 4], 0)
+//!     .unwrap();
+//! vm.run(io::empty(), io::sink()).unwrap();
+//! assert_eq!((vm.cell_at(0), vm.cell_at(1)), (Some(&0), Some(&7)));
+//! ```
 
 mod machine;
-pub use machine::{InterpretError, Machine, TapeKind, DEFAULT_TAPE_SIZE};
+pub use machine::{
+    CellKind, CellOverflow, DebugSink, DumpFormat, EofBehaviour, ErrorSite, GrowthStrategy,
+    InputMode, InterpretError, Machine, MachineConfigError, MachineState, OutputMode, RestoreError,
+    Rewrite, RunOutcome, RunStats, StepOutcome, TapeKind, TraceEvent, DEFAULT_TAPE_SIZE,
+};
+
+/// Configuring a virtual machine one option at a time
+mod builder;
+pub use builder::MachineBuilder;
+
+/// Cells holding arbitrarily large numbers
+#[cfg(feature = "bignum")]
+mod bignum;
+
+/// The compact form instructions are decoded into before running
+mod op;
+
+/// Reporting which instructions of a program were never executed
+mod coverage;
+pub use coverage::{CoverageReport, UnexecutedInstruction};
+
+/// Recording a program's IO and replaying it
+mod transcript;
+pub use transcript::{IoDirection, IoEvent, Replay};
+
+/// Translating programs into source code in other languages
+mod codegen;
+pub use codegen::{CellType, Codegen, Language};
+
+/// Storage backends for the virtual machine's tape
+mod tape;
+pub use tape::{SparseTape, Tape};
 
 /// A Write wrapper type which ensures a newline terminates the output
 mod newline_wrap;
 pub use newline_wrap::NewlineWrap;
+
+/// The version of this crate
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
@@ -1,35 +1,318 @@
 //! The brainfuck virtual machine
 
 use std::{
+    borrow::Cow,
     fmt,
     io::{self, Read, Write},
+    marker::PhantomData,
+    num::Wrapping,
+    path::PathBuf,
+    sync::atomic::{AtomicBool, Ordering},
 };
 
-use bft_types::{Instruction, Program};
+use bft_types::{Instruction, Program, SourceLocation};
+
+use crate::{op::Op, CoverageReport, IoDirection, IoEvent, Replay, Tape, UnexecutedInstruction};
 
 /// The result of executing a single brainfuck command
 pub type CommandResult = Result<usize, InterpretError>;
 
 /// The brainfuck virtual machine state
+///
+/// The tape is stored in a `T`, by default a `Vec` holding every cell.
 #[derive(Debug, Clone)]
-#[allow(dead_code)]
-pub struct Machine<'a, Cell: CellKind> {
-    /// The program the VM is running
-    program: &'a Program,
+pub struct Machine<'a, Cell: CellKind, T: Tape<Cell> = Vec<Cell>> {
+    /// The program the VM is running, either borrowed or owned by the VM
+    program: Cow<'a, Program>,
+
+    /// The program's instructions decoded for the dispatch loop, at the same indices
+    ops: Vec<Op>,
 
     /// The memory backing the virtual machine
-    tape: Vec<Cell>,
+    tape: T,
+
+    /// The number of cells the tape started with
+    tape_size: usize,
+
+    /// The type of the cells on the tape
+    cell_kind: PhantomData<Cell>,
+
+    /// How the tape grows when the head runs off the end, None if the tape can't grow
+    growth: Option<GrowthStrategy>,
+
+    /// Can the tape also grow to the left of the cell the head started on
+    grow_left: bool,
+
+    /// Does the head wrap around to the other end of the tape when it runs off either end
+    wrapping: bool,
+
+    /// The most cells a growable tape can grow to, if limited
+    max_cells: Option<usize>,
 
-    /// Can the tape grow?
-    tape_can_grow: bool,
+    /// The most instructions [`Machine::run`] executes before stopping, if limited
+    max_steps: Option<u64>,
+
+    /// The index in `tape` of the cell the head started on, which is only non-zero once the
+    /// tape has grown to the left
+    origin: usize,
 
     /// The current location of the head of the tape
     dp: usize,
 
+    /// The cells the tape starts with, from its first cell
+    initial_cells: Vec<Cell>,
+
+    /// The location of the head when the program starts
+    initial_dp: usize,
+
+    /// The lowest and highest cells the head has been on, relative to the cell it started on
+    reached: (isize, isize),
+
     /// The current location of the head of the tape
     ip: usize,
+
+    /// The number of instructions executed
+    steps: u64,
+
+    /// The number of times each instruction has been executed, if they are being counted
+    execution_counts: Option<Vec<u64>>,
+
+    /// Every byte read or written by the program, if its IO is being recorded
+    transcript: Option<Vec<IoEvent>>,
+
+    /// The recorded run the program must repeat, if one is being replayed
+    replay: Option<Replay>,
+
+    /// Watches the innermost loop for iterations which change nothing, if hangs are detected
+    hang_detector: Option<HangDetector>,
+
+    /// What the `,` instruction does when the input is exhausted
+    eof_behaviour: EofBehaviour,
+
+    /// What `+` and `-` do when a cell would overflow
+    overflow: CellOverflow,
+
+    /// Should multiplication loops be run as multiply-adds, when cells wrap
+    multiply_loops: bool,
+
+    /// How much of a cell the `.` instruction writes
+    output_mode: OutputMode,
+
+    /// How much of a cell the `,` instruction reads
+    input_mode: InputMode,
+
+    /// Where the `#` instruction writes its report
+    debug_sink: DebugSink,
+
+    /// The number of instructions executed between checks of the cancellation flag
+    cancel_check_interval: u64,
+
+    /// The number of instructions [`Machine::run_async`] executes between yields to the
+    /// executor, if it yields other than at IO
+    #[cfg(feature = "async")]
+    yield_interval: Option<u64>,
+
+    /// The instructions to pause before executing, kept sorted
+    breakpoints: Vec<usize>,
+
+    /// The cells to pause after a change to, kept sorted
+    watchpoints: Vec<isize>,
+
+    /// The breakpoint execution last paused at, which isn't hit again when resuming
+    paused_at: Option<usize>,
+}
+
+/// Whether the virtual machine can continue after executing a step
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StepOutcome {
+    /// There are more instructions left to execute
+    Running,
+    /// The program has finished executing
+    Halted,
+}
+
+/// Why [`Machine::run_until_break`] stopped
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RunOutcome<Cell> {
+    /// The program has finished executing
+    Halted,
+    /// The instruction at a breakpoint is about to be executed
+    Breakpoint {
+        /// The breakpoint which was hit
+        ip: usize,
+    },
+    /// The value of a watched cell changed
+    Watchpoint {
+        /// The index of the cell which changed
+        cell: isize,
+        /// The value of the cell before the change
+        old: Cell,
+        /// The value of the cell after the change
+        new: Cell,
+    },
+}
+
+/// A record of a single instruction executed by [`Machine::run_traced`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceEvent<Cell> {
+    /// The index of the instruction
+    pub ip: usize,
+    /// The instruction which was executed
+    pub instruction: Instruction,
+    /// Where the instruction came from in the program's source
+    pub location: SourceLocation,
+    /// The location of the head of the tape when the instruction was executed
+    pub dp: isize,
+    /// The value of the cell under the head before the instruction was executed
+    pub before: Cell,
+    /// The value of the same cell after the instruction was executed
+    pub after: Cell,
+    /// The index of the next instruction to be executed
+    pub next_ip: usize,
+    /// What ran in place of the instruction, if it is part of a loop run as multiply-adds
+    pub rewrite: Option<Rewrite>,
+}
+
+impl<Cell: fmt::Display> fmt::Display for TraceEvent<Cell> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "ip={} '{}'", self.ip, self.instruction.as_char())?;
+        if let Some(rewrite) = self.rewrite {
+            write!(f, " as {rewrite}")?;
+        }
+        write!(
+            f,
+            " dp={} cell={} -> ip={} cell={}",
+            self.dp, self.before, self.next_ip, self.after
+        )
+    }
+}
+
+/// The operation run in place of an instruction of a loop rewritten by
+/// [`Machine::with_multiply_loops`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rewrite {
+    /// Added the cell under the head times `factor` to the cell `offset` cells away
+    MulAdd {
+        /// The distance to the cell added to, negative to the left
+        offset: i32,
+        /// The multiple of the cell under the head added
+        factor: i16,
+    },
+    /// Cleared the cell under the head, the loop's counter
+    SetZero,
+    /// Jumped past the rest of the loop
+    SkipLoop,
+}
+
+impl Rewrite {
+    /// What `op` did in place of `instruction`, if it was rewritten
+    fn of(op: Op, instruction: Instruction) -> Option<Self> {
+        if op == Op::from(instruction) {
+            return None;
+        }
+        match op {
+            Op::MulAdd { offset, factor } => Some(Self::MulAdd { offset, factor }),
+            Op::SetZero => Some(Self::SetZero),
+            Op::Jz(_) => Some(Self::SkipLoop),
+            _ => unreachable!("only multiplication loops are rewritten"),
+        }
+    }
+}
+
+impl fmt::Display for Rewrite {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MulAdd { offset, factor } => write!(f, "mul-add({offset:+}, {factor})"),
+            Self::SetZero => write!(f, "set-zero"),
+            Self::SkipLoop => write!(f, "skip-loop"),
+        }
+    }
+}
+
+/// What the `,` instruction does when there is no more input to read
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EofBehaviour {
+    /// Stop the program with an IoError
+    #[default]
+    Error,
+    /// Set the cell to zero
+    Zero,
+    /// Set the cell to minus one, i.e. the maximum value of an unsigned cell
+    MinusOne,
+    /// Leave the cell unchanged
+    Unchanged,
+}
+
+/// What happens when `+` or `-` takes a cell beyond the range of values it can hold
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CellOverflow {
+    /// Wrap around to the other end of the range
+    #[default]
+    Wrapping,
+    /// Stop the program with a CellOverflow error
+    Error,
+    /// Stay at the end of the range
+    Saturating,
+}
+
+/// Where the `#` debug instruction writes its report of the machine's state
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DebugSink {
+    /// Write the report to stderr
+    #[default]
+    Stderr,
+    /// Write the report into the program's output, between whatever `.` writes
+    Output,
+    /// Discard the report
+    Discard,
+}
+
+/// How much of a cell the `.` instruction writes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputMode {
+    /// Write every byte of the cell, most significant first
+    #[default]
+    FullCell,
+    /// Write only the least significant byte of the cell, as with 8-bit cells
+    LowByte,
+    /// Write the value of the cell in decimal followed by a space, for programs computing
+    /// numbers rather than text
+    Decimal,
+}
+
+/// How much of a cell the `,` instruction reads
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum InputMode {
+    /// Read a single byte into the cell
+    #[default]
+    Byte,
+    /// Read as many bytes as the cell is wide, most significant first, mirroring
+    /// [`OutputMode::FullCell`]
+    FullCell,
+}
+
+/// The formats the tape can be dumped in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DumpFormat {
+    /// The big-endian bytes of each cell
+    Raw,
+    /// A hexdump with the cell under the data pointer surrounded by brackets
+    Hex,
 }
 
+/// The number of cells either side of the head shown when a machine is displayed
+const DISPLAY_WINDOW: usize = 4;
+
+/// The number of cells on each line of a hexdump of the tape
+const DUMP_CELLS_PER_LINE: usize = 16;
+
+/// The default number of instructions executed between checks of the cancellation flag
+const DEFAULT_CANCEL_CHECK_INTERVAL: u64 = 1024;
+
+/// The number of times a loop must repeat without changing anything before it is treated as
+/// hung, see [`Machine::with_hang_detection`]
+const HANG_REPEATS: u32 = 1000;
+
 /// The default size of the virtual machine's tape
 pub const DEFAULT_TAPE_SIZE: usize = 30_000;
 
@@ -37,27 +320,124 @@ pub const DEFAULT_TAPE_SIZE: usize = 30_000;
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum TapeKind {
     /// a growable tape
-    Growable,
+    Growable {
+        /// how the tape grows when the head runs off the end
+        growth: GrowthStrategy,
+    },
+    /// a growable tape which can also grow to the left of the starting cell, giving cells with
+    /// negative indices
+    GrowableBothEnds {
+        /// how the tape grows when the head runs off either end
+        growth: GrowthStrategy,
+    },
     /// a fixed-size tape
     FixedSize,
+    /// a fixed-size tape whose ends are joined, so the head wraps around rather than running off
+    Wrapping,
+    /// a growable tape meant for a [`SparseTape`](crate::SparseTape), which grows for free as
+    /// only the cells written to are stored
+    Sparse,
+}
+
+/// How a growable tape is extended when the head runs off the end of it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GrowthStrategy {
+    /// Double the length of the tape, growing by at least one cell
+    #[default]
+    Doubling,
+    /// Grow the tape by a fixed number of cells, an increment of zero grows by one cell
+    FixedIncrement(usize),
+}
+
+impl GrowthStrategy {
+    /// The length a tape of length `len` should grow to
+    ///
+    /// ```
+    /// # use bft_interp::GrowthStrategy;
+    /// assert_eq!(GrowthStrategy::Doubling.grown_len(0), 1);
+    /// assert_eq!(GrowthStrategy::Doubling.grown_len(100), 200);
+    /// assert_eq!(GrowthStrategy::FixedIncrement(10).grown_len(100), 110);
+    /// assert_eq!(GrowthStrategy::FixedIncrement(0).grown_len(100), 101);
+    /// ```
+    pub fn grown_len(self, len: usize) -> usize {
+        match self {
+            Self::Doubling => len.saturating_mul(2).max(len + 1),
+            Self::FixedIncrement(n) => len + n.max(1),
+        }
+    }
 }
 
 /// The bounds required for a type to act as a cell
-pub trait CellKind: Default + Clone {
+pub trait CellKind: Default + Clone + PartialEq {
     /// Increment the cell by one, wrapping the result of the computation
     fn wrapping_inc(&mut self);
 
     /// Decrement the cell by one, wrapping the result of the computation
     fn wrapping_dec(&mut self);
 
+    /// Add `amount` to the cell, wrapping the result of the computation
+    fn wrapping_add_by(&mut self, amount: u8);
+
+    /// Subtract `amount` from the cell, wrapping the result of the computation
+    fn wrapping_sub_by(&mut self, amount: u8);
+
+    /// Add `amount` to the cell, returning false and leaving it unchanged if it would overflow
+    fn checked_add_by(&mut self, amount: u8) -> bool;
+
+    /// Subtract `amount` from the cell, returning false and leaving it unchanged if it would
+    /// overflow
+    fn checked_sub_by(&mut self, amount: u8) -> bool;
+
+    /// Add `amount` to the cell, stopping at the largest value it can hold
+    fn saturating_add_by(&mut self, amount: u8);
+
+    /// Subtract `amount` from the cell, stopping at the smallest value it can hold
+    fn saturating_sub_by(&mut self, amount: u8);
+
+    /// Add `counter` multiplied by `factor` to the cell, wrapping the result of the computation
+    ///
+    /// This is the effect of a loop adding `factor` to the cell each time it decrements
+    /// `counter` down to zero.
+    fn wrapping_add_product(&mut self, counter: &Self, factor: i16);
+
     /// Does this cell contain zero
     fn is_zero(&self) -> bool;
 
-    /// Set the value of the cell
+    /// Set the value of the cell from a byte of input
+    ///
+    /// For cells of the same width as the byte its bit pattern is reinterpreted, so a signed
+    /// cell set from 0xFF contains -1.
     fn set_value(&mut self, value: u8);
 
     /// The value of the cell as a slice of bytes
     fn as_bytes(&self) -> Box<[u8]>;
+
+    /// Write the bytes of [`CellKind::as_bytes`] to `writer`
+    ///
+    /// By default the bytes are collected with [`CellKind::as_bytes`], which allocates, so cells
+    /// which can provide their bytes without allocating should override this.
+    fn write_be_bytes(&self, writer: &mut impl Write) -> io::Result<()> {
+        writer.write_all(&self.as_bytes())
+    }
+
+    /// The least significant byte of the cell
+    fn low_byte(&self) -> u8;
+
+    /// Set the value of the cell from its big-endian bytes
+    ///
+    /// `bytes` must be exactly as long as the cell is wide.
+    fn set_from_be_bytes(&mut self, bytes: &[u8]);
+
+    /// The value of the cell written in decimal
+    ///
+    /// By default the bytes of the cell are read as an unsigned number of up to 128 bits.
+    fn to_decimal(&self) -> String {
+        let value = self
+            .as_bytes()
+            .iter()
+            .fold(0_u128, |value, &byte| value << 8 | u128::from(byte));
+        value.to_string()
+    }
 }
 
 /// Implement CellKind for a builtin numeric type
@@ -72,21 +452,91 @@ macro_rules! cell_kind_impl {
                 *self = self.wrapping_sub(1);
             }
 
+            fn wrapping_add_by(&mut self, amount: u8) {
+                // reinterpreting the byte is fine as addition wraps modulo the cell width
+                *self = self.wrapping_add(amount as $type);
+            }
+
+            fn wrapping_sub_by(&mut self, amount: u8) {
+                *self = self.wrapping_sub(amount as $type);
+            }
+
+            fn checked_add_by(&mut self, amount: u8) -> bool {
+                checked_by(*self, amount, <$type>::checked_add)
+                    .map(|value| *self = value)
+                    .is_some()
+            }
+
+            fn checked_sub_by(&mut self, amount: u8) -> bool {
+                checked_by(*self, amount, <$type>::checked_sub)
+                    .map(|value| *self = value)
+                    .is_some()
+            }
+
+            fn saturating_add_by(&mut self, amount: u8) {
+                *self = checked_by(*self, amount, <$type>::checked_add).unwrap_or(<$type>::MAX);
+            }
+
+            fn saturating_sub_by(&mut self, amount: u8) {
+                *self = checked_by(*self, amount, <$type>::checked_sub).unwrap_or(<$type>::MIN);
+            }
+
+            fn wrapping_add_product(&mut self, counter: &Self, factor: i16) {
+                // the cast keeps the factor's value modulo the cell width, which is all that
+                // wrapping arithmetic needs
+                *self = self.wrapping_add(counter.wrapping_mul(factor as $type));
+            }
+
             fn is_zero(&self) -> bool {
                 *self == 0
             }
 
             fn set_value(&mut self, value: u8) {
-                *self = value.into();
+                // reinterpret the byte so that signed cells round trip values >= 0x80
+                *self = value as $type;
             }
 
             fn as_bytes(&self) -> Box<[u8]> {
                 Box::new(self.to_be_bytes())
             }
+
+            fn write_be_bytes(&self, writer: &mut impl Write) -> io::Result<()> {
+                writer.write_all(&self.to_be_bytes())
+            }
+
+            fn low_byte(&self) -> u8 {
+                // truncation keeps the low byte, whatever the sign
+                *self as u8
+            }
+
+            fn set_from_be_bytes(&mut self, bytes: &[u8]) {
+                let bytes = bytes
+                    .try_into()
+                    .expect("one byte given per byte of the cell");
+                *self = <$type>::from_be_bytes(bytes);
+            }
+
+            fn to_decimal(&self) -> String {
+                self.to_string()
+            }
         }
     };
 }
 
+/// Apply a checked operation to `value` and `amount`, None if it overflows
+///
+/// An i8 can't hold every byte, so when `T` can't hold `amount` it is applied in steps which fit.
+fn checked_by<T: TryFrom<u8> + Copy>(value: T, amount: u8, op: fn(T, T) -> Option<T>) -> Option<T> {
+    match T::try_from(amount) {
+        Ok(amount) => op(value, amount),
+        Err(_) => {
+            let half = T::try_from(amount / 2).ok()?;
+            let rest = T::try_from(amount % 2).ok()?;
+            op(op(op(value, half)?, half)?, rest)
+        }
+    }
+}
+
 /// Implement CellKind for a list of builtin types
 macro_rules! cell_kind_impl_all {
     ($($type:ty),+) => {
@@ -96,11 +546,77 @@ macro_rules! cell_kind_impl_all {
     };
 }
 
-cell_kind_impl_all!(u8, u16, u32, u64, u128, i16, i32, i64, i128);
+cell_kind_impl_all!(u8, u16, u32, u64, u128, i8, i16, i32, i64, i128);
+
+/// A wrapping integer behaves exactly like the cell it wraps, as cells already wrap
+impl<T: CellKind> CellKind for Wrapping<T> {
+    fn wrapping_inc(&mut self) {
+        self.0.wrapping_inc();
+    }
+
+    fn wrapping_dec(&mut self) {
+        self.0.wrapping_dec();
+    }
+
+    fn wrapping_add_by(&mut self, amount: u8) {
+        self.0.wrapping_add_by(amount);
+    }
+
+    fn wrapping_sub_by(&mut self, amount: u8) {
+        self.0.wrapping_sub_by(amount);
+    }
+
+    fn checked_add_by(&mut self, amount: u8) -> bool {
+        self.0.checked_add_by(amount)
+    }
+
+    fn checked_sub_by(&mut self, amount: u8) -> bool {
+        self.0.checked_sub_by(amount)
+    }
+
+    fn saturating_add_by(&mut self, amount: u8) {
+        self.0.saturating_add_by(amount);
+    }
+
+    fn saturating_sub_by(&mut self, amount: u8) {
+        self.0.saturating_sub_by(amount);
+    }
+
+    fn wrapping_add_product(&mut self, counter: &Self, factor: i16) {
+        self.0.wrapping_add_product(&counter.0, factor);
+    }
+
+    fn is_zero(&self) -> bool {
+        self.0.is_zero()
+    }
+
+    fn set_value(&mut self, value: u8) {
+        self.0.set_value(value);
+    }
+
+    fn as_bytes(&self) -> Box<[u8]> {
+        self.0.as_bytes()
+    }
+
+    fn write_be_bytes(&self, writer: &mut impl Write) -> io::Result<()> {
+        self.0.write_be_bytes(writer)
+    }
+
+    fn low_byte(&self) -> u8 {
+        self.0.low_byte()
+    }
+
+    fn set_from_be_bytes(&mut self, bytes: &[u8]) {
+        self.0.set_from_be_bytes(bytes);
+    }
+
+    fn to_decimal(&self) -> String {
+        self.0.to_decimal()
+    }
+}
 
-#[allow(dead_code)]
-impl<'a, Cell: CellKind> Machine<'a, Cell> {
-    /// Create a new virtual machine with a growable tape
+impl<'a, Cell: CellKind, T: Tape<Cell>> Machine<'a, Cell, T> {
+    /// Create a new virtual machine
     ///
     /// `tape_size`: the size of the tape to allocate for the virtual machine
     ///
@@ -108,378 +624,4171 @@ impl<'a, Cell: CellKind> Machine<'a, Cell> {
     /// # use bft_interp::{Machine, TapeKind};
     /// # use bft_types::Program;
     /// let prog = Program::from_file("../programs/example.bf").unwrap();
-    /// let vm = Machine::<u8>::new(1000, TapeKind::Growable, &prog);
+    /// let vm = Machine::<u8>::new(1000, TapeKind::Growable { growth: Default::default() }, &prog);
     /// ```
+    ///
+    /// # Panics
+    /// When the tape size is rejected by [`Machine::try_new`].
     pub fn new(tape_size: usize, tape_kind: TapeKind, program: &'a Program) -> Self {
+        Self::try_new(tape_size, tape_kind, program).unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// Create a new virtual machine, checking the tape has at least one cell and that its cells
+    /// fit in memory
+    ///
+    /// ```
+    /// # use bft_interp::{Machine, MachineConfigError, TapeKind};
+    /// # use bft_types::Program;
+    /// let prog = Program::from_file("../programs/example.bf").unwrap();
+    /// let err = Machine::<u8>::try_new(0, TapeKind::FixedSize, &prog).unwrap_err();
+    /// assert_eq!(err, MachineConfigError::EmptyTape);
+    /// ```
+    pub fn try_new(
+        tape_size: usize,
+        tape_kind: TapeKind,
+        program: &'a Program,
+    ) -> Result<Self, MachineConfigError> {
+        check_tape_size::<Cell>(tape_size)?;
+        Ok(Self::with_program(
+            tape_size,
+            tape_kind,
+            Cow::Borrowed(program),
+        ))
+    }
+
+    /// Create a new virtual machine which owns its program
+    ///
+    /// As nothing is borrowed the machine can outlive the scope it was created in, for example
+    /// by being moved into another thread.
+    ///
+    /// ```
+    /// # use bft_interp::{Machine, TapeKind};
+    /// # use bft_types::Program;
+    /// # use std::{io, thread};
+    /// let prog = Program::from_file("../programs/example.bf").unwrap();
+    /// let mut vm = Machine::<u8>::new_owned(1000, TapeKind::FixedSize, prog);
+    /// let output = thread::spawn(move || {
+    ///     let mut output = Vec::new();
+    ///     vm.run(io::empty(), &mut output).unwrap();
+    ///     output
+    /// });
+    /// assert_eq!(output.join().unwrap(), b"hello world");
+    /// ```
+    ///
+    /// # Panics
+    /// When the tape size is rejected by [`Machine::try_new`].
+    pub fn new_owned(
+        tape_size: usize,
+        tape_kind: TapeKind,
+        program: Program,
+    ) -> Machine<'static, Cell, T> {
+        if let Err(e) = check_tape_size::<Cell>(tape_size) {
+            panic!("{e}");
+        }
+        Machine::with_program(tape_size, tape_kind, Cow::Owned(program))
+    }
+
+    /// Create a new virtual machine running `program`, however it is held
+    fn with_program(tape_size: usize, tape_kind: TapeKind, program: Cow<'a, Program>) -> Self {
         Self {
+            ops: Op::decode(&program, false),
             program,
-            tape: vec![Cell::default(); tape_size],
-            tape_can_grow: tape_kind == TapeKind::Growable,
+            tape: T::with_len(tape_size),
+            tape_size,
+            cell_kind: PhantomData,
+            growth: match tape_kind {
+                TapeKind::Growable { growth } | TapeKind::GrowableBothEnds { growth } => {
+                    Some(growth)
+                }
+                TapeKind::Sparse => Some(GrowthStrategy::default()),
+                TapeKind::FixedSize | TapeKind::Wrapping => None,
+            },
+            grow_left: matches!(tape_kind, TapeKind::GrowableBothEnds { .. }),
+            wrapping: tape_kind == TapeKind::Wrapping,
+            max_cells: None,
+            max_steps: None,
+            origin: 0,
             dp: 0,
+            initial_cells: Vec::new(),
+            initial_dp: 0,
+            reached: (0, 0),
             ip: 0,
+            steps: 0,
+            execution_counts: None,
+            transcript: None,
+            replay: None,
+            hang_detector: None,
+            eof_behaviour: EofBehaviour::default(),
+            overflow: CellOverflow::default(),
+            multiply_loops: false,
+            output_mode: OutputMode::default(),
+            input_mode: InputMode::default(),
+            debug_sink: DebugSink::default(),
+            cancel_check_interval: DEFAULT_CANCEL_CHECK_INTERVAL,
+            #[cfg(feature = "async")]
+            yield_interval: None,
+            breakpoints: Vec::new(),
+            watchpoints: Vec::new(),
+            paused_at: None,
         }
     }
 
-    /// Create a new virtual machine with a fixed size tape
+    /// Set what the `,` instruction does when the input is exhausted
     ///
-    /// `tape_size`: the size of the tape to allocate for the virtual machine
+    /// `eof_behaviour`: the behaviour to use, by default EOF is an error
+    ///
+    /// ```
+    /// # use bft_interp::{EofBehaviour, Machine, TapeKind};
+    /// # use bft_types::Program;
+    /// # use std::{io, path::Path};
+    /// let prog = Program::try_new(Path::new("-"), ",[.,]").unwrap();
+    /// let mut vm = Machine::<u8>::new(1000, TapeKind::FixedSize, &prog)
+    ///     .with_eof_behaviour(EofBehaviour::Zero);
+    /// let mut output = Vec::new();
+    /// vm.run(&b"cat"[..], &mut output).unwrap();
+    /// assert_eq!(output, b"cat");
+    /// ```
+    pub fn with_eof_behaviour(mut self, eof_behaviour: EofBehaviour) -> Self {
+        self.eof_behaviour = eof_behaviour;
+        self
+    }
+
+    /// Set what `+` and `-` do when they would take a cell beyond the range of values it can
+    /// hold
+    ///
+    /// `overflow`: the behaviour to use, by default cells wrap around
+    ///
+    /// ```
+    /// # use bft_interp::{CellOverflow, InterpretError, Machine, TapeKind};
+    /// # use bft_types::Program;
+    /// # use std::{io, path::Path};
+    /// let prog = Program::try_new(Path::new("-"), "-").unwrap();
+    /// let mut vm = Machine::<u8>::new(1, TapeKind::FixedSize, &prog)
+    ///     .with_overflow(CellOverflow::Error);
+    /// let err = vm.run(io::empty(), io::sink()).unwrap_err();
+    /// assert!(matches!(err, InterpretError::CellOverflow { ip_at_error: 0, dp: 0, .. }));
+    /// ```
+    pub fn with_overflow(mut self, overflow: CellOverflow) -> Self {
+        self.overflow = overflow;
+        self.decode();
+        self
+    }
+
+    /// Set whether loops like `[->+++>+<<]`, which add multiples of a counter to other cells,
+    /// are run as a multiply-add for each cell rather than once for each time round the loop
+    ///
+    /// Only cells which wrap around are multiplied, as overflowing part way through a loop
+    /// can't be reproduced otherwise. Nor are loops rewritten while profiling, so that every
+    /// instruction which runs is counted. A rewritten loop takes fewer steps, and is traced by
+    /// [`Machine::run_traced`] as the operations which replaced it, see [`Rewrite`].
     ///
     /// ```
     /// # use bft_interp::{Machine, TapeKind};
     /// # use bft_types::Program;
-    /// # use std::io;
-    /// let prog = Program::from_file("../programs/example.bf").unwrap();
-    /// let mut vm = Machine::<u8>::new(1000, TapeKind::FixedSize, &prog);
-    /// vm.run(io::stdin().lock(), io::stdout().lock());
+    /// # use std::{io, path::Path};
+    /// let prog = Program::try_new(Path::new("-"), "-[->+++<]").unwrap();
+    /// let mut vm = Machine::<u8>::new(2, TapeKind::FixedSize, &prog).with_multiply_loops(true);
+    /// vm.run(io::empty(), io::sink()).unwrap();
+    /// assert_eq!(vm.cell_at(1), Some(&253));
+    /// assert_eq!(vm.steps(), 4);
     /// ```
-    #[allow(unused_mut)]
-    pub fn run(
-        &mut self,
-        mut input: impl Read,
-        mut output: impl Write,
-    ) -> Result<(), InterpretError> {
-        while let Some(&instr) = self.program.instructions().get(self.ip) {
-            self.ip = match instr {
-                Instruction::Inc => self.move_head_right()?,
-                Instruction::Dec => self.move_head_left()?,
-                Instruction::Succ => self.increment_cell()?,
-                Instruction::Pred => self.decrement_cell()?,
-                Instruction::In => self.read_value(&mut input)?,
-                Instruction::Out => self.write_value(&mut output)?,
-                Instruction::Jz { dest } => self.jump_if_zero(dest)?,
-                Instruction::Jnz { pair_loc } => pair_loc,
-            };
-        }
+    pub fn with_multiply_loops(mut self, multiply_loops: bool) -> Self {
+        self.multiply_loops = multiply_loops;
+        self.decode();
+        self
+    }
 
-        Ok(())
+    /// Decode the program's instructions for the dispatch loop
+    fn decode(&mut self) {
+        let multiply = self.multiply_loops
+            && self.overflow == CellOverflow::Wrapping
+            && self.execution_counts.is_none();
+        self.ops = Op::decode(&self.program, multiply);
     }
 
-    /// Move the tape head one position to the left
+    /// Set how much of a cell the `.` instruction writes
     ///
-    /// If the tape head runs off the end TapeRunOffError is returned
-    fn move_head_left(&mut self) -> CommandResult {
-        match self.dp.checked_sub(1) {
-            Some(new_dp) => {
-                self.dp = new_dp;
-                Ok(self.ip + 1)
-            }
-            None => Err(InterpretError::TapeRunOffError {
-                ip_at_error: self.ip,
-            }),
-        }
+    /// `output_mode`: the mode to use, by default every byte of the cell is written
+    ///
+    /// ```
+    /// # use bft_interp::{Machine, OutputMode, TapeKind};
+    /// # use bft_types::Program;
+    /// # use std::{io, path::Path};
+    /// let prog = Program::try_new(Path::new("-"), "-.").unwrap();
+    /// let mut vm = Machine::<u32>::new(1000, TapeKind::FixedSize, &prog)
+    ///     .with_output_mode(OutputMode::LowByte);
+    /// let mut output = Vec::new();
+    /// vm.run(io::empty(), &mut output).unwrap();
+    /// assert_eq!(output, [0xFF]);
+    /// ```
+    pub fn with_output_mode(mut self, output_mode: OutputMode) -> Self {
+        self.output_mode = output_mode;
+        self
     }
 
-    /// Move the tape head one position to the right
+    /// Set how much of a cell the `,` instruction reads
     ///
-    /// If the tape head runs off the end TapeRunOffError is returned
-    fn move_head_right(&mut self) -> CommandResult {
-        self.dp += 1;
-        if self.dp >= self.tape.len() {
-            if self.tape_can_grow {
-                self.tape.reserve(1);
-                self.tape.resize(self.tape.capacity(), Cell::default());
-            } else {
-                // move head left doesn't affect the dp on error
-                // we should behave the same
-                self.dp -= 1;
-                return Err(InterpretError::TapeRunOffError {
-                    ip_at_error: self.ip,
-                });
-            }
-        }
+    /// `input_mode`: the mode to use, by default a single byte is read
+    ///
+    /// ```
+    /// # use bft_interp::{InputMode, Machine, TapeKind};
+    /// # use bft_types::Program;
+    /// # use std::{io, path::Path};
+    /// let prog = Program::try_new(Path::new("-"), ",").unwrap();
+    /// let mut vm = Machine::<u16>::new(1000, TapeKind::FixedSize, &prog)
+    ///     .with_input_mode(InputMode::FullCell);
+    /// vm.run(&[0x12, 0x34][..], io::sink()).unwrap();
+    /// assert_eq!(vm.cell_at(0), Some(&0x1234));
+    /// ```
+    pub fn with_input_mode(mut self, input_mode: InputMode) -> Self {
+        self.input_mode = input_mode;
+        self
+    }
 
-        Ok(self.ip + 1)
+    /// Limit how many cells a growable tape can grow to
+    ///
+    /// Moving the head beyond the limit fails with a TapeLimitExceeded error, rather than
+    /// growing the tape until memory runs out.
+    ///
+    /// `max_cells`: the most cells the tape can have
+    ///
+    /// ```
+    /// # use bft_interp::{InterpretError, Machine, TapeKind};
+    /// # use bft_types::Program;
+    /// # use std::{io, path::Path};
+    /// let prog = Program::try_new(Path::new("-"), "+[>+]").unwrap();
+    /// let growable = TapeKind::Growable { growth: Default::default() };
+    /// let mut vm = Machine::<u8>::new(1, growable, &prog).with_max_cells(100);
+    /// let err = vm.run(io::empty(), io::sink()).unwrap_err();
+    /// assert!(matches!(err, InterpretError::TapeLimitExceeded { limit: 100, .. }));
+    /// ```
+    pub fn with_max_cells(mut self, max_cells: usize) -> Self {
+        self.max_cells = Some(max_cells);
+        self
     }
 
-    /// Increment the value of the cell at the current data pointer
-    fn increment_cell(&mut self) -> CommandResult {
-        self.tape[self.dp].wrapping_inc();
-        Ok(self.ip + 1)
+    /// Limit the number of instructions [`Machine::run`] executes, as with
+    /// [`Machine::run_with_limit`]
+    ///
+    /// ```
+    /// # use bft_interp::{InterpretError, Machine, TapeKind};
+    /// # use bft_types::Program;
+    /// # use std::{io, path::Path};
+    /// let prog = Program::try_new(Path::new("-"), "+[]").unwrap();
+    /// let mut vm = Machine::<u8>::new(1, TapeKind::FixedSize, &prog).with_max_steps(100);
+    /// let err = vm.run(io::empty(), io::sink()).unwrap_err();
+    /// assert!(matches!(err, InterpretError::StepLimitExceeded { steps: 100, .. }));
+    /// ```
+    pub fn with_max_steps(mut self, max_steps: u64) -> Self {
+        self.max_steps = Some(max_steps);
+        self
     }
 
-    /// Decrement the value of the cell at the current data pointer
-    fn decrement_cell(&mut self) -> CommandResult {
-        self.tape[self.dp].wrapping_dec();
-        Ok(self.ip + 1)
+    /// Set whether to count the number of times each instruction is executed, which costs a
+    /// little speed
+    ///
+    /// The counts are used by [`Machine::execution_counts`] and [`Machine::coverage`]. Loops
+    /// aren't run as multiply-adds while profiling, see [`Machine::with_multiply_loops`].
+    pub fn with_profiling(mut self, profiling: bool) -> Self {
+        self.execution_counts = profiling.then(|| vec![0; self.program.instructions().len()]);
+        self.decode();
+        self
     }
 
-    /// Read a single u8 from a reader and assign it to the value of the tape
-    fn read_value(&mut self, reader: &mut impl Read) -> CommandResult {
-        let mut buf = [0u8];
-        if let Err(inner) = reader.read_exact(&mut buf) {
-            return Err(InterpretError::IoError {
+    /// Set whether to record every byte read by `,` and written by `.` in a transcript
+    ///
+    /// The transcript is retrieved with [`Machine::transcript`] and can be repeated by a later
+    /// run with [`Machine::with_replay`].
+    pub fn with_recording(mut self, recording: bool) -> Self {
+        self.transcript = recording.then(Vec::new);
+        self
+    }
+
+    /// Have the program repeat a recorded run, see [`Replay`]
+    ///
+    /// `,` reads the recorded input instead of the reader it is given. A read or write which
+    /// doesn't match the recording stops the program with [`InterpretError::IoError`], and
+    /// [`Replay::finish`] on [`Machine::replay`] checks nothing was left out once it stops.
+    pub fn with_replay(mut self, replay: Replay) -> Self {
+        self.replay = Some(replay);
+        self
+    }
+
+    /// Set whether to stop a program with [`InterpretError::SuspectedInfiniteLoop`] when a loop
+    /// repeats many times without changing anything
+    ///
+    /// A loop is only caught once its `]` keeps jumping back with the head on the same cell and
+    /// no cell written or IO performed since it last did, so a loop which is merely slow is
+    /// never caught.
+    ///
+    /// ```
+    /// # use bft_interp::{InterpretError, Machine, TapeKind};
+    /// # use bft_types::Program;
+    /// # use std::{io, path::Path};
+    /// let prog = Program::try_new(Path::new("-"), "+[]").unwrap();
+    /// let mut vm = Machine::<u8>::new(1, TapeKind::FixedSize, &prog).with_hang_detection(true);
+    /// let err = vm.run(io::empty(), io::sink()).unwrap_err();
+    /// assert!(matches!(err, InterpretError::SuspectedInfiniteLoop { ip_at_error: 2, .. }));
+    /// ```
+    pub fn with_hang_detection(mut self, detect_hangs: bool) -> Self {
+        self.hang_detector = detect_hangs.then(HangDetector::default);
+        self
+    }
+
+    /// Set where the `#` instruction writes its report of the machine's state
+    ///
+    /// `debug_sink`: where to write the report, by default stderr
+    ///
+    /// ```
+    /// # use bft_interp::{DebugSink, Machine, TapeKind};
+    /// # use bft_types::{ParseOptions, Program};
+    /// # use std::{io, path::Path};
+    /// let options = ParseOptions::default().with_debug_chars(true);
+    /// let prog = Program::try_new_with_options(Path::new("x.bf"), ">++#", options).unwrap();
+    /// let mut vm = Machine::<u8>::new(3, TapeKind::FixedSize, &prog)
+    ///     .with_debug_sink(DebugSink::Output);
+    /// let mut output = Vec::new();
+    /// vm.run(io::empty(), &mut output).unwrap();
+    /// assert_eq!(
+    ///     String::from_utf8(output).unwrap(),
+    ///     "x.bf:1:4: ip 3, dp 1, cells 0..3: 0 [2] 0\n"
+    /// );
+    /// ```
+    pub fn with_debug_sink(mut self, debug_sink: DebugSink) -> Self {
+        self.debug_sink = debug_sink;
+        self
+    }
+
+    /// Set how often [`Machine::run_cancellable`] checks whether it has been cancelled
+    ///
+    /// `interval`: the number of instructions executed between checks, values below one are
+    /// treated as one
+    pub fn with_cancel_check_interval(mut self, interval: u64) -> Self {
+        self.cancel_check_interval = interval.max(1);
+        self
+    }
+
+    /// Make [`Machine::run_async`] yield to the executor every so often, so that a program which
+    /// runs for a long time without IO doesn't starve other tasks
+    ///
+    /// `interval`: the number of instructions executed between yields, values below one are
+    /// treated as one
+    #[cfg(feature = "async")]
+    pub fn with_yield_interval(mut self, interval: u64) -> Self {
+        self.yield_interval = Some(interval.max(1));
+        self
+    }
+
+    /// Start the program with `cells` at the beginning of the tape and the head on the cell at
+    /// index `dp`, which [`Machine::reset`] also returns to
+    ///
+    /// A growable or sparse tape grows to hold every cell, any other tape must already be long
+    /// enough. The head must be on a cell of the tape.
+    ///
+    /// ```
+    /// # use bft_interp::{Machine, TapeKind};
+    /// # use bft_types::Program;
+    /// # use std::{io, path::Path};
+    /// // multiply the first two cells into the third, using the fourth as scratch space
+    /// let prog = Program::try_new(Path::new("-"), "[>[->+>+<<]>>[-<<+>>]<<<-]").unwrap();
+    /// let mut vm = Machine::<u8>::new(4, TapeKind::FixedSize, &prog)
+    ///     .with_initial_tape(&[6, 7], 0)
+    ///     .unwrap();
+    /// vm.run(io::empty(), io::sink()).unwrap();
+    /// assert_eq!(vm.cell_at(2), Some(&42));
+    /// ```
+    pub fn with_initial_tape(
+        mut self,
+        cells: &[Cell],
+        dp: usize,
+    ) -> Result<Self, MachineConfigError> {
+        let tape_size = self.tape.len();
+        if cells.len() > tape_size {
+            if self.growth.is_none() {
+                return Err(MachineConfigError::InitialTapeTooLong {
+                    cells: cells.len(),
+                    tape_size,
+                });
+            }
+            check_tape_size::<Cell>(cells.len())?;
+            self.tape_size = cells.len();
+        }
+        if dp >= self.tape_size {
+            return Err(MachineConfigError::HeadOffTape {
+                dp,
+                tape_size: self.tape_size,
+            });
+        }
+
+        self.initial_cells = cells.to_vec();
+        self.initial_dp = dp;
+        self.reset();
+        Ok(self)
+    }
+
+    /// Run the program to completion, or until the limit set by [`Machine::with_max_steps`]
+    ///
+    /// Like every limit on the number of steps, it counts each instruction executed since the
+    /// machine was created or reset, see [`Machine::steps`], so a run resumed after an error
+    /// carries on counting from where it stopped.
+    ///
+    /// `input`: where the `,` instruction reads bytes from
+    /// `output`: where the `.` instruction writes bytes to
+    ///
+    /// ```
+    /// # use bft_interp::{Machine, TapeKind};
+    /// # use bft_types::Program;
+    /// # use std::io;
+    /// let prog = Program::from_file("../programs/example.bf").unwrap();
+    /// let mut vm = Machine::<u8>::new(1000, TapeKind::FixedSize, &prog);
+    /// vm.run(io::stdin().lock(), io::stdout().lock());
+    /// ```
+    pub fn run(&mut self, input: impl Read, output: impl Write) -> Result<(), InterpretError> {
+        self.run_limited(input, output, self.max_steps)
+    }
+
+    /// Run the program until it completes or `max_steps` instructions have been executed
+    ///
+    /// If the program hasn't finished after executing `max_steps` instructions then a
+    /// StepLimitExceeded error is returned. Every instruction executed since the machine was
+    /// created or reset counts as a step, just as for [`Machine::with_max_steps`], which this
+    /// limit is used in place of.
+    ///
+    /// `input`: where the `,` instruction reads bytes from
+    /// `output`: where the `.` instruction writes bytes to
+    /// `max_steps`: the maximum number of instructions to execute
+    ///
+    /// ```
+    /// # use bft_interp::{InterpretError, Machine, TapeKind};
+    /// # use bft_types::Program;
+    /// # use std::{io, path::Path};
+    /// let prog = Program::try_new(Path::new("-"), "+[]").unwrap();
+    /// let mut vm = Machine::<u8>::new(1000, TapeKind::FixedSize, &prog);
+    /// let err = vm.run_with_limit(io::empty(), io::sink(), 1000).unwrap_err();
+    /// assert!(matches!(err, InterpretError::StepLimitExceeded { steps: 1000, .. }));
+    /// ```
+    pub fn run_with_limit(
+        &mut self,
+        input: impl Read,
+        output: impl Write,
+        max_steps: u64,
+    ) -> Result<(), InterpretError> {
+        self.run_limited(input, output, Some(max_steps))
+    }
+
+    /// Run the program until it completes or has executed `max_steps` instructions in all, if
+    /// limited
+    fn run_limited(
+        &mut self,
+        mut input: impl Read,
+        mut output: impl Write,
+        max_steps: Option<u64>,
+    ) -> Result<(), InterpretError> {
+        while self.ip < self.ops.len() {
+            self.check_step_limit(max_steps)?;
+            self.step(&mut input, &mut output)?;
+        }
+
+        Ok(())
+    }
+
+    /// Fail with a StepLimitExceeded error if `max_steps` instructions have already been
+    /// executed since the machine was created or reset
+    ///
+    /// Every run method checks its limit with this before each instruction, so they all count
+    /// steps the same way.
+    fn check_step_limit(&self, max_steps: Option<u64>) -> Result<(), InterpretError> {
+        match max_steps {
+            Some(max) if self.steps >= max => Err(InterpretError::StepLimitExceeded {
                 ip_at_error: self.ip,
-                inner,
+                site: self.error_site(),
+                steps: self.steps,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Run the program to completion, passing a record of each instruction executed to `tracer`
+    ///
+    /// Like [`Machine::run`], the program stops with a StepLimitExceeded error once it has
+    /// executed the limit set by [`Machine::with_max_steps`].
+    ///
+    /// `input`: where the `,` instruction reads bytes from
+    /// `output`: where the `.` instruction writes bytes to
+    /// `tracer`: called after each instruction is executed
+    ///
+    /// ```
+    /// # use bft_interp::{Machine, TapeKind};
+    /// # use bft_types::Program;
+    /// # use std::{io, path::Path};
+    /// let prog = Program::try_new(Path::new("-"), "+>").unwrap();
+    /// let mut vm = Machine::<u8>::new(10, TapeKind::FixedSize, &prog);
+    /// let mut trace = Vec::new();
+    /// vm.run_traced(io::empty(), io::sink(), |event| trace.push(event.to_string()))
+    ///     .unwrap();
+    /// assert_eq!(
+    ///     trace,
+    ///     ["ip=0 '+' dp=0 cell=0 -> ip=1 cell=1", "ip=1 '>' dp=0 cell=1 -> ip=2 cell=1"]
+    /// );
+    /// ```
+    pub fn run_traced(
+        &mut self,
+        mut input: impl Read,
+        mut output: impl Write,
+        mut tracer: impl FnMut(&TraceEvent<Cell>),
+    ) -> Result<(), InterpretError> {
+        while let Some(&op) = self.ops.get(self.ip) {
+            self.check_step_limit(self.max_steps)?;
+
+            let (ip, dp) = (self.ip, self.dp());
+            let before = self.cell().clone();
+
+            self.step(&mut input, &mut output)?;
+
+            let instruction = self.program.instructions()[ip];
+            tracer(&TraceEvent {
+                ip,
+                instruction,
+                location: self.program.source_map()[ip],
+                dp,
+                before,
+                after: self
+                    .cell_at(dp)
+                    .expect("traced cell is on the tape")
+                    .clone(),
+                next_ip: self.ip,
+                rewrite: Rewrite::of(op, instruction),
             });
+        }
+
+        Ok(())
+    }
+
+    /// Run the program to completion, awaiting its IO
+    ///
+    /// Behaves just like [`Machine::run`], other than yielding to the executor while waiting
+    /// for `input` or `output`, and every so often if [`Machine::with_yield_interval`] is set.
+    /// The output is flushed after each write.
+    ///
+    /// `input`: where the `,` instruction reads bytes from
+    /// `output`: where the `.` instruction writes bytes to
+    ///
+    /// ```
+    /// # use bft_interp::{Machine, TapeKind};
+    /// # use bft_types::Program;
+    /// # use std::path::Path;
+    /// # tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+    /// let prog = Program::try_new(Path::new("-"), ",+.").unwrap();
+    /// let mut vm = Machine::<u8>::new(1, TapeKind::FixedSize, &prog).with_yield_interval(100);
+    /// let mut output = Vec::new();
+    /// vm.run_async(&b"a"[..], &mut output).await.unwrap();
+    /// assert_eq!(output, b"b");
+    /// # });
+    /// ```
+    #[cfg(feature = "async")]
+    pub async fn run_async(
+        &mut self,
+        mut input: impl tokio::io::AsyncRead + Unpin,
+        mut output: impl tokio::io::AsyncWrite + Unpin,
+    ) -> Result<(), InterpretError> {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let mut until_yield = self.yield_interval.unwrap_or(0);
+        let mut read = Vec::new();
+        let mut written = Vec::new();
+        while let Some(&op) = self.ops.get(self.ip) {
+            if let Some(interval) = self.yield_interval {
+                if until_yield == 0 {
+                    tokio::task::yield_now().await;
+                    until_yield = interval;
+                }
+                until_yield -= 1;
+            }
+            self.check_step_limit(self.max_steps)?;
+
+            // the input is read up front, then the instruction reads it from memory
+            read.clear();
+            if op == Op::In && self.replay.is_none() {
+                read.resize(self.input_width(), 0);
+                let mut filled = 0;
+                while filled < read.len() {
+                    match input.read(&mut read[filled..]).await {
+                        Ok(0) => break,
+                        Ok(n) => filled += n,
+                        Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                        Err(inner) => {
+                            return Err(InterpretError::IoError {
+                                ip_at_error: self.ip,
+                                site: self.error_site(),
+                                direction: IoDirection::Input,
+                                inner,
+                            })
+                        }
+                    }
+                }
+                read.truncate(filled);
+            }
+
+            let undo = matches!(op, Op::Out | Op::Debug).then(|| self.undo_point());
+            self.step(&mut &read[..], &mut written)?;
+            if let Some(undo) = undo.filter(|_| !written.is_empty()) {
+                if let Err(inner) = output.write_all(&written).await {
+                    // like a failed write when running synchronously, the instruction isn't
+                    // executed
+                    self.undo_step(undo);
+                    return Err(InterpretError::IoError {
+                        ip_at_error: self.ip,
+                        site: self.error_site(),
+                        direction: IoDirection::Output,
+                        inner,
+                    });
+                }
+                output.flush().await.ok();
+                written.clear();
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Capture everything a step writing output changes, see [`Machine::undo_step`]
+    #[cfg(feature = "async")]
+    fn undo_point(&self) -> UndoPoint {
+        UndoPoint {
+            ip: self.ip,
+            steps: self.steps,
+            transcript_len: self.transcript.as_ref().map(Vec::len),
+            replay_position: self.replay.as_ref().map(Replay::position),
+            hang_detector: self.hang_detector.clone(),
+        }
+    }
+
+    /// Undo the single step taken since `undo` was captured, leaving the machine as though the
+    /// instruction was never executed
+    #[cfg(feature = "async")]
+    fn undo_step(&mut self, undo: UndoPoint) {
+        self.ip = undo.ip;
+        self.steps = undo.steps;
+        if let Some(counts) = &mut self.execution_counts {
+            counts[undo.ip] -= 1;
+        }
+        if let (Some(transcript), Some(len)) = (&mut self.transcript, undo.transcript_len) {
+            transcript.truncate(len);
+        }
+        if let (Some(replay), Some(position)) = (&mut self.replay, undo.replay_position) {
+            replay.rewind_to(position);
+        }
+        self.hang_detector = undo.hang_detector;
+    }
+
+    /// Run the program until it completes or `cancel` is set
+    ///
+    /// The flag is only checked every so often, see [`Machine::with_cancel_check_interval`],
+    /// so a few more instructions may be executed after it is set. Once noticed a Cancelled
+    /// error is returned and the machine can be resumed by running it again. Like
+    /// [`Machine::run`], the program also stops once it reaches the limit set by
+    /// [`Machine::with_max_steps`].
+    ///
+    /// `input`: where the `,` instruction reads bytes from
+    /// `output`: where the `.` instruction writes bytes to
+    /// `cancel`: set to stop the program
+    ///
+    /// ```
+    /// # use bft_interp::{InterpretError, Machine, TapeKind};
+    /// # use bft_types::Program;
+    /// # use std::{io, path::Path, sync::atomic::AtomicBool};
+    /// let prog = Program::try_new(Path::new("-"), "+[]").unwrap();
+    /// let mut vm = Machine::<u8>::new(1000, TapeKind::FixedSize, &prog);
+    /// let cancel = AtomicBool::new(true);
+    /// let err = vm.run_cancellable(io::empty(), io::sink(), &cancel).unwrap_err();
+    /// assert!(matches!(err, InterpretError::Cancelled { .. }));
+    /// ```
+    pub fn run_cancellable(
+        &mut self,
+        mut input: impl Read,
+        mut output: impl Write,
+        cancel: &AtomicBool,
+    ) -> Result<(), InterpretError> {
+        let mut until_check = 0;
+        while self.ip < self.ops.len() {
+            if until_check == 0 {
+                if cancel.load(Ordering::Relaxed) {
+                    return Err(InterpretError::Cancelled {
+                        ip_at_error: self.ip,
+                        site: self.error_site(),
+                    });
+                }
+                until_check = self.cancel_check_interval;
+            }
+
+            self.check_step_limit(self.max_steps)?;
+            self.step(&mut input, &mut output)?;
+            until_check -= 1;
+        }
+
+        Ok(())
+    }
+
+    /// Return the virtual machine to the state it was created in, ready to run the program again
+    ///
+    /// The tape is zeroed and shrunk back to its original size without giving up its memory,
+    /// and every breakpoint and watchpoint is removed. Anything set with the `with_*` methods is
+    /// kept. Running the program after a reset behaves exactly as it would on a new machine.
+    ///
+    /// ```
+    /// # use bft_interp::{Machine, TapeKind};
+    /// # use bft_types::Program;
+    /// # use std::{io, path::Path};
+    /// let prog = Program::try_new(Path::new("-"), "+++.").unwrap();
+    /// let mut vm = Machine::<u8>::new(10, TapeKind::FixedSize, &prog);
+    /// let (mut first, mut second) = (Vec::new(), Vec::new());
+    /// vm.run(io::empty(), &mut first).unwrap();
+    /// vm.reset();
+    /// vm.run(io::empty(), &mut second).unwrap();
+    /// assert_eq!(first, second);
+    /// ```
+    pub fn reset(&mut self) {
+        self.tape.reset(self.tape_size);
+        for (i, cell) in self.initial_cells.iter().enumerate() {
+            *self.tape.cell_mut(i) = cell.clone();
+        }
+        self.origin = 0;
+        self.dp = self.initial_dp;
+        self.reached = (self.dp(), self.dp());
+        self.ip = 0;
+        self.steps = 0;
+        if let Some(counts) = &mut self.execution_counts {
+            counts.clear();
+            counts.resize(self.program.instructions().len(), 0);
+        }
+        if let Some(transcript) = &mut self.transcript {
+            transcript.clear();
+        }
+        if let Some(replay) = &mut self.replay {
+            replay.rewind();
+        }
+        if let Some(detector) = &mut self.hang_detector {
+            *detector = HangDetector::default();
+        }
+        self.breakpoints.clear();
+        self.watchpoints.clear();
+        self.paused_at = None;
+    }
+
+    /// Reset the virtual machine as with [`Machine::reset`], then have it run `program`
+    ///
+    /// `program`: the program to run from now on
+    pub fn reset_with_program(&mut self, program: &'a Program) {
+        self.program = Cow::Borrowed(program);
+        self.decode();
+        self.reset();
+    }
+
+    /// Have the virtual machine run `program` from its first instruction, keeping the tape and
+    /// the position of the head
+    ///
+    /// Breakpoints and profiling counts refer to the old program so are cleared, anything set
+    /// with the `with_*` methods is kept.
+    ///
+    /// ```
+    /// # use bft_interp::{Machine, TapeKind};
+    /// # use bft_types::Program;
+    /// # use std::{io, path::Path};
+    /// let first = Program::try_new(Path::new("-"), "+++>").unwrap();
+    /// let mut vm = Machine::<u8>::new(10, TapeKind::FixedSize, &first);
+    /// vm.run(io::empty(), io::sink()).unwrap();
+    ///
+    /// vm.continue_with(Program::try_new(Path::new("-"), "++<+").unwrap());
+    /// vm.run(io::empty(), io::sink()).unwrap();
+    /// assert_eq!((vm.cell_at(0), vm.cell_at(1), vm.dp()), (Some(&4), Some(&2), 0));
+    /// ```
+    pub fn continue_with(&mut self, program: Program) {
+        self.program = Cow::Owned(program);
+        self.decode();
+        self.ip = 0;
+        if let Some(counts) = &mut self.execution_counts {
+            counts.clear();
+            counts.resize(self.program.instructions().len(), 0);
+        }
+        if let Some(detector) = &mut self.hang_detector {
+            *detector = HangDetector::default();
+        }
+        self.breakpoints.clear();
+        self.paused_at = None;
+    }
+
+    /// Pause [`Machine::run_until_break`] before executing the instruction at `ip`
+    pub fn add_breakpoint(&mut self, ip: usize) {
+        if let Err(idx) = self.breakpoints.binary_search(&ip) {
+            self.breakpoints.insert(idx, ip);
+        }
+    }
+
+    /// Stop pausing before executing the instruction at `ip`, returning whether there was a
+    /// breakpoint there
+    pub fn remove_breakpoint(&mut self, ip: usize) -> bool {
+        match self.breakpoints.binary_search(&ip) {
+            Ok(idx) => {
+                self.breakpoints.remove(idx);
+                true
+            }
+            Err(_) => false,
+        }
+    }
+
+    /// Pause [`Machine::run_until_break`] whenever the value of the cell at `cell` changes
+    pub fn add_watchpoint(&mut self, cell: isize) {
+        if let Err(idx) = self.watchpoints.binary_search(&cell) {
+            self.watchpoints.insert(idx, cell);
+        }
+    }
+
+    /// Stop pausing when the value of the cell at `cell` changes, returning whether it was
+    /// being watched
+    pub fn remove_watchpoint(&mut self, cell: isize) -> bool {
+        match self.watchpoints.binary_search(&cell) {
+            Ok(idx) => {
+                self.watchpoints.remove(idx);
+                true
+            }
+            Err(_) => false,
+        }
+    }
+
+    /// Run the program until it completes, reaches a breakpoint or changes a watched cell
+    ///
+    /// Calling this again after a breakpoint or watchpoint resumes execution from where it
+    /// paused, without hitting the same breakpoint again straight away. The limit set by
+    /// [`Machine::with_max_steps`] applies to every instruction executed since the machine was
+    /// created or reset, however many calls they were spread across.
+    ///
+    /// `input`: where the `,` instruction reads bytes from
+    /// `output`: where the `.` instruction writes bytes to
+    ///
+    /// ```
+    /// # use bft_interp::{Machine, RunOutcome, TapeKind};
+    /// # use bft_types::Program;
+    /// # use std::{io, path::Path};
+    /// let prog = Program::try_new(Path::new("-"), "++>+").unwrap();
+    /// let mut vm = Machine::<u8>::new(10, TapeKind::FixedSize, &prog);
+    /// vm.add_breakpoint(1);
+    /// vm.add_watchpoint(1);
+    ///
+    /// let (mut input, mut output) = (io::empty(), io::sink());
+    /// let mut run = || vm.run_until_break(&mut input, &mut output).unwrap();
+    /// assert_eq!(run(), RunOutcome::Breakpoint { ip: 1 });
+    /// assert_eq!(run(), RunOutcome::Watchpoint { cell: 1, old: 0, new: 1 });
+    /// assert_eq!(run(), RunOutcome::Halted);
+    /// ```
+    pub fn run_until_break(
+        &mut self,
+        mut input: impl Read,
+        mut output: impl Write,
+    ) -> Result<RunOutcome<Cell>, InterpretError> {
+        let mut resuming = self.paused_at.take() == Some(self.ip);
+        while self.ip < self.ops.len() {
+            if !resuming && self.breakpoints.binary_search(&self.ip).is_ok() {
+                self.paused_at = Some(self.ip);
+                return Ok(RunOutcome::Breakpoint { ip: self.ip });
+            }
+            resuming = false;
+            self.check_step_limit(self.max_steps)?;
+
+            // only the cell under the head can be changed by an instruction
+            let watched = self
+                .watchpoints
+                .binary_search(&self.dp())
+                .is_ok()
+                .then(|| (self.dp(), self.cell().clone()));
+
+            self.step(&mut input, &mut output)?;
+
+            if let Some((cell, old)) = watched {
+                let new = self
+                    .cell_at(cell)
+                    .expect("watched cell is on the tape")
+                    .clone();
+                if new != old {
+                    return Ok(RunOutcome::Watchpoint { cell, old, new });
+                }
+            }
+        }
+
+        Ok(RunOutcome::Halted)
+    }
+
+    /// Execute exactly one instruction
+    ///
+    /// Returns whether the program halted, if the program has already halted then no
+    /// instruction is executed.
+    ///
+    /// `input`: where the `,` instruction reads bytes from
+    /// `output`: where the `.` instruction writes bytes to
+    ///
+    /// ```
+    /// # use bft_interp::{Machine, StepOutcome, TapeKind};
+    /// # use bft_types::Program;
+    /// # use std::io;
+    /// let prog = Program::from_file("../programs/example.bf").unwrap();
+    /// let mut vm = Machine::<u8>::new(1000, TapeKind::FixedSize, &prog);
+    /// let outcome = vm.step(&mut io::empty(), &mut io::sink()).unwrap();
+    /// assert_eq!(outcome, StepOutcome::Running);
+    /// assert_eq!(vm.ip(), 1);
+    /// assert_eq!(vm.cell_at(0), Some(&1));
+    /// ```
+    pub fn step(
+        &mut self,
+        input: &mut impl Read,
+        output: &mut impl Write,
+    ) -> Result<StepOutcome, InterpretError> {
+        let Some(&op) = self.ops.get(self.ip) else {
+            return Ok(StepOutcome::Halted);
+        };
+        let ip = self.ip;
+
+        let next = match op {
+            Op::Right(n) => self.move_head_right(n as usize)?,
+            Op::Left(n) => self.move_head_left(n as usize)?,
+            Op::Succ => self.increment_cell()?,
+            Op::Pred => self.decrement_cell()?,
+            Op::Add(n) => self.add_to_cell(n)?,
+            Op::Sub(n) => self.subtract_from_cell(n)?,
+            Op::SetZero => self.clear_cell()?,
+            Op::In => self.read_value(input)?,
+            Op::Out => self.write_value(output)?,
+            Op::Jz(dest) => self.jump_if_zero(dest as usize)?,
+            Op::Jnz(dest) => self.jump_if_not_zero(dest as usize)?,
+            Op::Debug => self.write_debug_report(output)?,
+            Op::MulAdd { offset, factor } => self.multiply_add(offset, factor)?,
         };
+        if let Some(detector) = &mut self.hang_detector {
+            if detector.is_hung(op, ip, next, self.dp) {
+                return Err(InterpretError::SuspectedInfiniteLoop {
+                    ip_at_error: ip,
+                    site: self.error_site(),
+                });
+            }
+        }
+        self.ip = next;
+        self.steps += 1;
+        if let Some(counts) = &mut self.execution_counts {
+            counts[ip] += 1;
+        }
+
+        if self.ip < self.ops.len() {
+            Ok(StepOutcome::Running)
+        } else {
+            Ok(StepOutcome::Halted)
+        }
+    }
+
+    /// Every byte read or written by the program so far, in order, if enabled with
+    /// [`Machine::with_recording`]
+    pub fn transcript(&self) -> Option<&[IoEvent]> {
+        self.transcript.as_deref()
+    }
+
+    /// The recorded run being repeated, if set with [`Machine::with_replay`]
+    pub fn replay(&self) -> Option<&Replay> {
+        self.replay.as_ref()
+    }
+
+    /// The index of the next instruction to be executed
+    pub fn ip(&self) -> usize {
+        self.ip
+    }
+
+    /// The number of instructions executed since the machine was created or last reset
+    ///
+    /// ```
+    /// # use bft_interp::{Machine, TapeKind};
+    /// # use bft_types::Program;
+    /// # use std::{io, path::Path};
+    /// let prog = Program::try_new(Path::new("-"), "++[-]").unwrap();
+    /// let mut vm = Machine::<u8>::new(1, TapeKind::FixedSize, &prog);
+    /// vm.run(io::empty(), io::sink()).unwrap();
+    /// assert_eq!(vm.steps(), 7);
+    /// ```
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
+
+    /// The number of times each instruction has been executed, indexed by instruction pointer,
+    /// if enabled with [`Machine::with_profiling`]
+    pub fn execution_counts(&self) -> Option<&[u64]> {
+        self.execution_counts.as_deref()
+    }
+
+    /// The instructions which haven't been executed yet, if execution is being counted with
+    /// [`Machine::with_profiling`]
+    ///
+    /// A loop skipped because its cell was zero leaves its whole body unexecuted.
+    ///
+    /// ```
+    /// # use bft_interp::{Machine, TapeKind};
+    /// # use bft_types::Program;
+    /// # use std::{io, path::Path};
+    /// let prog = Program::try_new(Path::new("dead.bf"), "[->+<]+.").unwrap();
+    /// let mut vm = Machine::<u8>::new(2, TapeKind::FixedSize, &prog).with_profiling(true);
+    /// vm.run(io::empty(), io::sink()).unwrap();
+    /// let report = vm.coverage().unwrap();
+    /// assert_eq!(report.unexecuted.len(), 5);
+    /// assert_eq!(
+    ///     report.to_string(),
+    ///     "coverage: 3 of 8 instructions executed\n  dead.bf:1:2: ->+<]"
+    /// );
+    /// ```
+    pub fn coverage(&self) -> Option<CoverageReport> {
+        let counts = self.execution_counts.as_ref()?;
+        let unexecuted = self
+            .program
+            .instructions_with_locations()
+            .zip(counts)
+            .enumerate()
+            .filter(|(_, (_, &count))| count == 0)
+            .map(|(ip, ((instruction, location), _))| UnexecutedInstruction {
+                ip,
+                location,
+                instruction,
+            })
+            .collect();
+        Some(CoverageReport {
+            filename: self.program.filename().to_owned(),
+            instructions: counts.len(),
+            unexecuted,
+        })
+    }
+
+    /// Statistics about the run so far, which are kept up to date even when it stops with an error
+    ///
+    /// ```
+    /// # use bft_interp::{Machine, TapeKind};
+    /// # use bft_types::Program;
+    /// # use std::{io, path::Path};
+    /// let prog = Program::try_new(Path::new("-"), ">>>+[<+]").unwrap();
+    /// let mut vm = Machine::<u8>::new(1, TapeKind::Growable { growth: Default::default() }, &prog);
+    /// assert!(vm.run(io::empty(), io::sink()).is_err());
+    /// let stats = vm.stats();
+    /// assert_eq!(stats.steps, 14);
+    /// assert_eq!(stats.peak_tape_len, 4);
+    /// assert_eq!(stats.dp, 0);
+    /// ```
+    pub fn stats(&self) -> RunStats {
+        RunStats {
+            steps: self.steps,
+            peak_tape_len: (self.reached.1 - self.reached.0) as usize + 1,
+            dp: self.dp(),
+        }
+    }
+
+    /// The index of the cell under the head of the tape, relative to the cell it started on
+    pub fn dp(&self) -> isize {
+        self.dp as isize - self.origin as isize
+    }
+
+    /// The value of the cell at `index`, if the tape extends that far
+    pub fn cell_at(&self, index: isize) -> Option<&Cell> {
+        self.tape.cell(self.origin.checked_add_signed(index)?)
+    }
+
+    /// The contents of the virtual machine's memory, the first cell has the index given by
+    /// [`Machine::tape_start`]
+    pub fn tape(&self) -> &T {
+        &self.tape
+    }
+
+    /// The index of the first cell of the tape, which is negative once the tape has grown to the
+    /// left of the cell the head started on
+    pub fn tape_start(&self) -> isize {
+        -(self.origin as isize)
+    }
+
+    /// Capture the state of the virtual machine so that it can be restored later
+    ///
+    /// ```
+    /// # use bft_interp::{Machine, TapeKind};
+    /// # use bft_types::Program;
+    /// # use std::{io, path::Path};
+    /// let prog = Program::try_new(Path::new("-"), "+++").unwrap();
+    /// let mut vm = Machine::<u8>::new(10, TapeKind::FixedSize, &prog);
+    /// let start = vm.snapshot();
+    /// vm.run(io::empty(), io::sink()).unwrap();
+    /// assert_eq!(vm.cell_at(0), Some(&3));
+    ///
+    /// vm.restore(&start).unwrap();
+    /// assert_eq!((vm.ip(), vm.cell_at(0)), (0, Some(&0)));
+    /// ```
+    pub fn snapshot(&self) -> MachineState<Cell> {
+        MachineState {
+            tape: self.tape.to_cells(),
+            origin: self.origin,
+            dp: self.dp,
+            ip: self.ip,
+            steps: self.steps,
+            program_hash: program_hash(&self.program),
+        }
+    }
+
+    /// Return the virtual machine to a state captured by [`Machine::snapshot`]
+    ///
+    /// The step count goes back to what it was when the state was captured, and the hang
+    /// detector starts afresh. The profile and the transcript are left alone, as they record
+    /// everything the machine has really executed.
+    ///
+    /// Fails without changing anything if the state was captured from a machine running a
+    /// different program, or doesn't hold together: the head and the cell it started on must
+    /// be on the tape, and the next instruction at most one past the end of the program.
+    pub fn restore(&mut self, state: &MachineState<Cell>) -> Result<(), RestoreError> {
+        let tape_len = state.tape.len();
+        if state.program_hash != program_hash(&self.program) {
+            return Err(RestoreError::DifferentProgram);
+        } else if state.dp >= tape_len {
+            return Err(RestoreError::HeadOffTape {
+                dp: state.dp,
+                tape_len,
+            });
+        } else if state.origin >= tape_len {
+            return Err(RestoreError::OriginOffTape {
+                origin: state.origin,
+                tape_len,
+            });
+        } else if state.ip > self.ops.len() {
+            return Err(RestoreError::InstructionOutOfRange {
+                ip: state.ip,
+                instructions: self.ops.len(),
+            });
+        }
+
+        self.tape = T::from_cells(state.tape.clone());
+        self.origin = state.origin;
+        self.dp = state.dp;
+        self.note_head();
+        self.ip = state.ip;
+        self.steps = state.steps;
+        if let Some(detector) = &mut self.hang_detector {
+            *detector = HangDetector::default();
+        }
+        self.paused_at = None;
+        Ok(())
+    }
+
+    /// Describe the next instruction and the cells within `window` of the head
+    ///
+    /// Each cell is shown with its index relative to the cell the head started on and its
+    /// value, followed by the character it holds if that is printable ASCII. The cell under the
+    /// head is surrounded by stars and `…` marks where the tape carries on beyond the window.
+    ///
+    /// `window`: the number of cells to show either side of the head
+    ///
+    /// ```
+    /// # use bft_interp::{Machine, TapeKind};
+    /// # use bft_types::Program;
+    /// # use std::{io, path::Path};
+    /// let prog = Program::try_new(Path::new("x.bf"), "++++++++[>++++++++<-]>+>").unwrap();
+    /// let mut vm = Machine::<u8>::new(10, TapeKind::FixedSize, &prog);
+    /// vm.run(io::empty(), io::sink()).unwrap();
+    /// assert_eq!(
+    ///     vm.visualize(2),
+    ///     "ip 24 halted, dp 2: [0]=0 [1]=65 'A' *[2]=0* [3]=0 [4]=0 …"
+    /// );
+    /// ```
+    pub fn visualize(&self, window: usize) -> String {
+        let instruction = match self.program.instructions().get(self.ip) {
+            Some(_) => {
+                let site = self.error_site();
+                format!("at {site} '{}'", site.instruction.as_char())
+            }
+            None => "halted".to_owned(),
+        };
+        let start = self.dp.saturating_sub(window);
+        let end = self
+            .dp
+            .saturating_add(window)
+            .saturating_add(1)
+            .min(self.tape.len());
+        let mut cells: Vec<String> = (start..end)
+            .map(|i| {
+                let cell = self.tape.cell(i).expect("window is on the tape");
+                let index = i as isize - self.origin as isize;
+                let mut text = format!("[{index}]={}", cell.to_decimal());
+                if let Some(c) = printable_char(cell) {
+                    text.push_str(&format!(" '{c}'"));
+                }
+                if i == self.dp {
+                    format!("*{text}*")
+                } else {
+                    text
+                }
+            })
+            .collect();
+        if start > 0 {
+            cells.insert(0, "…".to_owned());
+        }
+        if end < self.tape.len() {
+            cells.push("…".to_owned());
+        }
+        format!(
+            "ip {} {instruction}, dp {}: {}",
+            self.ip,
+            self.dp(),
+            cells.join(" ")
+        )
+    }
+
+    /// Write the contents of the tape to `writer`
+    ///
+    /// Trailing zero cells beyond the data pointer are not written, nor are leading zero cells
+    /// the tape grew to the left of both the data pointer and the starting cell.
+    ///
+    /// `writer`: where to write the dump to
+    /// `format`: how the dump should be formatted
+    ///
+    /// ```
+    /// # use bft_interp::{DumpFormat, Machine, TapeKind};
+    /// # use bft_types::Program;
+    /// # use std::{io, path::Path};
+    /// let prog = Program::try_new(Path::new("-"), "+>++>+++<").unwrap();
+    /// let mut vm = Machine::<u8>::new(1000, TapeKind::FixedSize, &prog);
+    /// vm.run(io::empty(), io::sink()).unwrap();
+    ///
+    /// let mut dump = Vec::new();
+    /// vm.dump_tape(&mut dump, DumpFormat::Hex).unwrap();
+    /// assert_eq!(dump, b"00000000:  01 [02] 03 \n");
+    /// ```
+    pub fn dump_tape(&self, mut writer: impl Write, format: DumpFormat) -> io::Result<()> {
+        let span = self.tape.nonzero_span();
+        let end = span
+            .map_or(0, |(_, last)| last + 1)
+            .max(self.dp + 1)
+            .min(self.tape.len());
+        let begin = span
+            .map_or(usize::MAX, |(first, _)| first)
+            .min(self.dp)
+            .min(self.origin);
+        let cells: Vec<&Cell> = (begin..end).filter_map(|i| self.tape.cell(i)).collect();
+
+        match format {
+            DumpFormat::Raw => {
+                for cell in cells {
+                    writer.write_all(&cell.as_bytes())?;
+                }
+            }
+            DumpFormat::Hex => {
+                for (line_no, line) in cells.chunks(DUMP_CELLS_PER_LINE).enumerate() {
+                    let start = begin + line_no * DUMP_CELLS_PER_LINE;
+                    // offsets are relative to the cell the head started on
+                    let offset = start as isize - self.origin as isize;
+                    if offset < 0 {
+                        write!(writer, "-{:07x}: ", offset.unsigned_abs())?;
+                    } else {
+                        write!(writer, "{offset:08x}: ")?;
+                    }
+                    for (i, cell) in line.iter().enumerate() {
+                        let hex: String =
+                            cell.as_bytes().iter().map(|b| format!("{b:02x}")).collect();
+                        if start + i == self.dp {
+                            write!(writer, "[{hex}]")?;
+                        } else {
+                            write!(writer, " {hex} ")?;
+                        }
+                    }
+                    writeln!(writer)?;
+                }
+            }
+        }
+
+        writer.flush()
+    }
+
+    /// The cell under the head of the tape
+    fn cell(&self) -> &Cell {
+        self.tape
+            .cell(self.dp)
+            .expect("the head is always on the tape")
+    }
+
+    /// Describe where in the program the instruction at the instruction pointer came from
+    fn error_site(&self) -> ErrorSite {
+        ErrorSite {
+            filename: self.program.filename().to_owned(),
+            location: self.program.source_map()[self.ip],
+            instruction: self.program.instructions()[self.ip],
+        }
+    }
+
+    /// The length to grow the tape to so that it has at least `needed` cells, None if that
+    /// would exceed the limit on the number of cells
+    fn grown_len(&self, growth: GrowthStrategy, needed: usize) -> Option<usize> {
+        if self.max_cells.is_some_and(|max| needed > max) {
+            return None;
+        }
+
+        let mut new_len = self.tape.len();
+        while new_len < needed {
+            new_len = growth.grown_len(new_len);
+        }
+
+        Some(self.max_cells.map_or(new_len, |max| new_len.min(max)))
+    }
+
+    /// The error returned when the head moves beyond the limit on the number of cells
+    fn tape_limit_error(&self) -> InterpretError {
+        InterpretError::TapeLimitExceeded {
+            ip_at_error: self.ip,
+            site: self.error_site(),
+            limit: self.max_cells.unwrap_or(usize::MAX),
+            cell_bytes: std::mem::size_of::<Cell>(),
+        }
+    }
+
+    /// Move the tape head `n` positions to the left
+    ///
+    /// If the tape head runs off the end TapeRunOffError is returned
+    fn move_head_left(&mut self, n: usize) -> CommandResult {
+        if n > self.dp && self.grow_left {
+            if let Some(growth) = self.growth {
+                let needed = self.tape.len().saturating_add(n - self.dp);
+                let new_len = self
+                    .grown_len(growth, needed)
+                    .ok_or_else(|| self.tape_limit_error())?;
+
+                // shift everything right, keeping the logical position of every cell the same
+                let added = new_len - self.tape.len();
+                self.tape.grow_front(added);
+                self.origin += added;
+                self.dp += added;
+            }
+        }
+
+        match self.dp.checked_sub(n) {
+            Some(new_dp) => {
+                self.dp = new_dp;
+                self.note_head();
+                Ok(self.ip + 1)
+            }
+            None if self.wrapping && !self.tape.is_empty() => {
+                let len = self.tape.len();
+                self.dp = (self.dp + len - n % len) % len;
+                self.note_head();
+                Ok(self.ip + 1)
+            }
+            None => Err(InterpretError::TapeRunOffError {
+                ip_at_error: self.ip,
+                site: self.error_site(),
+            }),
+        }
+    }
+
+    /// Move the tape head `n` positions to the right
+    ///
+    /// If the tape head runs off the end TapeRunOffError is returned
+    fn move_head_right(&mut self, n: usize) -> CommandResult {
+        let new_dp = self.dp.saturating_add(n);
+        if new_dp >= self.tape.len() {
+            if let Some(growth) = self.growth {
+                let new_len = self
+                    .grown_len(growth, new_dp.saturating_add(1))
+                    .ok_or_else(|| self.tape_limit_error())?;
+                self.tape.grow_to(new_len);
+            } else if self.wrapping && !self.tape.is_empty() {
+                let len = self.tape.len();
+                self.dp = (self.dp + n % len) % len;
+                self.note_head();
+                return Ok(self.ip + 1);
+            } else {
+                // move head left doesn't affect the dp on error
+                // we should behave the same
+                return Err(InterpretError::TapeRunOffError {
+                    ip_at_error: self.ip,
+                    site: self.error_site(),
+                });
+            }
+        }
+
+        self.dp = new_dp;
+        self.note_head();
+        Ok(self.ip + 1)
+    }
+
+    /// Widen the range of cells the head has reached to include the one it is on
+    fn note_head(&mut self) {
+        let dp = self.dp();
+        self.reached = (self.reached.0.min(dp), self.reached.1.max(dp));
+    }
+
+    /// Increment the value of the cell at the current data pointer
+    fn increment_cell(&mut self) -> CommandResult {
+        if self.overflow == CellOverflow::Wrapping {
+            self.tape.cell_mut(self.dp).wrapping_inc();
+            Ok(self.ip + 1)
+        } else {
+            self.add_to_cell(1)
+        }
+    }
+
+    /// Decrement the value of the cell at the current data pointer
+    fn decrement_cell(&mut self) -> CommandResult {
+        if self.overflow == CellOverflow::Wrapping {
+            self.tape.cell_mut(self.dp).wrapping_dec();
+            Ok(self.ip + 1)
+        } else {
+            self.subtract_from_cell(1)
+        }
+    }
+
+    /// Add `n` to the value of the cell at the current data pointer
+    fn add_to_cell(&mut self, n: u8) -> CommandResult {
+        let cell = self.tape.cell_mut(self.dp);
+        let in_range = match self.overflow {
+            CellOverflow::Wrapping => {
+                cell.wrapping_add_by(n);
+                true
+            }
+            CellOverflow::Error => cell.checked_add_by(n),
+            CellOverflow::Saturating => {
+                cell.saturating_add_by(n);
+                true
+            }
+        };
+        self.overflow_check(in_range)
+    }
+
+    /// Subtract `n` from the value of the cell at the current data pointer
+    fn subtract_from_cell(&mut self, n: u8) -> CommandResult {
+        let cell = self.tape.cell_mut(self.dp);
+        let in_range = match self.overflow {
+            CellOverflow::Wrapping => {
+                cell.wrapping_sub_by(n);
+                true
+            }
+            CellOverflow::Error => cell.checked_sub_by(n),
+            CellOverflow::Saturating => {
+                cell.saturating_sub_by(n);
+                true
+            }
+        };
+        self.overflow_check(in_range)
+    }
+
+    /// Move on to the next instruction, unless the current one would have overflowed its cell
+    fn overflow_check(&self, in_range: bool) -> CommandResult {
+        if in_range {
+            Ok(self.ip + 1)
+        } else {
+            Err(InterpretError::CellOverflow {
+                ip_at_error: self.ip,
+                site: self.error_site(),
+                dp: self.dp(),
+            })
+        }
+    }
+
+    /// Add the current cell multiplied by `factor` to the cell `offset` cells away, unless the
+    /// current cell is zero
+    ///
+    /// The first multiply-add of a loop checks the head can reach every cell the loop does
+    /// before changing anything. If it can't, the loop is run as written so that it stops with
+    /// the same error, and leaves the same tape, as it would have without being rewritten.
+    fn multiply_add(&mut self, offset: i32, factor: i16) -> CommandResult {
+        let counter = self.cell().clone();
+        if counter.is_zero() {
+            return Ok(self.ip + 1);
+        }
+        if let Instruction::Jz { dest } = self.program.instructions()[self.ip] {
+            if !self.loop_reachable() {
+                return self.run_loop_body(dest);
+            }
+        }
+
+        let distance = offset.unsigned_abs() as usize;
+        if offset < 0 {
+            self.move_head_left(distance)?;
+            self.tape
+                .cell_mut(self.dp)
+                .wrapping_add_product(&counter, factor);
+            self.move_head_right(distance)?;
+        } else {
+            self.move_head_right(distance)?;
+            self.tape
+                .cell_mut(self.dp)
+                .wrapping_add_product(&counter, factor);
+            self.move_head_left(distance)?;
+        }
+        Ok(self.ip + 1)
+    }
+
+    /// Can the head reach the furthest cells either side that the multiply-adds starting at the
+    /// instruction pointer add to, growing the tape if it needs to
+    fn loop_reachable(&mut self) -> bool {
+        let (mut lowest, mut highest) = (0, 0);
+        for op in &self.ops[self.ip..] {
+            let Op::MulAdd { offset, .. } = *op else {
+                break;
+            };
+            lowest = lowest.min(offset);
+            highest = highest.max(offset);
+        }
+
+        // growing the tape to the left moves the cell the head is on
+        let dp = self.dp();
+        let reachable = self.move_head_left(lowest.unsigned_abs() as usize).is_ok() && {
+            self.dp = self.origin.wrapping_add_signed(dp);
+            self.move_head_right(highest.unsigned_abs() as usize)
+                .is_ok()
+        };
+        self.dp = self.origin.wrapping_add_signed(dp);
+        reachable
+    }
+
+    /// Run one iteration of the body of the loop at the instruction pointer, which jumps past
+    /// it to `dest`, as written rather than as multiply-adds, then go back to its start
+    ///
+    /// An error is reported at the instruction in the body which failed.
+    fn run_loop_body(&mut self, dest: usize) -> CommandResult {
+        let start = self.ip;
+        for ip in start + 1..dest - 1 {
+            self.ip = ip;
+            let res = match Op::from(self.program.instructions()[ip]) {
+                Op::Right(n) => self.move_head_right(n as usize),
+                Op::Left(n) => self.move_head_left(n as usize),
+                Op::Succ => self.increment_cell(),
+                Op::Pred => self.decrement_cell(),
+                Op::Add(n) => self.add_to_cell(n),
+                Op::Sub(n) => self.subtract_from_cell(n),
+                _ => unreachable!("multiplication loops only move the head and add to cells"),
+            };
+            if let Err(e) = res {
+                self.ip = start;
+                return Err(e);
+            }
+        }
+        self.ip = start;
+        Ok(start)
+    }
+
+    /// Set the value of the cell at the current data pointer to zero
+    fn clear_cell(&mut self) -> CommandResult {
+        self.tape.cell_mut(self.dp).set_value(0);
+        Ok(self.ip + 1)
+    }
+
+    /// Write a line summarising the state of the machine to the debug sink
+    ///
+    /// The summary shows up to 16 cells around the head, with the cell under it in brackets.
+    fn write_debug_report(&mut self, output: &mut impl Write) -> CommandResult {
+        /// The number of cells either side of the head to report on
+        const WINDOW: usize = 8;
+
+        let start = self.dp.saturating_sub(WINDOW);
+        let end = (self.dp + WINDOW).min(self.tape.len());
+        let cells: Vec<String> = (start..end)
+            .map(|i| {
+                let value = self
+                    .tape
+                    .cell(i)
+                    .expect("window is on the tape")
+                    .to_decimal();
+                if i == self.dp {
+                    format!("[{value}]")
+                } else {
+                    value
+                }
+            })
+            .collect();
+        let relative = |i: usize| i as isize - self.origin as isize;
+        let report = format!(
+            "{}: ip {}, dp {}, cells {}..{}: {}\n",
+            self.error_site(),
+            self.ip,
+            self.dp(),
+            relative(start),
+            relative(end),
+            cells.join(" ")
+        );
+
+        let res = match self.debug_sink {
+            DebugSink::Stderr => io::stderr().write_all(report.as_bytes()),
+            DebugSink::Output => output.write_all(report.as_bytes()),
+            DebugSink::Discard => Ok(()),
+        };
+        if let Err(inner) = res {
+            return Err(InterpretError::IoError {
+                ip_at_error: self.ip,
+                site: self.error_site(),
+                direction: IoDirection::Output,
+                inner,
+            });
+        }
+
+        Ok(self.ip + 1)
+    }
+
+    /// Read a value from a reader according to the input mode and assign it to the value of
+    /// the tape
+    ///
+    /// If the reader is exhausted then the cell is updated according to the EOF behaviour,
+    /// unless it ran out part way through a cell which is always an error.
+    fn read_value(&mut self, reader: &mut impl Read) -> CommandResult {
+        let width = self.input_width();
+        let mut buf = vec![0u8; width];
+        let read = match &mut self.replay {
+            Some(replay) => replay.read(self.ip, &mut buf),
+            None => read_up_to(reader, &mut buf),
+        };
+        let read = read.map_err(|inner| InterpretError::IoError {
+            ip_at_error: self.ip,
+            site: self.error_site(),
+            direction: IoDirection::Input,
+            inner,
+        })?;
+        if read == 0 {
+            self.record(IoDirection::Input, [None]);
+        } else {
+            self.record(IoDirection::Input, buf[..read].iter().copied().map(Some));
+        }
+
+        let cell = self.tape.cell_mut(self.dp);
+        if read == width {
+            match self.input_mode {
+                InputMode::Byte => cell.set_value(buf[0]),
+                InputMode::FullCell => cell.set_from_be_bytes(&buf),
+            }
+        } else if read == 0 {
+            match self.eof_behaviour {
+                EofBehaviour::Error => {
+                    return Err(InterpretError::IoError {
+                        ip_at_error: self.ip,
+                        site: self.error_site(),
+                        direction: IoDirection::Input,
+                        inner: io::Error::new(
+                            io::ErrorKind::UnexpectedEof,
+                            "no input left to read",
+                        ),
+                    })
+                }
+                EofBehaviour::Zero => cell.set_value(0),
+                EofBehaviour::MinusOne => {
+                    cell.set_value(0);
+                    cell.wrapping_dec();
+                }
+                EofBehaviour::Unchanged => {}
+            }
+        } else {
+            return Err(InterpretError::IoError {
+                ip_at_error: self.ip,
+                site: self.error_site(),
+                direction: IoDirection::Input,
+                inner: io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    format!("input ended after {read} of the {width} bytes of a cell"),
+                ),
+            });
+        }
+
+        Ok(self.ip + 1)
+    }
+
+    /// The number of bytes the `,` instruction reads, according to the input mode
+    fn input_width(&self) -> usize {
+        match self.input_mode {
+            InputMode::Byte => 1,
+            InputMode::FullCell => std::mem::size_of::<Cell>(),
+        }
+    }
+
+    /// Append the bytes read or written by the current instruction to the transcript, if IO is
+    /// being recorded
+    ///
+    /// `direction`: whether the bytes were read or written
+    /// `bytes`: the bytes, None when the input was exhausted
+    fn record(&mut self, direction: IoDirection, bytes: impl IntoIterator<Item = Option<u8>>) {
+        let ip = self.ip;
+        if let Some(transcript) = &mut self.transcript {
+            transcript.extend(bytes.into_iter().map(|byte| IoEvent {
+                direction,
+                byte,
+                ip,
+            }));
+        }
+    }
+
+    /// Write the value of the current cell into the writer, according to the output mode
+    ///
+    /// While replaying, nothing is written unless the bytes match the recording.
+    fn write_value(&mut self, writer: &mut impl Write) -> CommandResult {
+        let replay_position = self.replay.as_ref().map(Replay::position);
+        let cell = self.cell();
+        let bytes =
+            (self.transcript.is_some() || self.replay.is_some()).then(|| match self.output_mode {
+                OutputMode::FullCell => cell.as_bytes().into_vec(),
+                OutputMode::LowByte => vec![cell.low_byte()],
+                OutputMode::Decimal => format!("{} ", cell.to_decimal()).into_bytes(),
+            });
+        if let (Some(replay), Some(bytes)) = (&mut self.replay, &bytes) {
+            if let Err(inner) = replay.write(self.ip, bytes) {
+                return Err(InterpretError::IoError {
+                    ip_at_error: self.ip,
+                    site: self.error_site(),
+                    direction: IoDirection::Output,
+                    inner,
+                });
+            }
+        }
+
+        let cell = self.cell();
+        let res = match self.output_mode {
+            OutputMode::FullCell => cell.write_be_bytes(writer),
+            OutputMode::LowByte => writer.write_all(&[cell.low_byte()]),
+            OutputMode::Decimal => write!(writer, "{} ", cell.to_decimal()),
+        };
+        if let (Ok(()), Some(bytes)) = (&res, bytes) {
+            self.record(IoDirection::Output, bytes.into_iter().map(Some));
+        }
+
+        if let Err(inner) = res {
+            // the write never happened, so neither did the recorded one it was checked against
+            if let (Some(replay), Some(position)) = (&mut self.replay, replay_position) {
+                replay.rewind_to(position);
+            }
+            return Err(InterpretError::IoError {
+                ip_at_error: self.ip,
+                site: self.error_site(),
+                direction: IoDirection::Output,
+                inner,
+            });
+        };
+
+        writer.flush().ok();
+
+        Ok(self.ip + 1)
+    }
+
+    /// Jump forward if the value of the tape at the data pointer is zero
+    fn jump_if_zero(&mut self, dest: usize) -> CommandResult {
+        if self.cell().is_zero() {
+            Ok(dest)
+        } else {
+            Ok(self.ip + 1)
+        }
+    }
+
+    /// Jump backward if the value of the tape at the data pointer is not zero
+    fn jump_if_not_zero(&mut self, dest: usize) -> CommandResult {
+        if self.cell().is_zero() {
+            Ok(self.ip + 1)
+        } else {
+            Ok(dest)
+        }
+    }
+}
+
+/// Shows the next instruction and the cells near the head, see [`Machine::visualize`]
+impl<Cell: CellKind, T: Tape<Cell>> fmt::Display for Machine<'_, Cell, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.visualize(DISPLAY_WINDOW))
+    }
+}
+
+/// The printable ASCII character a cell holds, if it holds one
+fn printable_char(cell: &impl CellKind) -> Option<char> {
+    let bytes = cell.as_bytes();
+    let (&low, high) = bytes.split_last()?;
+    let printable = low.is_ascii_graphic() || low == b' ';
+    (printable && high.iter().all(|&b| b == 0)).then_some(char::from(low))
+}
+
+/// A fingerprint of a program's instructions, used to check a state is restored into a machine
+/// running the same program
+///
+/// This is the 64 bit FNV-1a hash of each instruction written as a tag byte followed by its
+/// operand as 8 little endian bytes, so it is the same on every platform and Rust release and a
+/// stored state keeps restoring.
+fn program_hash(program: &Program) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0100_0000_01b3;
+
+    let mut hash = OFFSET_BASIS;
+    for &instruction in program.instructions() {
+        let (tag, operand) = match instruction {
+            Instruction::Inc => (0, 0),
+            Instruction::Dec => (1, 0),
+            Instruction::Succ => (2, 0),
+            Instruction::Pred => (3, 0),
+            Instruction::Out => (4, 0),
+            Instruction::In => (5, 0),
+            Instruction::Jz { dest } => (6, dest as u64),
+            Instruction::Jnz { dest } => (7, dest as u64),
+            Instruction::Add(n) => (8, u64::from(n)),
+            Instruction::Sub(n) => (9, u64::from(n)),
+            Instruction::Right(n) => (10, n as u64),
+            Instruction::Left(n) => (11, n as u64),
+            Instruction::SetZero => (12, 0),
+            Instruction::Debug => (13, 0),
+        };
+        for byte in std::iter::once(tag).chain(u64::to_le_bytes(operand)) {
+            hash ^= u64::from(byte);
+            hash = hash.wrapping_mul(PRIME);
+        }
+    }
+    hash
+}
+
+/// Statistics about a virtual machine's run, see [`Machine::stats`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RunStats {
+    /// The number of instructions executed
+    pub steps: u64,
+
+    /// The number of cells from the furthest the head reached to the left to the furthest it
+    /// reached to the right, however many cells the tape has allocated
+    pub peak_tape_len: usize,
+
+    /// The index of the cell under the head, relative to the cell it started on
+    pub dp: isize,
+}
+
+/// Tracks the innermost loop jumped back to, to spot it repeating without changing anything
+#[derive(Debug, Clone)]
+struct HangDetector {
+    /// The `]` which last jumped back
+    ip: usize,
+
+    /// The data pointer when it did
+    dp: usize,
+
+    /// Has a cell been written or IO been performed since
+    changed: bool,
+
+    /// The number of times in a row it has jumped back with nothing changed
+    repeats: u32,
+}
+
+impl Default for HangDetector {
+    fn default() -> Self {
+        Self {
+            ip: usize::MAX,
+            dp: 0,
+            changed: false,
+            repeats: 0,
+        }
+    }
+}
+
+impl HangDetector {
+    /// Observe an executed instruction, returning whether the loop it closes looks hung
+    ///
+    /// `op`: the instruction executed
+    /// `ip`: the index of the instruction
+    /// `next`: the index of the instruction to execute next
+    /// `dp`: the data pointer after it was executed
+    fn is_hung(&mut self, op: Op, ip: usize, next: usize, dp: usize) -> bool {
+        match op {
+            Op::Jnz(dest) if next == dest as usize => {
+                let unchanged = !self.changed && self.ip == ip && self.dp == dp;
+                self.repeats = if unchanged { self.repeats + 1 } else { 0 };
+                self.ip = ip;
+                self.dp = dp;
+                self.changed = false;
+                self.repeats > HANG_REPEATS
+            }
+            Op::Right(_) | Op::Left(_) | Op::Jz(_) | Op::Jnz(_) => false,
+            Op::Succ
+            | Op::Pred
+            | Op::Add(_)
+            | Op::Sub(_)
+            | Op::SetZero
+            | Op::In
+            | Op::Out
+            | Op::Debug
+            | Op::MulAdd { .. } => {
+                self.changed = true;
+                false
+            }
+        }
+    }
+}
+
+/// Everything a step writing output changes, so that it can be undone if the output can't be
+/// written, see [`Machine::undo_step`]
+#[cfg(feature = "async")]
+struct UndoPoint {
+    /// The instruction being executed
+    ip: usize,
+
+    /// The number of instructions executed before it
+    steps: u64,
+
+    /// The number of events recorded before it, if recording
+    transcript_len: Option<usize>,
+
+    /// The next recorded event to repeat before it, if replaying
+    replay_position: Option<usize>,
+
+    /// The hang detector before it, if detecting hangs
+    hang_detector: Option<HangDetector>,
+}
+
+/// The state of a virtual machine at a point in its execution, see [`Machine::snapshot`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MachineState<Cell> {
+    /// The contents of the tape
+    pub tape: Vec<Cell>,
+
+    /// The index in `tape` of the cell the head started on
+    pub origin: usize,
+
+    /// The index in `tape` of the cell under the head
+    pub dp: usize,
+
+    /// The index of the next instruction to be executed
+    pub ip: usize,
+
+    /// The number of instructions executed before the state was captured
+    pub steps: u64,
+
+    /// A fingerprint of the instructions of the program the state was captured from
+    pub program_hash: u64,
+}
+
+/// The reason a state captured by [`Machine::snapshot`] couldn't be restored
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum RestoreError {
+    /// The state was captured from a machine running a different program
+    DifferentProgram,
+
+    /// The head isn't on the state's tape
+    HeadOffTape {
+        /// The index of the cell under the head
+        dp: usize,
+        /// The number of cells on the tape
+        tape_len: usize,
+    },
+
+    /// The cell the head started on isn't on the state's tape
+    OriginOffTape {
+        /// The index of the cell the head started on
+        origin: usize,
+        /// The number of cells on the tape
+        tape_len: usize,
+    },
+
+    /// The next instruction is beyond the end of the program
+    InstructionOutOfRange {
+        /// The index of the next instruction
+        ip: usize,
+        /// The number of instructions in the program
+        instructions: usize,
+    },
+}
+
+impl fmt::Display for RestoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::DifferentProgram => {
+                write!(f, "the machine state was captured from a different program")
+            }
+            Self::HeadOffTape { dp, tape_len } => write!(
+                f,
+                "the head is on cell {dp} of a machine state with {tape_len} cells"
+            ),
+            Self::OriginOffTape { origin, tape_len } => write!(
+                f,
+                "the head started on cell {origin} of a machine state with {tape_len} cells"
+            ),
+            Self::InstructionOutOfRange { ip, instructions } => write!(
+                f,
+                "the next instruction is {ip} in a program of {instructions} instructions"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for RestoreError {}
+
+/// The error returned when a machine can't be created with the tape it was asked for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum MachineConfigError {
+    /// The tape had no cells, so there was nowhere for the data pointer to point
+    EmptyTape,
+
+    /// The tape had more cells than could ever be allocated
+    TapeTooLarge {
+        /// The number of cells asked for
+        cells: usize,
+        /// The size in bytes of each cell
+        cell_bytes: usize,
+    },
+
+    /// The initial contents of a tape which can't grow had more cells than the tape
+    InitialTapeTooLong {
+        /// The number of initial cells
+        cells: usize,
+        /// The number of cells on the tape
+        tape_size: usize,
+    },
+
+    /// The head would have started beyond the end of the tape
+    HeadOffTape {
+        /// The index of the cell the head was to start on
+        dp: usize,
+        /// The number of cells on the tape
+        tape_size: usize,
+    },
+
+    /// A limit on the number of cells was given for a tape which can't grow
+    MaxCellsWithoutGrowth {
+        /// The kind of tape which can't grow
+        tape_kind: TapeKind,
+    },
+
+    /// The tape started with more cells than it was limited to
+    TapeAboveMaxCells {
+        /// The number of cells the tape starts with
+        tape_size: usize,
+        /// The most cells the tape can grow to
+        max_cells: usize,
+    },
+}
+
+impl fmt::Display for MachineConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::EmptyTape => write!(f, "the tape must have at least one cell"),
+            Self::TapeTooLarge { cells, cell_bytes } => write!(
+                f,
+                "a tape of {cells} {cell_bytes}-byte cells is too large to allocate"
+            ),
+            Self::InitialTapeTooLong { cells, tape_size } => write!(
+                f,
+                "{cells} initial cells don't fit on a tape of {tape_size} cells"
+            ),
+            Self::HeadOffTape { dp, tape_size } => write!(
+                f,
+                "the head can't start on cell {dp} of a tape of {tape_size} cells"
+            ),
+            Self::MaxCellsWithoutGrowth { tape_kind } => write!(
+                f,
+                "a limit on the number of cells needs a tape which can grow, not {tape_kind:?}"
+            ),
+            Self::TapeAboveMaxCells {
+                tape_size,
+                max_cells,
+            } => write!(
+                f,
+                "the tape can't start with {tape_size} cells when it is limited to {max_cells}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for MachineConfigError {}
+
+/// Check a tape of `cells` cells can be created, the size of its cells in bytes adding up to no
+/// more than the largest allocation
+fn check_tape_size<Cell>(cells: usize) -> Result<(), MachineConfigError> {
+    let cell_bytes = std::mem::size_of::<Cell>();
+    if cells == 0 {
+        return Err(MachineConfigError::EmptyTape);
+    }
+    match cells.checked_mul(cell_bytes) {
+        Some(bytes) if bytes <= isize::MAX as usize => Ok(()),
+        _ => Err(MachineConfigError::TapeTooLarge { cells, cell_bytes }),
+    }
+}
+
+/// Read from `reader` until `buf` is full or the reader is exhausted, returning the number of
+/// bytes read
+fn read_up_to(reader: &mut impl Read, buf: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+
+    Ok(filled)
+}
+
+/// errors that can occor while interpreting a brainfuck program
+///
+/// More kinds may be added as the machine gains ways to stop a program.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum InterpretError {
+    /// The data pointer exceed the bounds of the tape
+    TapeRunOffError {
+        /// The instruction which lead to the error
+        ip_at_error: usize,
+        /// Where the instruction which lead to the error came from
+        site: ErrorSite,
+    },
+
+    /// The virtual machine failed to perform an IO operation
+    IoError {
+        /// The instruction which lead to the error
+        ip_at_error: usize,
+        /// Where the instruction which lead to the error came from
+        site: ErrorSite,
+        /// Whether reading input or writing output failed
+        direction: IoDirection,
+        /// The inner IO error which caused the failure
+        inner: io::Error,
+    },
+
+    /// The program didn't finish within the allowed number of steps
+    StepLimitExceeded {
+        /// The instruction which would have been executed next
+        ip_at_error: usize,
+        /// Where the instruction which would have been executed next came from
+        site: ErrorSite,
+        /// The number of steps which were executed
+        steps: u64,
+    },
+
+    /// The tape would have had to grow beyond its limit on the number of cells
+    TapeLimitExceeded {
+        /// The instruction which lead to the error
+        ip_at_error: usize,
+        /// Where the instruction which lead to the error came from
+        site: ErrorSite,
+        /// The most cells the tape could have
+        limit: usize,
+        /// The size in bytes of each cell
+        cell_bytes: usize,
+    },
+
+    /// The program was cancelled before it finished
+    Cancelled {
+        /// The instruction which would have been executed next
+        ip_at_error: usize,
+        /// Where the instruction which would have been executed next came from
+        site: ErrorSite,
+    },
+
+    /// A cell would have gone beyond the range of values it can hold
+    CellOverflow {
+        /// The instruction which lead to the error
+        ip_at_error: usize,
+        /// Where the instruction which lead to the error came from
+        site: ErrorSite,
+        /// The index of the cell, relative to the cell the head started on
+        dp: isize,
+    },
+
+    /// A loop kept repeating without changing anything, so would never have finished
+    SuspectedInfiniteLoop {
+        /// The `]` which kept jumping back
+        ip_at_error: usize,
+        /// Where the `]` came from
+        site: ErrorSite,
+    },
+}
+
+/// The location in the program's source code of the instruction which caused an error
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ErrorSite {
+    /// The file the program was loaded from
+    pub filename: PathBuf,
+    /// The location of the instruction in the file
+    pub location: SourceLocation,
+    /// The instruction being executed
+    pub instruction: Instruction,
+}
+
+impl fmt::Display for ErrorSite {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}:{}",
+            self.filename.display(),
+            self.location.line + 1,
+            self.location.column + 1
+        )
+    }
+}
+
+impl InterpretError {
+    /// The instruction pointer of the instruction which lead to the error
+    pub fn ip(&self) -> usize {
+        match self {
+            Self::TapeRunOffError { ip_at_error, .. }
+            | Self::IoError { ip_at_error, .. }
+            | Self::StepLimitExceeded { ip_at_error, .. }
+            | Self::TapeLimitExceeded { ip_at_error, .. }
+            | Self::Cancelled { ip_at_error, .. }
+            | Self::CellOverflow { ip_at_error, .. }
+            | Self::SuspectedInfiniteLoop { ip_at_error, .. } => *ip_at_error,
+        }
+    }
+
+    /// Where in the source code the instruction which lead to the error came from
+    pub fn site(&self) -> &ErrorSite {
+        match self {
+            Self::TapeRunOffError { site, .. }
+            | Self::IoError { site, .. }
+            | Self::StepLimitExceeded { site, .. }
+            | Self::TapeLimitExceeded { site, .. }
+            | Self::Cancelled { site, .. }
+            | Self::CellOverflow { site, .. }
+            | Self::SuspectedInfiniteLoop { site, .. } => site,
+        }
+    }
+}
+
+impl fmt::Display for InterpretError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let site = self.site();
+        let instr = site.instruction.as_char();
+        match self {
+            Self::TapeRunOffError { .. } => {
+                write!(f, "{site}: tape run off while executing '{instr}'")
+            }
+            Self::IoError {
+                direction, inner, ..
+            } => {
+                let action = match direction {
+                    IoDirection::Input => "read input",
+                    IoDirection::Output => "write output",
+                };
+                write!(
+                    f,
+                    "{site}: failed to {action} ({inner}) while executing '{instr}'"
+                )
+            }
+            Self::StepLimitExceeded { steps, .. } => {
+                write!(
+                    f,
+                    "{site}: program didn't finish within {steps} steps, stopped before '{instr}'"
+                )
+            }
+            Self::TapeLimitExceeded {
+                limit, cell_bytes, ..
+            } => {
+                let bytes = limit.saturating_mul(*cell_bytes);
+                write!(
+                    f,
+                    "{site}: tape can't grow beyond its limit of {limit} cells ({bytes} bytes of {cell_bytes}-byte cells) while executing '{instr}'"
+                )
+            }
+            Self::Cancelled { .. } => {
+                write!(f, "{site}: program was cancelled, stopped before '{instr}'")
+            }
+            Self::CellOverflow { dp, .. } => {
+                write!(f, "{site}: cell {dp} overflowed while executing '{instr}'")
+            }
+            Self::SuspectedInfiniteLoop { .. } => {
+                write!(
+                    f,
+                    "{site}: loop repeated {HANG_REPEATS} times without changing anything, stopped at '{instr}'"
+                )
+            }
+        }
+    }
+}
+
+/// The underlying IO error is the source of an [`InterpretError::IoError`]
+///
+/// ```
+/// # use bft_interp::{Machine, TapeKind};
+/// # use bft_types::Program;
+/// # use std::{error::Error, io, path::Path};
+/// let prog = Program::try_new(Path::new("-"), ",").unwrap();
+/// let mut machine = Machine::<u8>::new(1, TapeKind::FixedSize, &prog);
+/// let err = machine.run(io::empty(), io::sink()).unwrap_err();
+/// let inner = err.source().unwrap().downcast_ref::<io::Error>().unwrap();
+/// assert_eq!(inner.kind(), io::ErrorKind::UnexpectedEof);
+/// ```
+impl std::error::Error for InterpretError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::IoError { inner, .. } => Some(inner),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io::ErrorKind, path::Path};
+
+    use super::*;
+    use crate::SparseTape;
+    use bft_types::ParseOptions;
+    use rstest::rstest;
+
+    #[test]
+    fn test_move_head_right_grows() {
+        let prog = Program::from_file("../programs/example.bf").unwrap();
+        let growth = GrowthStrategy::Doubling;
+        let mut machine = Machine::<u8>::new(1, TapeKind::Growable { growth }, &prog);
+
+        for i in 0..100 {
+            assert_eq!(machine.dp, i);
+            assert!(machine.tape.len() > i);
+            let new_ip = machine.move_head_right(1).unwrap();
+            assert_eq!(new_ip, machine.ip + 1);
+            machine.ip = new_ip;
+            assert_eq!(machine.dp, i + 1);
+            assert!(machine.tape.len() > i + 1);
+        }
+    }
+
+    #[rstest]
+    #[case(GrowthStrategy::Doubling, 1, 1, 2)]
+    #[case(GrowthStrategy::Doubling, 1, 2, 4)]
+    #[case(GrowthStrategy::Doubling, 1, 4, 8)]
+    #[case(GrowthStrategy::Doubling, 1, 100, 128)]
+    #[case(GrowthStrategy::Doubling, 3, 3, 6)]
+    #[case(GrowthStrategy::FixedIncrement(1), 1, 100, 101)]
+    #[case(GrowthStrategy::FixedIncrement(10), 1, 100, 101)]
+    #[case(GrowthStrategy::FixedIncrement(10), 1, 101, 111)]
+    #[case(GrowthStrategy::FixedIncrement(0), 5, 7, 8)]
+    fn test_tape_growth_strategy(
+        #[case] growth: GrowthStrategy,
+        #[case] initial_len: usize,
+        #[case] moves: usize,
+        #[case] expected_len: usize,
+    ) {
+        let prog = Program::from_file("../programs/example.bf").unwrap();
+        let mut machine = Machine::<u8>::new(initial_len, TapeKind::Growable { growth }, &prog);
+
+        for _ in 0..moves {
+            machine.move_head_right(1).unwrap();
+        }
+        assert_eq!(machine.tape.len(), expected_len);
+    }
+
+    #[test]
+    fn test_move_head_right_run_off() {
+        let prog = Program::try_new(std::path::Path::new("-"), ">".repeat(100)).unwrap();
+        let mut machine = Machine::<u8>::new(100, TapeKind::FixedSize, &prog);
+
+        for _ in 0..99 {
+            let new_ip = machine.move_head_right(1).unwrap();
+            assert_eq!(new_ip, machine.ip + 1);
+            machine.ip = new_ip;
+        }
+        assert!(matches!(
+            machine.move_head_right(1).unwrap_err(),
+            InterpretError::TapeRunOffError {
+                ip_at_error,
+                ..
+            } if ip_at_error == machine.ip
+        ));
+    }
+
+    #[test]
+    fn test_move_head_left() {
+        let prog = Program::from_file("../programs/example.bf").unwrap();
+        let growth = GrowthStrategy::default();
+        let mut machine = Machine::<u8>::new(1, TapeKind::Growable { growth }, &prog);
+
+        for _ in 0..100 {
+            machine.move_head_right(1).unwrap();
+        }
+        for i in (0..100).rev() {
+            assert_eq!(machine.dp, i + 1);
+            let new_ip = machine.move_head_left(1).unwrap();
+            assert_eq!(machine.dp, i);
+            assert_eq!(new_ip, machine.ip + 1);
+            machine.ip = new_ip;
+        }
+    }
+
+    #[test]
+    fn test_move_head_left_run_off() {
+        let prog = Program::from_file("../programs/example.bf").unwrap();
+        let mut machine = Machine::<u8>::new(100, TapeKind::FixedSize, &prog);
+
+        assert!(matches!(
+            machine.move_head_left(1).unwrap_err(),
+            InterpretError::TapeRunOffError { ip_at_error: 0, .. }
+        ));
+    }
+
+    #[test]
+    fn test_increment_cell() {
+        let prog = Program::from_file("../programs/example.bf").unwrap();
+        let mut machine = Machine::<u8>::new(100, TapeKind::FixedSize, &prog);
+
+        let new_ip = machine.increment_cell().unwrap();
+        assert_eq!(new_ip, machine.ip + 1);
+        assert_eq!(machine.tape[0], 1);
+        machine.ip = new_ip;
+
+        machine.tape[0].set_value(0xFF);
+        let new_ip = machine.increment_cell().unwrap();
+        assert_eq!(new_ip, machine.ip + 1);
+        assert_eq!(machine.tape[0], 0);
+    }
+
+    #[test]
+    fn test_decrement_cell() {
+        let prog = Program::from_file("../programs/example.bf").unwrap();
+        let mut machine = Machine::<u8>::new(100, TapeKind::FixedSize, &prog);
+
+        let new_ip = machine.decrement_cell().unwrap();
+        assert_eq!(new_ip, machine.ip + 1);
+        assert_eq!(machine.tape[0], 0xFF);
+        machine.ip = new_ip;
+
+        let new_ip = machine.decrement_cell().unwrap();
+        assert_eq!(new_ip, machine.ip + 1);
+        assert_eq!(machine.tape[0], 0xFE);
+    }
+
+    #[test]
+    fn test_read_value() {
+        let prog = Program::from_file("../programs/example.bf").unwrap();
+        let mut machine = Machine::<u8>::new(100, TapeKind::FixedSize, &prog);
+
+        let data = 0xDEADBEEF_u32.to_be_bytes();
+        let mut reader = std::io::Cursor::new(data);
+        for d in data {
+            let new_ip = machine.read_value(&mut reader).unwrap();
+            assert_eq!(machine.tape[0], d);
+            assert_eq!(new_ip, machine.ip + 1);
+            machine.ip = new_ip;
+        }
+        machine.read_value(&mut reader).unwrap_err();
+    }
+
+    #[rstest]
+    #[case(EofBehaviour::Zero, b"abc")]
+    #[case(EofBehaviour::MinusOne, b"abc\xFF\xFF\xFF")]
+    #[case(EofBehaviour::Unchanged, b"abcccc")]
+    fn test_eof_behaviour(#[case] eof_behaviour: EofBehaviour, #[case] expected: &[u8]) {
+        let prog = Program::try_new(std::path::Path::new("-"), ",[.,]").unwrap();
+        let mut machine =
+            Machine::<u8>::new(1, TapeKind::FixedSize, &prog).with_eof_behaviour(eof_behaviour);
+
+        // enough steps to output the input and then go around the loop three more times
+        let mut output = Vec::new();
+        let res = machine.run_with_limit(&b"abc"[..], &mut output, 1 + 1 + 3 * 3 + 3 * 3);
+        assert_eq!(output, expected);
+        if eof_behaviour == EofBehaviour::Zero {
+            res.unwrap();
+        } else {
+            assert!(matches!(
+                res.unwrap_err(),
+                InterpretError::StepLimitExceeded { .. }
+            ));
+        }
+    }
+
+    #[test]
+    fn test_eof_behaviour_error() {
+        let prog = Program::try_new(std::path::Path::new("-"), ",[.,]").unwrap();
+        let mut machine = Machine::<u8>::new(1, TapeKind::FixedSize, &prog);
+
+        let mut output = Vec::new();
+        let err = machine.run(&b"abc"[..], &mut output).unwrap_err();
+        assert_eq!(output, b"abc");
+        assert!(matches!(err,
+            InterpretError::IoError { ip_at_error: 3, inner, .. }
+            if inner.kind() == ErrorKind::UnexpectedEof
+        ));
+    }
+
+    #[test]
+    fn test_eof_minus_one_wide_cell() {
+        let prog = Program::try_new(std::path::Path::new("-"), ",.").unwrap();
+        let mut machine = Machine::<u16>::new(1, TapeKind::FixedSize, &prog)
+            .with_eof_behaviour(EofBehaviour::MinusOne);
+
+        let mut output = Vec::new();
+        machine.run(io::empty(), &mut output).unwrap();
+        assert_eq!(output, u16::MAX.to_be_bytes());
+    }
+
+    #[test]
+    fn test_write_value() {
+        let prog = Program::from_file("../programs/example.bf").unwrap();
+        let mut machine = Machine::<u8>::new(100, TapeKind::FixedSize, &prog);
+
+        for i in u8::MIN..=u8::MAX {
+            let mut writer = std::io::Cursor::new(vec![0u8]);
+            machine.tape[0].set_value(i);
+            let new_ip = machine.write_value(&mut writer).unwrap();
+            assert_eq!(writer.get_ref(), &[i]);
+            assert_eq!(new_ip, machine.ip + 1);
+            machine.ip = new_ip;
+        }
+    }
+
+    #[test]
+    fn test_write_value_is_be() {
+        let prog = Program::from_file("../programs/example.bf").unwrap();
+        let mut machine = Machine::<u32>::new(100, TapeKind::FixedSize, &prog);
+
+        let val = 0xDEAD_BEEF;
+        machine.tape[0] = val;
+        let mut writer = std::io::Cursor::new(vec![0u8; 4]);
+        for _ in 0..4 {
+            let new_ip = machine.write_value(&mut writer).unwrap();
+            assert_eq!(new_ip, machine.ip + 1);
+            machine.ip = new_ip;
+        }
+
+        assert_eq!(&writer.get_ref()[..4], &val.to_be_bytes());
+    }
+
+    #[test]
+    fn test_i8_wraps() {
+        let prog = Program::try_new(std::path::Path::new("-"), "-.").unwrap();
+        let mut machine = Machine::<i8>::new(1, TapeKind::FixedSize, &prog);
+
+        machine.tape[0] = i8::MAX;
+        machine.increment_cell().unwrap();
+        assert_eq!(machine.tape[0], i8::MIN);
+        machine.decrement_cell().unwrap();
+        assert_eq!(machine.tape[0], i8::MAX);
+
+        machine.tape[0] = 0;
+        let mut output = Vec::new();
+        machine.run(io::empty(), &mut output).unwrap();
+        assert_eq!(machine.tape[0], -1);
+        assert_eq!(output, [0xFF]);
+    }
+
+    #[test]
+    fn test_i8_round_trips_high_bytes() {
+        let prog = Program::try_new(std::path::Path::new("-"), ",.,.,.,.").unwrap();
+        let mut machine = Machine::<i8>::new(1, TapeKind::FixedSize, &prog);
+
+        let input = [0x00, 0x7F, 0x80, 0xFF];
+        let mut output = Vec::new();
+        machine.run(&input[..], &mut output).unwrap();
+        assert_eq!(output, input);
+        assert_eq!(machine.tape[0], -1);
+    }
+
+    #[test]
+    fn test_is_zero() {
+        let prog = Program::from_file("../programs/example.bf").unwrap();
+        let mut machine = Machine::<u8>::new(100, TapeKind::FixedSize, &prog);
+
+        assert!(machine.tape[0].is_zero());
+        machine.tape[0].wrapping_inc();
+        assert!(!machine.tape[0].is_zero());
+    }
+
+    #[test]
+    fn test_jump_if_zero() {
+        let prog = Program::from_file("../programs/example.bf").unwrap();
+        let mut machine = Machine::<u8>::new(100, TapeKind::FixedSize, &prog);
+
+        assert!(machine.tape[0].is_zero());
+        assert_eq!(machine.jump_if_zero(1234).unwrap(), 1234);
+        machine.tape[0].wrapping_inc();
+        assert!(!machine.tape[0].is_zero());
+        assert_eq!(machine.ip, 0);
+        assert_eq!(machine.jump_if_zero(1234).unwrap(), 1);
+    }
+
+    #[test]
+    fn test_jump_if_not_zero() {
+        let prog = Program::from_file("../programs/example.bf").unwrap();
+        let mut machine = Machine::<u8>::new(100, TapeKind::FixedSize, &prog);
+
+        assert!(machine.tape[0].is_zero());
+        assert_eq!(machine.ip, 0);
+        assert_eq!(machine.jump_if_not_zero(1234).unwrap(), 1);
+        machine.tape[0].wrapping_inc();
+        assert!(!machine.tape[0].is_zero());
+        assert_eq!(machine.jump_if_not_zero(1234).unwrap(), 1234);
+    }
+
+    #[test]
+    fn test_loop_dispatch_count() {
+        // each iteration of the loop executes - and ] once, with [ only executed on entry
+        let prog = Program::try_new(std::path::Path::new("-"), "+++[-]").unwrap();
+        let expected_steps = 3 + 1 + 3 * 2;
+
+        let mut machine = Machine::<u8>::new(1, TapeKind::FixedSize, &prog);
+        machine
+            .run_with_limit(io::empty(), io::sink(), expected_steps)
+            .unwrap();
+
+        let mut machine = Machine::<u8>::new(1, TapeKind::FixedSize, &prog);
+        machine
+            .run_with_limit(io::empty(), io::sink(), expected_steps - 1)
+            .unwrap_err();
+    }
+
+    #[test]
+    fn test_dump_tape() {
+        let prog = Program::try_new(std::path::Path::new("-"), "+++++[>>>++++++++<<<-]").unwrap();
+        let mut machine = Machine::<u8>::new(DEFAULT_TAPE_SIZE, TapeKind::FixedSize, &prog);
+        machine.run(io::empty(), io::sink()).unwrap();
+        assert_eq!(machine.tape()[3], 40);
+        assert_eq!(machine.dp(), 0);
+
+        let mut raw = Vec::new();
+        machine.dump_tape(&mut raw, DumpFormat::Raw).unwrap();
+        assert_eq!(raw, [0, 0, 0, 40]);
+
+        let mut hex = Vec::new();
+        machine.dump_tape(&mut hex, DumpFormat::Hex).unwrap();
+        assert_eq!(
+            String::from_utf8(hex).unwrap(),
+            "00000000: [00] 00  00  28 \n"
+        );
+    }
+
+    #[test]
+    fn test_dump_tape_multiple_lines() {
+        let prog = Program::try_new(std::path::Path::new("-"), ">".repeat(17)).unwrap();
+        let mut machine = Machine::<u16>::new(DEFAULT_TAPE_SIZE, TapeKind::FixedSize, &prog);
+        machine.run(io::empty(), io::sink()).unwrap();
+
+        let mut hex = Vec::new();
+        machine.dump_tape(&mut hex, DumpFormat::Hex).unwrap();
+        let hex = String::from_utf8(hex).unwrap();
+        let lines: Vec<_> = hex.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0], format!("00000000: {}", " 0000 ".repeat(16)));
+        assert_eq!(lines[1], "00000010:  0000 [0000]");
+    }
+
+    #[test]
+    fn test_error_source_location() {
+        let prog = Program::try_new(std::path::Path::new("oops.bf"), "comment\n+>\n  >>").unwrap();
+        let mut machine = Machine::<u8>::new(3, TapeKind::FixedSize, &prog);
+
+        let err = machine.run(io::empty(), io::sink()).unwrap_err();
+        assert_eq!(err.ip(), 3);
+        assert_eq!(err.site().location, SourceLocation { line: 2, column: 3 });
+        assert_eq!(err.site().instruction, Instruction::Inc);
+        assert_eq!(
+            err.to_string(),
+            "oops.bf:3:4: tape run off while executing '>'"
+        );
+
+        let prog = Program::try_new(std::path::Path::new("oops.bf"), "\n\n\t<").unwrap();
+        let mut machine = Machine::<u8>::new(3, TapeKind::FixedSize, &prog);
+
+        let err = machine.run(io::empty(), io::sink()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "oops.bf:3:2: tape run off while executing '<'"
+        );
+    }
+
+    #[test]
+    fn test_run_hello_world() {
+        let prog = Program::from_file("../programs/example.bf").unwrap();
+        let mut machine = Machine::<u8>::new(DEFAULT_TAPE_SIZE, TapeKind::FixedSize, &prog);
+
+        let mut output = Vec::new();
+        machine.run(io::empty(), &mut output).unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        assert_eq!(output, "hello world");
+    }
+
+    #[rstest]
+    #[case::wrapping(CellOverflow::Wrapping, Some(255))]
+    #[case::saturating(CellOverflow::Saturating, Some(0))]
+    #[case::error(CellOverflow::Error, None)]
+    fn test_overflow_decrementing_zero(
+        #[case] overflow: CellOverflow,
+        #[case] expected: Option<u8>,
+    ) {
+        let prog = Program::try_new(Path::new("-"), ">-").unwrap();
+        for prog in [prog.clone(), prog.optimized()] {
+            let mut machine =
+                Machine::<u8>::new(2, TapeKind::FixedSize, &prog).with_overflow(overflow);
+            match (machine.run(io::empty(), io::sink()), expected) {
+                (Ok(()), Some(value)) => assert_eq!(machine.cell_at(1), Some(&value)),
+                (Err(err), None) => {
+                    assert!(matches!(
+                        err,
+                        InterpretError::CellOverflow {
+                            ip_at_error: 1,
+                            dp: 1,
+                            ..
+                        }
+                    ));
+                    assert_eq!(
+                        err.to_string(),
+                        "-:1:2: cell 1 overflowed while executing '-'"
+                    );
+                    assert_eq!(machine.cell_at(1), Some(&0));
+                }
+                (res, _) => panic!("unexpected result {res:?}"),
+            }
+        }
+    }
+
+    #[rstest]
+    #[case::fits(-100, 200, Some(100))]
+    #[case::overflows(-100, 228, None)]
+    #[case::largest_byte(-128, 255, Some(127))]
+    fn test_checked_add_signed_cell_large_amount(
+        #[case] start: i8,
+        #[case] amount: u8,
+        #[case] expected: Option<i8>,
+    ) {
+        let mut cell = start;
+        assert_eq!(cell.checked_add_by(amount), expected.is_some());
+        assert_eq!(cell, expected.unwrap_or(start));
+
+        let mut cell = start;
+        cell.saturating_add_by(amount);
+        assert_eq!(cell, expected.unwrap_or(i8::MAX));
+    }
+
+    #[test]
+    fn test_visualize_window() {
+        let source = format!(">>>>>{}>{}.", "+".repeat(72), "+".repeat(101));
+        let prog = Program::try_new(Path::new("v.bf"), source)
+            .unwrap()
+            .optimized();
+        let mut machine = Machine::<u8>::new(20, TapeKind::FixedSize, &prog);
+        machine
+            .run_with_limit(io::empty(), io::sink(), 4)
+            .unwrap_err();
+        assert_eq!(
+            machine.visualize(1),
+            "ip 4 at v.bf:1:180 '.', dp 6: … [5]=72 'H' *[6]=101 'e'* [7]=0 …"
+        );
+        assert_eq!(
+            machine.to_string(),
+            "ip 4 at v.bf:1:180 '.', dp 6: … [2]=0 [3]=0 [4]=0 [5]=72 'H' *[6]=101 'e'* [7]=0 \
+             [8]=0 [9]=0 [10]=0 …"
+        );
+    }
+
+    #[test]
+    fn test_visualize_clipped_at_tape_ends() {
+        let prog = Program::try_new(Path::new("v.bf"), "-<+").unwrap();
+        let growth = GrowthStrategy::Doubling;
+        let mut machine = Machine::<i8>::new(2, TapeKind::GrowableBothEnds { growth }, &prog);
+        machine.step(&mut io::empty(), &mut io::sink()).unwrap();
+        // a negative cell holds no character
+        assert_eq!(
+            machine.visualize(3),
+            "ip 1 at v.bf:1:2 '<', dp 0: *[0]=-1* [1]=0"
+        );
+
+        machine.run(io::empty(), io::sink()).unwrap();
+        // the tape grew by two cells to the left
+        assert_eq!(
+            machine.visualize(1),
+            "ip 3 halted, dp -1: [-2]=0 *[-1]=1* [0]=-1 …"
+        );
+        // a window wider than the tape shows the whole tape
+        assert_eq!(
+            machine.visualize(usize::MAX),
+            "ip 3 halted, dp -1: [-2]=0 *[-1]=1* [0]=-1 [1]=0"
+        );
+    }
+
+    #[rstest]
+    #[case::example("../programs/example.bf", "", EofBehaviour::Error)]
+    #[case::rot13("../programs/rot13.bf", "Hello, World!", EofBehaviour::MinusOne)]
+    #[case::numwarp("../programs/numwarp.bf", "123-45\n", EofBehaviour::Zero)]
+    #[case::tic_tac_toe(
+        "../programs/tic_tac_toe.bf",
+        "1\n2\n3\n4\n5\n6\n7\n8\n9\n",
+        EofBehaviour::Zero
+    )]
+    fn test_multiply_loops_output_unchanged(
+        #[case] path: &str,
+        #[case] input: &str,
+        #[case] eof_behaviour: EofBehaviour,
+    ) {
+        let prog = Program::from_file(path).unwrap();
+        for prog in [prog.optimized(), prog] {
+            let run = |multiply_loops| {
+                let mut machine = Machine::<u8>::new(DEFAULT_TAPE_SIZE, TapeKind::FixedSize, &prog)
+                    .with_eof_behaviour(eof_behaviour)
+                    .with_multiply_loops(multiply_loops);
+                let mut output = Vec::new();
+                machine.run(input.as_bytes(), &mut output).unwrap();
+                (output, machine.steps())
+            };
+            let (plain, plain_steps) = run(false);
+            let (multiplied, multiplied_steps) = run(true);
+            assert!(!plain.is_empty());
+            assert_eq!(plain, multiplied);
+            assert!(multiplied_steps <= plain_steps);
+        }
+    }
+
+    #[rstest]
+    #[case::wrapping_u8(CellOverflow::Wrapping, Some(253))]
+    #[case::error(CellOverflow::Error, None)]
+    fn test_multiply_loops_only_when_wrapping(
+        #[case] overflow: CellOverflow,
+        #[case] expected: Option<u8>,
+    ) {
+        let prog = Program::try_new(Path::new("-"), "-[->+++<]").unwrap();
+        let mut machine = Machine::<u8>::new(2, TapeKind::FixedSize, &prog)
+            .with_multiply_loops(true)
+            .with_overflow(overflow);
+        let res = machine.run(io::empty(), io::sink());
+        match expected {
+            Some(value) => {
+                res.unwrap();
+                assert_eq!(machine.cell_at(1), Some(&value));
+            }
+            // adding 3 a cell at a time overflows, which a multiply-add can't reproduce
+            None => assert!(matches!(res, Err(InterpretError::CellOverflow { .. }))),
+        }
+    }
+
+    #[test]
+    fn test_multiply_loops_wide_and_signed_cells() {
+        let prog = Program::try_new(Path::new("-"), "-[->+++>--<<]").unwrap();
+        let mut machine =
+            Machine::<u16>::new(3, TapeKind::FixedSize, &prog).with_multiply_loops(true);
+        machine.run(io::empty(), io::sink()).unwrap();
+        assert_eq!(machine.cell_at(1), Some(&65533));
+        assert_eq!(machine.cell_at(2), Some(&2));
+
+        let mut machine =
+            Machine::<i8>::new(3, TapeKind::FixedSize, &prog).with_multiply_loops(true);
+        machine.run(io::empty(), io::sink()).unwrap();
+        assert_eq!(machine.cell_at(1), Some(&-3));
+        assert_eq!(machine.cell_at(2), Some(&2));
+
+        // a fixed size tape is run off just as it would be by the loop
+        let prog = Program::try_new(Path::new("-"), "+++[->+>>+<<<]").unwrap();
+        let run = |multiply_loops| {
+            let mut machine = Machine::<u8>::new(3, TapeKind::FixedSize, &prog)
+                .with_multiply_loops(multiply_loops);
+            let err = machine.run(io::empty(), io::sink()).unwrap_err();
+            let tape: Vec<u8> = (0..3).map(|i| *machine.cell_at(i).unwrap()).collect();
+            (err.ip(), tape)
+        };
+        assert_eq!(run(true), (8, vec![2, 1, 0]));
+        assert_eq!(run(true), run(false));
+    }
+
+    #[test]
+    fn test_run_hello_world_wrapping_cells() {
+        let prog = Program::from_file("../programs/example.bf").unwrap();
+        let mut machine =
+            Machine::<Wrapping<u8>>::new(DEFAULT_TAPE_SIZE, TapeKind::FixedSize, &prog);
+
+        let mut output = Vec::new();
+        machine.run(io::empty(), &mut output).unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), "hello world");
+
+        let prog = Program::try_new(Path::new("-"), "-").unwrap();
+        let mut machine = Machine::<Wrapping<i16>>::new(1, TapeKind::FixedSize, &prog);
+        machine.run(io::empty(), io::sink()).unwrap();
+        assert_eq!(machine.cell_at(0), Some(&Wrapping(-1)));
+    }
+
+    #[test]
+    fn test_step() {
+        let prog = Program::try_new(std::path::Path::new("-"), "+>+<[-]").unwrap();
+        let mut machine = Machine::<u8>::new(2, TapeKind::FixedSize, &prog);
+        let (mut input, mut output) = (io::empty(), io::sink());
+
+        let mut step = || machine.step(&mut input, &mut output).unwrap();
+        assert_eq!(step(), StepOutcome::Running);
+        assert_eq!(step(), StepOutcome::Running);
+        assert_eq!(step(), StepOutcome::Running);
+        assert_eq!(step(), StepOutcome::Running);
+        assert_eq!((machine.ip(), machine.dp()), (4, 0));
+        assert_eq!(machine.cell_at(0), Some(&1));
+        assert_eq!(machine.cell_at(1), Some(&1));
+        assert_eq!(machine.cell_at(2), None);
+
+        // [ - ] falls through once the cell is zero
+        let mut outcomes = Vec::new();
+        loop {
+            let outcome = machine.step(&mut input, &mut output).unwrap();
+            outcomes.push(outcome);
+            if outcome == StepOutcome::Halted {
+                break;
+            }
+        }
+        assert_eq!(outcomes.len(), 3);
+        assert_eq!(machine.cell_at(0), Some(&0));
+
+        // stepping a halted machine does nothing
+        let ip = machine.ip();
+        assert_eq!(
+            machine.step(&mut input, &mut output).unwrap(),
+            StepOutcome::Halted
+        );
+        assert_eq!(machine.ip(), ip);
+    }
+
+    #[rstest]
+    #[case(TapeKind::FixedSize)]
+    #[case(TapeKind::Wrapping)]
+    #[case(TapeKind::Growable { growth: GrowthStrategy::Doubling })]
+    #[case(TapeKind::Sparse)]
+    fn test_try_new_empty_tape(#[case] tape_kind: TapeKind) {
+        let prog = Program::try_new(Path::new("-"), "+").unwrap();
+        let res = Machine::<u8>::try_new(0, tape_kind, &prog);
+        assert_eq!(res.err(), Some(MachineConfigError::EmptyTape));
+    }
+
+    #[test]
+    fn test_try_new_tape_too_large() {
+        let prog = Program::try_new(Path::new("-"), "+").unwrap();
+        let cells = usize::MAX / 2;
+        let err = Machine::<u32>::try_new(cells, TapeKind::FixedSize, &prog)
+            .err()
+            .unwrap();
+        assert_eq!(
+            err,
+            MachineConfigError::TapeTooLarge {
+                cells,
+                cell_bytes: 4
+            }
+        );
+        assert_eq!(
+            err.to_string(),
+            format!("a tape of {cells} 4-byte cells is too large to allocate")
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "the tape must have at least one cell")]
+    fn test_new_empty_tape_panics() {
+        let prog = Program::try_new(Path::new("-"), "+").unwrap();
+        Machine::<u8>::new(0, TapeKind::FixedSize, &prog);
+    }
+
+    #[test]
+    fn test_try_new_growable_single_cell() {
+        let prog = Program::try_new(Path::new("-"), "+>++>+++").unwrap();
+        let growth = GrowthStrategy::FixedIncrement(1);
+        let mut machine = Machine::<u8>::try_new(1, TapeKind::Growable { growth }, &prog).unwrap();
+        machine.run(io::empty(), io::sink()).unwrap();
+        assert_eq!(machine.tape().len(), 3);
+        assert_eq!(*machine.cell_at(2).unwrap(), 3);
+    }
+
+    #[test]
+    fn test_continue_with_after_error() {
+        let prog = Program::try_new(Path::new("-"), "++>>+").unwrap();
+        let mut machine = Machine::<u8>::new(2, TapeKind::FixedSize, &prog).with_profiling(true);
+        let err = machine.run(io::empty(), io::sink()).unwrap_err();
+        assert!(matches!(
+            err,
+            InterpretError::TapeRunOffError { ip_at_error: 3, .. }
+        ));
+
+        machine.continue_with(Program::try_new(Path::new("-"), "<.").unwrap());
+        assert_eq!((machine.ip(), machine.dp()), (0, 1));
+        let mut output = Vec::new();
+        machine.run(io::empty(), &mut output).unwrap();
+        assert_eq!(output, [2]);
+        assert_eq!(machine.execution_counts(), Some(&[1, 1][..]));
+    }
+
+    /// Multiplies the first two cells into the third, using the fourth as scratch space
+    const MULTIPLY: &str = "[>[->+>+<<]>>[-<<+>>]<<<-]";
+
+    #[rstest]
+    #[case(&[6, 7], [0, 7, 42, 0])]
+    #[case(&[0, 9], [0, 9, 0, 0])]
+    #[case(&[15, 17], [0, 17, 255, 0])]
+    #[case(&[16, 16, 3], [0, 16, 3, 0])]
+    fn test_initial_tape_multiply(#[case] cells: &[u8], #[case] expected: [u8; 4]) {
+        let prog = Program::try_new(Path::new("-"), MULTIPLY).unwrap();
+        let mut machine = Machine::<u8>::new(4, TapeKind::FixedSize, &prog)
+            .with_initial_tape(cells, 0)
+            .unwrap();
+        machine.run(io::empty(), io::sink()).unwrap();
+        assert_eq!(machine.tape().to_cells(), expected);
+        assert_eq!(machine.dp(), 0);
+    }
+
+    #[test]
+    fn test_initial_tape_head() {
+        let prog = Program::try_new(Path::new("-"), "+<.").unwrap();
+        let mut machine = Machine::<u8>::new(3, TapeKind::FixedSize, &prog)
+            .with_initial_tape(b"ab", 1)
+            .unwrap();
+        assert_eq!(machine.dp(), 1);
+
+        let mut output = Vec::new();
+        machine.run(io::empty(), &mut output).unwrap();
+        assert_eq!(output, b"a");
+        assert_eq!(machine.tape().to_cells(), [b'a', b'c', 0]);
+
+        machine.reset();
+        assert_eq!((machine.dp(), machine.ip()), (1, 0));
+        assert_eq!(machine.tape().to_cells(), [b'a', b'b', 0]);
+    }
+
+    #[rstest]
+    #[case(TapeKind::Growable { growth: GrowthStrategy::Doubling })]
+    #[case(TapeKind::GrowableBothEnds { growth: GrowthStrategy::Doubling })]
+    fn test_initial_tape_grows(#[case] tape_kind: TapeKind) {
+        let prog = Program::try_new(Path::new("-"), MULTIPLY).unwrap();
+        let mut machine = Machine::<u8>::new(1, tape_kind, &prog)
+            .with_initial_tape(&[3, 4, 0, 0, 1], 0)
+            .unwrap();
+        machine.run(io::empty(), io::sink()).unwrap();
+        assert_eq!(machine.tape().to_cells(), [0, 4, 12, 0, 1]);
+
+        machine.reset();
+        assert_eq!(machine.tape().to_cells(), [3, 4, 0, 0, 1]);
+    }
+
+    #[test]
+    fn test_initial_tape_sparse() {
+        let prog = Program::try_new(Path::new("-"), MULTIPLY).unwrap();
+        let mut machine = Machine::<u8, SparseTape<u8>>::new(1, TapeKind::Sparse, &prog)
+            .with_initial_tape(&[5, 5], 0)
+            .unwrap();
+        machine.run(io::empty(), io::sink()).unwrap();
+        assert_eq!(machine.cell_at(2), Some(&25));
+    }
+
+    #[rstest]
+    #[case(TapeKind::FixedSize, &[1, 2, 3, 4], 0, MachineConfigError::InitialTapeTooLong { cells: 4, tape_size: 3 })]
+    #[case(TapeKind::Wrapping, &[1, 2, 3, 4], 0, MachineConfigError::InitialTapeTooLong { cells: 4, tape_size: 3 })]
+    #[case(TapeKind::FixedSize, &[1, 2], 3, MachineConfigError::HeadOffTape { dp: 3, tape_size: 3 })]
+    #[case(TapeKind::Growable { growth: GrowthStrategy::Doubling }, &[1, 2, 3, 4], 4, MachineConfigError::HeadOffTape { dp: 4, tape_size: 4 })]
+    fn test_initial_tape_invalid(
+        #[case] tape_kind: TapeKind,
+        #[case] cells: &[u8],
+        #[case] dp: usize,
+        #[case] expected: MachineConfigError,
+    ) {
+        let prog = Program::try_new(Path::new("-"), MULTIPLY).unwrap();
+        let res = Machine::<u8>::new(3, tape_kind, &prog).with_initial_tape(cells, dp);
+        assert_eq!(res.err(), Some(expected));
+    }
+
+    #[test]
+    fn test_initial_tape_error_messages() {
+        let too_long = MachineConfigError::InitialTapeTooLong {
+            cells: 4,
+            tape_size: 3,
+        };
+        assert_eq!(
+            too_long.to_string(),
+            "4 initial cells don't fit on a tape of 3 cells"
+        );
+        let off_tape = MachineConfigError::HeadOffTape {
+            dp: 3,
+            tape_size: 3,
+        };
+        assert_eq!(
+            off_tape.to_string(),
+            "the head can't start on cell 3 of a tape of 3 cells"
+        );
+    }
+
+    /// A writer whose every write fails
+    struct BrokenPipe;
+
+    impl Write for BrokenPipe {
+        fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+            Err(ErrorKind::BrokenPipe.into())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[rstest]
+    #[case(",", Some(ErrorKind::UnexpectedEof))]
+    #[case(".", Some(ErrorKind::BrokenPipe))]
+    #[case("<", None)]
+    #[case("[]", None)]
+    fn test_error_source(#[case] source: &str, #[case] expected: Option<ErrorKind>) {
+        use std::error::Error;
+
+        let prog = Program::try_new(Path::new("-"), source).unwrap();
+        let mut machine = Machine::<u8>::new(1, TapeKind::FixedSize, &prog);
+        let err = match source {
+            "[]" => machine.run_with_limit(io::empty(), BrokenPipe, 0),
+            _ => machine.run(io::empty(), BrokenPipe),
+        }
+        .unwrap_err();
+
+        let kind = err
+            .source()
+            .map(|source| source.downcast_ref::<io::Error>().unwrap().kind());
+        assert_eq!(kind, expected);
+    }
+
+    #[rstest]
+    #[case(
+        ",",
+        IoDirection::Input,
+        "-:1:1: failed to read input (no input left to read) while executing ','"
+    )]
+    #[case(
+        ".",
+        IoDirection::Output,
+        "-:1:1: failed to write output (broken pipe) while executing '.'"
+    )]
+    fn test_io_error_direction(
+        #[case] source: &str,
+        #[case] expected: IoDirection,
+        #[case] message: &str,
+    ) {
+        let prog = Program::try_new(Path::new("-"), source).unwrap();
+        let mut machine = Machine::<u8>::new(1, TapeKind::FixedSize, &prog);
+        let err = machine.run(io::empty(), BrokenPipe).unwrap_err();
+        assert!(matches!(err, InterpretError::IoError { direction, .. } if direction == expected));
+        assert_eq!(err.to_string(), message);
+    }
+
+    #[test]
+    fn test_debug_report_failure_is_output() {
+        let prog = Program::try_new_with_options(
+            Path::new("-"),
+            "#",
+            ParseOptions::default().with_debug_chars(true),
+        )
+        .unwrap();
+        let mut machine =
+            Machine::<u8>::new(1, TapeKind::FixedSize, &prog).with_debug_sink(DebugSink::Output);
+        let err = machine.run(io::empty(), BrokenPipe).unwrap_err();
+        assert!(matches!(
+            err,
+            InterpretError::IoError {
+                direction: IoDirection::Output,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_run_rot13() {
+        let prog = Program::from_file("../programs/rot13.bf").unwrap();
+        let mut machine = Machine::<u8>::new(DEFAULT_TAPE_SIZE, TapeKind::FixedSize, &prog);
+
+        let mut output = Vec::new();
+        let input =
+            io::Cursor::new(b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789");
+        let err = machine.run(input, &mut output).unwrap_err();
+        assert!(matches!(err,
+            InterpretError::IoError { ip_at_error, direction: IoDirection::Input, inner, .. }
+            if ip_at_error == 187 && inner.kind() == ErrorKind::UnexpectedEof
+        ));
+
+        let output = String::from_utf8(output).unwrap();
+        assert_eq!(
+            output,
+            "nopqrstuvwxyzabcdefghijklmNOPQRSTUVWXYZABCDEFGHIJKLM0123456789"
+        );
+    }
+
+    #[test]
+    fn test_run_with_limit_completes() {
+        let prog = Program::from_file("../programs/example.bf").unwrap();
+        let mut machine = Machine::<u8>::new(DEFAULT_TAPE_SIZE, TapeKind::FixedSize, &prog);
+
+        let mut output = Vec::new();
+        machine
+            .run_with_limit(io::empty(), &mut output, 1_000_000)
+            .unwrap();
+        assert_eq!(output, b"hello world");
+
+        let prog = Program::from_file("../programs/rot13.bf").unwrap();
+        let mut machine = Machine::<u8>::new(DEFAULT_TAPE_SIZE, TapeKind::FixedSize, &prog);
+
+        let mut output = Vec::new();
+        let input = io::Cursor::new(b"Hello");
+        let err = machine
+            .run_with_limit(input, &mut output, 1_000_000)
+            .unwrap_err();
+        assert!(matches!(err, InterpretError::IoError { .. }));
+        assert_eq!(output, b"Uryyb");
+    }
+
+    #[test]
+    fn test_run_with_limit_infinite_loop() {
+        let prog = Program::try_new(std::path::Path::new("-"), "+[]").unwrap();
+        let mut machine = Machine::<u8>::new(DEFAULT_TAPE_SIZE, TapeKind::FixedSize, &prog);
+
+        let err = machine
+            .run_with_limit(io::empty(), io::sink(), 101)
+            .unwrap_err();
+        // + then [ then ] jumping back to itself, so the next instruction is always ]
+        assert!(matches!(
+            err,
+            InterpretError::StepLimitExceeded {
+                ip_at_error: 2,
+                steps: 101,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_folded_instructions() {
+        let prog = Program::try_new(std::path::Path::new("-"), "+++>>>>>--<<.")
+            .unwrap()
+            .optimized();
+        let growth = GrowthStrategy::FixedIncrement(1);
+        let mut machine = Machine::<u8>::new(1, TapeKind::Growable { growth }, &prog);
+
+        let mut output = Vec::new();
+        machine.run(io::empty(), &mut output).unwrap();
+        assert_eq!(output, [0]);
+        assert_eq!(machine.dp(), 3);
+        assert_eq!(*machine.tape(), [3, 0, 0, 0, 0, 0xFE]);
+
+        let prog = Program::try_new(std::path::Path::new("-"), ">>>")
+            .unwrap()
+            .optimized();
+        let mut machine = Machine::<u8>::new(3, TapeKind::FixedSize, &prog);
+        let err = machine.run(io::empty(), io::sink()).unwrap_err();
+        assert!(matches!(
+            err,
+            InterpretError::TapeRunOffError { ip_at_error: 0, .. }
+        ));
+        assert_eq!(machine.dp(), 0);
+    }
+
+    #[rstest]
+    #[case("../programs/example.bf", b"")]
+    #[case("../programs/rot13.bf", b"Hello, World!")]
+    #[case("../programs/numwarp.bf", b"3.14159-2718\n")]
+    #[case("../programs/tic_tac_toe.bf", b"1\n5\n9\n3\n7\n")]
+    fn test_optimized_output_matches(#[case] path: &str, #[case] input: &[u8]) {
+        let run = |prog: &Program| {
+            let mut machine = Machine::<u8>::new(DEFAULT_TAPE_SIZE, TapeKind::FixedSize, prog);
+            let mut output = Vec::new();
+            // the interactive programs only stop once they run out of input
+            let res = machine.run_with_limit(input, &mut output, 100_000_000);
+            let eof = matches!(res, Err(InterpretError::IoError { .. }));
+            (output, res.is_ok() || eof)
+        };
+
+        let prog = Program::from_file(path).unwrap();
+        let (expected, stopped) = run(&prog);
+        assert!(stopped);
+        assert!(!expected.is_empty());
+        assert_eq!(run(&prog.optimized()), (expected, stopped));
+    }
+
+    #[rstest]
+    #[case("../programs/example.bf", b"")]
+    #[case("../programs/rot13.bf", b"Hello, World!")]
+    #[case("../programs/numwarp.bf", b"3.14159-2718\n")]
+    #[case("../programs/tic_tac_toe.bf", b"1\n5\n9\n3\n7\n")]
+    fn test_sparse_tape_matches_dense(#[case] path: &str, #[case] input: &[u8]) {
+        fn run<T: Tape<u8>>(prog: &Program, input: &[u8]) -> (Vec<u8>, Vec<u8>, isize) {
+            let growth = GrowthStrategy::Doubling;
+            let mut machine = Machine::<u8, T>::new(1, TapeKind::Growable { growth }, prog);
+            let mut output = Vec::new();
+            // the interactive programs only stop once they run out of input
+            let res = machine.run_with_limit(input, &mut output, 100_000_000);
+            assert!(res.is_ok() || matches!(res, Err(InterpretError::IoError { .. })));
+            (output, machine.tape().to_cells(), machine.dp())
+        }
+
+        // optimized to keep the slower sparse tape quick enough for a test
+        let prog = Program::from_file(path).unwrap().optimized();
+        let dense = run::<Vec<u8>>(&prog, input);
+        assert!(!dense.0.is_empty());
+        assert_eq!(run::<SparseTape<u8>>(&prog, input), dense);
+    }
+
+    #[test]
+    fn test_sparse_tape_grows_cheaply() {
+        let prog = Program::try_new(std::path::Path::new("-"), "+>>>>+<<<<").unwrap();
+        let mut machine = Machine::<u8, SparseTape<u8>>::new(1, TapeKind::Sparse, &prog);
+        machine.run(io::empty(), io::sink()).unwrap();
+        assert_eq!(machine.tape().to_cells(), [1, 0, 0, 0, 1, 0, 0, 0]);
+
+        let snapshot = machine.snapshot();
+        let mut restored = Machine::<u8, SparseTape<u8>>::new(1, TapeKind::Sparse, &prog);
+        restored.restore(&snapshot).unwrap();
+        assert_eq!(restored.cell_at(4), Some(&1));
+
+        let mut dump = Vec::new();
+        machine.dump_tape(&mut dump, DumpFormat::Hex).unwrap();
+        assert_eq!(dump, b"00000000: [01] 00  00  00  01 \n");
+    }
+
+    #[test]
+    fn test_reset_reruns_identically() {
+        let prog = Program::from_file("../programs/example.bf").unwrap();
+        let growth = GrowthStrategy::Doubling;
+        let mut machine = Machine::<u8>::new(1, TapeKind::GrowableBothEnds { growth }, &prog);
+        let fresh = machine.clone();
+
+        let mut first = Vec::new();
+        machine.run(io::empty(), &mut first).unwrap();
+        assert_eq!(first, b"hello world");
+        let grown = machine.tape().capacity();
+
+        machine.add_breakpoint(3);
+        machine.reset();
+        assert_eq!(machine.tape().capacity(), grown);
+        assert_eq!(*machine.tape(), *fresh.tape());
+        assert_eq!((machine.ip(), machine.dp()), (0, 0));
+
+        let mut second = Vec::new();
+        assert_eq!(
+            machine.run_until_break(io::empty(), &mut second).unwrap(),
+            RunOutcome::Halted
+        );
+        assert_eq!(first, second);
+
+        let other = Program::try_new(std::path::Path::new("-"), "<+.").unwrap();
+        machine.reset_with_program(&other);
+        let mut output = Vec::new();
+        machine.run(io::empty(), &mut output).unwrap();
+        assert_eq!(output, [1]);
+        assert_eq!(machine.dp(), -1);
+    }
+
+    #[test]
+    fn test_clear_loop_steps() {
+        let count_steps = |prog: &Program| {
+            let mut machine = Machine::<u8>::new(1, TapeKind::FixedSize, prog);
+            let (mut input, mut output) = (io::empty(), io::sink());
+            let mut steps = 1;
+            while machine.step(&mut input, &mut output).unwrap() == StepOutcome::Running {
+                steps += 1;
+            }
+            assert_eq!(machine.cell_at(0), Some(&0));
+            steps
+        };
+
+        let source = format!("{}[-]", "+".repeat(200));
+        let prog = Program::try_new(std::path::Path::new("-"), source).unwrap();
+        assert_eq!(count_steps(&prog), 200 + 1 + 200 * 2);
+        assert_eq!(count_steps(&prog.optimized()), 2);
+    }
+
+    #[test]
+    fn test_low_byte() {
+        assert_eq!(0x1234_u16.low_byte(), 0x34);
+        assert_eq!(u64::MAX.low_byte(), 0xFF);
+        assert_eq!((-2_i32).low_byte(), 0xFE);
+    }
+
+    #[test]
+    fn test_low_byte_output_matches_u8() {
+        // unlike example.bf this doesn't rely on cells wrapping at 8 bits
+        let source = "++++++++[>++++[>++>+++>+++>+<<<<-]>+>+>->>+[<]<-]>>.>---.+++++++..+++.>>.<-.<.+++.------.--------.>>+.>++.";
+        let prog = Program::try_new(std::path::Path::new("-"), source).unwrap();
+
+        fn run<Cell: CellKind>(prog: &Program) -> Vec<u8> {
+            let mut machine = Machine::<Cell>::new(DEFAULT_TAPE_SIZE, TapeKind::FixedSize, prog)
+                .with_output_mode(OutputMode::LowByte);
+            let mut output = Vec::new();
+            machine.run(io::empty(), &mut output).unwrap();
+            output
+        }
+
+        assert_eq!(run::<u8>(&prog), b"Hello World!\n");
+        assert_eq!(run::<u16>(&prog), b"Hello World!\n");
+        assert_eq!(run::<u32>(&prog), b"Hello World!\n");
+    }
+
+    #[test]
+    fn test_decimal_output() {
+        let prog = Program::try_new(std::path::Path::new("-"), ".>.>.>.").unwrap();
+
+        fn run<Cell: CellKind>(prog: &Program, cells: [Cell; 4], mode: OutputMode) -> Vec<u8> {
+            let mut machine =
+                Machine::<Cell>::new(4, TapeKind::FixedSize, prog).with_output_mode(mode);
+            machine.tape = cells.to_vec();
+            let mut output = Vec::new();
+            machine.run(io::empty(), &mut output).unwrap();
+            output
+        }
+
+        assert_eq!(
+            run::<u8>(&prog, [0, 65, 255, 10], OutputMode::Decimal),
+            b"0 65 255 10 "
+        );
+        assert_eq!(
+            run::<u8>(&prog, [0, 65, 255, 10], OutputMode::LowByte),
+            b"\0A\xFF\n"
+        );
+        let wide = [0, 65_535, u32::MAX, 1 << 20];
+        assert_eq!(
+            run::<u32>(&prog, wide, OutputMode::Decimal),
+            b"0 65535 4294967295 1048576 "
+        );
+        assert_eq!(
+            run::<i8>(&prog, [0, -1, 127, -128], OutputMode::Decimal),
+            b"0 -1 127 -128 "
+        );
+    }
+
+    #[derive(Debug, Default, Clone, PartialEq)]
+    struct PairCell([u8; 2]);
+
+    impl CellKind for PairCell {
+        fn wrapping_inc(&mut self) {}
+        fn wrapping_dec(&mut self) {}
+        fn wrapping_add_by(&mut self, _: u8) {}
+        fn wrapping_sub_by(&mut self, _: u8) {}
+        fn checked_add_by(&mut self, _: u8) -> bool {
+            true
+        }
+        fn checked_sub_by(&mut self, _: u8) -> bool {
+            true
+        }
+        fn saturating_add_by(&mut self, _: u8) {}
+        fn saturating_sub_by(&mut self, _: u8) {}
+        fn wrapping_add_product(&mut self, _: &Self, _: i16) {}
+        fn is_zero(&self) -> bool {
+            self.0 == [0, 0]
+        }
+        fn set_value(&mut self, value: u8) {
+            self.0 = [0, value];
+        }
+        fn as_bytes(&self) -> Box<[u8]> {
+            Box::new(self.0)
+        }
+        fn low_byte(&self) -> u8 {
+            self.0[1]
+        }
+        fn set_from_be_bytes(&mut self, bytes: &[u8]) {
+            self.0 = bytes.try_into().unwrap();
+        }
+    }
+
+    #[test]
+    fn test_debug_report_where_hash_occurs() {
+        let options = ParseOptions::default().with_debug_chars(true);
+        let source = "++++++++[>++++++++<-]>+.#>#<<.#";
+        let prog = Program::try_new_with_options(Path::new("d.bf"), source, options).unwrap();
+
+        for prog in [prog.clone(), prog.optimized()] {
+            let mut machine = Machine::<u8>::new(20, TapeKind::FixedSize, &prog)
+                .with_debug_sink(DebugSink::Output);
+            let mut output = Vec::new();
+            machine.run(io::empty(), &mut output).unwrap();
+            let ip = |column| {
+                prog.source_map()
+                    .iter()
+                    .position(|l| l.column == column)
+                    .unwrap()
+            };
+            assert_eq!(
+                String::from_utf8(output).unwrap(),
+                format!(
+                    "A\
+                    d.bf:1:25: ip {}, dp 1, cells 0..9: 0 [65] 0 0 0 0 0 0 0\n\
+                    d.bf:1:27: ip {}, dp 2, cells 0..10: 0 65 [0] 0 0 0 0 0 0 0\n\
+                    \0\
+                    d.bf:1:31: ip {}, dp 0, cells 0..8: [0] 65 0 0 0 0 0 0\n",
+                    ip(24),
+                    ip(26),
+                    ip(30)
+                )
+            );
+        }
+
+        let mut machine =
+            Machine::<u8>::new(20, TapeKind::FixedSize, &prog).with_debug_sink(DebugSink::Discard);
+        let mut output = Vec::new();
+        machine.run(io::empty(), &mut output).unwrap();
+        assert_eq!(output, b"A\0");
+    }
+
+    #[test]
+    fn test_coverage_dead_branch() {
+        // the second loop can't be entered as the first always leaves the cell at zero
+        let source = "++[-]\n[>+++<-]\n>.";
+        let prog = Program::try_new(Path::new("dead.bf"), source).unwrap();
+        let mut machine = Machine::<u8>::new(2, TapeKind::FixedSize, &prog).with_profiling(true);
+        machine.run(io::empty(), io::sink()).unwrap();
+
+        assert_eq!(
+            machine.execution_counts().unwrap(),
+            [1, 1, 1, 2, 2, 1, 0, 0, 0, 0, 0, 0, 0, 1, 1]
+        );
+        let report = machine.coverage().unwrap();
+        assert!(!report.is_complete());
+        let unexecuted: Vec<_> = report.unexecuted.iter().map(|u| u.ip).collect();
+        assert_eq!(unexecuted, [6, 7, 8, 9, 10, 11, 12]);
+        assert_eq!(
+            report.unexecuted[0].location,
+            SourceLocation { line: 1, column: 1 }
+        );
+        assert_eq!(
+            report.to_string(),
+            "coverage: 8 of 15 instructions executed\n  dead.bf:2:2: >+++<-]"
+        );
+
+        machine.reset();
+        assert_eq!(machine.coverage().unwrap().unexecuted.len(), 15);
+
+        let optimized = prog.optimized();
+        let mut machine =
+            Machine::<u8>::new(2, TapeKind::FixedSize, &optimized).with_profiling(true);
+        machine.run(io::empty(), io::sink()).unwrap();
+        assert_eq!(
+            machine.coverage().unwrap().to_string(),
+            "coverage: 5 of 10 instructions executed\n  dead.bf:2:2: >+++<-]"
+        );
+
+        let machine = Machine::<u8>::new(2, TapeKind::FixedSize, &prog);
+        assert_eq!(machine.coverage(), None);
+    }
+
+    #[test]
+    fn test_default_to_decimal() {
+        assert_eq!(PairCell([0x01, 0x02]).to_decimal(), "258");
+        assert_eq!(PairCell([0xFF, 0xFF]).to_decimal(), "65535");
+    }
+
+    #[test]
+    fn test_full_cell_round_trip() {
+        let prog = Program::try_new(std::path::Path::new("-"), ".").unwrap();
+        let mut machine = Machine::<u32>::new(1, TapeKind::FixedSize, &prog);
+        machine.tape[0] = 0xDEADBEEF;
+        let mut output = Vec::new();
+        machine.run(io::empty(), &mut output).unwrap();
+        assert_eq!(output, [0xDE, 0xAD, 0xBE, 0xEF]);
+
+        let prog = Program::try_new(std::path::Path::new("-"), ",").unwrap();
+        let mut machine =
+            Machine::<u32>::new(1, TapeKind::FixedSize, &prog).with_input_mode(InputMode::FullCell);
+        machine.run(&output[..], io::sink()).unwrap();
+        assert_eq!(machine.cell_at(0), Some(&0xDEADBEEF));
+    }
+
+    #[test]
+    fn test_full_cell_partial_read() {
+        let prog = Program::try_new(std::path::Path::new("-"), ",").unwrap();
+        let mut machine = Machine::<u32>::new(1, TapeKind::FixedSize, &prog)
+            .with_input_mode(InputMode::FullCell)
+            .with_eof_behaviour(EofBehaviour::Zero);
+        let err = machine.run(&[1, 2][..], io::sink()).unwrap_err();
+        assert!(matches!(
+            &err,
+            InterpretError::IoError { inner, .. } if inner.kind() == ErrorKind::UnexpectedEof
+        ));
+        assert!(err.to_string().contains("after 2 of the 4 bytes"));
+
+        // running out before a cell starts still follows the EOF behaviour
+        let mut machine = Machine::<u32>::new(1, TapeKind::FixedSize, &prog)
+            .with_input_mode(InputMode::FullCell)
+            .with_eof_behaviour(EofBehaviour::MinusOne);
+        machine.run(io::empty(), io::sink()).unwrap();
+        assert_eq!(machine.cell_at(0), Some(&u32::MAX));
+    }
+
+    #[test]
+    fn test_owned_machine_in_thread() {
+        fn assert_send<T: Send + 'static>(_: &T) {}
 
-        self.tape[self.dp].set_value(buf[0]);
+        let prog = Program::from_file("../programs/example.bf").unwrap();
+        let mut machine = Machine::<u8>::new_owned(DEFAULT_TAPE_SIZE, TapeKind::FixedSize, prog);
+        assert_send(&machine);
 
-        Ok(self.ip + 1)
+        let handle = std::thread::spawn(move || {
+            let mut output = Vec::new();
+            machine.run(io::empty(), &mut output).unwrap();
+            output
+        });
+        assert_eq!(handle.join().unwrap(), b"hello world");
     }
 
-    /// Write the big-endian byte value of the current cell into the writer
-    fn write_value(&mut self, writer: &mut impl Write) -> CommandResult {
-        let buf = self.tape[self.dp].as_bytes();
-
-        if let Err(inner) = writer.write_all(&buf) {
-            return Err(InterpretError::IoError {
-                ip_at_error: self.ip,
-                inner,
-            });
+    #[test]
+    fn test_run_cancellable() {
+        use std::{
+            sync::{mpsc, Arc},
+            thread,
+            time::Duration,
         };
 
-        writer.flush().ok();
+        let prog = Program::try_new(std::path::Path::new("-"), "+[]").unwrap();
+        let mut machine =
+            Machine::<u8>::new_owned(1, TapeKind::FixedSize, prog).with_cancel_check_interval(100);
+        let cancel = Arc::new(AtomicBool::new(false));
 
-        Ok(self.ip + 1)
+        let (tx, rx) = mpsc::channel();
+        let flag = Arc::clone(&cancel);
+        let handle = thread::spawn(move || {
+            let res = machine.run_cancellable(io::empty(), io::sink(), &flag);
+            tx.send(()).unwrap();
+            res
+        });
+
+        // the program never finishes by itself
+        assert!(rx.recv_timeout(Duration::from_millis(100)).is_err());
+
+        cancel.store(true, Ordering::Relaxed);
+        rx.recv_timeout(Duration::from_secs(5))
+            .expect("run_cancellable should return promptly once cancelled");
+        let err = handle.join().unwrap().unwrap_err();
+        assert!(matches!(
+            err,
+            InterpretError::Cancelled { ip_at_error: 2, .. }
+        ));
+        assert_eq!(
+            err.to_string(),
+            "-:1:3: program was cancelled, stopped before ']'"
+        );
     }
 
-    /// Jump forward if the value of the tape at the data pointer is zerIf the byte at the data pointer is nonzero, then instead of moving the instruction pointer forward to the next command, jump it back to the command after the matching [ command.o
-    fn jump_if_zero(&mut self, dest: usize) -> CommandResult {
-        if self.tape[self.dp].is_zero() {
-            Ok(dest)
-        } else {
-            Ok(self.ip + 1)
-        }
+    #[rstest]
+    #[case::empty_loop("+[]", 2)]
+    #[case::moves_back_and_forth("+[><]", 4)]
+    #[case::inner_loop("+>+<[>[]<]", 7)]
+    fn test_hang_detected(#[case] source: &str, #[case] ip: usize) {
+        let prog = Program::try_new(Path::new("-"), source).unwrap();
+        let mut machine =
+            Machine::<u8>::new(2, TapeKind::FixedSize, &prog).with_hang_detection(true);
+        let err = machine.run(io::empty(), io::sink()).unwrap_err();
+        assert_eq!(err.ip(), ip);
+        assert!(matches!(err, InterpretError::SuspectedInfiniteLoop { .. }));
+        assert_eq!(machine.ip(), ip);
     }
-}
 
-/// errors that can occor while interpreting a brainfuck program
-#[derive(Debug)]
-pub enum InterpretError {
-    /// The data pointer exceed the bounds of the tape
-    TapeRunOffError {
-        /// The instruction which lead to the error
-        ip_at_error: usize,
-    },
+    #[rstest]
+    #[case::counter("++++++++[>++++++++[>++++++++<-]<-]")]
+    #[case::search_left("+>+>+>+>[<]")]
+    #[case::clear("-[-]")]
+    fn test_long_loop_not_flagged(#[case] source: &str) {
+        let prog = Program::try_new(Path::new("-"), source).unwrap();
+        let mut machine =
+            Machine::<u16>::new(8, TapeKind::FixedSize, &prog).with_hang_detection(true);
+        machine.run(io::empty(), io::sink()).unwrap();
+    }
 
-    /// The virtual machine failed to perform an IO operation
-    IoError {
-        /// The instruction which lead to the error
-        ip_at_error: usize,
-        /// The inner IO error which caused the failure
-        inner: io::Error,
-    },
-}
+    #[test]
+    fn test_hang_error_message() {
+        let prog = Program::try_new(Path::new("-"), "+[]").unwrap();
+        let mut machine =
+            Machine::<u8>::new(1, TapeKind::FixedSize, &prog).with_hang_detection(true);
+        let err = machine.run(io::empty(), io::sink()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "-:1:3: loop repeated 1000 times without changing anything, stopped at ']'"
+        );
+    }
 
-impl fmt::Display for InterpretError {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            Self::TapeRunOffError { ip_at_error } => {
-                write!(
-                    f,
-                    "Error: Exceeded the bounds of the tape, IP={ip_at_error}"
-                )
-            }
-            Self::IoError { ip_at_error, inner } => {
-                write!(f, "Error: Failed to perform IO ({inner}), IP={ip_at_error}")
-            }
+    #[test]
+    fn test_snapshot_restore() {
+        let prog = Program::from_file("../programs/example.bf").unwrap();
+        let mut machine = Machine::<u8>::new(DEFAULT_TAPE_SIZE, TapeKind::FixedSize, &prog);
+
+        let mut output = Vec::new();
+        for _ in 0..50 {
+            machine.step(&mut io::empty(), &mut output).unwrap();
         }
+        let state = machine.snapshot();
+        assert_eq!(state.ip, machine.ip());
+
+        let mut first = output.clone();
+        machine.run(io::empty(), &mut first).unwrap();
+
+        machine.restore(&state).unwrap();
+        assert_eq!(machine.snapshot(), state);
+        let mut second = output;
+        machine.run(io::empty(), &mut second).unwrap();
+
+        assert_eq!(first, b"hello world");
+        assert_eq!(first, second);
     }
-}
 
-impl std::error::Error for InterpretError {}
+    #[test]
+    fn test_restore_different_program() {
+        let prog = Program::try_new(std::path::Path::new("-"), "+").unwrap();
+        let other = Program::try_new(std::path::Path::new("-"), "-").unwrap();
+        let state = Machine::<u8>::new(1, TapeKind::FixedSize, &other).snapshot();
 
-#[cfg(test)]
-mod tests {
-    use std::io::ErrorKind;
+        let mut machine = Machine::<u8>::new(1, TapeKind::FixedSize, &prog);
+        machine.run(io::empty(), io::sink()).unwrap();
+        assert_eq!(machine.restore(&state), Err(RestoreError::DifferentProgram));
+        assert_eq!((machine.ip(), machine.cell_at(0)), (1, Some(&1)));
+    }
 
-    use super::*;
+    #[test]
+    fn test_program_hash_is_stable() {
+        // a stored state must keep restoring, whatever platform or Rust release it is read on
+        let prog = Program::try_new(Path::new("-"), "+[-]>.").unwrap();
+        assert_eq!(program_hash(&prog), 0x47e1_d6a8_30b8_5b41);
+        let optimized = Program::try_new(Path::new("-"), "++[-]")
+            .unwrap()
+            .optimized();
+        assert_ne!(program_hash(&optimized), program_hash(&prog));
+    }
+
+    #[rstest]
+    #[case::empty_tape(
+        |state: &mut MachineState<u8>| state.tape.clear(),
+        RestoreError::HeadOffTape { dp: 0, tape_len: 0 },
+        "the head is on cell 0 of a machine state with 0 cells"
+    )]
+    #[case::head_off_tape(
+        |state: &mut MachineState<u8>| state.dp = 3,
+        RestoreError::HeadOffTape { dp: 3, tape_len: 3 },
+        "the head is on cell 3 of a machine state with 3 cells"
+    )]
+    #[case::origin_off_tape(
+        |state: &mut MachineState<u8>| state.origin = 7,
+        RestoreError::OriginOffTape { origin: 7, tape_len: 3 },
+        "the head started on cell 7 of a machine state with 3 cells"
+    )]
+    #[case::ip_past_end(
+        |state: &mut MachineState<u8>| state.ip = 3,
+        RestoreError::InstructionOutOfRange { ip: 3, instructions: 2 },
+        "the next instruction is 3 in a program of 2 instructions"
+    )]
+    fn test_restore_invalid_state(
+        #[case] corrupt: fn(&mut MachineState<u8>),
+        #[case] expected: RestoreError,
+        #[case] message: &str,
+    ) {
+        let prog = Program::try_new(Path::new("-"), ">+").unwrap();
+        let mut machine = Machine::<u8>::new(3, TapeKind::FixedSize, &prog);
+        let mut state = machine.snapshot();
+        corrupt(&mut state);
+
+        machine.run(io::empty(), io::sink()).unwrap();
+        assert_eq!(machine.restore(&state), Err(expected));
+        assert_eq!(expected.to_string(), message);
+        // nothing was changed, so the machine is still usable
+        assert_eq!(
+            (machine.ip(), machine.dp(), machine.cell_at(1)),
+            (2, 1, Some(&1))
+        );
+
+        // a state which has run to the end of the program is fine
+        state = machine.snapshot();
+        assert_eq!(machine.restore(&state), Ok(()));
+    }
 
     #[test]
-    fn test_move_head_right_grows() {
-        let prog = Program::from_file("../programs/example.bf").unwrap();
-        let mut machine = Machine::<u8>::new(1, TapeKind::Growable, &prog);
+    fn test_restore_resets_counters() {
+        let prog = Program::try_new(Path::new("-"), "+[]").unwrap();
+        let mut fresh = Machine::<u8>::new(1, TapeKind::FixedSize, &prog).with_hang_detection(true);
+        fresh.run(io::empty(), io::sink()).unwrap_err();
+        let steps_to_detect = fresh.steps();
 
-        for i in 0..100 {
-            assert_eq!(machine.dp, i);
-            assert!(machine.tape.len() > i);
-            let new_ip = machine.move_head_right().unwrap();
-            assert_eq!(new_ip, machine.ip + 1);
-            machine.ip = new_ip;
-            assert_eq!(machine.dp, i + 1);
-            assert!(machine.tape.len() > i + 1);
+        let mut machine =
+            Machine::<u8>::new(1, TapeKind::FixedSize, &prog).with_hang_detection(true);
+        let start = machine.snapshot();
+        for _ in 1..steps_to_detect {
+            machine.step(&mut io::empty(), &mut io::sink()).unwrap();
         }
+        machine.restore(&start).unwrap();
+        assert_eq!(machine.steps(), 0);
+
+        // the loop has to repeat as many times again before it is taken to have hung
+        let err = machine.run(io::empty(), io::sink()).unwrap_err();
+        assert!(matches!(err, InterpretError::SuspectedInfiniteLoop { .. }));
+        assert_eq!(machine.steps(), steps_to_detect);
     }
 
     #[test]
-    fn test_move_head_right_run_off() {
+    fn test_breakpoint_hit_count() {
         let prog = Program::from_file("../programs/example.bf").unwrap();
-        let mut machine = Machine::<u8>::new(100, TapeKind::FixedSize, &prog);
 
-        for _ in 0..99 {
-            let new_ip = machine.move_head_right().unwrap();
-            assert_eq!(new_ip, machine.ip + 1);
-            machine.ip = new_ip;
+        // count how often the first instruction of the main loop is executed by stepping
+        let mut machine = Machine::<u8>::new(DEFAULT_TAPE_SIZE, TapeKind::FixedSize, &prog);
+        let mut executions = 0;
+        loop {
+            executions += usize::from(machine.ip() == 2);
+            let outcome = machine.step(&mut io::empty(), &mut io::sink()).unwrap();
+            if outcome == StepOutcome::Halted {
+                break;
+            }
+        }
+
+        let mut machine = Machine::<u8>::new(DEFAULT_TAPE_SIZE, TapeKind::FixedSize, &prog);
+        machine.add_breakpoint(2);
+        let mut output = Vec::new();
+        let mut hits = 0;
+        while let RunOutcome::Breakpoint { ip } =
+            machine.run_until_break(io::empty(), &mut output).unwrap()
+        {
+            assert_eq!(ip, 2);
+            hits += 1;
         }
+        assert_eq!(hits, executions);
+        assert!(hits > 1);
+        assert_eq!(output, b"hello world");
+
+        assert!(machine.remove_breakpoint(2));
+        assert!(!machine.remove_breakpoint(2));
+    }
+
+    #[test]
+    fn test_run_until_break_step_limit() {
+        let prog = Program::try_new(std::path::Path::new("-"), "+++[-]").unwrap();
+        let mut machine = Machine::<u8>::new(1, TapeKind::FixedSize, &prog).with_max_steps(5);
+        machine.add_breakpoint(2);
+
+        // the limit counts the steps taken before the breakpoint too
+        let outcome = machine.run_until_break(io::empty(), io::sink()).unwrap();
+        assert_eq!(outcome, RunOutcome::Breakpoint { ip: 2 });
+        let err = machine
+            .run_until_break(io::empty(), io::sink())
+            .unwrap_err();
         assert!(matches!(
-            machine.move_head_right().unwrap_err(),
-            InterpretError::TapeRunOffError {
-                ip_at_error
-            } if ip_at_error == machine.ip
+            err,
+            InterpretError::StepLimitExceeded { steps: 5, .. }
         ));
+        assert_eq!(machine.steps(), 5);
     }
 
-    #[test]
-    fn test_move_head_left() {
-        let prog = Program::from_file("../programs/example.bf").unwrap();
-        let mut machine = Machine::<u8>::new(1, TapeKind::Growable, &prog);
+    #[rstest]
+    #[case::run("run")]
+    #[case::with_limit("run_with_limit")]
+    #[case::cancellable("run_cancellable")]
+    #[case::traced("run_traced")]
+    #[case::until_break("run_until_break")]
+    fn test_step_limit_counts_every_step(#[case] method: &str) {
+        let prog = Program::try_new(Path::new("-"), "+[]").unwrap();
+        let mut machine = Machine::<u8>::new(1, TapeKind::FixedSize, &prog).with_max_steps(10);
+        // steps taken before the run count towards the limit
+        for _ in 0..4 {
+            machine.step(&mut io::empty(), &mut io::sink()).unwrap();
+        }
 
-        for _ in 0..100 {
-            machine.move_head_right().unwrap();
+        let cancel = std::sync::atomic::AtomicBool::new(false);
+        let err = match method {
+            "run" => machine.run(io::empty(), io::sink()),
+            "run_with_limit" => machine.run_with_limit(io::empty(), io::sink(), 10),
+            "run_cancellable" => machine.run_cancellable(io::empty(), io::sink(), &cancel),
+            "run_traced" => machine.run_traced(io::empty(), io::sink(), |_| {}),
+            _ => machine.run_until_break(io::empty(), io::sink()).map(|_| ()),
         }
-        for i in (0..100).rev() {
-            assert_eq!(machine.dp, i + 1);
-            let new_ip = machine.move_head_left().unwrap();
-            assert_eq!(machine.dp, i);
-            assert_eq!(new_ip, machine.ip + 1);
-            machine.ip = new_ip;
+        .unwrap_err();
+        assert!(matches!(
+            err,
+            InterpretError::StepLimitExceeded { steps: 10, .. }
+        ));
+        assert_eq!(machine.steps(), 10);
+    }
+
+    #[test]
+    fn test_watchpoint() {
+        let prog = Program::try_new(std::path::Path::new("-"), ">+++<+>[-]").unwrap();
+        let mut machine = Machine::<u8>::new(2, TapeKind::FixedSize, &prog);
+        machine.add_watchpoint(1);
+
+        let mut changes = Vec::new();
+        loop {
+            match machine.run_until_break(io::empty(), io::sink()).unwrap() {
+                RunOutcome::Watchpoint { cell, old, new } => changes.push((cell, old, new)),
+                RunOutcome::Halted => break,
+                RunOutcome::Breakpoint { .. } => unreachable!(),
+            }
         }
+        assert_eq!(
+            changes,
+            [
+                (1, 0, 1),
+                (1, 1, 2),
+                (1, 2, 3),
+                (1, 3, 2),
+                (1, 2, 1),
+                (1, 1, 0)
+            ]
+        );
     }
 
     #[test]
-    fn test_move_head_left_run_off() {
-        let prog = Program::from_file("../programs/example.bf").unwrap();
-        let mut machine = Machine::<u8>::new(100, TapeKind::FixedSize, &prog);
+    fn test_run_traced() {
+        let prog = Program::try_new(std::path::Path::new("-"), "+[-]>").unwrap();
+        let mut machine = Machine::<u8>::new(2, TapeKind::FixedSize, &prog);
+
+        let mut trace = Vec::new();
+        machine
+            .run_traced(io::empty(), io::sink(), |event| {
+                trace.push(event.clone());
+            })
+            .unwrap();
+
+        let lines: Vec<String> = trace.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            lines,
+            [
+                "ip=0 '+' dp=0 cell=0 -> ip=1 cell=1",
+                "ip=1 '[' dp=0 cell=1 -> ip=2 cell=1",
+                "ip=2 '-' dp=0 cell=1 -> ip=3 cell=0",
+                "ip=3 ']' dp=0 cell=0 -> ip=4 cell=0",
+                "ip=4 '>' dp=0 cell=0 -> ip=5 cell=0",
+            ]
+        );
+        assert_eq!(trace[3].location, SourceLocation { line: 0, column: 3 });
+        assert_eq!(trace[3].instruction, Instruction::Jnz { dest: 2 });
+        assert!(trace.iter().all(|event| event.rewrite.is_none()));
+    }
+
+    #[test]
+    fn test_run_traced_step_limit() {
+        let prog = Program::try_new(std::path::Path::new("-"), "+[]").unwrap();
+        let mut machine = Machine::<u8>::new(1, TapeKind::FixedSize, &prog).with_max_steps(10);
 
+        let mut traced = 0;
+        let err = machine
+            .run_traced(io::empty(), io::sink(), |_| traced += 1)
+            .unwrap_err();
         assert!(matches!(
-            machine.move_head_left().unwrap_err(),
-            InterpretError::TapeRunOffError { ip_at_error: 0 }
+            err,
+            InterpretError::StepLimitExceeded { steps: 10, .. }
         ));
+        assert_eq!(traced, 10);
     }
 
     #[test]
-    fn test_increment_cell() {
-        let prog = Program::from_file("../programs/example.bf").unwrap();
-        let mut machine = Machine::<u8>::new(100, TapeKind::FixedSize, &prog);
+    fn test_run_traced_multiply_loop() {
+        let prog = Program::try_new(Path::new("-"), "++[->+++<]>.")
+            .unwrap()
+            .optimized();
+        let mut machine =
+            Machine::<u8>::new(2, TapeKind::FixedSize, &prog).with_multiply_loops(true);
 
-        let new_ip = machine.increment_cell().unwrap();
-        assert_eq!(new_ip, machine.ip + 1);
-        assert_eq!(machine.tape[0], 1);
-        machine.ip = new_ip;
+        let mut trace = Vec::new();
+        machine
+            .run_traced(io::empty(), io::sink(), |event| {
+                trace.push(event.to_string());
+            })
+            .unwrap();
+        assert_eq!(
+            trace,
+            [
+                "ip=0 '+' dp=0 cell=0 -> ip=1 cell=2",
+                "ip=1 '[' as mul-add(+1, 3) dp=0 cell=2 -> ip=2 cell=2",
+                "ip=2 '-' as set-zero dp=0 cell=2 -> ip=3 cell=0",
+                "ip=3 '>' as skip-loop dp=0 cell=0 -> ip=7 cell=0",
+                "ip=7 '>' dp=0 cell=0 -> ip=8 cell=0",
+                "ip=8 '.' dp=1 cell=6 -> ip=9 cell=6",
+            ]
+        );
+    }
 
-        machine.tape[0].set_value(0xFF);
-        let new_ip = machine.increment_cell().unwrap();
-        assert_eq!(new_ip, machine.ip + 1);
-        assert_eq!(machine.tape[0], 0);
+    #[test]
+    fn test_coverage_multiply_loop() {
+        let prog = Program::try_new(Path::new("-"), "++[->+++<]>.")
+            .unwrap()
+            .optimized();
+        for (multiply_loops, profiling) in [(true, true), (false, true)] {
+            let mut machine = Machine::<u8>::new(2, TapeKind::FixedSize, &prog)
+                .with_multiply_loops(multiply_loops)
+                .with_profiling(profiling);
+            machine.run(io::empty(), io::sink()).unwrap();
+            let coverage = machine.coverage().unwrap();
+            assert!(coverage.unexecuted.is_empty(), "{coverage}");
+            assert_eq!(machine.execution_counts().unwrap()[2], 2);
+        }
     }
 
     #[test]
-    fn test_decrement_cell() {
-        let prog = Program::from_file("../programs/example.bf").unwrap();
-        let mut machine = Machine::<u8>::new(100, TapeKind::FixedSize, &prog);
+    fn test_grow_left() {
+        // write to the cell ten to the left, then the starting cell, then print them both
+        let source = format!(
+            "{left}{a}{right}{b}{left}.{right}.",
+            left = "<".repeat(10),
+            right = ">".repeat(10),
+            a = "+".repeat(65),
+            b = "+".repeat(66),
+        );
+        let prog = Program::try_new(std::path::Path::new("-"), source).unwrap();
+        let growth = GrowthStrategy::FixedIncrement(4);
+        let mut machine = Machine::<u8>::new(1, TapeKind::GrowableBothEnds { growth }, &prog);
 
-        let new_ip = machine.decrement_cell().unwrap();
-        assert_eq!(new_ip, machine.ip + 1);
-        assert_eq!(machine.tape[0], 0xFF);
-        machine.ip = new_ip;
+        let mut output = Vec::new();
+        machine.run(io::empty(), &mut output).unwrap();
+        assert_eq!(output, b"AB");
+        assert_eq!(machine.dp(), 0);
+        assert_eq!(machine.tape_start(), -12);
+        assert_eq!(machine.cell_at(-10), Some(&65));
+        assert_eq!(machine.cell_at(0), Some(&66));
+        assert_eq!(machine.cell_at(-13), None);
 
-        let new_ip = machine.decrement_cell().unwrap();
-        assert_eq!(new_ip, machine.ip + 1);
-        assert_eq!(machine.tape[0], 0xFE);
+        let mut hex = Vec::new();
+        machine.dump_tape(&mut hex, DumpFormat::Hex).unwrap();
+        assert_eq!(
+            String::from_utf8(hex).unwrap(),
+            format!("-000000a:  41 {}[42]\n", " 00 ".repeat(9))
+        );
     }
 
     #[test]
-    fn test_read_value() {
-        let prog = Program::from_file("../programs/example.bf").unwrap();
-        let mut machine = Machine::<u8>::new(100, TapeKind::FixedSize, &prog);
+    fn test_grow_left_only_when_asked() {
+        let prog = Program::try_new(std::path::Path::new("-"), "<").unwrap();
+        let growth = GrowthStrategy::default();
+        let mut machine = Machine::<u8>::new(1, TapeKind::Growable { growth }, &prog);
+        let err = machine.run(io::empty(), io::sink()).unwrap_err();
+        assert!(matches!(err, InterpretError::TapeRunOffError { .. }));
 
-        let data = 0xDEADBEEF_u32.to_be_bytes();
-        let mut reader = std::io::Cursor::new(data);
-        for d in data {
-            let new_ip = machine.read_value(&mut reader).unwrap();
-            assert_eq!(machine.tape[0], d);
-            assert_eq!(new_ip, machine.ip + 1);
-            machine.ip = new_ip;
-        }
-        machine.read_value(&mut reader).unwrap_err();
+        let mut machine = Machine::<u8>::new(1, TapeKind::GrowableBothEnds { growth }, &prog);
+        machine.run(io::empty(), io::sink()).unwrap();
+        assert_eq!(machine.dp(), -1);
     }
 
     #[test]
-    fn test_write_value() {
-        let prog = Program::from_file("../programs/example.bf").unwrap();
-        let mut machine = Machine::<u8>::new(100, TapeKind::FixedSize, &prog);
-
-        for i in u8::MIN..=u8::MAX {
-            let mut writer = std::io::Cursor::new(vec![0u8]);
-            machine.tape[0].set_value(i);
-            let new_ip = machine.write_value(&mut writer).unwrap();
-            assert_eq!(writer.get_ref(), &[i]);
-            assert_eq!(new_ip, machine.ip + 1);
-            machine.ip = new_ip;
+    fn test_wrapping_tape() {
+        let prog = Program::try_new(std::path::Path::new("-"), "").unwrap();
+        let mut machine = Machine::<u8>::new(5, TapeKind::Wrapping, &prog);
+        for _ in 0..7 {
+            machine.move_head_right(1).unwrap();
         }
+        assert_eq!(machine.dp(), 2);
+        machine.move_head_right(14).unwrap();
+        assert_eq!(machine.dp(), 1);
+        machine.move_head_left(2).unwrap();
+        assert_eq!(machine.dp(), 4);
+        machine.move_head_left(11).unwrap();
+        assert_eq!(machine.dp(), 3);
+        assert_eq!(machine.tape().len(), 5);
     }
 
     #[test]
-    fn test_write_value_is_be() {
-        let prog = Program::from_file("../programs/example.bf").unwrap();
-        let mut machine = Machine::<u32>::new(100, TapeKind::FixedSize, &prog);
+    fn test_run_wrapping_tape() {
+        // set the last cell by going left from the first, then reach it again from the right
+        let source = format!("<{}>>>>>.", "+".repeat(65));
+        let prog = Program::try_new(std::path::Path::new("-"), source).unwrap();
 
-        let val = 0xDEAD_BEEF;
-        machine.tape[0] = val;
-        let mut writer = std::io::Cursor::new(vec![0u8; 4]);
-        for _ in 0..4 {
-            let new_ip = machine.write_value(&mut writer).unwrap();
-            assert_eq!(new_ip, machine.ip + 1);
-            machine.ip = new_ip;
-        }
+        let mut machine = Machine::<u8>::new(5, TapeKind::Wrapping, &prog);
+        let mut output = Vec::new();
+        machine.run(io::empty(), &mut output).unwrap();
+        assert_eq!(output, b"A");
+        assert_eq!(machine.dp(), 4);
 
-        assert_eq!(&writer.get_ref()[..4], &val.to_be_bytes());
+        let mut machine = Machine::<u8>::new(5, TapeKind::FixedSize, &prog);
+        let err = machine.run(io::empty(), io::sink()).unwrap_err();
+        assert!(matches!(
+            err,
+            InterpretError::TapeRunOffError { ip_at_error: 0, .. }
+        ));
     }
 
     #[test]
-    fn test_is_zero() {
-        let prog = Program::from_file("../programs/example.bf").unwrap();
-        let mut machine = Machine::<u8>::new(100, TapeKind::FixedSize, &prog);
+    fn test_max_cells() {
+        let prog = Program::try_new(std::path::Path::new("lim.bf"), "+[>+]").unwrap();
+        let growth = GrowthStrategy::Doubling;
+        let mut machine =
+            Machine::<u16>::new(1, TapeKind::Growable { growth }, &prog).with_max_cells(5);
 
-        assert!(machine.tape[0].is_zero());
-        machine.tape[0].wrapping_inc();
-        assert!(!machine.tape[0].is_zero());
+        let err = machine.run(io::empty(), io::sink()).unwrap_err();
+        assert!(matches!(
+            err,
+            InterpretError::TapeLimitExceeded {
+                ip_at_error: 2,
+                limit: 5,
+                cell_bytes: 2,
+                ..
+            }
+        ));
+        assert_eq!(machine.dp(), 4);
+        assert_eq!(*machine.tape(), [1; 5]);
+        assert_eq!(
+            err.to_string(),
+            "lim.bf:1:3: tape can't grow beyond its limit of 5 cells (10 bytes of 2-byte cells) while executing '>'"
+        );
+
+        // the limit applies when growing to the left too
+        let prog = Program::try_new(std::path::Path::new("-"), "<<<").unwrap();
+        let mut machine =
+            Machine::<u8>::new(1, TapeKind::GrowableBothEnds { growth }, &prog).with_max_cells(3);
+        let err = machine.run(io::empty(), io::sink()).unwrap_err();
+        assert!(matches!(
+            err,
+            InterpretError::TapeLimitExceeded { ip_at_error: 2, .. }
+        ));
+        assert_eq!(machine.dp(), -2);
     }
 
     #[test]
-    fn test_jump_if_zero() {
-        let prog = Program::from_file("../programs/example.bf").unwrap();
-        let mut machine = Machine::<u8>::new(100, TapeKind::FixedSize, &prog);
+    fn test_replay_failed_write() {
+        let prog = Program::try_new(Path::new("-"), ",.").unwrap();
+        let mut machine = Machine::<u8>::new(1, TapeKind::FixedSize, &prog).with_recording(true);
+        machine.run(&b"a"[..], io::sink()).unwrap();
+        let replay = Replay::new(machine.transcript().unwrap().iter().copied());
 
-        assert!(machine.tape[0].is_zero());
-        assert_eq!(machine.jump_if_zero(1234).unwrap(), 1234);
-        machine.tape[0].wrapping_inc();
-        assert!(!machine.tape[0].is_zero());
-        assert_eq!(machine.ip, 0);
-        assert_eq!(machine.jump_if_zero(1234).unwrap(), 1);
+        // the recorded write is still to come once the real one fails
+        let mut machine = Machine::<u8>::new(1, TapeKind::FixedSize, &prog).with_replay(replay);
+        machine.run(io::empty(), BrokenPipe).unwrap_err();
+        assert_eq!(machine.replay().map(Replay::remaining), Some(1));
+        let mut output = Vec::new();
+        machine.run(io::empty(), &mut output).unwrap();
+        assert_eq!(output, b"a");
+        machine.replay().unwrap().finish().unwrap();
     }
 
     #[test]
-    fn test_run_hello_world() {
-        let prog = Program::from_file("../programs/example.bf").unwrap();
-        let mut machine = Machine::<u8>::new(DEFAULT_TAPE_SIZE, TapeKind::FixedSize, &prog);
+    fn test_replay_changed_program() {
+        let prog = Program::try_new(std::path::Path::new("-"), ",[.,]").unwrap();
+        let mut machine = Machine::<u8>::new(1, TapeKind::FixedSize, &prog)
+            .with_eof_behaviour(EofBehaviour::Zero)
+            .with_recording(true);
+        machine.run(&b"hi"[..], io::sink()).unwrap();
+        let replay = Replay::new(machine.transcript().unwrap().iter().copied());
 
+        // the same program repeats the run, reading nothing from the reader it is given
+        let mut machine = Machine::<u8>::new(1, TapeKind::FixedSize, &prog)
+            .with_eof_behaviour(EofBehaviour::Zero)
+            .with_replay(replay.clone());
         let mut output = Vec::new();
         machine.run(io::empty(), &mut output).unwrap();
+        assert_eq!(output, b"hi");
+        machine.replay().unwrap().finish().unwrap();
 
-        let output = String::from_utf8(output).unwrap();
-        assert_eq!(output, "hello world");
+        // a changed program writes something else, which is never written
+        let changed = Program::try_new(std::path::Path::new("-"), ",[+.,]").unwrap();
+        let mut machine = Machine::<u8>::new(1, TapeKind::FixedSize, &changed)
+            .with_eof_behaviour(EofBehaviour::Zero)
+            .with_replay(replay.clone());
+        let mut output = Vec::new();
+        let err = machine.run(io::empty(), &mut output).unwrap_err();
+        assert!(matches!(
+            err,
+            InterpretError::IoError {
+                ip_at_error: 3,
+                direction: IoDirection::Output,
+                ..
+            }
+        ));
+        assert!(output.is_empty());
+
+        // as does one reading from a different instruction
+        let changed = Program::try_new(std::path::Path::new("-"), ">,[.,]").unwrap();
+        let mut machine = Machine::<u8>::new(2, TapeKind::FixedSize, &changed)
+            .with_eof_behaviour(EofBehaviour::Zero)
+            .with_replay(replay);
+        let err = machine.run(io::empty(), io::sink()).unwrap_err();
+        assert!(matches!(
+            err,
+            InterpretError::IoError {
+                ip_at_error: 1,
+                direction: IoDirection::Input,
+                ..
+            }
+        ));
     }
 
     #[test]
-    fn test_run_rot13() {
-        let prog = Program::from_file("../programs/rot13.bf").unwrap();
-        let mut machine = Machine::<u8>::new(DEFAULT_TAPE_SIZE, TapeKind::FixedSize, &prog);
+    fn test_stats_peak_tape_len() {
+        let prog = Program::try_new(std::path::Path::new("-"), ">>>>+<<").unwrap();
+        let mut machine = Machine::<u8>::new(30000, TapeKind::FixedSize, &prog);
+        machine.run(io::empty(), io::sink()).unwrap();
+        assert_eq!(machine.stats().peak_tape_len, 5);
+        machine.reset();
+        assert_eq!(machine.stats().peak_tape_len, 1);
 
-        let mut output = Vec::new();
-        let input =
-            io::Cursor::new(b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789");
-        let err = machine.run(input, &mut output).unwrap_err();
-        assert!(matches!(err,
-            InterpretError::IoError { ip_at_error, inner }
-            if ip_at_error == 187 && inner.kind() == ErrorKind::UnexpectedEof
-        ));
+        let prog = Program::try_new(std::path::Path::new("-"), "<<+>>>>").unwrap();
+        let growth = GrowthStrategy::Doubling;
+        let mut machine = Machine::<u8>::new(1, TapeKind::GrowableBothEnds { growth }, &prog);
+        machine.run(io::empty(), io::sink()).unwrap();
+        assert_eq!(machine.stats().peak_tape_len, 5);
 
-        let output = String::from_utf8(output).unwrap();
-        assert_eq!(
-            output,
-            "nopqrstuvwxyzabcdefghijklmNOPQRSTUVWXYZABCDEFGHIJKLM0123456789"
-        );
+        // a wrapping tape is crossed to reach the cell to the left of the start
+        let prog = Program::try_new(std::path::Path::new("-"), "<").unwrap();
+        let mut machine = Machine::<u8>::new(10, TapeKind::Wrapping, &prog);
+        machine.run(io::empty(), io::sink()).unwrap();
+        assert_eq!(machine.stats().peak_tape_len, 10);
     }
 }
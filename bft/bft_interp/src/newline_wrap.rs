@@ -3,10 +3,10 @@ use std::io::{self, Write};
 /// Wrap an object implementing Write such that on drop a newline is written if what was written
 /// didn't end in a newline
 pub struct NewlineWrap<W: Write> {
-    /// inner Write instance
-    inner: W,
-    /// last byte written to the inner instance
-    last_written: u8,
+    /// inner Write instance, only taken when the wrapper is consumed
+    inner: Option<W>,
+    /// last byte written to the inner instance, if anything has been written
+    last_written: Option<u8>,
 }
 
 impl<W: Write> NewlineWrap<W> {
@@ -21,35 +21,175 @@ impl<W: Write> NewlineWrap<W> {
     ///     write!(wrapped, "This doesn't end in a newline.").unwrap();
     /// }
     /// assert_eq!(data_written, b"This doesn't end in a newline.\n");
+    ///
+    /// // nothing is added when nothing was written
+    /// let mut data_written = Vec::new();
+    /// drop(NewlineWrap::new(&mut data_written));
+    /// assert!(data_written.is_empty());
     /// ```
     pub fn new(writer: W) -> Self {
         Self {
-            inner: writer,
-            last_written: 0,
+            inner: Some(writer),
+            last_written: None,
+        }
+    }
+
+    /// Write the trailing newline if needed and flush, returning the inner writer
+    ///
+    /// Unlike dropping the wrapper, any error writing the newline or flushing is reported.
+    ///
+    /// ```
+    /// # use bft_interp::NewlineWrap;
+    /// # use std::io::Write;
+    /// let mut wrapped = NewlineWrap::new(Vec::new());
+    /// write!(wrapped, "no newline").unwrap();
+    /// assert_eq!(wrapped.finish().unwrap(), b"no newline\n");
+    /// ```
+    pub fn finish(mut self) -> io::Result<W> {
+        self.terminate()?;
+        Ok(self.take_inner())
+    }
+
+    /// Return the inner writer without writing the trailing newline
+    ///
+    /// ```
+    /// # use bft_interp::NewlineWrap;
+    /// # use std::io::Write;
+    /// let mut wrapped = NewlineWrap::new(Vec::new());
+    /// write!(wrapped, "no newline").unwrap();
+    /// assert_eq!(wrapped.into_inner(), b"no newline");
+    /// ```
+    pub fn into_inner(mut self) -> W {
+        self.take_inner()
+    }
+
+    /// The inner writer, which is always present until the wrapper is consumed
+    fn inner(&mut self) -> &mut W {
+        self.inner
+            .as_mut()
+            .expect("inner writer is only taken when the wrapper is consumed")
+    }
+
+    /// Take the inner writer, leaving nothing for drop to write to
+    fn take_inner(&mut self) -> W {
+        self.inner
+            .take()
+            .expect("inner writer is only taken when the wrapper is consumed")
+    }
+
+    /// Write a newline if something was written and it didn't end in one, then flush
+    fn terminate(&mut self) -> io::Result<()> {
+        if self.last_written.is_some_and(|last| last != b'\n') {
+            self.write_all(b"\n")?;
         }
+        self.flush()
     }
 }
 
 impl<W: Write> Write for NewlineWrap<W> {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-        let res = self.inner.write(buf)?;
-        if let Some(last) = buf.last() {
-            self.last_written = *last;
+        let res = self.inner().write(buf)?;
+        // only the bytes the inner writer accepted have been written
+        if let Some(&last) = buf[..res].last() {
+            self.last_written = Some(last);
         }
 
         Ok(res)
     }
 
     fn flush(&mut self) -> io::Result<()> {
-        self.inner.flush()
+        self.inner().flush()
     }
 }
 
 impl<W: Write> Drop for NewlineWrap<W> {
     fn drop(&mut self) {
-        if self.last_written != b'\n' {
-            self.write_all(b"\n").ok();
-            self.flush().ok();
+        // finish or into_inner may already have taken the writer
+        if self.inner.is_some() {
+            self.terminate().ok();
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A writer which accepts everything but always fails to flush
+    #[derive(Debug)]
+    struct FailingFlush(Vec<u8>);
+
+    impl Write for FailingFlush {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Err(io::Error::new(io::ErrorKind::BrokenPipe, "pipe is full"))
+        }
+    }
+
+    /// A writer which only accepts a single byte per call to write
+    #[derive(Debug, Default)]
+    struct OneByteAtATime(Vec<u8>);
+
+    impl Write for OneByteAtATime {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.write(&buf[..buf.len().min(1)])
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_partial_write_tracking() {
+        let mut wrapped = NewlineWrap::new(OneByteAtATime::default());
+        assert_eq!(wrapped.write(b"ab\n").unwrap(), 1);
+        assert_eq!(wrapped.write(b"").unwrap(), 0);
+        assert_eq!(wrapped.finish().unwrap().0, b"a\n");
+
+        let mut wrapped = NewlineWrap::new(OneByteAtATime::default());
+        wrapped.write_all(b"abc").unwrap();
+        assert_eq!(wrapped.finish().unwrap().0, b"abc\n");
+
+        let mut wrapped = NewlineWrap::new(OneByteAtATime::default());
+        wrapped.write_all(b"abc\n").unwrap();
+        assert_eq!(wrapped.finish().unwrap().0, b"abc\n");
+    }
+
+    #[test]
+    fn test_finish_reports_flush_error() {
+        let mut wrapped = NewlineWrap::new(FailingFlush(Vec::new()));
+        write!(wrapped, "abc").unwrap();
+        let err = wrapped.finish().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::BrokenPipe);
+    }
+
+    #[test]
+    fn test_finish_only_adds_missing_newline() {
+        let mut wrapped = NewlineWrap::new(Vec::new());
+        wrapped.write_all(b"abc\n").unwrap();
+        assert_eq!(wrapped.finish().unwrap(), b"abc\n");
+    }
+
+    #[test]
+    fn test_no_output_no_newline() {
+        let wrapped = NewlineWrap::new(Vec::new());
+        assert_eq!(wrapped.finish().unwrap(), b"");
+
+        let mut wrapped = NewlineWrap::new(Vec::new());
+        wrapped.write_all(b"").unwrap();
+        assert_eq!(wrapped.finish().unwrap(), b"");
+    }
+
+    #[test]
+    fn test_into_inner_skips_newline() {
+        let mut data_written = Vec::new();
+        let mut wrapped = NewlineWrap::new(&mut data_written);
+        write!(wrapped, "abc").unwrap();
+        wrapped.into_inner();
+        assert_eq!(data_written, b"abc");
+    }
+}
@@ -0,0 +1,221 @@
+use std::collections::BTreeMap;
+
+use bft_types::{Instruction, Program};
+
+/// An instruction decoded into the compact form the dispatch loop executes
+///
+/// Each op sits at the same index as the instruction it was decoded from, so its source
+/// location is still found through the program.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Op {
+    /// Move the head right by this many cells
+    Right(u32),
+    /// Move the head left by this many cells
+    Left(u32),
+    /// Increment the cell under the head
+    Succ,
+    /// Decrement the cell under the head
+    Pred,
+    /// Add to the cell under the head
+    Add(u8),
+    /// Subtract from the cell under the head
+    Sub(u8),
+    /// Set the cell under the head to zero
+    SetZero,
+    /// Read into the cell under the head
+    In,
+    /// Write the cell under the head
+    Out,
+    /// Jump to this index if the cell under the head is zero
+    Jz(u32),
+    /// Jump to this index if the cell under the head isn't zero
+    Jnz(u32),
+    /// Report the state of the machine
+    Debug,
+    /// Add the cell under the head multiplied by `factor` to the cell `offset` cells away,
+    /// unless the cell under the head is zero
+    MulAdd {
+        /// The distance to the cell added to, negative to the left
+        offset: i32,
+        /// The multiple of the cell under the head to add
+        factor: i16,
+    },
+}
+
+impl Op {
+    /// Decode every instruction of `program`, rewriting multiplication loops if asked to
+    ///
+    /// Panics if an operand doesn't fit in 32 bits, which needs a program of over 4GiB.
+    ///
+    /// `program`: the program to decode
+    /// `multiply_loops`: should loops which move multiples of a counter into other cells be
+    /// replaced by [`Op::MulAdd`]s
+    pub(crate) fn decode(program: &Program, multiply_loops: bool) -> Vec<Self> {
+        let mut ops: Vec<Self> = program
+            .instructions()
+            .iter()
+            .map(|&instr| Self::from(instr))
+            .collect();
+        if multiply_loops {
+            rewrite_multiplication_loops(&mut ops);
+        }
+        ops
+    }
+}
+
+/// Replace each multiplication loop, such as `[->+++>+<<]`, with a [`Op::MulAdd`] for each
+/// cell it adds to, then a [`Op::SetZero`] of its counter and a jump past the rest of the loop
+///
+/// The ops stay at the same indices so source locations are unaffected, the ops left over at
+/// the end of the loop are simply never reached.
+fn rewrite_multiplication_loops(ops: &mut [Op]) {
+    for start in 0..ops.len() {
+        let Op::Jz(dest) = ops[start] else {
+            continue;
+        };
+        let end = dest as usize - 1;
+        let Some(targets) = multiplication_targets(&ops[start + 1..end]) else {
+            continue;
+        };
+        if targets.len() + 2 > end - start + 1 {
+            continue;
+        }
+
+        let mut i = start;
+        for (offset, factor) in targets {
+            ops[i] = Op::MulAdd { offset, factor };
+            i += 1;
+        }
+        // the counter is zero from here on, so the jump is always taken
+        ops[i] = Op::SetZero;
+        ops[i + 1] = Op::Jz(dest);
+    }
+}
+
+/// The cells a loop body adds multiples of its counter to, and the multiples, if it is a
+/// multiplication loop
+///
+/// The body must only move the head and add to cells, ending back where it started having
+/// subtracted exactly one from the counter. The furthest the head strays either way must be
+/// onto a cell it adds to, so that the loop can only run off the end of the tape if its
+/// replacement does too.
+fn multiplication_targets(body: &[Op]) -> Option<Vec<(i32, i16)>> {
+    let mut offset: i64 = 0;
+    let (mut lowest, mut highest) = (0, 0);
+    let mut amounts: BTreeMap<i64, i64> = BTreeMap::new();
+    for &op in body {
+        match op {
+            Op::Right(n) => offset += i64::from(n),
+            Op::Left(n) => offset -= i64::from(n),
+            Op::Succ => *amounts.entry(offset).or_default() += 1,
+            Op::Pred => *amounts.entry(offset).or_default() -= 1,
+            Op::Add(n) => *amounts.entry(offset).or_default() += i64::from(n),
+            Op::Sub(n) => *amounts.entry(offset).or_default() -= i64::from(n),
+            _ => return None,
+        }
+        lowest = lowest.min(offset);
+        highest = highest.max(offset);
+    }
+    if offset != 0 || amounts.remove(&0) != Some(-1) {
+        return None;
+    }
+
+    amounts.retain(|_, amount| *amount != 0);
+    let reaches_edge = |edge| edge == 0 || amounts.contains_key(&edge);
+    if !reaches_edge(lowest) || !reaches_edge(highest) {
+        return None;
+    }
+    amounts
+        .into_iter()
+        .map(|(offset, amount)| Some((i32::try_from(offset).ok()?, i16::try_from(amount).ok()?)))
+        .collect()
+}
+
+impl From<Instruction> for Op {
+    fn from(instr: Instruction) -> Self {
+        let operand = |n: usize| u32::try_from(n).expect("operands fit in 32 bits");
+        match instr {
+            Instruction::Inc => Self::Right(1),
+            Instruction::Dec => Self::Left(1),
+            Instruction::Succ => Self::Succ,
+            Instruction::Pred => Self::Pred,
+            Instruction::Right(n) => Self::Right(operand(n)),
+            Instruction::Left(n) => Self::Left(operand(n)),
+            Instruction::Add(n) => Self::Add(n),
+            Instruction::Sub(n) => Self::Sub(n),
+            Instruction::SetZero => Self::SetZero,
+            Instruction::In => Self::In,
+            Instruction::Out => Self::Out,
+            Instruction::Jz { dest } => Self::Jz(operand(dest)),
+            Instruction::Jnz { dest } => Self::Jnz(operand(dest)),
+            Instruction::Debug => Self::Debug,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+    use std::path::Path;
+
+    #[test]
+    fn test_op_is_compact() {
+        assert_eq!(std::mem::size_of::<Op>(), 8);
+    }
+
+    #[test]
+    fn test_decode_keeps_indices() {
+        let prog = Program::try_new(Path::new("-"), "+[->>,.<]")
+            .unwrap()
+            .optimized();
+        assert_eq!(
+            Op::decode(&prog, false),
+            [
+                Op::Add(1),
+                Op::Jz(8),
+                Op::Sub(1),
+                Op::Right(2),
+                Op::In,
+                Op::Out,
+                Op::Left(1),
+                Op::Jnz(2),
+            ]
+        );
+    }
+
+    #[rstest]
+    #[case::copy_right("[->+++>+<<]", &[(1, 3), (2, 1)])]
+    #[case::copy_left("[<-->-]", &[(-1, -2)])]
+    #[case::counter_decremented_last("[>+<-]", &[(1, 1)])]
+    fn test_multiplication_loop_rewritten(#[case] source: &str, #[case] targets: &[(i32, i16)]) {
+        for prog in [Program::try_new(Path::new("-"), source).unwrap()] {
+            for prog in [prog.optimized(), prog] {
+                let ops = Op::decode(&prog, true);
+                let muls: Vec<(i32, i16)> = ops
+                    .iter()
+                    .map_while(|op| match *op {
+                        Op::MulAdd { offset, factor } => Some((offset, factor)),
+                        _ => None,
+                    })
+                    .collect();
+                assert_eq!(muls, targets);
+                assert_eq!(ops[muls.len()], Op::SetZero);
+                assert_eq!(ops[muls.len() + 1], Op::Jz(ops.len() as u32));
+            }
+        }
+    }
+
+    #[rstest]
+    #[case::io("[->+.<]")]
+    #[case::unbalanced("[->+<<]")]
+    #[case::counter_by_two("[-->+<]")]
+    #[case::strays_past_target("[->>+<<<>]")]
+    #[case::nested("[->[-]<]")]
+    fn test_other_loops_left_alone(#[case] source: &str) {
+        let prog = Program::try_new(Path::new("-"), source)
+            .unwrap()
+            .optimized();
+        assert_eq!(Op::decode(&prog, true), Op::decode(&prog, false));
+    }
+}
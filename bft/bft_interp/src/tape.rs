@@ -0,0 +1,275 @@
+use std::{
+    collections::{btree_map::Entry, BTreeMap},
+    mem,
+};
+
+use crate::CellKind;
+
+/// Storage for the cells of the virtual machine's tape
+///
+/// Cells are indexed from zero, the machine keeps track of which of them the head started on.
+pub trait Tape<Cell: CellKind> {
+    /// Create a tape of `len` zeroed cells
+    fn with_len(len: usize) -> Self;
+
+    /// Create a tape holding `cells`
+    fn from_cells(cells: Vec<Cell>) -> Self;
+
+    /// The number of cells on the tape
+    fn len(&self) -> usize;
+
+    /// Does the tape have no cells
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The cell at `index`, if the tape extends that far
+    fn cell(&self, index: usize) -> Option<&Cell>;
+
+    /// The cell at `index` for modification
+    ///
+    /// Panics if `index` is beyond the end of the tape.
+    fn cell_mut(&mut self, index: usize) -> &mut Cell;
+
+    /// Extend the tape with zeroed cells so that it has `len` cells
+    fn grow_to(&mut self, len: usize);
+
+    /// Insert `n` zeroed cells before the first cell, moving every cell `n` places right
+    fn grow_front(&mut self, n: usize);
+
+    /// A copy of every cell on the tape
+    fn to_cells(&self) -> Vec<Cell>;
+
+    /// The indices of the first and last cells which aren't zero, if any aren't
+    fn nonzero_span(&self) -> Option<(usize, usize)>;
+
+    /// Zero every cell and shrink the tape back to `len` cells, keeping any memory allocated
+    fn reset(&mut self, len: usize);
+}
+
+impl<Cell: CellKind> Tape<Cell> for Vec<Cell> {
+    fn with_len(len: usize) -> Self {
+        vec![Cell::default(); len]
+    }
+
+    fn from_cells(cells: Vec<Cell>) -> Self {
+        cells
+    }
+
+    fn len(&self) -> usize {
+        Vec::len(self)
+    }
+
+    fn cell(&self, index: usize) -> Option<&Cell> {
+        self.get(index)
+    }
+
+    fn cell_mut(&mut self, index: usize) -> &mut Cell {
+        &mut self[index]
+    }
+
+    fn grow_to(&mut self, len: usize) {
+        self.resize(len, Cell::default());
+    }
+
+    fn grow_front(&mut self, n: usize) {
+        self.splice(0..0, std::iter::repeat_n(Cell::default(), n));
+    }
+
+    fn to_cells(&self) -> Vec<Cell> {
+        self.clone()
+    }
+
+    fn nonzero_span(&self) -> Option<(usize, usize)> {
+        let first = self.iter().position(|cell| !cell.is_zero())?;
+        let last = self.iter().rposition(|cell| !cell.is_zero())?;
+        Some((first, last))
+    }
+
+    fn reset(&mut self, len: usize) {
+        self.truncate(len);
+        self.fill(Cell::default());
+        self.resize(len, Cell::default());
+    }
+}
+
+/// A tape which only stores the cells which aren't zero
+///
+/// Growing the tape costs nothing however far it grows, which suits programs moving the head a
+/// long way while touching only a few cells. Accessing a cell is slower than with a `Vec`.
+///
+/// ```
+/// # use bft_interp::{Machine, SparseTape, TapeKind};
+/// # use bft_types::Program;
+/// # use std::{io, path::Path};
+/// let source = format!("+{}+", ">".repeat(1_000_000));
+/// let prog = Program::try_new(Path::new("-"), source).unwrap();
+/// let mut vm = Machine::<u8, SparseTape<u8>>::new(1, TapeKind::Sparse, &prog);
+/// vm.run(io::empty(), io::sink()).unwrap();
+/// assert_eq!(vm.cell_at(1_000_000), Some(&1));
+/// assert_eq!(vm.cell_at(500_000), Some(&0));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct SparseTape<Cell> {
+    /// The cells which aren't zero, by index
+    ///
+    /// The cell last handed out by `cell_mut` is kept even if it was left at zero, until the
+    /// next change to the tape forgets it.
+    cells: BTreeMap<usize, Cell>,
+    /// The index of the cell last handed out by `cell_mut`
+    written: Option<usize>,
+    /// The number of cells on the tape
+    len: usize,
+    /// The value of every cell which hasn't been written to
+    zero: Cell,
+}
+
+impl<Cell: CellKind> Tape<Cell> for SparseTape<Cell> {
+    fn with_len(len: usize) -> Self {
+        Self {
+            cells: BTreeMap::new(),
+            written: None,
+            len,
+            zero: Cell::default(),
+        }
+    }
+
+    fn from_cells(cells: Vec<Cell>) -> Self {
+        let mut tape = Self::with_len(cells.len());
+        tape.cells = cells
+            .into_iter()
+            .enumerate()
+            .filter(|(_, cell)| !cell.is_zero())
+            .collect();
+        tape
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn cell(&self, index: usize) -> Option<&Cell> {
+        (index < self.len).then(|| self.cells.get(&index).unwrap_or(&self.zero))
+    }
+
+    fn cell_mut(&mut self, index: usize) -> &mut Cell {
+        assert!(
+            index < self.len,
+            "cell {index} is beyond the end of a tape of {} cells",
+            self.len
+        );
+        self.forget_zeroed();
+        self.written = Some(index);
+        self.cells.entry(index).or_default()
+    }
+
+    fn grow_to(&mut self, len: usize) {
+        self.len = self.len.max(len);
+    }
+
+    fn grow_front(&mut self, n: usize) {
+        self.forget_zeroed();
+        self.cells = mem::take(&mut self.cells)
+            .into_iter()
+            .map(|(i, cell)| (i + n, cell))
+            .collect();
+        self.len += n;
+    }
+
+    fn to_cells(&self) -> Vec<Cell> {
+        let mut cells = vec![Cell::default(); self.len];
+        for (&i, cell) in &self.cells {
+            cells[i] = cell.clone();
+        }
+        cells
+    }
+
+    fn nonzero_span(&self) -> Option<(usize, usize)> {
+        let mut nonzero = self
+            .cells
+            .iter()
+            .filter(|(_, cell)| !cell.is_zero())
+            .map(|(&i, _)| i);
+        let first = nonzero.next()?;
+        Some((first, nonzero.next_back().unwrap_or(first)))
+    }
+
+    fn reset(&mut self, len: usize) {
+        self.cells.clear();
+        self.written = None;
+        self.len = len;
+    }
+}
+
+impl<Cell: CellKind> SparseTape<Cell> {
+    /// Stop storing the cell last handed out by `cell_mut` if it was left at zero
+    fn forget_zeroed(&mut self) {
+        if let Some(Entry::Occupied(cell)) = self.written.take().map(|i| self.cells.entry(i)) {
+            if cell.get().is_zero() {
+                cell.remove();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sparse_tape() {
+        let mut tape = SparseTape::<u8>::with_len(3);
+        assert_eq!(tape.cell(2), Some(&0));
+        assert_eq!(tape.cell(3), None);
+
+        *tape.cell_mut(1) = 5;
+        tape.grow_to(1_000_000_000);
+        assert_eq!(tape.len(), 1_000_000_000);
+        *tape.cell_mut(999_999_999) = 7;
+        assert_eq!(tape.cells.len(), 2);
+
+        tape.grow_front(2);
+        assert_eq!(tape.cell(3), Some(&5));
+        assert_eq!(tape.cell(1_000_000_001), Some(&7));
+        assert_eq!(tape.len(), 1_000_000_002);
+
+        tape.reset(3);
+        assert_eq!(tape.to_cells(), [0; 3]);
+    }
+
+    #[test]
+    fn test_sparse_tape_forgets_zeroed_cells() {
+        let mut tape = SparseTape::<u8>::with_len(10);
+        *tape.cell_mut(2) = 1;
+        *tape.cell_mut(5) = 1;
+        *tape.cell_mut(2) = 0;
+        // a cell handed out but left at zero is forgotten by the next write
+        let _ = tape.cell_mut(7);
+        *tape.cell_mut(5) -= 1;
+        *tape.cell_mut(9) = 3;
+        assert_eq!(tape.cells.len(), 1);
+        assert_eq!(tape.cell(2), Some(&0));
+        assert_eq!(tape.cell(9), Some(&3));
+    }
+
+    #[test]
+    fn test_nonzero_span() {
+        let cells = vec![0_u8, 0, 4, 0, 1, 0];
+        assert_eq!(cells.nonzero_span(), Some((2, 4)));
+        assert_eq!(SparseTape::from_cells(cells).nonzero_span(), Some((2, 4)));
+
+        let mut tape = SparseTape::<u8>::with_len(3);
+        *tape.cell_mut(1) = 0;
+        assert_eq!(tape.nonzero_span(), None);
+        assert_eq!(vec![0_u8; 3].nonzero_span(), None);
+    }
+
+    #[test]
+    fn test_sparse_tape_round_trips_cells() {
+        let cells = vec![0_u16, 3, 0, 0, 0xFFFF, 0];
+        let tape = SparseTape::from_cells(cells.clone());
+        assert_eq!(tape.cells.len(), 2);
+        assert_eq!(tape.to_cells(), cells);
+        assert_eq!(Vec::from_cells(cells.clone()).to_cells(), cells);
+    }
+}
@@ -0,0 +1,264 @@
+use std::io;
+
+/// Which way a byte crossed between the program and the outside world
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum IoDirection {
+    /// Read by the `,` instruction
+    Input,
+
+    /// Written by the `.` instruction
+    Output,
+}
+
+/// A byte read or written by a program, see [`Machine::with_recording`]
+///
+/// [`Machine::with_recording`]: crate::Machine::with_recording
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct IoEvent {
+    /// Whether the byte was read or written
+    pub direction: IoDirection,
+
+    /// The byte itself, None when `,` found the input exhausted
+    pub byte: Option<u8>,
+
+    /// The index of the instruction which read or wrote the byte
+    pub ip: usize,
+}
+
+/// A recorded run for a program to repeat, see [`Machine::with_replay`]
+///
+/// While replaying, each `,` reads the input recorded for it rather than from the reader it is
+/// given, and each `.` must write the bytes recorded for it. A read or write by any other
+/// instruction, or beyond the end of the recording, is an error as the program must have taken
+/// a different path.
+///
+/// ```
+/// # use bft_interp::{EofBehaviour, Machine, Replay, TapeKind};
+/// # use bft_types::Program;
+/// # use std::{io, path::Path};
+/// let prog = Program::try_new(Path::new("-"), ",[.,]").unwrap();
+/// let mut vm = Machine::<u8>::new(1, TapeKind::FixedSize, &prog)
+///     .with_eof_behaviour(EofBehaviour::Zero)
+///     .with_recording(true);
+/// vm.run(&b"hi"[..], io::sink()).unwrap();
+///
+/// let replay = Replay::new(vm.transcript().unwrap().iter().copied());
+/// let mut vm = Machine::<u8>::new(1, TapeKind::FixedSize, &prog)
+///     .with_eof_behaviour(EofBehaviour::Zero)
+///     .with_replay(replay);
+/// let mut output = Vec::new();
+/// vm.run(io::empty(), &mut output).unwrap();
+/// assert_eq!(output, b"hi");
+/// vm.replay().unwrap().finish().unwrap();
+/// ```
+///
+/// [`Machine::with_replay`]: crate::Machine::with_replay
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Replay {
+    /// Every event recorded during the original run
+    events: Vec<IoEvent>,
+
+    /// The index of the next event the program should repeat
+    next: usize,
+}
+
+impl Replay {
+    /// Replay the events of a transcript
+    ///
+    /// `transcript`: the events recorded during the original run
+    pub fn new(transcript: impl IntoIterator<Item = IoEvent>) -> Self {
+        Self {
+            events: transcript.into_iter().collect(),
+            next: 0,
+        }
+    }
+
+    /// The number of recorded events which haven't been replayed yet
+    pub fn remaining(&self) -> usize {
+        self.events.len() - self.next
+    }
+
+    /// Check that the program repeated every recorded event
+    pub fn finish(&self) -> io::Result<()> {
+        match self.events.get(self.next) {
+            None => Ok(()),
+            Some(event) => Err(mismatch(format!(
+                "the program stopped with {} recorded event(s) left, the first {}",
+                self.remaining(),
+                describe(event)
+            ))),
+        }
+    }
+
+    /// Start replaying from the first recorded event again
+    pub(crate) fn rewind(&mut self) {
+        self.rewind_to(0);
+    }
+
+    /// The index of the next event the program should repeat
+    pub(crate) fn position(&self) -> usize {
+        self.next
+    }
+
+    /// Go back to repeating from the event at `position`, as returned by [`Replay::position`]
+    pub(crate) fn rewind_to(&mut self, position: usize) {
+        self.next = position;
+    }
+
+    /// Fill `buf` with the input recorded for the `,` at `ip`, returning the number of bytes
+    /// read, 0 where the input was exhausted
+    ///
+    /// `ip`: the index of the instruction reading
+    /// `buf`: where to put the bytes read
+    pub(crate) fn read(&mut self, ip: usize, buf: &mut [u8]) -> io::Result<usize> {
+        match self.events.get(self.next) {
+            Some(event) if event.direction == IoDirection::Input && event.ip == ip => {}
+            event => {
+                return Err(mismatch(format!(
+                    "the program read by ip {ip} where the recording has {}",
+                    event.map_or_else(|| "no more IO".to_owned(), describe)
+                )))
+            }
+        }
+
+        if self.events[self.next].byte.is_none() {
+            self.next += 1;
+            return Ok(0);
+        }
+        let mut filled = 0;
+        while let Some(&IoEvent {
+            direction: IoDirection::Input,
+            byte: Some(byte),
+            ip: event_ip,
+        }) = self.events.get(self.next)
+        {
+            if filled == buf.len() || event_ip != ip {
+                break;
+            }
+            buf[filled] = byte;
+            filled += 1;
+            self.next += 1;
+        }
+        Ok(filled)
+    }
+
+    /// Check that the `.` at `ip` writes the output recorded for it, moving past it if so
+    ///
+    /// Nothing is replayed unless every byte matches.
+    ///
+    /// `ip`: the index of the instruction writing
+    /// `bytes`: the bytes being written
+    pub(crate) fn write(&mut self, ip: usize, bytes: &[u8]) -> io::Result<()> {
+        for (i, &byte) in bytes.iter().enumerate() {
+            let expected = IoEvent {
+                direction: IoDirection::Output,
+                byte: Some(byte),
+                ip,
+            };
+            match self.events.get(self.next + i) {
+                Some(event) if *event == expected => {}
+                event => {
+                    return Err(mismatch(format!(
+                        "the program wrote {byte:#04x} by ip {ip} where the recording has {}",
+                        event.map_or_else(|| "no more IO".to_owned(), describe)
+                    )))
+                }
+            }
+        }
+        self.next += bytes.len();
+        Ok(())
+    }
+}
+
+/// An error for a program which didn't repeat its recorded run
+fn mismatch(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Describe a recorded event for an error
+fn describe(event: &IoEvent) -> String {
+    match (event.direction, event.byte) {
+        (IoDirection::Input, None) => format!("the end of the input read by ip {}", event.ip),
+        (IoDirection::Input, Some(_)) => format!("a read by ip {}", event.ip),
+        (IoDirection::Output, Some(byte)) => format!("a write of {byte:#04x} by ip {}", event.ip),
+        (IoDirection::Output, None) => format!("a write by ip {}", event.ip),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn input(byte: Option<u8>, ip: usize) -> IoEvent {
+        IoEvent {
+            direction: IoDirection::Input,
+            byte,
+            ip,
+        }
+    }
+
+    fn output(byte: u8, ip: usize) -> IoEvent {
+        IoEvent {
+            direction: IoDirection::Output,
+            byte: Some(byte),
+            ip,
+        }
+    }
+
+    #[test]
+    fn test_replay_reads_recorded_input() {
+        let mut replay = Replay::new([input(Some(b'a'), 0), input(Some(b'b'), 0), output(b'b', 1)]);
+        let mut buf = [0; 4];
+        assert_eq!(replay.read(0, &mut buf).unwrap(), 2);
+        assert_eq!(&buf[..2], b"ab");
+        let err = replay.read(0, &mut buf).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "the program read by ip 0 where the recording has a write of 0x62 by ip 1"
+        );
+        replay.write(1, b"b").unwrap();
+        replay.finish().unwrap();
+
+        replay.rewind();
+        assert_eq!(replay.remaining(), 3);
+        let err = replay.read(2, &mut buf).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "the program read by ip 2 where the recording has a read by ip 0"
+        );
+    }
+
+    #[test]
+    fn test_replay_checks_output() {
+        let mut replay = Replay::new([output(b'a', 1), output(b'b', 1)]);
+        let err = replay.write(1, b"ax").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "the program wrote 0x78 by ip 1 where the recording has a write of 0x62 by ip 1"
+        );
+        // nothing was replayed by the failed write
+        assert_eq!(replay.remaining(), 2);
+        replay.write(1, b"ab").unwrap();
+        let err = replay.write(1, b"c").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "the program wrote 0x63 by ip 1 where the recording has no more IO"
+        );
+    }
+
+    #[test]
+    fn test_replay_reproduces_end_of_input() {
+        let mut replay = Replay::new([input(None, 0), input(Some(b'a'), 2)]);
+        let mut buf = [0; 1];
+        assert_eq!(replay.read(0, &mut buf).unwrap(), 0);
+        assert_eq!(replay.remaining(), 1);
+        let err = replay.finish().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "the program stopped with 1 recorded event(s) left, the first a read by ip 2"
+        );
+    }
+}
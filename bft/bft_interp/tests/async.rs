@@ -0,0 +1,138 @@
+#![cfg(feature = "async")]
+//! Running programs with tokio's asynchronous IO
+
+use bft_interp::{EofBehaviour, InterpretError, Machine, MachineBuilder, Replay, TapeKind};
+use bft_types::Program;
+use std::{
+    io::{self, Write},
+    path::Path,
+    pin::Pin,
+    task::{Context, Poll},
+};
+use tokio::io::{duplex, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+/// Output which fails every write, whether written synchronously or not
+struct Broken;
+
+impl Write for Broken {
+    fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+        Err(io::ErrorKind::BrokenPipe.into())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl AsyncWrite for Broken {
+    fn poll_write(self: Pin<&mut Self>, _: &mut Context<'_>, _: &[u8]) -> Poll<io::Result<usize>> {
+        Poll::Ready(Err(io::ErrorKind::BrokenPipe.into()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[tokio::test]
+async fn test_rot13_over_duplex() {
+    let prog = Program::from_file("../programs/rot13.bf").unwrap();
+    // small buffers so the program and the client have to take turns
+    let (mut to_machine, machine_input) = duplex(4);
+    let (machine_output, mut from_machine) = duplex(4);
+
+    let machine = async move {
+        let mut vm = Machine::<u8>::new(100, TapeKind::FixedSize, &prog)
+            .with_eof_behaviour(EofBehaviour::MinusOne);
+        vm.run_async(machine_input, machine_output).await
+    };
+    let send = async move {
+        to_machine.write_all(b"Hello, World!").await.unwrap();
+    };
+    let receive = async {
+        let mut output = Vec::new();
+        from_machine.read_to_end(&mut output).await.unwrap();
+        output
+    };
+    let (res, (), output) = tokio::join!(machine, send, receive);
+    res.unwrap();
+    assert_eq!(output, b"Uryyb, Jbeyq!");
+}
+
+#[tokio::test]
+async fn test_long_running_program_yields() {
+    let prog = Program::try_new(Path::new("-"), "+[]").unwrap();
+    let mut vm = Machine::<u8>::new(1, TapeKind::FixedSize, &prog).with_yield_interval(1000);
+    let mut output = Vec::new();
+    // without yielding the loop would never give the other branch a chance to finish
+    tokio::select! {
+        biased;
+        _ = vm.run_async(tokio::io::empty(), &mut output) => panic!("the program never halts"),
+        _ = tokio::task::yield_now() => {}
+    }
+    assert!(vm.steps() > 0);
+}
+
+#[tokio::test]
+async fn test_step_limit() {
+    let prog = Program::try_new(Path::new("-"), "+[]").unwrap();
+    let mut vm: Machine<u8> = MachineBuilder::new().max_steps(50).build(&prog).unwrap();
+    let err = vm
+        .run_async(tokio::io::empty(), tokio::io::sink())
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        InterpretError::StepLimitExceeded { steps: 50, .. }
+    ));
+    assert_eq!(vm.steps(), 50);
+}
+
+#[tokio::test]
+async fn test_failed_write_is_undone() {
+    let prog = Program::try_new(Path::new("-"), ",+[-.]").unwrap();
+    let machine = || {
+        Machine::<u8>::new(1, TapeKind::FixedSize, &prog)
+            .with_recording(true)
+            .with_profiling(true)
+            .with_hang_detection(true)
+    };
+
+    let mut sync_vm = machine();
+    let sync_err = sync_vm.run(&b"a"[..], Broken).unwrap_err();
+    let mut async_vm = machine();
+    let async_err = async_vm.run_async(&b"a"[..], Broken).await.unwrap_err();
+
+    // the `.` is left unexecuted, exactly as when running synchronously
+    assert!(matches!(
+        async_err,
+        InterpretError::IoError { ip_at_error: 4, .. }
+    ));
+    assert_eq!(async_err.ip(), sync_err.ip());
+    assert_eq!(async_vm.ip(), sync_vm.ip());
+    assert_eq!(async_vm.steps(), sync_vm.steps());
+    assert_eq!(async_vm.execution_counts(), sync_vm.execution_counts());
+    assert_eq!(async_vm.transcript(), sync_vm.transcript());
+}
+
+#[tokio::test]
+async fn test_failed_write_keeps_replay_position() {
+    let prog = Program::try_new(Path::new("-"), ",.").unwrap();
+    let mut recorder = Machine::<u8>::new(1, TapeKind::FixedSize, &prog).with_recording(true);
+    recorder.run(&b"a"[..], io::sink()).unwrap();
+    let replay = Replay::new(recorder.transcript().unwrap().iter().copied());
+
+    let mut vm = Machine::<u8>::new(1, TapeKind::FixedSize, &prog).with_replay(replay);
+    vm.run_async(tokio::io::empty(), Broken).await.unwrap_err();
+    assert_eq!(vm.replay().map(Replay::remaining), Some(1));
+
+    // retrying the write repeats the rest of the recording
+    let mut output = Vec::new();
+    vm.run_async(tokio::io::empty(), &mut output).await.unwrap();
+    assert_eq!(output, b"a");
+    assert_eq!(vm.replay().map(Replay::remaining), Some(0));
+}
@@ -0,0 +1,63 @@
+//! Checks that the optimisations never change what a program does
+
+use bft_interp::{EofBehaviour, InterpretError, Machine, TapeKind};
+use bft_types::{test_util::program_source, Program};
+use proptest::prelude::*;
+use std::path::Path;
+
+/// The number of cells on the tape
+const CELLS: usize = 64;
+
+/// The most instructions the plain interpreter executes before a program is skipped
+const MAX_STEPS: u64 = 10_000;
+
+/// How a run ended, the output written and the final state of the tape
+type Outcome = (Result<(), InterpretError>, Vec<u8>, Vec<u8>);
+
+/// Run `program` on a fixed size tape, optionally optimising it first
+fn run(program: &Program, optimise: bool, input: &[u8], eof: EofBehaviour) -> Outcome {
+    let program = if optimise {
+        program.optimized()
+    } else {
+        program.clone()
+    };
+    let mut machine = Machine::<u8>::new(CELLS, TapeKind::FixedSize, &program)
+        .with_eof_behaviour(eof)
+        .with_multiply_loops(optimise);
+    let mut output = Vec::new();
+    let res = machine.run_with_limit(input, &mut output, MAX_STEPS);
+    let tape = (0..CELLS as isize)
+        .map(|i| *machine.cell_at(i).unwrap())
+        .collect();
+    (res, output, tape)
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(2048))]
+
+    #[test]
+    fn test_optimised_matches_plain(
+        body in program_source(48),
+        input in prop::collection::vec(any::<u8>(), 0..8),
+        eof in prop_oneof![Just(EofBehaviour::Zero), Just(EofBehaviour::MinusOne)],
+    ) {
+        // start in the middle of the tape so most programs don't immediately run off it
+        let source = format!("{}{body}", ">".repeat(CELLS / 2));
+        let program = Program::try_new(Path::new("<generated>"), &source).unwrap();
+        let (plain_res, plain_output, plain_tape) = run(&program, false, &input, eof);
+        // the optimised program takes fewer steps, so stops somewhere else when cut short
+        prop_assume!(!matches!(plain_res, Err(InterpretError::StepLimitExceeded { .. })));
+
+        let (res, output, tape) = run(&program, true, &input, eof);
+        prop_assert_eq!(
+            plain_res.is_ok(),
+            res.is_ok(),
+            "program {} gave {:?} plain but {:?} optimised",
+            source,
+            plain_res,
+            res
+        );
+        prop_assert_eq!(plain_output, output, "program {} wrote different output", source);
+        prop_assert_eq!(plain_tape, tape, "program {} left a different tape", source);
+    }
+}
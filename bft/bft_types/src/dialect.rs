@@ -0,0 +1,355 @@
+use core::fmt;
+use std::error::Error;
+
+use crate::{
+    program::{BF_ALPHABET, DEBUG_CHAR},
+    BfParseErrorKind, Program, SourceLocation,
+};
+
+/// The Ook! spelling of each instruction, in the same order as [`BF_ALPHABET`]
+const OOK_TOKENS: [&str; 8] = [
+    "Ook. Ook?",
+    "Ook? Ook.",
+    "Ook. Ook.",
+    "Ook! Ook!",
+    "Ook! Ook.",
+    "Ook. Ook!",
+    "Ook! Ook?",
+    "Ook? Ook!",
+];
+
+/// The words an Ook! program is written with, two to an instruction
+const OOK_WORDS: [&str; 3] = ["Ook.", "Ook?", "Ook!"];
+
+/// A language spelling the eight brainfuck instructions differently
+///
+/// Every dialect parses to the same [`Instruction`](crate::Instruction)s, with each source
+/// location pointing at the start of the instruction's token.
+///
+/// ```
+/// # use bft_types::{Dialect, Program};
+/// # use std::path::Path;
+/// let ook = Program::try_new_with_dialect(Path::new("-"), "Ook. Ook. Ook! Ook.", Dialect::Ook)
+///     .unwrap();
+/// let bf = Program::try_new(Path::new("-"), "+.").unwrap();
+/// assert_eq!(ook.instructions(), bf.instructions());
+/// assert_eq!(Dialect::Brainfuck.translate(&ook), "+.");
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum Dialect {
+    /// The usual single characters, `><+-.,[]`
+    #[default]
+    Brainfuck,
+
+    /// Pairs of `Ook.`, `Ook?` and `Ook!` separated by whitespace
+    Ook,
+
+    /// Eight arbitrary tokens, see [`TokenMap`]
+    Custom(TokenMap),
+}
+
+impl Dialect {
+    /// The token `instruction`, a brainfuck character, is spelled as in this dialect
+    fn token(&self, instruction: char) -> Option<&str> {
+        let index = BF_ALPHABET.find(instruction);
+        match self {
+            Self::Brainfuck if instruction == DEBUG_CHAR => Some("#"),
+            Self::Brainfuck => index.map(|i| &BF_ALPHABET[i..=i]),
+            Self::Ook => index.map(|i| OOK_TOKENS[i]),
+            Self::Custom(map) => index.map(|i| map.tokens[i].as_str()),
+        }
+    }
+
+    /// Write `program` in this dialect, without its comments
+    ///
+    /// Tokens longer than a character are separated by spaces. Folded runs are expanded, and
+    /// [`Instruction::Debug`](crate::Instruction::Debug) is only kept in brainfuck, the other
+    /// dialects having no way to spell it.
+    pub fn translate(&self, program: &Program) -> String {
+        let single_chars = match self {
+            Self::Brainfuck => true,
+            Self::Ook => false,
+            Self::Custom(map) => map.tokens.iter().all(|t| t.chars().count() == 1),
+        };
+
+        let mut tokens = Vec::new();
+        for instruction in program.instructions() {
+            for c in instruction.to_string().chars() {
+                tokens.extend(self.token(c));
+            }
+        }
+        tokens.join(if single_chars { "" } else { " " })
+    }
+
+    /// Split the code of an Ook! program into brainfuck characters and where each started
+    ///
+    /// Words other than `Ook.`, `Ook?` and `Ook!` are comments. Fails on `Ook? Ook?`, which
+    /// isn't an instruction, and on a word left without a partner at the end.
+    pub(crate) fn ook_tokens(
+        code: &str,
+    ) -> Result<Vec<(u8, SourceLocation)>, (BfParseErrorKind, SourceLocation)> {
+        let mut tokens = Vec::new();
+        let mut first: Option<(&str, SourceLocation)> = None;
+        for (line_no, line) in code.lines().enumerate() {
+            for (column, word) in words(line) {
+                if !OOK_WORDS.contains(&word) {
+                    continue;
+                }
+                let location = SourceLocation {
+                    line: line_no,
+                    column,
+                };
+                let Some((first_word, start)) = first.take() else {
+                    first = Some((word, location));
+                    continue;
+                };
+
+                let pair = format!("{first_word} {word}");
+                let index = OOK_TOKENS
+                    .iter()
+                    .position(|&token| token == pair)
+                    .ok_or((BfParseErrorKind::InvalidToken, start))?;
+                tokens.push((BF_ALPHABET.as_bytes()[index], start));
+            }
+        }
+
+        match first {
+            Some((_, unpaired)) => Err((BfParseErrorKind::InvalidToken, unpaired)),
+            None => Ok(tokens),
+        }
+    }
+}
+
+/// The whitespace separated words of `line` and the column each starts at
+fn words(line: &str) -> Vec<(usize, &str)> {
+    let mut words = Vec::new();
+    // the column and byte offset of the word being read
+    let mut start = None;
+    for (column, (byte, c)) in line.char_indices().enumerate() {
+        match (start, c.is_whitespace()) {
+            (None, false) => start = Some((column, byte)),
+            (Some((column, from)), true) => {
+                words.push((column, &line[from..byte]));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some((column, from)) = start {
+        words.push((column, &line[from..]));
+    }
+    words
+}
+
+/// Eight distinct tokens standing for the instructions `><+-.,[]`, in that order
+///
+/// Anything in a program which isn't one of the tokens is a comment, and where tokens overlap
+/// the longest is taken.
+///
+/// ```
+/// # use bft_types::{Dialect, Program, TokenMap};
+/// # use std::path::Path;
+/// let map = TokenMap::new(["r", "l", "inc", "dec", "out", "in", "(", ")"]).unwrap();
+/// let program =
+///     Program::try_new_with_dialect(Path::new("-"), "inc (out)", Dialect::Custom(map)).unwrap();
+/// assert_eq!(Dialect::Brainfuck.translate(&program), "+[.]");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TokenMap {
+    /// the token for each instruction, in the same order as [`BF_ALPHABET`]
+    tokens: [String; 8],
+}
+
+impl TokenMap {
+    /// Map each of `tokens` onto the instructions `><+-.,[]`, in that order
+    ///
+    /// Fails if a token is empty, spans more than one line or is used twice.
+    pub fn new(tokens: [&str; 8]) -> Result<Self, TokenMapError> {
+        for (i, token) in tokens.iter().enumerate() {
+            let instruction = BF_ALPHABET.as_bytes()[i] as char;
+            if token.is_empty() {
+                return Err(TokenMapError::Empty { instruction });
+            }
+            if token.contains(['\n', '\r']) {
+                return Err(TokenMapError::MultiLine { instruction });
+            }
+            if tokens[..i].contains(token) {
+                return Err(TokenMapError::Duplicate {
+                    token: (*token).to_owned(),
+                });
+            }
+        }
+
+        Ok(Self {
+            tokens: tokens.map(str::to_owned),
+        })
+    }
+
+    /// The tokens standing for `><+-.,[]`, in that order
+    pub fn tokens(&self) -> &[String; 8] {
+        &self.tokens
+    }
+
+    /// Split `code` into brainfuck characters and where each token started
+    pub(crate) fn tokenise(&self, code: &str) -> Vec<(u8, SourceLocation)> {
+        // tried longest first so a token containing another one wins
+        let mut by_length: Vec<usize> = (0..self.tokens.len()).collect();
+        by_length.sort_by_key(|&i| std::cmp::Reverse(self.tokens[i].len()));
+
+        let mut tokens = Vec::new();
+        for (line_no, line) in code.lines().enumerate() {
+            // the byte offset the last token ran up to
+            let mut next = 0;
+            for (column, (byte, _)) in line.char_indices().enumerate() {
+                if byte < next {
+                    continue;
+                }
+                let rest = &line[byte..];
+                if let Some(&i) = by_length
+                    .iter()
+                    .find(|&&i| rest.starts_with(&self.tokens[i]))
+                {
+                    let location = SourceLocation {
+                        line: line_no,
+                        column,
+                    };
+                    tokens.push((BF_ALPHABET.as_bytes()[i], location));
+                    next = byte + self.tokens[i].len();
+                }
+            }
+        }
+        tokens
+    }
+}
+
+/// Why a [`TokenMap`] couldn't be made
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum TokenMapError {
+    /// The token for an instruction was empty
+    Empty {
+        /// The brainfuck character for the instruction
+        instruction: char,
+    },
+
+    /// The token for an instruction contained a line break
+    MultiLine {
+        /// The brainfuck character for the instruction
+        instruction: char,
+    },
+
+    /// The same token was given for more than one instruction
+    Duplicate {
+        /// The token given more than once
+        token: String,
+    },
+}
+
+impl fmt::Display for TokenMapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Empty { instruction } => write!(f, "the token for `{instruction}` is empty"),
+            Self::MultiLine { instruction } => {
+                write!(f, "the token for `{instruction}` spans more than one line")
+            }
+            Self::Duplicate { token } => {
+                write!(
+                    f,
+                    "the token {token:?} is used for more than one instruction"
+                )
+            }
+        }
+    }
+}
+
+impl Error for TokenMapError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BfParseError;
+    use rstest::rstest;
+    use std::path::Path;
+
+    /// A custom dialect spelling each instruction as a word
+    fn words_map() -> TokenMap {
+        TokenMap::new(["right", "left", "inc", "dec", "out", "in", "loop", "end"]).unwrap()
+    }
+
+    fn parse(source: &str, dialect: Dialect) -> Result<Program, BfParseError> {
+        Program::try_new_with_dialect(Path::new("-"), source, dialect)
+    }
+
+    #[test]
+    fn test_ook_hello_world() {
+        let ook = parse(include_str!("../../programs/hello_world.ook"), Dialect::Ook).unwrap();
+        let bf = Program::try_new(
+            Path::new("hello_world.bf"),
+            include_str!("../../programs/hello_world.bf"),
+        )
+        .unwrap();
+        assert_eq!(ook.instructions(), bf.instructions());
+    }
+
+    #[rstest]
+    #[case(Dialect::Brainfuck)]
+    #[case(Dialect::Ook)]
+    #[case(Dialect::Custom(words_map()))]
+    #[case(Dialect::Custom(TokenMap::new(["a", "b", "c", "d", "e", "f", "g", "h"]).unwrap()))]
+    #[case(Dialect::Custom(TokenMap::new(["+", "-", ">", "<", "]", "[", ".", ","]).unwrap()))]
+    fn test_translate_round_trip(#[case] dialect: Dialect) {
+        let bf = Program::try_new(
+            Path::new("example.bf"),
+            include_str!("../../programs/example.bf"),
+        )
+        .unwrap();
+        let translated = parse(&dialect.translate(&bf), dialect).unwrap();
+        assert_eq!(translated.instructions(), bf.instructions());
+    }
+
+    #[test]
+    fn test_locations_at_token_start() {
+        let ook = parse("Ook. Ook.\n  Ook!\nOok. x Ook. Ook?", Dialect::Ook).unwrap();
+        assert_eq!(
+            ook.source_map(),
+            [
+                SourceLocation { line: 0, column: 0 },
+                SourceLocation { line: 1, column: 2 },
+                SourceLocation { line: 2, column: 7 },
+            ]
+        );
+        assert_eq!(Dialect::Brainfuck.translate(&ook), "+.>");
+
+        let custom = parse("inc loop  dec end", Dialect::Custom(words_map())).unwrap();
+        let columns: Vec<_> = custom.source_map().iter().map(|l| l.column).collect();
+        assert_eq!(columns, [0, 4, 10, 14]);
+    }
+
+    #[test]
+    fn test_longest_token_wins() {
+        let map = TokenMap::new(["a", "aa", "b", "bb", "c", "cc", "d", "dd"]).unwrap();
+        let program = parse("aaa bb", Dialect::Custom(map)).unwrap();
+        assert_eq!(Dialect::Brainfuck.translate(&program), "<>-");
+    }
+
+    #[rstest]
+    #[case("Ook? Ook?", SourceLocation { line: 0, column: 0 })]
+    #[case("Ook. Ook.\nOok!", SourceLocation { line: 1, column: 0 })]
+    fn test_invalid_ook(#[case] source: &str, #[case] location: SourceLocation) {
+        let err = parse(source, Dialect::Ook).unwrap_err();
+        assert_eq!(
+            (err.kind(), err.location()),
+            (BfParseErrorKind::InvalidToken, location)
+        );
+    }
+
+    #[rstest]
+    #[case(["", "l", "+", "-", ".", ",", "[", "]"], TokenMapError::Empty { instruction: '>' })]
+    #[case(["r", "l", "+", "-", ".", ",", "[", ""], TokenMapError::Empty { instruction: ']' })]
+    #[case(["r", "l", "+", "-", ".", ",", "[\n", "]"], TokenMapError::MultiLine { instruction: '[' })]
+    #[case(["r", "l", "+", "-", ".", ",", "[", "r"], TokenMapError::Duplicate { token: "r".into() })]
+    #[case(["x", "x", "+", "-", ".", ",", "[", "]"], TokenMapError::Duplicate { token: "x".into() })]
+    fn test_invalid_token_map(#[case] tokens: [&str; 8], #[case] expected: TokenMapError) {
+        assert_eq!(TokenMap::new(tokens), Err(expected));
+    }
+}
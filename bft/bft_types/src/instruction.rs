@@ -1,5 +1,8 @@
+use core::fmt;
+
 /// The brainfuck language instructions
-#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Instruction {
     /// `>` Increment the data pointer by one (to point to the next cell to the right).
     Inc,
@@ -21,13 +24,128 @@ pub enum Instruction {
 
     /// `[` If the byte at the data pointer is zero, then instead of moving the instruction pointer forward to the next command, jump it forward to the command after the matching ] command.
     Jz {
-        /// The value of the data pointer if the jump is taken
+        /// The value of the instruction pointer if the jump is taken
         dest: usize,
     },
 
-    /// `]` Unconditionally jump back to the matching bracket
+    /// `]` If the byte at the data pointer is nonzero, then instead of moving the instruction pointer forward to the next command, jump it back to the command after the matching [ command.
     Jnz {
-        /// The location of the bracket's matching pair
-        pair_loc: usize,
+        /// The value of the instruction pointer if the jump is taken
+        dest: usize,
     },
+
+    /// A run of `+` folded together by [`Program::optimized`](crate::Program::optimized), adds
+    /// its count to the byte at the data pointer.
+    Add(u8),
+
+    /// A run of `-` folded together by [`Program::optimized`](crate::Program::optimized),
+    /// subtracts its count from the byte at the data pointer.
+    Sub(u8),
+
+    /// A run of `>` folded together by [`Program::optimized`](crate::Program::optimized), moves
+    /// the data pointer right by its count.
+    Right(usize),
+
+    /// A run of `<` folded together by [`Program::optimized`](crate::Program::optimized), moves
+    /// the data pointer left by its count.
+    Left(usize),
+
+    /// A `[-]` or `[+]` loop recognised by [`Program::optimized`](crate::Program::optimized),
+    /// sets the byte at the data pointer to zero.
+    SetZero,
+
+    /// `#` Report the state of the machine, only parsed when enabled with
+    /// [`ParseOptions::with_debug_chars`](crate::ParseOptions::with_debug_chars).
+    Debug,
+}
+
+impl Instruction {
+    /// The brainfuck character this instruction is written as
+    ///
+    /// ```
+    /// # use bft_types::Instruction;
+    /// assert_eq!(Instruction::Inc.as_char(), '>');
+    /// assert_eq!(Instruction::Jz { dest: 10 }.as_char(), '[');
+    /// ```
+    pub fn as_char(&self) -> char {
+        match self {
+            Self::Inc | Self::Right(_) => '>',
+            Self::Dec | Self::Left(_) => '<',
+            Self::Succ | Self::Add(_) => '+',
+            Self::Pred | Self::Sub(_) => '-',
+            Self::Out => '.',
+            Self::In => ',',
+            Self::Jz { .. } | Self::SetZero => '[',
+            Self::Jnz { .. } => ']',
+            Self::Debug => '#',
+        }
+    }
+
+    /// A short description of what this instruction does
+    ///
+    /// ```
+    /// # use bft_types::Instruction;
+    /// assert_eq!(Instruction::Out.description(), "Output the byte at the data pointer");
+    /// assert_eq!(Instruction::Jnz { dest: 1 }.description(), "Jump if not zero");
+    /// ```
+    pub fn description(&self) -> &'static str {
+        match self {
+            Self::Inc => "Increment the data pointer by one",
+            Self::Dec => "Decrement the data pointer by one",
+            Self::Succ => "Increment the byte at the data pointer by one",
+            Self::Pred => "Decrement the byte at the data pointer by one",
+            Self::Out => "Output the byte at the data pointer",
+            Self::In => "Accept one byte of input",
+            Self::Jz { .. } => "Jump if zero",
+            Self::Jnz { .. } => "Jump if not zero",
+            Self::Add(_) => "Add to the byte at the data pointer",
+            Self::Sub(_) => "Subtract from the byte at the data pointer",
+            Self::Right(_) => "Move the data pointer right",
+            Self::Left(_) => "Move the data pointer left",
+            Self::SetZero => "Set the byte at the data pointer to zero",
+            Self::Debug => "Report the state of the machine",
+        }
+    }
+
+    /// The brainfuck source this instruction stands for, as a token and how many times it repeats
+    ///
+    /// Folded runs repeat their character and a cleared cell is written as `[-]`.
+    pub(crate) fn source_token(&self) -> (&'static str, usize) {
+        let token = match self {
+            Self::Inc | Self::Right(_) => ">",
+            Self::Dec | Self::Left(_) => "<",
+            Self::Succ | Self::Add(_) => "+",
+            Self::Pred | Self::Sub(_) => "-",
+            Self::Out => ".",
+            Self::In => ",",
+            Self::Jz { .. } => "[",
+            Self::Jnz { .. } => "]",
+            Self::SetZero => "[-]",
+            Self::Debug => "#",
+        };
+        let count = match *self {
+            Self::Add(n) | Self::Sub(n) => usize::from(n),
+            Self::Right(n) | Self::Left(n) => n,
+            _ => 1,
+        };
+        (token, count)
+    }
+}
+
+/// Writes the brainfuck source the instruction stands for, expanding folded runs
+///
+/// ```
+/// # use bft_types::Instruction;
+/// assert_eq!(Instruction::Jz { dest: 10 }.to_string(), "[");
+/// assert_eq!(Instruction::Add(3).to_string(), "+++");
+/// assert_eq!(Instruction::SetZero.to_string(), "[-]");
+/// ```
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (token, count) = self.source_token();
+        for _ in 0..count {
+            f.write_str(token)?;
+        }
+        Ok(())
+    }
 }
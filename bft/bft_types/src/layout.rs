@@ -0,0 +1,113 @@
+use crate::Instruction;
+
+/// Lays out brainfuck source a line at a time, wrapping code and indenting loop bodies
+///
+/// Each bracket goes on a line of its own, with the body of its loop indented by `indent`
+/// spaces more than the bracket. Other instructions are wrapped into lines of at most `width`
+/// characters, indentation included, though every line holds at least one instruction. Lines
+/// of other text, such as comments, can be placed between the code at the indentation of the
+/// loop they are in.
+///
+/// ```
+/// # use bft_types::{Instruction, SourceLayout};
+/// let mut layout = SourceLayout::new(80, 2);
+/// layout.push_line("count down");
+/// layout.push_blank();
+/// for instr in [Instruction::Jz { dest: 2 }, Instruction::Pred, Instruction::Jnz { dest: 1 }] {
+///     layout.push_instruction(instr);
+/// }
+/// assert_eq!(layout.finish(), "count down\n\n[\n  -\n]\n");
+/// ```
+#[derive(Debug, Clone)]
+pub struct SourceLayout {
+    /// The finished lines, without their newlines
+    lines: Vec<String>,
+    /// The instructions on the line of code being built, without its indentation
+    code: String,
+    /// The number of loops the next line is inside
+    depth: usize,
+    /// The column to wrap code at
+    width: usize,
+    /// The number of spaces to indent each loop body by
+    indent: usize,
+}
+
+impl SourceLayout {
+    /// Start laying out source with nothing in it
+    ///
+    /// `width`: the column to wrap code at
+    /// `indent`: the number of spaces to indent each loop body by
+    pub fn new(width: usize, indent: usize) -> Self {
+        Self {
+            lines: Vec::new(),
+            code: String::new(),
+            depth: 0,
+            width,
+            indent,
+        }
+    }
+
+    /// Add an instruction, written out as its brainfuck source
+    ///
+    /// Folded runs are written out in full and a cleared cell as `[-]`, as for [`Program`]'s
+    /// `Display`.
+    ///
+    /// [`Program`]: crate::Program
+    pub fn push_instruction(&mut self, instr: Instruction) {
+        match instr {
+            Instruction::Jz { .. } => {
+                self.push_line("[");
+                self.depth += 1;
+            }
+            Instruction::Jnz { .. } => {
+                self.end_code();
+                self.depth = self.depth.saturating_sub(1);
+                self.push_line("]");
+            }
+            _ => {
+                let room = self.width.saturating_sub(self.depth * self.indent);
+                let (token, count) = instr.source_token();
+                for _ in 0..count {
+                    if !self.code.is_empty() && self.code.len() + token.len() > room {
+                        self.end_code();
+                    }
+                    self.code.push_str(token);
+                }
+            }
+        }
+    }
+
+    /// Add a line of text at the current indentation, after any code being built
+    pub fn push_line(&mut self, text: &str) {
+        self.end_code();
+        let indentation = " ".repeat(self.depth * self.indent);
+        self.lines.push(indentation + text);
+    }
+
+    /// Add a blank line, unless it would be the first line or follow another blank line
+    pub fn push_blank(&mut self) {
+        self.end_code();
+        if self.lines.last().is_some_and(|line| !line.is_empty()) {
+            self.lines.push(String::new());
+        }
+    }
+
+    /// The source laid out so far, with every line ended by a newline and no blank lines at
+    /// the end
+    pub fn finish(mut self) -> String {
+        self.end_code();
+        while self.lines.last().is_some_and(String::is_empty) {
+            self.lines.pop();
+        }
+        self.lines.iter().flat_map(|line| [line, "\n"]).collect()
+    }
+
+    /// Finish the line of code being built, if there is one
+    fn end_code(&mut self) {
+        if !self.code.is_empty() {
+            let code = std::mem::take(&mut self.code);
+            let indentation = " ".repeat(self.depth * self.indent);
+            self.lines.push(indentation + &code);
+        }
+    }
+}
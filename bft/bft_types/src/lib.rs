@@ -5,8 +5,30 @@
 
 /// the brainfuck program
 mod program;
-pub use program::{Program, SourceLocation};
+pub use program::{BfParseError, BfParseErrorKind, ParseOptions, Program, SourceLocation};
 
 /// the instructions of the brainfuck program
 mod instruction;
 pub use instruction::Instruction;
+
+/// the languages spelling brainfuck's instructions differently
+mod dialect;
+pub use dialect::{Dialect, TokenMap, TokenMapError};
+
+/// parsing which keeps the comments of a brainfuck program
+mod lossless;
+pub use lossless::SourceToken;
+
+/// laying out brainfuck source with wrapped lines and indented loops
+mod layout;
+pub use layout::SourceLayout;
+
+/// statistics describing the shape of a brainfuck program
+mod stats;
+pub use stats::ProgramStats;
+
+#[cfg(feature = "test-util")]
+pub mod test_util;
+
+/// The version of this crate
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
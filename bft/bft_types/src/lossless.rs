@@ -0,0 +1,128 @@
+use std::path::Path;
+
+use crate::{BfParseError, ParseOptions, Program, SourceLocation};
+
+/// A piece of a brainfuck source file, as kept by [`Program::parse_lossless`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SourceToken {
+    /// The instruction at this index of the program's instructions
+    Instruction(usize),
+
+    /// A run of characters on a single line which aren't instructions
+    Comment {
+        /// The characters of the comment, including any whitespace
+        text: String,
+        /// Where the comment starts in the source code
+        location: SourceLocation,
+    },
+
+    /// The end of a line
+    Newline,
+}
+
+impl Program {
+    /// Parse a program, also keeping every comment and line break of its source
+    ///
+    /// Parsing the text of the tokens back together gives the original source, except for line
+    /// endings which are all reported as [`SourceToken::Newline`]. Fails in the same way as
+    /// [`Program::try_new`].
+    ///
+    /// ```
+    /// # use bft_types::{Program, SourceLocation, SourceToken};
+    /// # use std::path::Path;
+    /// let (program, tokens) = Program::parse_lossless(Path::new("-"), "add +\n").unwrap();
+    /// assert_eq!(program.instructions().len(), 1);
+    /// assert_eq!(
+    ///     tokens,
+    ///     [
+    ///         SourceToken::Comment {
+    ///             text: "add ".to_owned(),
+    ///             location: SourceLocation { line: 0, column: 0 },
+    ///         },
+    ///         SourceToken::Instruction(0),
+    ///         SourceToken::Newline,
+    ///     ]
+    /// );
+    /// ```
+    pub fn parse_lossless(
+        filename: &Path,
+        file_contents: impl AsRef<str>,
+    ) -> Result<(Self, Vec<SourceToken>), BfParseError> {
+        let file_contents = file_contents.as_ref();
+        let program = Self::try_new(filename, file_contents)?;
+
+        let mut tokens = Vec::new();
+        let mut instructions = 0..program.instructions().len();
+        for (line_no, line) in file_contents.lines().enumerate() {
+            if line_no > 0 {
+                tokens.push(SourceToken::Newline);
+            }
+
+            // the comment being built up, and the column it started at
+            let mut comment: Option<(String, usize)> = None;
+            for (column, c) in line.chars().enumerate() {
+                if ParseOptions::default().is_instruction(c) {
+                    if let Some((text, column)) = comment.take() {
+                        tokens.push(comment_token(text, line_no, column));
+                    }
+                    let index = instructions
+                        .next()
+                        .expect("domain precondition broken, more instructions than parsed");
+                    tokens.push(SourceToken::Instruction(index));
+                } else {
+                    comment
+                        .get_or_insert_with(|| (String::new(), column))
+                        .0
+                        .push(c);
+                }
+            }
+            if let Some((text, column)) = comment {
+                tokens.push(comment_token(text, line_no, column));
+            }
+        }
+        if file_contents.ends_with('\n') {
+            tokens.push(SourceToken::Newline);
+        }
+
+        Ok((program, tokens))
+    }
+}
+
+/// A comment token for `text`, which started at `line` and `column`
+fn comment_token(text: String, line: usize, column: usize) -> SourceToken {
+    SourceToken::Comment {
+        text,
+        location: SourceLocation { line, column },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_lossless_keeps_everything() {
+        let source = "a+ b\r\n\n[c]\n";
+        let (program, tokens) = Program::parse_lossless(Path::new("-"), source).unwrap();
+
+        let mut rebuilt = String::new();
+        for token in &tokens {
+            match token {
+                SourceToken::Instruction(i) => {
+                    rebuilt.push(program.instructions()[*i].as_char());
+                }
+                SourceToken::Comment { text, .. } => rebuilt.push_str(text),
+                SourceToken::Newline => rebuilt.push('\n'),
+            }
+        }
+        assert_eq!(rebuilt, "a+ b\n\n[c]\n");
+        assert_eq!(tokens[2], comment_token(" b".to_owned(), 0, 2),);
+        assert_eq!(tokens[6], comment_token("c".to_owned(), 2, 1));
+    }
+
+    #[test]
+    fn test_parse_lossless_fails_like_try_new() {
+        let err = Program::parse_lossless(Path::new("-"), "x[").unwrap_err();
+        assert_eq!(err, Program::try_new(Path::new("-"), "x[").unwrap_err());
+    }
+}
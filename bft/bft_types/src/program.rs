@@ -2,23 +2,191 @@ use core::fmt;
 use std::{
     collections::BTreeMap,
     error::Error,
+    io::Read,
     path::{Path, PathBuf},
 };
 
-use crate::Instruction;
+use crate::{Dialect, Instruction, SourceLayout};
 
 /// A brainfuck Program
 #[derive(Debug, Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "UncheckedProgram")
+)]
 pub struct Program {
     /// filename the program was created from
     filename: PathBuf,
 
     /// instructions contained within the file the program was loaded from
     instructions: Vec<Instruction>,
+
+    /// location in the source file of each instruction
+    source_map: Vec<SourceLocation>,
+
+    /// the input following the program's code, if it was separated from it by a `!`
+    embedded_input: Option<String>,
+}
+
+/// A program as it was deserialized, before its jumps have been checked
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct UncheckedProgram {
+    /// filename the program was created from
+    filename: PathBuf,
+
+    /// instructions of the program, whose jumps may not be valid
+    instructions: Vec<Instruction>,
+
+    /// location in the source file of each instruction
+    source_map: Vec<SourceLocation>,
+
+    /// the input following the program's code, absent from programs serialized without it
+    #[serde(default)]
+    embedded_input: Option<String>,
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<UncheckedProgram> for Program {
+    type Error = BfParseError;
+
+    fn try_from(unchecked: UncheckedProgram) -> Result<Self, Self::Error> {
+        let UncheckedProgram {
+            filename,
+            instructions,
+            source_map,
+            embedded_input,
+        } = unchecked;
+
+        let error = |location, kind| BfParseError {
+            filename: filename.clone(),
+            problems: vec![(kind, location)],
+        };
+
+        if instructions.len() != source_map.len() {
+            return Err(error(
+                SourceLocation { line: 0, column: 0 },
+                BfParseErrorKind::SourceMapMismatch,
+            ));
+        }
+
+        // each bracket must jump to just after its partner, with the pairs properly nested
+        let mut jump_stack = vec![];
+        for (i, instr) in instructions.iter().enumerate() {
+            match *instr {
+                Instruction::Jz { .. } => jump_stack.push(i),
+                Instruction::Jnz { dest } => {
+                    let jump_src = jump_stack
+                        .pop()
+                        .ok_or_else(|| error(source_map[i], BfParseErrorKind::UnopenedBracket))?;
+                    let paired = instructions[jump_src] == Instruction::Jz { dest: i + 1 };
+                    if !paired || dest != jump_src + 1 {
+                        return Err(error(source_map[i], BfParseErrorKind::InvalidJump));
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        if let Some(unclosed_brack) = jump_stack.pop() {
+            return Err(error(
+                source_map[unclosed_brack],
+                BfParseErrorKind::UnclosedBracket,
+            ));
+        }
+
+        Ok(Self {
+            filename,
+            instructions,
+            source_map,
+            embedded_input,
+        })
+    }
 }
 
 /// The alphabet of valid brainfuck characters
-const BF_ALPHABET: &str = "><+-.,[]";
+pub(crate) const BF_ALPHABET: &str = "><+-.,[]";
+
+/// The character parsed as [`Instruction::Debug`] when debug characters are enabled
+pub(crate) const DEBUG_CHAR: char = '#';
+
+/// The character separating a program's code from the input following it, when enabled
+const INPUT_SEPARATOR: char = '!';
+
+/// Extensions to the brainfuck language to accept while parsing, all disabled by default
+///
+/// ```
+/// # use bft_types::{Instruction, ParseOptions, Program};
+/// # use std::path::Path;
+/// let options = ParseOptions::default().with_debug_chars(true);
+/// let program = Program::try_new_with_options(Path::new("-"), "+#", options).unwrap();
+/// assert_eq!(program.instructions(), [Instruction::Succ, Instruction::Debug]);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ParseOptions {
+    /// How the instructions are spelled
+    dialect: Dialect,
+
+    /// Is `#` parsed as [`Instruction::Debug`] rather than being a comment
+    debug_chars: bool,
+
+    /// Does the first `!` outside of a loop end the code, with the rest being its input
+    input_separator: bool,
+}
+
+impl ParseOptions {
+    /// Set the dialect the program is written in, brainfuck by default
+    pub fn with_dialect(mut self, dialect: Dialect) -> Self {
+        self.dialect = dialect;
+        self
+    }
+
+    /// Set whether `#` is parsed as [`Instruction::Debug`] rather than being a comment
+    ///
+    /// Only brainfuck has debug characters, other dialects ignore this.
+    pub fn with_debug_chars(mut self, debug_chars: bool) -> Self {
+        self.debug_chars = debug_chars;
+        self
+    }
+
+    /// Set whether the first `!` outside of a loop ends the code, with everything after it
+    /// being the program's input, see [`Program::embedded_input`]
+    ///
+    /// A `!` inside a loop stays a comment. Only brainfuck has embedded input, other dialects
+    /// ignore this.
+    pub fn with_input_separator(mut self, input_separator: bool) -> Self {
+        self.input_separator = input_separator;
+        self
+    }
+
+    /// Split `source` into its code and the input embedded after it, if there is any
+    fn split_embedded_input<'s>(&self, source: &'s str) -> (&'s str, Option<&'s str>) {
+        if !self.input_separator || self.dialect != Dialect::Brainfuck {
+            return (source, None);
+        }
+
+        let mut depth = 0_usize;
+        for (i, c) in source.char_indices() {
+            match c {
+                '[' => depth += 1,
+                // an unopened bracket is reported once the code is parsed
+                ']' => depth = depth.saturating_sub(1),
+                INPUT_SEPARATOR if depth == 0 => {
+                    let input = &source[i + INPUT_SEPARATOR.len_utf8()..];
+                    return (&source[..i], Some(input));
+                }
+                _ => {}
+            }
+        }
+        (source, None)
+    }
+
+    /// Is `c` an instruction when parsing with these options
+    pub(crate) fn is_instruction(&self, c: char) -> bool {
+        (c.is_ascii() && BF_ALPHABET.contains(c)) || (self.debug_chars && c == DEBUG_CHAR)
+    }
+}
 
 impl Program {
     /// Construct a new brainfuck program from a filename and it's contents
@@ -27,44 +195,73 @@ impl Program {
     /// `file_contents`: the contents of `filename`
     ///
     /// if `file_contents` is malformed (has unbalanced brackets) construction will fail
-    /// reporting whether an unopened or unclosed bracket caused the failure, and the
-    /// source code location of that failure.
+    /// reporting every unopened and unclosed bracket, and the source code location of each.
     ///
     /// ```
     /// # use bft_types::Program;
     /// # use std::path::Path;
     /// let contents = include_str!("../../programs/example.bf");
-    /// let program = Program::try_new(Path::new("../../programs/example.bf"), contents).unwrap();
+    /// let program = Program::try_new(Path::new("example.bf"), contents).unwrap();
     /// ```
     pub fn try_new(filename: &Path, file_contents: impl AsRef<str>) -> Result<Self, BfParseError> {
-        // first filter out comment characters
-        let mut tokens = Vec::new();
-        let mut token_sources = Vec::new();
-        for (line_no, line) in file_contents.as_ref().lines().enumerate() {
-            for (column, c) in line.chars().enumerate() {
-                if c.is_ascii() && BF_ALPHABET.contains(c) {
-                    tokens.push(c as u8);
-                    token_sources.push(SourceLocation {
-                        line: line_no,
-                        column,
-                    })
+        Self::try_new_with_options(filename, file_contents, ParseOptions::default())
+    }
+
+    /// Construct a new brainfuck program, accepting the extensions enabled in `options`
+    ///
+    /// `filename`: the file the program was loaded from
+    /// `file_contents`: the contents of `filename`
+    /// `options`: the extensions to the language to accept
+    ///
+    /// Fails in the same way as [`Program::try_new`].
+    pub fn try_new_with_options(
+        filename: &Path,
+        file_contents: impl AsRef<str>,
+        options: ParseOptions,
+    ) -> Result<Self, BfParseError> {
+        let (code, embedded_input) = options.split_embedded_input(file_contents.as_ref());
+
+        // first filter out comments, leaving the brainfuck character for each token
+        let (tokens, token_sources): (Vec<u8>, Vec<SourceLocation>) = match &options.dialect {
+            Dialect::Brainfuck => {
+                let mut tokens = Vec::new();
+                for (line_no, line) in code.lines().enumerate() {
+                    for (column, c) in line.chars().enumerate() {
+                        if options.is_instruction(c) {
+                            let location = SourceLocation {
+                                line: line_no,
+                                column,
+                            };
+                            tokens.push((c as u8, location));
+                        }
+                    }
                 }
+                tokens.into_iter().unzip()
             }
-        }
+            Dialect::Ook => Dialect::ook_tokens(code)
+                .map_err(|problem| BfParseError {
+                    filename: filename.to_owned(),
+                    problems: vec![problem],
+                })?
+                .into_iter()
+                .unzip(),
+            Dialect::Custom(map) => map.tokenise(code).into_iter().unzip(),
+        };
 
         // track of all the jump destinations
         let mut jumps = BTreeMap::new();
         let mut jump_stack = vec![];
+        // the index of every unbalanced bracket, carrying on past them to find them all
+        let mut unbalanced = vec![];
 
         for (i, op) in tokens.iter().copied().enumerate() {
             if op == b'[' {
                 jump_stack.push(i);
             } else if op == b']' {
-                let jump_src = jump_stack.pop().ok_or_else(|| BfParseError {
-                    filename: filename.to_owned(),
-                    location: token_sources[i],
-                    kind: BfParseErrorKind::UnopenedBracket,
-                })?;
+                let Some(jump_src) = jump_stack.pop() else {
+                    unbalanced.push((i, BfParseErrorKind::UnopenedBracket));
+                    continue;
+                };
 
                 // insert both the forward and backward jumps
                 jumps.insert(jump_src, i);
@@ -72,12 +269,20 @@ impl Program {
             }
         }
 
-        // if the jump stack has elements then there is an unbalanced open bracket
-        if let Some(unclosed_brack) = jump_stack.pop() {
+        // any brackets left on the jump stack are unbalanced open brackets
+        unbalanced.extend(
+            jump_stack
+                .into_iter()
+                .map(|i| (i, BfParseErrorKind::UnclosedBracket)),
+        );
+        if !unbalanced.is_empty() {
+            unbalanced.sort_by_key(|&(i, _)| i);
             return Err(BfParseError {
                 filename: filename.to_owned(),
-                location: token_sources[unclosed_brack],
-                kind: BfParseErrorKind::UnclosedBracket,
+                problems: unbalanced
+                    .into_iter()
+                    .map(|(i, kind)| (kind, token_sources[i]))
+                    .collect(),
             });
         }
 
@@ -96,8 +301,9 @@ impl Program {
                     dest: jumps[&i] + 1,
                 },
                 b']' => Instruction::Jnz {
-                    pair_loc: jumps[&i],
+                    dest: jumps[&i] + 1,
                 },
+                b'#' => Instruction::Debug,
                 _ => unreachable!(
                     "domain precondition broken, invalid instruction present after filtering"
                 ),
@@ -107,30 +313,119 @@ impl Program {
         Ok(Self {
             filename: filename.to_owned(),
             instructions: instrs,
+            source_map: token_sources,
+            embedded_input: embedded_input.map(str::to_owned),
         })
     }
 
+    /// Construct a program written in `dialect`, see [`Dialect`]
+    ///
+    /// `filename`: the file the program was loaded from
+    /// `file_contents`: the contents of `filename`
+    /// `dialect`: how the program's instructions are spelled
+    ///
+    /// Fails in the same way as [`Program::try_new`], or on an Ook! token with no instruction.
+    pub fn try_new_with_dialect(
+        filename: &Path,
+        file_contents: impl AsRef<str>,
+        dialect: Dialect,
+    ) -> Result<Self, BfParseError> {
+        let options = ParseOptions::default().with_dialect(dialect);
+        Self::try_new_with_options(filename, file_contents, options)
+    }
+
+    /// Construct a new brainfuck program from source code held in memory
+    ///
+    /// `name`: the name to report the program as having come from in errors
+    /// `src`: the brainfuck source code
+    ///
+    /// ```
+    /// # use bft_types::Program;
+    /// let program = Program::from_source("<inline>", "+[-]").unwrap();
+    /// assert_eq!(program.filename().to_str(), Some("<inline>"));
+    /// ```
+    pub fn from_source(
+        name: impl Into<PathBuf>,
+        src: impl AsRef<str>,
+    ) -> Result<Self, BfParseError> {
+        Self::try_new(&name.into(), src)
+    }
+
     /// Load a brainfuck program from a file:
-    /// `filename`: the file to load the program from
+    /// `filename`: the file to load the program from, which is kept exactly as given
     ///
     /// ```
     /// # use bft_types::Program;
-    /// let program = Program::from_file("../../programs/example.bf");
+    /// let program = Program::from_file("../programs/example.bf");
     /// ```
+    #[cfg(feature = "fs")]
     pub fn from_file<P: AsRef<Path>>(filename: P) -> Result<Self, Box<dyn Error>> {
         let path = filename.as_ref();
         let contents = std::fs::read_to_string(path)?;
-        let filename = path.file_name().ok_or_else(|| {
-            format!("Failed to load brainfuck program from {path:?} path doesn't point to a file.")
-        })?;
-        Ok(Self::try_new(Path::new(filename), contents)?)
+        Ok(Self::try_new(path, contents)?)
+    }
+
+    /// Load a brainfuck program by reading all of `reader`
+    ///
+    /// `name`: the name to report the program as having come from in errors
+    /// `reader`: where to read the program's source from, such as stdin
+    ///
+    /// ```
+    /// # use bft_types::Program;
+    /// let program = Program::from_reader("<stdin>", "+[-]".as_bytes()).unwrap();
+    /// assert_eq!(program.instructions().len(), 4);
+    /// ```
+    pub fn from_reader(
+        name: impl Into<PathBuf>,
+        reader: impl Read,
+    ) -> Result<Self, Box<dyn Error>> {
+        Self::from_reader_with_options(name, reader, ParseOptions::default())
+    }
+
+    /// Load a brainfuck program by reading all of `reader`, accepting the extensions enabled in
+    /// `options`
+    ///
+    /// `name`: the name to report the program as having come from in errors
+    /// `reader`: where to read the program's source from, such as stdin
+    /// `options`: the extensions to the language to accept
+    ///
+    /// ```
+    /// # use bft_types::{ParseOptions, Program};
+    /// let options = ParseOptions::default().with_debug_chars(true);
+    /// let program = Program::from_reader_with_options("<stdin>", "+#".as_bytes(), options).unwrap();
+    /// assert_eq!(program.instructions().len(), 2);
+    /// ```
+    pub fn from_reader_with_options(
+        name: impl Into<PathBuf>,
+        mut reader: impl Read,
+        options: ParseOptions,
+    ) -> Result<Self, Box<dyn Error>> {
+        let mut contents = String::new();
+        reader.read_to_string(&mut contents)?;
+        Ok(Self::try_new_with_options(&name.into(), contents, options)?)
+    }
+
+    /// The input which followed the program's code, if it was parsed with
+    /// [`ParseOptions::with_input_separator`] and had a `!` outside of any loop
+    ///
+    /// ```
+    /// # use bft_types::{ParseOptions, Program};
+    /// # use std::path::Path;
+    /// let options = ParseOptions::default().with_input_separator(true);
+    /// let program = Program::try_new_with_options(Path::new("-"), ",[.,]!hello", options).unwrap();
+    /// assert_eq!(program.instructions().len(), 5);
+    /// assert_eq!(program.embedded_input(), Some("hello"));
+    /// ```
+    pub fn embedded_input(&self) -> Option<&str> {
+        self.embedded_input.as_deref()
     }
 
     /// name of the file this program was loaded from
     ///
     /// ```
     /// # use bft_types::Program;
-    /// let program = Program::from_file("../programs/example.bf").unwrap();
+    /// let source = include_str!("../../programs/example.bf");
+    /// let program = Program::from_source("example.bf", source).unwrap();
     /// assert_eq!(program.filename().to_str(), Some("example.bf"));
     /// ```
     pub fn filename(&self) -> &Path {
@@ -141,7 +436,7 @@ impl Program {
     ///
     /// ```
     /// # use bft_types::Program;
-    /// let program = Program::from_file("../programs/example.bf").unwrap();
+    /// let program = Program::from_source("-", include_str!("../../programs/example.bf")).unwrap();
     /// for instr in program.instructions() {
     ///     println!("{instr:?}");
     /// }
@@ -149,14 +444,218 @@ impl Program {
     pub fn instructions(&self) -> &[Instruction] {
         &self.instructions
     }
+
+    /// location in the source file of each instruction, indexed by instruction pointer
+    ///
+    /// ```
+    /// # use bft_types::{Program, SourceLocation};
+    /// let program = Program::from_source("-", include_str!("../../programs/example.bf")).unwrap();
+    /// assert_eq!(program.source_map()[0], SourceLocation { line: 7, column: 3 });
+    /// ```
+    pub fn source_map(&self) -> &[SourceLocation] {
+        &self.source_map
+    }
+
+    /// instructions contained by the program paired with their location in the source file
+    ///
+    /// ```
+    /// # use bft_types::Program;
+    /// let program = Program::from_source("-", include_str!("../../programs/example.bf")).unwrap();
+    /// for (instr, location) in program.instructions_with_locations() {
+    ///     println!("{location}: {instr:?}");
+    /// }
+    /// ```
+    pub fn instructions_with_locations(
+        &self,
+    ) -> impl Iterator<Item = (Instruction, SourceLocation)> + '_ {
+        self.instructions
+            .iter()
+            .copied()
+            .zip(self.source_map.iter().copied())
+    }
+
+    /// An equivalent program with each run of `+`, `-`, `>` or `<` folded into a single
+    /// counted instruction, and each `[-]` or `[+]` loop replaced by a single
+    /// [`Instruction::SetZero`]
+    ///
+    /// Jump destinations are recomputed for the shorter program, and each folded instruction
+    /// keeps the source location of the first token in its run.
+    ///
+    /// ```
+    /// # use bft_types::{Instruction, Program};
+    /// let program = Program::from_source("-", "+++[->>+<<]").unwrap().optimized();
+    /// assert_eq!(
+    ///     program.instructions(),
+    ///     [
+    ///         Instruction::Add(3),
+    ///         Instruction::Jz { dest: 7 },
+    ///         Instruction::Sub(1),
+    ///         Instruction::Right(2),
+    ///         Instruction::Add(1),
+    ///         Instruction::Left(2),
+    ///         Instruction::Jnz { dest: 2 },
+    ///     ]
+    /// );
+    /// ```
+    pub fn optimized(&self) -> Self {
+        let mut instructions: Vec<Instruction> = Vec::with_capacity(self.instructions.len());
+        let mut source_map = Vec::with_capacity(self.source_map.len());
+        let mut jump_stack = vec![];
+
+        for (instr, location) in self.instructions_with_locations() {
+            let instr = match instr {
+                Instruction::Inc => Instruction::Right(1),
+                Instruction::Dec => Instruction::Left(1),
+                Instruction::Succ => Instruction::Add(1),
+                Instruction::Pred => Instruction::Sub(1),
+                Instruction::Jz { .. } => {
+                    // the destination is filled in once the matching bracket is reached
+                    jump_stack.push(instructions.len());
+                    Instruction::Jz { dest: 0 }
+                }
+                Instruction::Jnz { .. } => {
+                    let jump_src = jump_stack
+                        .pop()
+                        .expect("domain precondition broken, program has unbalanced brackets");
+                    instructions[jump_src] = Instruction::Jz {
+                        dest: instructions.len() + 1,
+                    };
+                    Instruction::Jnz { dest: jump_src + 1 }
+                }
+                other => other,
+            };
+
+            if let Some(last) = instructions.last_mut() {
+                if let Some(folded) = fold(*last, instr) {
+                    *last = folded;
+                    continue;
+                }
+            }
+
+            instructions.push(instr);
+            source_map.push(location);
+
+            // a loop which only steps the current cell by one always exits with it at zero
+            if let [.., Instruction::Jz { .. }, body, Instruction::Jnz { .. }] = instructions[..] {
+                if matches!(body, Instruction::Add(1) | Instruction::Sub(1)) {
+                    let loop_start = instructions.len() - 3;
+                    instructions.truncate(loop_start);
+                    instructions.push(Instruction::SetZero);
+                    source_map.truncate(loop_start + 1);
+                }
+            }
+        }
+
+        Self {
+            filename: self.filename.clone(),
+            instructions,
+            source_map,
+            embedded_input: self.embedded_input.clone(),
+        }
+    }
+
+    /// The program's source with every comment removed
+    ///
+    /// With `cancel` set, adjacent `+-`, `-+`, `<>` and `><` pairs are removed as well, repeatedly
+    /// so `+<>-` disappears entirely. Pairs are never cancelled across a bracket, so a loop's
+    /// body is only ever shortened.
+    ///
+    /// Cancelling assumes cells wrap and the tape is unbounded, so only preserves what a program
+    /// does under those assumptions. Without wrapping `-+` on a zero cell is an overflow error,
+    /// or leaves it at one if cells saturate, where the cancelled program leaves it at zero.
+    /// Likewise cancelling `<>` and `><` can hide the head briefly moving off the end of a
+    /// fixed size tape.
+    ///
+    /// ```
+    /// # use bft_types::Program;
+    /// let program = Program::from_source("-", "add +<>- then + [ -+ ] >").unwrap();
+    /// assert_eq!(program.minified(false), "+<>-+[-+]>");
+    /// assert_eq!(program.minified(true), "+[]>");
+    /// ```
+    pub fn minified(&self, cancel: bool) -> String {
+        let source = self.to_string();
+        if !cancel {
+            return source;
+        }
+
+        let mut minified = String::with_capacity(source.len());
+        for c in source.chars() {
+            let inverse = match c {
+                '+' => Some('-'),
+                '-' => Some('+'),
+                '>' => Some('<'),
+                '<' => Some('>'),
+                _ => None,
+            };
+            if inverse.is_some_and(|inverse| minified.ends_with(inverse)) {
+                minified.pop();
+            } else {
+                minified.push(c);
+            }
+        }
+        minified
+    }
+
+    /// Write the program back out as brainfuck, wrapping lines and indenting loop bodies as
+    /// described for [`SourceLayout`]
+    ///
+    /// Parsing the result gives back the same instructions, except that folded runs and
+    /// cleared cells are written out in full as they are for [`Program`]'s `Display`.
+    ///
+    /// ```
+    /// # use bft_types::Program;
+    /// let program = Program::from_source("-", "++[>+++<-]>.").unwrap();
+    /// assert_eq!(program.to_source_pretty(4, 2), "++\n[\n  >+\n  ++\n  <-\n]\n>.\n");
+    /// ```
+    pub fn to_source_pretty(&self, width: usize, indent: usize) -> String {
+        let mut layout = SourceLayout::new(width, indent);
+        for &instr in &self.instructions {
+            layout.push_instruction(instr);
+        }
+        layout.finish()
+    }
+}
+
+/// Writes the program as brainfuck source, one character per instruction with no comments
+///
+/// Folded runs are expanded and cleared cells are written as `[-]`, so parsing the output
+/// of an optimized program and optimizing it again gives back the same instructions.
+///
+/// ```
+/// # use bft_types::Program;
+/// let program = Program::from_source("-", "add + and [ - ] loop").unwrap();
+/// assert_eq!(program.to_string(), "+[-]");
+/// assert_eq!(program.optimized().to_string(), "+[-]");
+/// ```
+impl fmt::Display for Program {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for instr in &self.instructions {
+            write!(f, "{instr}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Combine two adjacent counted instructions of the same kind into one
+///
+/// Returns None when the instructions are of different kinds or their combined count doesn't fit.
+fn fold(first: Instruction, second: Instruction) -> Option<Instruction> {
+    match (first, second) {
+        (Instruction::Add(a), Instruction::Add(b)) => a.checked_add(b).map(Instruction::Add),
+        (Instruction::Sub(a), Instruction::Sub(b)) => a.checked_add(b).map(Instruction::Sub),
+        (Instruction::Right(a), Instruction::Right(b)) => a.checked_add(b).map(Instruction::Right),
+        (Instruction::Left(a), Instruction::Left(b)) => a.checked_add(b).map(Instruction::Left),
+        _ => None,
+    }
 }
 
 /// location of a token in the source code
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SourceLocation {
-    /// line of the token in the source code
+    /// line of the token in the source code, counting from zero
     pub line: usize,
-    /// column of the token in the source code
+    /// column of the token in the source code, counting characters rather than bytes from zero
     pub column: usize,
 }
 
@@ -167,46 +666,156 @@ impl fmt::Display for SourceLocation {
 }
 
 /// errors that can occur while parsing brainfuck programs
+///
+/// More kinds may be added as programs are checked more thoroughly.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
 pub enum BfParseErrorKind {
     /// There was an unclosed bracket in the program
     UnclosedBracket,
     /// There was an unopened bracket in the program
     UnopenedBracket,
+    /// A deserialized program had a bracket which didn't jump to just after its partner
+    InvalidJump,
+    /// A deserialized program didn't have exactly one source location per instruction
+    SourceMapMismatch,
+    /// A token didn't stand for any instruction, like `Ook? Ook?` or an unpaired `Ook.`
+    InvalidToken,
 }
 
 /// used to hold extra metadata about the location and type of error encountered while parsing
 /// brainfuck programs
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct BfParseError {
     /// name of the file the error originated in
     filename: PathBuf,
-    /// location in the file of the token causing the error
-    location: SourceLocation,
-    /// kind of error encountered
-    kind: BfParseErrorKind,
+    /// kind and location of every problem found, in the order they appear in the file, of which
+    /// there is always at least one
+    problems: Vec<(BfParseErrorKind, SourceLocation)>,
 }
 
-impl fmt::Display for BfParseError {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let BfParseError {
-            filename,
-            location,
-            kind,
-        } = self;
+impl BfParseError {
+    /// name of the file the error originated in
+    ///
+    /// ```
+    /// # use bft_types::Program;
+    /// # use std::path::Path;
+    /// let err = Program::from_source("prog.bf", "[").unwrap_err();
+    /// assert_eq!(err.filename(), Path::new("prog.bf"));
+    /// ```
+    pub fn filename(&self) -> &Path {
+        &self.filename
+    }
+
+    /// kind of the first problem encountered
+    ///
+    /// ```
+    /// # use bft_types::{BfParseErrorKind, Program};
+    /// let err = Program::from_source("-", "+]").unwrap_err();
+    /// assert_eq!(err.kind(), BfParseErrorKind::UnopenedBracket);
+    /// ```
+    pub fn kind(&self) -> BfParseErrorKind {
+        self.problems[0].0
+    }
+
+    /// location in the file of the token causing the first problem
+    ///
+    /// ```
+    /// # use bft_types::{Program, SourceLocation};
+    /// let err = Program::from_source("-", "+\n+[").unwrap_err();
+    /// assert_eq!(err.location(), SourceLocation { line: 1, column: 1 });
+    /// ```
+    pub fn location(&self) -> SourceLocation {
+        self.problems[0].1
+    }
+
+    /// kind and location of every problem found, in the order they appear in the file
+    ///
+    /// ```
+    /// # use bft_types::{BfParseErrorKind, Program, SourceLocation};
+    /// let err = Program::from_source("-", "]+[").unwrap_err();
+    /// assert_eq!(
+    ///     err.problems(),
+    ///     [
+    ///         (BfParseErrorKind::UnopenedBracket, SourceLocation { line: 0, column: 0 }),
+    ///         (BfParseErrorKind::UnclosedBracket, SourceLocation { line: 0, column: 2 }),
+    ///     ]
+    /// );
+    /// ```
+    pub fn problems(&self) -> &[(BfParseErrorKind, SourceLocation)] {
+        &self.problems
+    }
+
+    /// render each problem followed by the offending line of `source` with a caret beneath the
+    /// token causing it
+    ///
+    /// `source`: the source code of the program which failed to parse
+    ///
+    /// ```
+    /// # use bft_types::Program;
+    /// let source = "+\n+[";
+    /// let err = Program::from_source("prog.bf", source).unwrap_err();
+    /// assert_eq!(
+    ///     err.render(source),
+    ///     "Error in input file prog.bf, dangling open bracket found at line 2 column 2\n\
+    ///      2 | +[\n  |  ^"
+    /// );
+    /// ```
+    pub fn render(&self, source: &str) -> String {
+        let rendered: Vec<String> = self
+            .problems
+            .iter()
+            .map(|&(kind, location)| {
+                let line = source.lines().nth(location.line).unwrap_or_default();
+                // columns count characters, and tabs are kept so the caret lines up however
+                // wide they are
+                let indent: String = line
+                    .chars()
+                    .take(location.column)
+                    .map(|c| if c == '\t' { '\t' } else { ' ' })
+                    .collect();
+                let line_no = (location.line + 1).to_string();
+                let gutter = " ".repeat(line_no.len());
+                format!(
+                    "{}\n{line_no} | {line}\n{gutter} | {indent}^",
+                    self.describe(kind, location)
+                )
+            })
+            .collect();
+        rendered.join("\n")
+    }
+
+    /// describe a single problem with the program
+    fn describe(&self, kind: BfParseErrorKind, location: SourceLocation) -> String {
         let msg = match kind {
             BfParseErrorKind::UnclosedBracket => "dangling open bracket found at",
             BfParseErrorKind::UnopenedBracket => "dangling close bracket found at",
+            BfParseErrorKind::InvalidJump => "bracket with an invalid jump destination found at",
+            BfParseErrorKind::SourceMapMismatch => {
+                "source map not matching the instructions found at"
+            }
+            BfParseErrorKind::InvalidToken => "token with no instruction found at",
         };
 
-        write!(
-            f,
+        format!(
             "Error in input file {}, {msg} {location}",
-            filename.display()
+            self.filename.display()
         )
     }
 }
 
+impl fmt::Display for BfParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let described: Vec<String> = self
+            .problems
+            .iter()
+            .map(|&(kind, location)| self.describe(kind, location))
+            .collect();
+        write!(f, "{}", described.join("\n"))
+    }
+}
+
 impl Error for BfParseError {}
 
 #[cfg(test)]
@@ -221,32 +830,400 @@ mod tests {
     #[case("-", &[Instruction::Pred])]
     #[case(".", &[Instruction::Out])]
     #[case(",", &[Instruction::In])]
-    #[case("[]", &[Instruction::Jz { dest: 2 }, Instruction::Jnz { pair_loc: 0 }])]
+    #[case("[]", &[Instruction::Jz { dest: 2 }, Instruction::Jnz { dest: 1 }])]
+    #[case("[[]]", &[
+        Instruction::Jz { dest: 4 },
+        Instruction::Jz { dest: 3 },
+        Instruction::Jnz { dest: 2 },
+        Instruction::Jnz { dest: 1 },
+    ])]
     fn test_parse_basic(#[case] input: &str, #[case] expected: &[Instruction]) {
         let prog = Program::try_new(Path::new("-"), input).unwrap();
         assert_eq!(prog.instructions(), expected);
     }
 
     #[rstest]
-    #[case("[", BfParseErrorKind::UnclosedBracket, SourceLocation { line: 0, column: 0 })]
-    #[case("]", BfParseErrorKind::UnopenedBracket, SourceLocation { line: 0, column: 0 })]
-    #[case("][", BfParseErrorKind::UnopenedBracket, SourceLocation { line: 0, column: 0 })]
-    #[case("[[", BfParseErrorKind::UnclosedBracket, SourceLocation { line: 0, column: 1 })]
-    #[case("]]", BfParseErrorKind::UnopenedBracket, SourceLocation { line: 0, column: 0 })]
-    #[case("[[[[[[[[]]]]]]]]]", BfParseErrorKind::UnopenedBracket, SourceLocation { line: 0, column: 16 })]
-    #[case("[[[[[[[[[]]]]]]]]", BfParseErrorKind::UnclosedBracket, SourceLocation { line: 0, column: 0 })]
+    #[case("[", &[(BfParseErrorKind::UnclosedBracket, col(0))])]
+    #[case("]", &[(BfParseErrorKind::UnopenedBracket, col(0))])]
+    #[case("][", &[(BfParseErrorKind::UnopenedBracket, col(0)), (BfParseErrorKind::UnclosedBracket, col(1))])]
+    #[case("[[", &[(BfParseErrorKind::UnclosedBracket, col(0)), (BfParseErrorKind::UnclosedBracket, col(1))])]
+    #[case("]]", &[(BfParseErrorKind::UnopenedBracket, col(0)), (BfParseErrorKind::UnopenedBracket, col(1))])]
+    #[case("[[[[[[[[]]]]]]]]]", &[(BfParseErrorKind::UnopenedBracket, col(16))])]
+    #[case("[[[[[[[[[]]]]]]]]", &[(BfParseErrorKind::UnclosedBracket, col(0))])]
+    #[case("é→[", &[(BfParseErrorKind::UnclosedBracket, col(2))])]
     fn test_parse_fails_malformed_rstest(
         #[case] input: &str,
-        #[case] err_kind: BfParseErrorKind,
-        #[case] err_loc: SourceLocation,
+        #[case] problems: &[(BfParseErrorKind, SourceLocation)],
     ) {
-        let BfParseError {
-            filename,
-            location,
-            kind,
-        } = Program::try_new(Path::new("-"), input).unwrap_err();
-        assert_eq!(kind, err_kind);
-        assert_eq!(location, err_loc);
-        assert_eq!(filename, Path::new("-"));
+        let err = Program::try_new(Path::new("-"), input).unwrap_err();
+        assert_eq!(err.problems(), problems);
+        assert_eq!((err.kind(), err.location()), problems[0]);
+        assert_eq!(err.filename(), Path::new("-"));
+    }
+
+    /// a location on the first line of the source, `column` characters from its start
+    fn col(column: usize) -> SourceLocation {
+        SourceLocation { line: 0, column }
+    }
+
+    #[test]
+    fn test_parse_reports_every_bracket_error() {
+        let source = "]+[\n]]\n[-]>[";
+        let err = Program::try_new(Path::new("three.bf"), source).unwrap_err();
+        assert_eq!(
+            err.problems(),
+            [
+                (BfParseErrorKind::UnopenedBracket, col(0)),
+                (
+                    BfParseErrorKind::UnopenedBracket,
+                    SourceLocation { line: 1, column: 1 }
+                ),
+                (
+                    BfParseErrorKind::UnclosedBracket,
+                    SourceLocation { line: 2, column: 4 }
+                ),
+            ]
+        );
+        assert_eq!(
+            err.to_string(),
+            "Error in input file three.bf, dangling close bracket found at line 1 column 1
+Error in input file three.bf, dangling close bracket found at line 2 column 2
+Error in input file three.bf, dangling open bracket found at line 3 column 5"
+        );
+        assert_eq!(err.render(source).lines().count(), 9);
+    }
+
+    #[rstest]
+    #[case("\t[", "\t^")]
+    #[case("\t \t+[", "\t \t ^")]
+    #[case("é+[", "  ^")]
+    #[case("// ∑ ünïcödé\n++ → [", "     ^")]
+    fn test_render_caret(#[case] source: &str, #[case] caret_line: &str) {
+        let err = Program::try_new(Path::new("-"), source).unwrap_err();
+        let rendered = err.render(source);
+        let caret = rendered.lines().last().unwrap();
+        assert_eq!(caret.split_once(" | ").unwrap().1, caret_line);
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn test_from_file_keeps_full_path() {
+        let prog = Program::from_file("../programs/example.bf").unwrap();
+        assert_eq!(prog.filename(), Path::new("../programs/example.bf"));
+
+        let err = Program::from_file("../programs/unclosed_bracket.bf").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Error in input file ../programs/unclosed_bracket.bf, dangling open bracket found at line 1 column 12"
+        );
+    }
+
+    #[test]
+    fn test_from_source() {
+        let prog = Program::from_source("<eval>", "+-").unwrap();
+        assert_eq!(prog.filename(), Path::new("<eval>"));
+        assert_eq!(prog.instructions(), [Instruction::Succ, Instruction::Pred]);
+
+        let err = Program::from_source("<eval>", "[").unwrap_err();
+        assert_eq!(err.filename(), Path::new("<eval>"));
+        assert_eq!(err, Program::try_new(Path::new("<eval>"), "[").unwrap_err());
+        assert_ne!(err, Program::try_new(Path::new("-"), "[").unwrap_err());
+    }
+
+    #[rstest]
+    #[case(include_str!("../../programs/example.bf"))]
+    #[case(include_str!("../../programs/rot13.bf"))]
+    #[case(include_str!("../../programs/numwarp.bf"))]
+    #[case(include_str!("../../programs/tic_tac_toe.bf"))]
+    fn test_display_round_trips(#[case] source: &str) {
+        let prog = Program::from_source("-", source).unwrap();
+        let displayed = Program::from_source("-", prog.to_string()).unwrap();
+        assert_eq!(displayed.instructions(), prog.instructions());
+
+        for (width, indent) in [(80, 4), (1, 0), (10, 8)] {
+            let pretty = prog.to_source_pretty(width, indent);
+            let reparsed = Program::from_source("-", &pretty).unwrap();
+            assert_eq!(reparsed.instructions(), prog.instructions());
+            // wrapping only gives way when the indentation alone uses up the width
+            assert!(pretty
+                .lines()
+                .all(|l| l.len() <= width || l.trim_start().len() == 1));
+        }
+
+        let optimized = prog.optimized();
+        let redisplayed = Program::from_source("-", optimized.to_string()).unwrap();
+        assert_eq!(
+            redisplayed.optimized().instructions(),
+            optimized.instructions()
+        );
+        let repretty = Program::from_source("-", optimized.to_source_pretty(40, 2)).unwrap();
+        assert_eq!(
+            repretty.optimized().instructions(),
+            optimized.instructions()
+        );
+    }
+
+    #[rstest]
+    #[case("+-", "")]
+    #[case("+-+", "+")]
+    #[case(">+<<>->", ">+<->")]
+    #[case("+[-+]-", "+[]-")]
+    #[case("+[]-", "+[]-")]
+    #[case("+.-,<>", "+.-,")]
+    fn test_minified_cancels_pairs(#[case] input: &str, #[case] expected: &str) {
+        let prog = Program::from_source("-", input).unwrap();
+        assert_eq!(prog.minified(true), expected);
+        assert_eq!(prog.minified(false), input);
+    }
+
+    #[test]
+    fn test_debug_chars_opt_in() {
+        let source = "# a comment\n+#[-#]";
+        let strict = Program::try_new(Path::new("-"), source).unwrap();
+        assert!(!strict.instructions().contains(&Instruction::Debug));
+        assert_eq!(strict.to_string(), "+[-]");
+
+        let options = ParseOptions::default().with_debug_chars(true);
+        let extended = Program::try_new_with_options(Path::new("-"), source, options).unwrap();
+        let expected = [
+            (Instruction::Debug, SourceLocation { line: 0, column: 0 }),
+            (Instruction::Succ, SourceLocation { line: 1, column: 0 }),
+            (Instruction::Debug, SourceLocation { line: 1, column: 1 }),
+            (
+                Instruction::Jz { dest: 7 },
+                SourceLocation { line: 1, column: 2 },
+            ),
+            (Instruction::Pred, SourceLocation { line: 1, column: 3 }),
+            (Instruction::Debug, SourceLocation { line: 1, column: 4 }),
+            (
+                Instruction::Jnz { dest: 4 },
+                SourceLocation { line: 1, column: 5 },
+            ),
+        ];
+        assert_eq!(
+            extended.instructions_with_locations().collect::<Vec<_>>(),
+            expected
+        );
+        assert_eq!(extended.to_string(), "#+#[-#]");
+        // a debug character stops a loop being recognised as clearing the cell
+        assert_eq!(extended.optimized().stats().loops, 1);
+        assert!(!extended
+            .optimized()
+            .instructions()
+            .contains(&Instruction::SetZero));
+    }
+
+    #[rstest]
+    #[case("+!in", "+", Some("in"))]
+    #[case("+!", "+", Some(""))]
+    #[case("+", "+", None)]
+    #[case("[!]+!a!b", "[]+", Some("a!b"))]
+    #[case("wow! +[-]\n!\n[x]\n", "", Some(" +[-]\n!\n[x]\n"))]
+    fn test_input_separator(#[case] source: &str, #[case] code: &str, #[case] input: Option<&str>) {
+        let options = ParseOptions::default().with_input_separator(true);
+        let prog = Program::try_new_with_options(Path::new("-"), source, options).unwrap();
+        assert_eq!(prog.to_string(), code);
+        assert_eq!(prog.embedded_input(), input);
+        assert_eq!(prog.optimized().embedded_input(), input);
+
+        // without the option `!` is a comment like any other
+        let strict = Program::try_new(Path::new("-"), source).unwrap();
+        assert_eq!(strict.embedded_input(), None);
+    }
+
+    #[test]
+    fn test_input_separator_after_unopened_bracket() {
+        let options = ParseOptions::default().with_input_separator(true);
+        let err = Program::try_new_with_options(Path::new("-"), "+]!x]", options).unwrap_err();
+        assert_eq!(err.problems().len(), 1);
+        assert_eq!(err.kind(), BfParseErrorKind::UnopenedBracket);
+    }
+
+    #[test]
+    fn test_source_map_skips_comments() {
+        let input = "a + comment\n\tno code here\nxx[yy>\n  ] <.";
+        let prog = Program::try_new(Path::new("-"), input).unwrap();
+        assert_eq!(prog.instructions().len(), prog.source_map().len());
+
+        let expected = [
+            (Instruction::Succ, SourceLocation { line: 0, column: 2 }),
+            (
+                Instruction::Jz { dest: 4 },
+                SourceLocation { line: 2, column: 2 },
+            ),
+            (Instruction::Inc, SourceLocation { line: 2, column: 5 }),
+            (
+                Instruction::Jnz { dest: 2 },
+                SourceLocation { line: 3, column: 2 },
+            ),
+            (Instruction::Dec, SourceLocation { line: 3, column: 4 }),
+            (Instruction::Out, SourceLocation { line: 3, column: 5 }),
+        ];
+        assert_eq!(
+            prog.instructions_with_locations().collect::<Vec<_>>(),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_optimized_folds_runs() {
+        let prog = Program::try_new(Path::new("-"), "++ +\n>>-<.")
+            .unwrap()
+            .optimized();
+        let expected = [
+            (Instruction::Add(3), SourceLocation { line: 0, column: 0 }),
+            (Instruction::Right(2), SourceLocation { line: 1, column: 0 }),
+            (Instruction::Sub(1), SourceLocation { line: 1, column: 2 }),
+            (Instruction::Left(1), SourceLocation { line: 1, column: 3 }),
+            (Instruction::Out, SourceLocation { line: 1, column: 4 }),
+        ];
+        assert_eq!(
+            prog.instructions_with_locations().collect::<Vec<_>>(),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_optimized_splits_long_runs() {
+        let prog = Program::try_new(Path::new("-"), "+".repeat(300))
+            .unwrap()
+            .optimized();
+        assert_eq!(
+            prog.instructions(),
+            [Instruction::Add(255), Instruction::Add(45)]
+        );
+        assert_eq!(
+            prog.source_map()[1],
+            SourceLocation {
+                line: 0,
+                column: 255
+            }
+        );
+    }
+
+    #[test]
+    fn test_optimized_recomputes_jumps() {
+        let prog = Program::try_new(Path::new("-"), ">>[[--]<<]+")
+            .unwrap()
+            .optimized();
+        assert_eq!(
+            prog.instructions(),
+            [
+                Instruction::Right(2),
+                Instruction::Jz { dest: 7 },
+                Instruction::Jz { dest: 5 },
+                Instruction::Sub(2),
+                Instruction::Jnz { dest: 3 },
+                Instruction::Left(2),
+                Instruction::Jnz { dest: 2 },
+                Instruction::Add(1),
+            ]
+        );
+    }
+
+    #[rstest]
+    #[case("[-]", &[Instruction::SetZero])]
+    #[case("[+]", &[Instruction::SetZero])]
+    #[case("[--]", &[Instruction::Jz { dest: 3 }, Instruction::Sub(2), Instruction::Jnz { dest: 1 }])]
+    #[case("[>-]", &[
+        Instruction::Jz { dest: 4 },
+        Instruction::Right(1),
+        Instruction::Sub(1),
+        Instruction::Jnz { dest: 1 },
+    ])]
+    #[case("+[>[-]<-][[+]]", &[
+        Instruction::Add(1),
+        Instruction::Jz { dest: 7 },
+        Instruction::Right(1),
+        Instruction::SetZero,
+        Instruction::Left(1),
+        Instruction::Sub(1),
+        Instruction::Jnz { dest: 2 },
+        Instruction::Jz { dest: 10 },
+        Instruction::SetZero,
+        Instruction::Jnz { dest: 8 },
+    ])]
+    fn test_optimized_clear_loops(#[case] input: &str, #[case] expected: &[Instruction]) {
+        let prog = Program::try_new(Path::new("-"), input).unwrap().optimized();
+        assert_eq!(prog.instructions(), expected);
+        assert_eq!(prog.instructions().len(), prog.source_map().len());
+    }
+
+    #[test]
+    fn test_optimized_clear_loop_location() {
+        let prog = Program::try_new(Path::new("-"), "+\n [-] .")
+            .unwrap()
+            .optimized();
+        assert_eq!(
+            prog.source_map(),
+            [
+                SourceLocation { line: 0, column: 0 },
+                SourceLocation { line: 1, column: 1 },
+                SourceLocation { line: 1, column: 5 },
+            ]
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_round_trip() {
+        for prog in [
+            Program::from_source("rot13.bf", include_str!("../../programs/rot13.bf")).unwrap(),
+            Program::from_source("numwarp.bf", include_str!("../../programs/numwarp.bf"))
+                .unwrap()
+                .optimized(),
+        ] {
+            let json = serde_json::to_string(&prog).unwrap();
+            let parsed: Program = serde_json::from_str(&json).unwrap();
+            assert_eq!(parsed.filename(), prog.filename());
+            assert_eq!(parsed.instructions(), prog.instructions());
+            assert_eq!(parsed.source_map(), prog.source_map());
+        }
+
+        let json = serde_json::to_string(&BfParseErrorKind::UnclosedBracket).unwrap();
+        assert_eq!(
+            serde_json::from_str::<BfParseErrorKind>(&json).unwrap(),
+            BfParseErrorKind::UnclosedBracket
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[rstest]
+    #[case(
+        r#"[{"Jz":{"dest":5}},{"Jnz":{"dest":1}}]"#,
+        "bracket with an invalid jump"
+    )]
+    #[case(
+        r#"[{"Jz":{"dest":2}},{"Jnz":{"dest":0}}]"#,
+        "bracket with an invalid jump"
+    )]
+    #[case(r#"[{"Jz":{"dest":2}},"Inc"]"#, "dangling open bracket")]
+    #[case(r#"["Inc",{"Jnz":{"dest":1}}]"#, "dangling close bracket")]
+    fn test_serde_rejects_bad_jumps(#[case] instructions: &str, #[case] msg: &str) {
+        let location = r#"{"line":0,"column":0}"#;
+        let json = format!(
+            r#"{{"filename":"bad.bf","instructions":{instructions},"source_map":[{location},{location}]}}"#
+        );
+        let err = serde_json::from_str::<Program>(&json).unwrap_err();
+        assert!(err.to_string().contains(msg), "{err}");
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_rejects_crossed_jumps() {
+        // [ [ ] ] with each bracket paired with the wrong partner
+        let prog = Program::try_new(Path::new("-"), "[[]]").unwrap();
+        let mut json: serde_json::Value = serde_json::to_value(&prog).unwrap();
+        json["instructions"] = serde_json::json!([
+            {"Jz": {"dest": 3}},
+            {"Jz": {"dest": 4}},
+            {"Jnz": {"dest": 1}},
+            {"Jnz": {"dest": 2}},
+        ]);
+        assert!(serde_json::from_value::<Program>(json.clone()).is_err());
+
+        json["source_map"] = serde_json::json!([]);
+        let err = serde_json::from_value::<Program>(json).unwrap_err();
+        assert!(err.to_string().contains("source map"), "{err}");
     }
 }
@@ -0,0 +1,147 @@
+use core::fmt;
+use std::collections::BTreeMap;
+
+use crate::{Instruction, Program};
+
+/// The characters of the brainfuck language, each of which is counted even if a program doesn't
+/// use it
+///
+/// Counts are reported in the order of the characters themselves, `+,-.<>[]`, not this one.
+const OPCODES: [char; 8] = ['>', '<', '+', '-', '.', ',', '[', ']'];
+
+/// Summary statistics describing the shape of a program, see [`Program::stats`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProgramStats {
+    /// The number of instructions written as each brainfuck character, including those which
+    /// don't appear in the program
+    ///
+    /// Instructions are counted by [`Instruction::as_char`], so in an optimized program a folded
+    /// run counts once and a cleared cell counts as a `[`.
+    pub per_opcode: BTreeMap<char, usize>,
+
+    /// The total number of instructions
+    pub instructions: usize,
+
+    /// The number of loops, including loops optimized into a single instruction
+    pub loops: usize,
+
+    /// The deepest nesting of loops, zero when the program has no loops
+    pub max_depth: usize,
+
+    /// The number of `.` and `,` instructions
+    pub io: usize,
+}
+
+impl Program {
+    /// Count the instructions, loops and IO of the program
+    ///
+    /// ```
+    /// # use bft_types::Program;
+    /// let stats = Program::from_source("-", "+[>[-],.]").unwrap().stats();
+    /// assert_eq!(stats.instructions, 9);
+    /// assert_eq!(stats.per_opcode[&'+'], 1);
+    /// assert_eq!(stats.loops, 2);
+    /// assert_eq!(stats.max_depth, 2);
+    /// assert_eq!(stats.io, 2);
+    /// ```
+    pub fn stats(&self) -> ProgramStats {
+        let mut per_opcode: BTreeMap<char, usize> = OPCODES.iter().map(|&c| (c, 0)).collect();
+        let mut loops = 0;
+        let mut max_depth = 0;
+        let mut io = 0;
+
+        // the index of the `]` closing each loop enclosing the current instruction
+        let mut loop_ends: Vec<usize> = vec![];
+        for (i, instr) in self.instructions().iter().enumerate() {
+            *per_opcode.entry(instr.as_char()).or_default() += 1;
+
+            while loop_ends.last().is_some_and(|&end| end < i) {
+                loop_ends.pop();
+            }
+
+            match *instr {
+                Instruction::Jz { dest } => {
+                    loops += 1;
+                    // the jump lands just after the loop's closing bracket
+                    loop_ends.push(dest - 1);
+                    max_depth = max_depth.max(loop_ends.len());
+                }
+                Instruction::SetZero => {
+                    loops += 1;
+                    max_depth = max_depth.max(loop_ends.len() + 1);
+                }
+                Instruction::Out | Instruction::In => io += 1,
+                _ => {}
+            }
+        }
+
+        ProgramStats {
+            per_opcode,
+            instructions: self.instructions().len(),
+            loops,
+            max_depth,
+            io,
+        }
+    }
+}
+
+impl fmt::Display for ProgramStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{:<14}{:>8}", "instructions", self.instructions)?;
+        writeln!(f, "{:<14}{:>8}", "loops", self.loops)?;
+        writeln!(f, "{:<14}{:>8}", "max depth", self.max_depth)?;
+        write!(f, "{:<14}{:>8}", "io", self.io)?;
+        for (opcode, count) in &self.per_opcode {
+            write!(f, "\n{:<14}{count:>8}", format!("'{opcode}'"))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(include_str!("../../programs/example.bf"), 62, [13, 13, 3, 12, 11, 0, 5, 5], 5, 4, 11)]
+    #[case(include_str!("../../programs/rot13.bf"), 190, [39, 33, 41, 26, 1, 2, 24, 24], 24, 5, 3)]
+    fn test_stats(
+        #[case] source: &str,
+        #[case] instructions: usize,
+        #[case] counts: [usize; 8],
+        #[case] loops: usize,
+        #[case] max_depth: usize,
+        #[case] io: usize,
+    ) {
+        let stats = Program::from_source("-", source).unwrap().stats();
+        let expected = ProgramStats {
+            per_opcode: OPCODES.into_iter().zip(counts).collect(),
+            instructions,
+            loops,
+            max_depth,
+            io,
+        };
+        assert_eq!(stats, expected);
+    }
+
+    #[test]
+    fn test_stats_optimized() {
+        let prog = Program::from_source("-", "++[>[-]<-]").unwrap();
+        let stats = prog.optimized().stats();
+        assert_eq!(stats.instructions, 7);
+        assert_eq!(stats.loops, 2);
+        assert_eq!(stats.max_depth, 2);
+        assert_eq!(stats.per_opcode[&'['], 2);
+        assert_eq!(stats.per_opcode[&']'], 1);
+        assert_eq!(prog.stats().loops, 2);
+    }
+
+    #[test]
+    fn test_stats_sibling_loops() {
+        let stats = Program::from_source("-", "[[]][[][[]]]").unwrap().stats();
+        assert_eq!(stats.loops, 6);
+        assert_eq!(stats.max_depth, 3);
+        assert_eq!(stats.io, 0);
+    }
+}
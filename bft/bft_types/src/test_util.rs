@@ -0,0 +1,209 @@
+//! Generating random programs for property tests
+//!
+//! ```
+//! # use bft_types::test_util::program;
+//! # use proptest::{strategy::{Strategy, ValueTree}, test_runner::TestRunner};
+//! let mut runner = TestRunner::default();
+//! let program = program(64).new_tree(&mut runner).unwrap().current();
+//! assert!(program.instructions().len() <= 64);
+//! ```
+
+use std::path::Path;
+
+use proptest::{collection::vec, prelude::*};
+
+use crate::{Instruction, Program};
+
+/// How deeply [`program_source`] nests its loops
+const DEFAULT_DEPTH: u32 = 5;
+
+/// Source for a single instruction other than a bracket, or when `loops` is set possibly a loop
+/// shaped like the ones the optimisers rewrite
+fn atom(loops: bool) -> BoxedStrategy<String> {
+    let op = prop::sample::select(vec!["+", "-", ">", "<", ".", ","]).prop_map(str::to_owned);
+    if !loops {
+        return op.boxed();
+    }
+    prop_oneof![
+        6 => op,
+        1 => Just("[-]".to_owned()),
+        1 => (1..4_usize, 1..4_usize, any::<bool>()).prop_map(|(offset, factor, add)| {
+            let op = if add { "+" } else { "-" };
+            format!(
+                "[-{}{}{}]",
+                ">".repeat(offset),
+                op.repeat(factor),
+                "<".repeat(offset)
+            )
+        }),
+    ]
+    .boxed()
+}
+
+/// The source of a random program whose brackets are balanced, with at most `max_len`
+/// instructions
+///
+/// Without comments every character of the source is an instruction.
+pub fn program_source(max_len: usize) -> impl Strategy<Value = String> {
+    nested_program_source(DEFAULT_DEPTH, max_len)
+}
+
+/// The source of a random program like [`program_source`], with loops nested at most
+/// `max_depth` deep
+pub fn nested_program_source(max_depth: u32, max_len: usize) -> impl Strategy<Value = String> {
+    let leaf = vec(atom(max_depth > 0), 0..8).prop_map(|atoms| atoms.concat());
+    // the leaves already hold loops one deep
+    leaf.prop_recursive(max_depth.saturating_sub(1), 64, 4, |inner| {
+        vec(
+            prop_oneof![inner.clone(), inner.prop_map(|body| format!("[{body}]"))],
+            0..4,
+        )
+        .prop_map(|parts| parts.concat())
+    })
+    .prop_map(move |source| truncate_balanced(&source, max_len))
+}
+
+/// A random program with at most `max_len` instructions, see [`program_source`]
+pub fn program(max_len: usize) -> impl Strategy<Value = Program> {
+    program_source(max_len).prop_map(|source| {
+        Program::try_new(Path::new("<generated>"), &source).expect("brackets are balanced")
+    })
+}
+
+/// The source of a random program like [`nested_program_source`], along with the instructions
+/// it should parse to
+pub fn program_with_instructions(
+    max_depth: u32,
+    max_len: usize,
+) -> impl Strategy<Value = (String, Vec<Instruction>)> {
+    nested_program_source(max_depth, max_len).prop_map(|source| {
+        let instructions = expected_instructions(&source);
+        (source, instructions)
+    })
+}
+
+/// The instructions `source` stands for, worked out without the parser so it can be checked
+/// against them
+///
+/// # Panics
+/// When a character isn't an instruction or the brackets aren't balanced.
+fn expected_instructions(source: &str) -> Vec<Instruction> {
+    let mut instructions = Vec::with_capacity(source.len());
+    let mut open = vec![];
+    for (i, c) in source.chars().enumerate() {
+        let instr = match c {
+            '>' => Instruction::Inc,
+            '<' => Instruction::Dec,
+            '+' => Instruction::Succ,
+            '-' => Instruction::Pred,
+            '.' => Instruction::Out,
+            ',' => Instruction::In,
+            '[' => {
+                open.push(i);
+                // the destination is filled in once the matching bracket is reached
+                Instruction::Jz { dest: 0 }
+            }
+            ']' => {
+                let jump_src = open.pop().expect("brackets are balanced");
+                instructions[jump_src] = Instruction::Jz { dest: i + 1 };
+                Instruction::Jnz { dest: jump_src + 1 }
+            }
+            _ => panic!("{c:?} is not an instruction"),
+        };
+        instructions.push(instr);
+    }
+    assert!(open.is_empty(), "brackets are balanced");
+    instructions
+}
+
+/// Cut `source` down to at most `max_len` characters while keeping its brackets balanced
+fn truncate_balanced(source: &str, max_len: usize) -> String {
+    let mut out = String::new();
+    let mut open = 0;
+    for c in source.chars() {
+        // leave room to close every loop still open, including one opened now
+        let needed = open + usize::from(c == '[');
+        if c != ']' && out.len() + 1 + needed > max_len {
+            break;
+        }
+        match c {
+            '[' => open += 1,
+            ']' => open -= 1,
+            _ => {}
+        }
+        out.push(c);
+    }
+    out.extend(std::iter::repeat_n(']', open));
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// How deeply the loops of `source` are nested
+    fn nesting_depth(source: &str) -> u32 {
+        let (mut depth, mut deepest) = (0, 0);
+        for c in source.chars() {
+            match c {
+                '[' => {
+                    depth += 1;
+                    deepest = deepest.max(depth);
+                }
+                ']' => depth -= 1,
+                _ => {}
+            }
+        }
+        deepest
+    }
+
+    proptest! {
+        #[test]
+        fn test_generated_programs_parse(source in program_source(32)) {
+            prop_assert!(source.len() <= 32);
+            prop_assert!(Program::try_new(Path::new("-"), &source).is_ok(), "{source}");
+        }
+
+        #[test]
+        fn test_depth_and_length_bounded(
+            (max_depth, max_len, source) in (0..6_u32, 0..64_usize).prop_flat_map(
+                |(max_depth, max_len)| {
+                    (Just(max_depth), Just(max_len), nested_program_source(max_depth, max_len))
+                }
+            )
+        ) {
+            prop_assert!(source.len() <= max_len, "{source}");
+            prop_assert!(nesting_depth(&source) <= max_depth, "{source}");
+        }
+
+        #[test]
+        fn test_parses_to_generated_instructions(
+            (source, instructions) in program_with_instructions(6, 128)
+        ) {
+            let program = Program::try_new(Path::new("-"), &source).unwrap();
+            prop_assert_eq!(program.instructions(), &instructions[..]);
+        }
+
+        #[test]
+        fn test_brackets_point_past_partner((source, _) in program_with_instructions(6, 128)) {
+            let program = Program::try_new(Path::new("-"), &source).unwrap();
+            let instructions = program.instructions();
+            for (i, instr) in instructions.iter().enumerate() {
+                match *instr {
+                    Instruction::Jz { dest } => {
+                        prop_assert!(dest > i + 1);
+                        prop_assert_eq!(instructions[dest - 1], Instruction::Jnz { dest: i + 1 });
+                    }
+                    Instruction::Jnz { dest } => {
+                        prop_assert!(dest <= i);
+                        prop_assert_eq!(instructions[dest - 1], Instruction::Jz { dest: i + 1 });
+                        // the loop body between the pair holds whole loops only
+                        let body = &source[dest..i];
+                        prop_assert_eq!(body.matches('[').count(), body.matches(']').count());
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+}
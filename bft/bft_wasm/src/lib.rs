@@ -0,0 +1,90 @@
+#![deny(missing_docs)]
+#![deny(clippy::missing_docs_in_private_items)]
+
+//! JavaScript bindings for the brainfuck interpreter, built for wasm32-unknown-unknown
+//!
+//! ```
+//! let program = bft_wasm::parse("++++++++[>++++++++<-]>+.").unwrap();
+//! let result = bft_wasm::run(&program, &[], 1000);
+//! assert_eq!(result.output, b"A");
+//! assert_eq!(result.error, None);
+//! ```
+
+use std::path::Path;
+
+use bft_interp::{Machine, TapeKind, DEFAULT_TAPE_SIZE};
+use bft_types::Program;
+use wasm_bindgen::prelude::*;
+
+/// The name programs parsed from JavaScript are referred to by in errors
+const PROGRAM_NAME: &str = "<wasm>";
+
+/// A parsed program, ready to be run any number of times
+#[wasm_bindgen]
+#[derive(Debug)]
+pub struct ProgramHandle {
+    /// The parsed program
+    program: Program,
+}
+
+/// What happened when a program was run
+#[wasm_bindgen(getter_with_clone)]
+#[derive(Debug, Clone)]
+pub struct RunResult {
+    /// Everything the program wrote, up to any error
+    pub output: Vec<u8>,
+
+    /// The number of instructions executed
+    pub steps: u32,
+
+    /// Why the program stopped early, if it did
+    pub error: Option<String>,
+}
+
+/// Parse a program, returning a description of the problem if it is malformed
+///
+/// `source`: the program's source
+#[wasm_bindgen]
+pub fn parse(source: &str) -> Result<ProgramHandle, String> {
+    Program::try_new(Path::new(PROGRAM_NAME), source)
+        .map(|program| ProgramHandle { program })
+        .map_err(|e| e.to_string())
+}
+
+/// Run a program with the interpreter's default settings
+///
+/// `handle`: the program to run
+/// `input`: the bytes `,` reads, once they are exhausted `,` is an error
+/// `max_steps`: the most instructions to execute before stopping the program
+#[wasm_bindgen]
+pub fn run(handle: &ProgramHandle, input: &[u8], max_steps: u32) -> RunResult {
+    let mut machine = Machine::<u8>::new(DEFAULT_TAPE_SIZE, TapeKind::FixedSize, &handle.program);
+    let mut output = Vec::new();
+    let res = machine.run_with_limit(input, &mut output, max_steps.into());
+    RunResult {
+        output,
+        // no more than max_steps instructions are executed
+        steps: machine.steps() as u32,
+        error: res.err().map(|e| e.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_errors() {
+        let program = parse("+[]").unwrap();
+        let result = run(&program, &[], 100);
+        assert_eq!(result.steps, 100);
+        assert!(result.error.unwrap().contains("100"));
+
+        let program = parse(",.,").unwrap();
+        let result = run(&program, b"a", 100);
+        assert_eq!(result.output, b"a");
+        assert!(result.error.is_some());
+
+        assert!(parse("[").unwrap_err().contains("<wasm>"));
+    }
+}
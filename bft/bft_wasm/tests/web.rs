@@ -0,0 +1,12 @@
+#![cfg(target_arch = "wasm32")]
+//! Runs in a headless JavaScript runtime with `wasm-pack test --node`
+
+use wasm_bindgen_test::wasm_bindgen_test;
+
+#[wasm_bindgen_test]
+fn test_hello_world() {
+    let program = bft_wasm::parse(include_str!("../../programs/hello_world.bf")).unwrap();
+    let result = bft_wasm::run(&program, &[], 10_000);
+    assert_eq!(result.error, None);
+    assert_eq!(result.output, b"Hello World!\n");
+}
@@ -0,0 +1,20 @@
+Prints "Hello World!" followed by a newline
+
+++++++++                the outer loop runs eight times
+[
+    >++++               and the inner loop four times on each pass
+    [
+        >++>+++>+++>+<<<<-
+    ]
+    >+>+>->>+           adjust the totals in cells two to six
+    [<]<-               back to the counter through the empty inner counter
+]
+>>.                     H
+>---.                   e
++++++++..+++.           llo
+>>.                     space
+<-.                     W
+<.                      o
++++.------.--------.    rld
+>>+.                    exclamation mark
+>++.                    newline
@@ -0,0 +1,7 @@
+Reverses a line of input which must end with a newline
+
+>,----------            read a character and compare it with a newline
+[
+    ++++++++++>,----------
+]
+<[.<]                   write the characters back out in reverse
@@ -0,0 +1,416 @@
+use bft::CellSize;
+use bft_interp::{GrowthStrategy, InputMode, OutputMode, TapeKind, DEFAULT_TAPE_SIZE};
+use bft_types::Dialect;
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use crate::cli::{dialect, Emit, OnEof, Overflow};
+
+/// The version of the capabilities document format, bumped whenever a field is removed or
+/// changes meaning
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Every tape kind the interpreter supports
+const TAPE_KINDS: [TapeKind; 5] = [
+    TapeKind::FixedSize,
+    TapeKind::Growable {
+        growth: GrowthStrategy::Doubling,
+    },
+    TapeKind::GrowableBothEnds {
+        growth: GrowthStrategy::Doubling,
+    },
+    TapeKind::Wrapping,
+    TapeKind::Sparse,
+];
+
+/// A `--dialect` argument for every dialect the interpreter supports
+const DIALECT_ARGS: [&str; 3] = ["brainfuck", "ook", "> < + - . , [ ]"];
+
+/// Every way the interpreter can read a cell
+const INPUT_MODES: [InputMode; 2] = [InputMode::Byte, InputMode::FullCell];
+
+/// Every way the interpreter can write a cell
+const OUTPUT_MODES: [OutputMode; 3] = [
+    OutputMode::FullCell,
+    OutputMode::LowByte,
+    OutputMode::Decimal,
+];
+
+/// An optimisation pass turned on by `--optimize`
+#[derive(Debug, Clone, Copy)]
+enum Optimization {
+    /// Runs of `+`, `-`, `>` or `<` are folded into single instructions
+    FoldRuns,
+    /// `[-]` and `[+]` loops are replaced by setting the cell to zero
+    ClearLoops,
+    /// Loops which only multiply their counter into nearby cells run as multiply-adds
+    MultiplyLoops,
+}
+
+/// Every optimisation pass turned on by `--optimize`
+const OPTIMIZATIONS: [Optimization; 3] = [
+    Optimization::FoldRuns,
+    Optimization::ClearLoops,
+    Optimization::MultiplyLoops,
+];
+
+/// A machine-readable description of what this build of the interpreter supports
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Capabilities {
+    /// The version of this document's format
+    pub schema_version: u32,
+
+    /// The versions of each crate in the workspace
+    pub versions: Versions,
+
+    /// The supported cell widths in bits
+    pub cell_widths: Vec<u32>,
+
+    /// Are cells holding arbitrarily large numbers supported
+    pub unbounded_cells: bool,
+
+    /// The names of the supported tape kinds
+    pub tape_kinds: Vec<String>,
+
+    /// The names of the supported dialects, where `custom` is eight tokens of the user's choice
+    pub dialects: Vec<String>,
+
+    /// The names of the supported ways for `,` to read a cell
+    pub input_modes: Vec<String>,
+
+    /// The names of the supported ways for `.` to write a cell
+    pub output_modes: Vec<String>,
+
+    /// The names of the supported behaviours of `,` at the end of the input
+    pub eof_behaviours: Vec<String>,
+
+    /// The names of the supported behaviours of `+` and `-` when a cell overflows
+    pub overflow_modes: Vec<String>,
+
+    /// The names of the languages `compile` can translate programs into
+    pub compile_targets: Vec<String>,
+
+    /// The names of the optimisation passes turned on by `--optimize`
+    pub optimizations: Vec<String>,
+
+    /// The default values of the interpreter's limits
+    pub limits: Limits,
+}
+
+/// The versions of each crate in the workspace
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Versions {
+    /// The version of the bft binary
+    pub bft: String,
+    /// The version of the bft_interp crate
+    pub bft_interp: String,
+    /// The version of the bft_types crate
+    pub bft_types: String,
+}
+
+/// The default values of the interpreter's limits
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Limits {
+    /// The number of cells allocated for the tape when `--cells` isn't given
+    pub default_tape_size: usize,
+}
+
+/// The name used to refer to a tape kind in the capabilities document
+fn tape_kind_name(kind: TapeKind) -> &'static str {
+    match kind {
+        TapeKind::FixedSize => "fixed-size",
+        TapeKind::Growable { .. } => "growable",
+        TapeKind::GrowableBothEnds { .. } => "growable-both-ends",
+        TapeKind::Wrapping => "wrapping",
+        TapeKind::Sparse => "sparse",
+    }
+}
+
+/// The name used to refer to a dialect in the capabilities document
+fn dialect_name(dialect: &Dialect) -> &'static str {
+    match dialect {
+        Dialect::Brainfuck => "brainfuck",
+        Dialect::Ook => "ook",
+        Dialect::Custom(_) => "custom",
+    }
+}
+
+/// The name used to refer to an input mode in the capabilities document
+fn input_mode_name(mode: InputMode) -> &'static str {
+    match mode {
+        InputMode::Byte => "byte",
+        InputMode::FullCell => "full-cell",
+    }
+}
+
+/// The name used to refer to an output mode in the capabilities document
+fn output_mode_name(mode: OutputMode) -> &'static str {
+    match mode {
+        OutputMode::FullCell => "full-cell",
+        OutputMode::LowByte => "low-byte",
+        OutputMode::Decimal => "decimal",
+    }
+}
+
+/// The name used to refer to an optimisation pass in the capabilities document
+fn optimization_name(optimization: Optimization) -> &'static str {
+    match optimization {
+        Optimization::FoldRuns => "fold-runs",
+        Optimization::ClearLoops => "clear-loops",
+        Optimization::MultiplyLoops => "multiply-loops",
+    }
+}
+
+/// The names clap accepts for each variant of a CLI value enum
+fn value_names<T: ValueEnum>() -> Vec<String> {
+    T::value_variants()
+        .iter()
+        .filter_map(|v| v.to_possible_value())
+        .map(|v| v.get_name().to_owned())
+        .collect()
+}
+
+impl Capabilities {
+    /// Describe the capabilities of this build
+    pub fn current() -> Self {
+        Self {
+            schema_version: SCHEMA_VERSION,
+            versions: Versions {
+                bft: env!("CARGO_PKG_VERSION").to_owned(),
+                bft_interp: bft_interp::VERSION.to_owned(),
+                bft_types: bft_types::VERSION.to_owned(),
+            },
+            cell_widths: CellSize::value_variants()
+                .iter()
+                .filter_map(|size| size.bits())
+                .collect(),
+            unbounded_cells: CellSize::value_variants()
+                .iter()
+                .any(|size| size.bits().is_none()),
+            tape_kinds: TAPE_KINDS
+                .into_iter()
+                .map(|k| tape_kind_name(k).to_owned())
+                .collect(),
+            dialects: DIALECT_ARGS
+                .into_iter()
+                .map(|arg| {
+                    let dialect = dialect(arg).expect("every listed dialect argument is valid");
+                    dialect_name(&dialect).to_owned()
+                })
+                .collect(),
+            input_modes: INPUT_MODES
+                .into_iter()
+                .map(|m| input_mode_name(m).to_owned())
+                .collect(),
+            output_modes: OUTPUT_MODES
+                .into_iter()
+                .map(|m| output_mode_name(m).to_owned())
+                .collect(),
+            eof_behaviours: value_names::<OnEof>(),
+            overflow_modes: value_names::<Overflow>(),
+            compile_targets: value_names::<Emit>(),
+            optimizations: OPTIMIZATIONS
+                .into_iter()
+                .map(|o| optimization_name(o).to_owned())
+                .collect(),
+            limits: Limits {
+                default_tape_size: DEFAULT_TAPE_SIZE,
+            },
+        }
+    }
+
+    /// Render the capabilities as a JSON document
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("capabilities are always serializable")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::Args;
+    use crate::setup::{prepare, Prepared};
+    use bft::ProgramSource;
+    use bft_types::Instruction;
+    use clap::Parser;
+
+    /// Fails to compile when a tape kind is added, as a reminder to update `TAPE_KINDS`
+    fn tape_kind_index(kind: TapeKind) -> usize {
+        match kind {
+            TapeKind::FixedSize => 0,
+            TapeKind::Growable { .. } => 1,
+            TapeKind::GrowableBothEnds { .. } => 2,
+            TapeKind::Wrapping => 3,
+            TapeKind::Sparse => 4,
+        }
+    }
+
+    /// Fails to compile when a dialect is added, as a reminder to update `DIALECT_ARGS`
+    fn dialect_index(dialect: &Dialect) -> usize {
+        match dialect {
+            Dialect::Brainfuck => 0,
+            Dialect::Ook => 1,
+            Dialect::Custom(_) => 2,
+        }
+    }
+
+    /// Fails to compile when an input mode is added, as a reminder to update `INPUT_MODES`
+    fn input_mode_index(mode: InputMode) -> usize {
+        match mode {
+            InputMode::Byte => 0,
+            InputMode::FullCell => 1,
+        }
+    }
+
+    /// Fails to compile when an output mode is added, as a reminder to update `OUTPUT_MODES`
+    fn output_mode_index(mode: OutputMode) -> usize {
+        match mode {
+            OutputMode::FullCell => 0,
+            OutputMode::LowByte => 1,
+            OutputMode::Decimal => 2,
+        }
+    }
+
+    /// Fails to compile when an optimisation pass is added, as a reminder to update
+    /// `OPTIMIZATIONS`
+    fn optimization_index(optimization: Optimization) -> usize {
+        match optimization {
+            Optimization::FoldRuns => 0,
+            Optimization::ClearLoops => 1,
+            Optimization::MultiplyLoops => 2,
+        }
+    }
+
+    /// Does `--optimize` apply the pass when preparing `++[-]+[->++<]`
+    fn optimization_applied(optimization: Optimization, prepared: &Prepared) -> bool {
+        let instructions = prepared.program.instructions();
+        match optimization {
+            Optimization::FoldRuns => instructions.contains(&Instruction::Add(2)),
+            Optimization::ClearLoops => instructions.contains(&Instruction::SetZero),
+            Optimization::MultiplyLoops => prepared.machine.multiply_loops,
+        }
+    }
+
+    /// Fails to compile when a cell size is added, as a reminder to check the capabilities
+    fn cell_size_bits(size: CellSize) -> Option<u32> {
+        match size {
+            CellSize::U8 => Some(8),
+            CellSize::U16 => Some(16),
+            CellSize::U32 => Some(32),
+            CellSize::U64 => Some(64),
+            #[cfg(feature = "bignum")]
+            CellSize::Big => None,
+        }
+    }
+
+    fn round_trip() -> Capabilities {
+        serde_json::from_str(&Capabilities::current().to_json()).unwrap()
+    }
+
+    #[test]
+    fn test_capabilities_versions() {
+        let caps = round_trip();
+        assert_eq!(caps.schema_version, SCHEMA_VERSION);
+        assert_eq!(caps.versions.bft, env!("CARGO_PKG_VERSION"));
+        assert_eq!(caps.versions.bft_interp, bft_interp::VERSION);
+        assert_eq!(caps.versions.bft_types, bft_types::VERSION);
+        assert_eq!(caps.limits.default_tape_size, DEFAULT_TAPE_SIZE);
+    }
+
+    #[test]
+    fn test_capabilities_tape_kinds() {
+        let caps = round_trip();
+        assert_eq!(caps.tape_kinds.len(), TAPE_KINDS.len());
+        for kind in TAPE_KINDS {
+            assert_eq!(caps.tape_kinds[tape_kind_index(kind)], tape_kind_name(kind));
+        }
+    }
+
+    #[test]
+    fn test_capabilities_dialects() {
+        let caps = round_trip();
+        assert_eq!(caps.dialects.len(), DIALECT_ARGS.len());
+        for arg in DIALECT_ARGS {
+            let dialect = dialect(arg).unwrap();
+            assert_eq!(
+                caps.dialects[dialect_index(&dialect)],
+                dialect_name(&dialect)
+            );
+        }
+        assert_eq!(caps.dialects, ["brainfuck", "ook", "custom"]);
+    }
+
+    #[test]
+    fn test_capabilities_io_modes() {
+        let caps = round_trip();
+        assert_eq!(caps.input_modes.len(), INPUT_MODES.len());
+        for mode in INPUT_MODES {
+            assert_eq!(
+                caps.input_modes[input_mode_index(mode)],
+                input_mode_name(mode)
+            );
+        }
+        assert_eq!(caps.output_modes.len(), OUTPUT_MODES.len());
+        for mode in OUTPUT_MODES {
+            assert_eq!(
+                caps.output_modes[output_mode_index(mode)],
+                output_mode_name(mode)
+            );
+        }
+    }
+
+    #[test]
+    fn test_capabilities_cell_widths() {
+        let caps = round_trip();
+        let expected: Vec<u32> = CellSize::value_variants()
+            .iter()
+            .filter_map(|&size| cell_size_bits(size))
+            .collect();
+        assert_eq!(caps.cell_widths, expected);
+        assert!(caps.cell_widths.contains(&8));
+        assert_eq!(caps.unbounded_cells, cfg!(feature = "bignum"));
+    }
+
+    #[test]
+    fn test_capabilities_eof_behaviours() {
+        let caps = round_trip();
+        assert_eq!(caps.eof_behaviours.len(), OnEof::value_variants().len());
+        for name in &caps.eof_behaviours {
+            let on_eof = OnEof::from_str(name, false).unwrap();
+            // exhaustive so that new variants must be considered here
+            match on_eof {
+                OnEof::Error | OnEof::Zero | OnEof::MinusOne | OnEof::Unchanged => {}
+            }
+        }
+        assert!(caps.eof_behaviours.contains(&"minus-one".to_owned()));
+    }
+
+    #[test]
+    fn test_capabilities_optimizations() {
+        let caps = round_trip();
+        assert_eq!(caps.optimizations.len(), OPTIMIZATIONS.len());
+        let args = Args::parse_from(["bft", "--optimize", "--eval=++[-]+[->++<]"]);
+        let program = ProgramSource::Source {
+            name: "<eval>".into(),
+            source: args.eval.clone().unwrap(),
+        };
+        let prepared = prepare(program, &args.machine).unwrap();
+        for optimization in OPTIMIZATIONS {
+            assert_eq!(
+                caps.optimizations[optimization_index(optimization)],
+                optimization_name(optimization)
+            );
+            assert!(optimization_applied(optimization, &prepared));
+        }
+        assert_eq!(
+            caps.optimizations,
+            ["fold-runs", "clear-loops", "multiply-loops"]
+        );
+    }
+
+    #[test]
+    fn test_capabilities_overflow_modes() {
+        let caps = round_trip();
+        assert_eq!(caps.overflow_modes, ["wrap", "error", "saturate"]);
+    }
+}
@@ -1,25 +1,484 @@
-use bft_interp::DEFAULT_TAPE_SIZE;
-use clap::Parser;
+use bft::CellSize;
+use bft_interp::{CellOverflow, EofBehaviour, Language, DEFAULT_TAPE_SIZE};
+use bft_types::{Dialect, TokenMap};
+use clap::{Parser, Subcommand, ValueEnum};
 use clap_num::number_range;
-use std::path::PathBuf;
+use std::{num::NonZeroUsize, path::PathBuf};
+
+/// The number of cells a translated program's tape starts with when `--cells` isn't given
+const DEFAULT_CELLS: NonZeroUsize = NonZeroUsize::new(DEFAULT_TAPE_SIZE).unwrap();
 
 /// CLI Arguments for the interpreter
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
+#[command(
+    after_help = "Exit status: 0 on success, 2 for invalid arguments, 3 if the program \
+fails to parse, 4 if it stops with an error while running and 5 if a file can't be read or \
+output can't be written. `fmt --check` exits with 1 if the file isn't formatted."
+)]
 pub struct Args {
+    /// An alternative action to running a program
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
     /// The path to the brainfuck program to run
+    ///
+    /// `-` reads the program from stdin, after which `,` finds the input already exhausted unless
+    /// `--input` or `--input-str` is given.
+    #[arg(required_unless_present_any = ["capabilities", "eval"])]
+    pub program: Option<PathBuf>,
+
+    /// Run this brainfuck source rather than a program from a file
+    #[arg(long, value_name = "SOURCE", conflicts_with = "program")]
+    pub eval: Option<String>,
+
+    /// Read the program's input from this file rather than stdin
+    #[arg(long, value_name = "PATH", conflicts_with = "input_str")]
+    pub input: Option<PathBuf>,
+
+    /// Use this text as the program's input rather than reading stdin
+    #[arg(long, value_name = "TEXT")]
+    pub input_str: Option<String>,
+
+    /// Write the program's output to this file rather than stdout, exactly as it was written
+    #[arg(long, value_name = "PATH")]
+    pub output: Option<PathBuf>,
+
+    /// Leave the program's output on stdout as it was written, rather than ending it with a
+    /// newline if it doesn't already. Output written to `--output` never gets a newline added,
+    /// so this only affects stdout
+    #[arg(long)]
+    pub no_trailing_newline: bool,
+
+    /// Write every byte the program reads or writes to this file, as JSON lines
+    #[arg(long, value_name = "PATH")]
+    pub record: Option<PathBuf>,
+
+    /// Repeat the run recorded in this file by `--record`, feeding the program its input and
+    /// failing if the program reads or writes anything other than was recorded
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["input", "input_str"])]
+    pub replay: Option<PathBuf>,
+
+    /// How to configure the interpreter
+    #[command(flatten)]
+    pub machine: MachineArgs,
+
+    /// Print a JSON description of the features this build supports and exit
+    #[arg(long, exclusive = true)]
+    pub capabilities: bool,
+}
+
+/// Actions other than running a program
+#[derive(Subcommand)]
+pub enum Command {
+    /// Run a program under an interactive debugger, reading commands from stdin
+    ///
+    /// Commands: break <ip|line:col>, step [n], continue, print <cell>, tape <start> <len>,
+    /// where and quit. The debugger reports on stderr, leaving stdout for the program's
+    /// output, and the program's input is empty.
+    Debug {
+        /// The path to the brainfuck program to debug
+        program: PathBuf,
+
+        /// How to configure the interpreter
+        #[command(flatten)]
+        machine: MachineArgs,
+    },
+
+    /// Run brainfuck a line at a time, each line picking up where the last left the tape
+    ///
+    /// Commands: :tape, :reset, :load <file> and :quit. After each line the head and the cell
+    /// under it are reported on stderr, leaving stdout for the output, and the input is empty.
+    Repl {
+        /// How to configure the interpreter
+        #[command(flatten)]
+        machine: MachineArgs,
+    },
+
+    /// Print a table of statistics about a program without running it
+    Inspect {
+        /// The path to the brainfuck program to inspect
+        program: PathBuf,
+
+        /// Report on the program after folding runs of repeated instructions together
+        #[arg(long)]
+        optimize: bool,
+    },
+
+    /// Strip the comments from a program, writing what's left to stdout
+    Minify {
+        /// The path to the brainfuck program to minify
+        program: PathBuf,
+
+        /// Write the minified program to this file rather than stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        /// Also remove adjacent pairs of instructions which undo each other, like `+-` and `<>`
+        ///
+        /// This only keeps the program's behaviour with wrapping cells, as with saturating
+        /// cells or `--overflow error` a pair like `-+` changes a cell or stops the program.
+        #[arg(long)]
+        cancel: bool,
+    },
+
+    /// Check that programs parse without running them, printing a line about each to stdout
+    ///
+    /// Exits with the status of the first program which failed to load.
+    Check {
+        /// The paths to the brainfuck programs to check, `-` reads a program from stdin
+        #[arg(required = true)]
+        programs: Vec<PathBuf>,
+    },
+
+    /// Rewrite a program in the canonical style, indenting loops and keeping its comments
+    Fmt {
+        /// The path to the brainfuck program to format
+        program: PathBuf,
+
+        /// The column to wrap code at
+        #[arg(long, default_value_t = 80, value_parser = forbid_zero)]
+        width: usize,
+
+        /// The number of spaces to indent each loop body by
+        #[arg(long, default_value_t = 4)]
+        indent: usize,
+
+        /// Leave the file alone, exiting with status 1 if formatting would change it
+        #[arg(long)]
+        check: bool,
+    },
+
+    /// Rewrite a program in another dialect, dropping its comments
+    ///
+    /// A dialect is `brainfuck`, `ook`, or eight tokens for `><+-.,[]` separated by spaces.
+    Translate {
+        /// The path to the program to translate
+        program: PathBuf,
+
+        /// The dialect the program is written in
+        #[arg(long, value_parser = dialect, default_value = "brainfuck")]
+        from: Dialect,
+
+        /// The dialect to rewrite the program in
+        #[arg(long, value_parser = dialect)]
+        to: Dialect,
+
+        /// Write the translated program to this file rather than stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+
+    /// Translate a program into standalone Rust or C source code
+    ///
+    /// The translation runs like the interpreter with wrapping cells, exiting with status 4 if
+    /// the head runs off the tape or `,` fails at the end of the input.
+    Compile(CompileArgs),
+}
+
+/// Options for translating a program into another language
+#[derive(clap::Args)]
+pub struct CompileArgs {
+    /// The path to the brainfuck program to translate
     pub program: PathBuf,
 
+    /// The language to translate the program into
+    #[arg(long, value_enum, default_value_t = Emit::Rust)]
+    pub emit: Emit,
+
+    /// Write the translation to this file rather than stdout
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+
+    /// Should the translated program's tape automatically extend to the right?
+    #[arg(short, long)]
+    pub extensible: bool,
+
+    /// The number of cells the translated program's tape starts with
+    #[arg(short, long, default_value_t = DEFAULT_CELLS, value_parser = nonzero_cell_count)]
+    pub cells: NonZeroUsize,
+
+    /// The width in bits of each of the tape's cells, which can't be `big`
+    #[arg(long, value_enum, default_value_t = CellSize::U8)]
+    pub cell_size: CellSize,
+
+    /// What `,` does once the input is exhausted
+    #[arg(long, value_enum, default_value_t = OnEof::Error)]
+    pub on_eof: OnEof,
+
+    /// Fold runs of repeated instructions together before translating the program
+    #[arg(long)]
+    pub optimize: bool,
+}
+
+/// Options controlling how the interpreter runs a program
+#[derive(clap::Args)]
+pub struct MachineArgs {
     /// Should the interpreter's tape automatically extend?
     #[arg(short, long)]
     pub extensible: bool,
 
+    /// Let the tape also extend to the left of the starting cell, implies --extensible
+    #[arg(long)]
+    pub grow_left: bool,
+
+    /// Wrap the head around to the other end of the tape when it runs off either end
+    #[arg(long, conflicts_with_all = ["extensible", "grow_left"])]
+    pub wrap_tape: bool,
+
+    /// Only store the cells written to, so the tape can grow a long way cheaply
+    #[arg(long, conflicts_with_all = ["grow_left", "wrap_tape"])]
+    pub sparse_tape: bool,
+
+    /// The most cells the tape can grow to, implies --extensible
+    #[arg(long, value_parser = cell_count, conflicts_with = "wrap_tape")]
+    pub max_cells: Option<usize>,
+
     /// The number of cells to allocate for the interpreter's tape
-    #[arg(short, long, default_value_t = DEFAULT_TAPE_SIZE, value_parser = forbid_zero)]
+    ///
+    /// Accepts the suffixes k, M and G for powers of 1000 and Ki, Mi and Gi for powers of 1024,
+    /// in any case, so `30k` is 30000 cells and `1Mi` is 1048576.
+    #[arg(short, long, default_value_t = DEFAULT_TAPE_SIZE, value_parser = cell_count)]
     pub cells: usize,
+
+    /// The width in bits of each of the tape's cells
+    #[arg(long, value_enum, default_value_t = CellSize::U8)]
+    pub cell_size: CellSize,
+
+    /// What `,` does once the input is exhausted
+    #[arg(long, value_enum, default_value_t = OnEof::Error)]
+    pub on_eof: OnEof,
+
+    /// What `+` and `-` do when a cell would go beyond the range of values it can hold
+    #[arg(long, value_enum, default_value_t = Overflow::Wrap)]
+    pub overflow: Overflow,
+
+    /// Print a hexdump of the tape to stderr once the program stops
+    #[arg(long)]
+    pub dump_tape: bool,
+
+    /// Print the time taken, instructions executed and the state of the tape to stderr once the
+    /// program stops
+    #[arg(long)]
+    pub stats: bool,
+
+    /// List the instructions which were never executed to stderr once the program stops
+    #[arg(long)]
+    pub coverage: bool,
+
+    /// Stop the program after executing this many instructions
+    #[arg(long)]
+    pub max_steps: Option<u64>,
+
+    /// Stop the program when a loop keeps repeating without changing anything
+    #[arg(long)]
+    pub detect_hangs: bool,
+
+    /// Print a line to stderr describing each instruction as it is executed
+    #[arg(long)]
+    pub trace: bool,
+
+    /// Treat `#` as an instruction printing the state of the machine to stderr, rather than
+    /// a comment
+    #[arg(long)]
+    pub enable_debug_chars: bool,
+
+    /// Treat everything after the first `!` outside of a loop as the program's input, which is
+    /// used unless `--input` or `--input-str` is given
+    #[arg(long)]
+    pub input_separator: bool,
+
+    /// The dialect the program is written in: `brainfuck`, `ook`, or eight tokens for
+    /// `><+-.,[]` separated by spaces
+    #[arg(long, value_parser = dialect, default_value = "brainfuck")]
+    pub dialect: Dialect,
+
+    /// Fold runs of repeated instructions together before running the program, and run loops
+    /// which only multiply their counter into nearby cells as a few multiplications
+    ///
+    /// The debugger still steps through such loops one instruction at a time.
+    #[arg(long)]
+    pub optimize: bool,
+
+    /// Write every byte of a wide cell on `.`, rather than only its least significant byte
+    #[arg(long)]
+    pub full_cell_output: bool,
+
+    /// Write the value of the cell in decimal followed by a space on `.`, rather than as bytes
+    #[arg(long, conflicts_with = "full_cell_output")]
+    pub numeric_output: bool,
+
+    /// Read every byte of a wide cell on `,`, rather than a single byte
+    ///
+    /// Big cells have no fixed width to read, so can't be used with this.
+    #[arg(long)]
+    pub full_cell_input: bool,
+}
+
+/// The behaviours `,` can have on reaching the end of the input
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OnEof {
+    /// Stop the program with an error
+    Error,
+    /// Set the cell to zero
+    Zero,
+    /// Set the cell to minus one
+    MinusOne,
+    /// Leave the cell unchanged
+    Unchanged,
+}
+
+impl From<OnEof> for EofBehaviour {
+    fn from(value: OnEof) -> Self {
+        match value {
+            OnEof::Error => Self::Error,
+            OnEof::Zero => Self::Zero,
+            OnEof::MinusOne => Self::MinusOne,
+            OnEof::Unchanged => Self::Unchanged,
+        }
+    }
+}
+
+/// The behaviours of `+` and `-` when a cell would overflow
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Overflow {
+    /// Wrap around to the other end of the range
+    Wrap,
+    /// Stop the program with an error
+    Error,
+    /// Stay at the end of the range
+    Saturate,
+}
+
+impl From<Overflow> for CellOverflow {
+    fn from(value: Overflow) -> Self {
+        match value {
+            Overflow::Wrap => Self::Wrapping,
+            Overflow::Error => Self::Error,
+            Overflow::Saturate => Self::Saturating,
+        }
+    }
+}
+
+/// The languages `compile` can translate a program into
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Emit {
+    /// Rust, buildable with `rustc`
+    Rust,
+    /// C99, buildable with `cc`
+    C,
+}
+
+impl From<Emit> for Language {
+    fn from(value: Emit) -> Self {
+        match value {
+            Emit::Rust => Self::Rust,
+            Emit::C => Self::C,
+        }
+    }
+}
+
+/// Value parser for a dialect, `brainfuck` (or `bf`), `ook`, or the eight tokens standing for
+/// `><+-.,[]` separated by spaces
+pub fn dialect(s: &str) -> Result<Dialect, String> {
+    match s {
+        "brainfuck" | "bf" => Ok(Dialect::Brainfuck),
+        "ook" => Ok(Dialect::Ook),
+        custom => {
+            let tokens: Vec<&str> = custom.split_whitespace().collect();
+            let tokens: [&str; 8] = tokens.try_into().map_err(|tokens: Vec<&str>| {
+                format!(
+                    "expected brainfuck, ook or 8 tokens separated by spaces, found {} tokens",
+                    tokens.len()
+                )
+            })?;
+            TokenMap::new(tokens)
+                .map(Dialect::Custom)
+                .map_err(|e| e.to_string())
+        }
+    }
 }
 
 /// Value parser to prevent forbid a value from being zero
 fn forbid_zero(s: &str) -> Result<usize, String> {
     number_range(s, 1, usize::MAX)
 }
+
+/// Value parser for a number of cells, which can't be zero
+///
+/// The number can be followed by a suffix multiplying it, ignoring case: `k`, `M` and `G` for
+/// powers of 1000 and `Ki`, `Mi` and `Gi` for powers of 1024.
+fn cell_count(s: &str) -> Result<usize, String> {
+    nonzero_cell_count(s).map(NonZeroUsize::get)
+}
+
+/// Value parser for a number of cells as [`cell_count`], giving a type which can't be zero
+fn nonzero_cell_count(s: &str) -> Result<NonZeroUsize, String> {
+    let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (digits, suffix) = s.split_at(split);
+    if digits.is_empty() {
+        return Err(format!(
+            "expected a number of cells, optionally followed by a suffix like k or Mi, not `{s}`"
+        ));
+    }
+
+    let multiplier: usize = match suffix.to_ascii_lowercase().as_str() {
+        "" => 1,
+        "k" => 1_000,
+        "m" => 1_000_000,
+        "g" => 1_000_000_000,
+        "ki" => 1 << 10,
+        "mi" => 1 << 20,
+        "gi" => 1 << 30,
+        _ => {
+            return Err(format!(
+                "unknown suffix `{suffix}`, expected one of k, M, G, Ki, Mi or Gi"
+            ))
+        }
+    };
+
+    // the digits can only fail to parse by overflowing
+    let cells = digits
+        .parse::<usize>()
+        .ok()
+        .and_then(|n| n.checked_mul(multiplier))
+        .ok_or_else(|| format!("`{s}` is more than the {} cells supported", usize::MAX))?;
+    NonZeroUsize::new(cells).ok_or_else(|| "the number of cells can't be zero".to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("30000", 30_000)]
+    #[case("30k", 30_000)]
+    #[case("64K", 64_000)]
+    #[case("1M", 1_000_000)]
+    #[case("2m", 2_000_000)]
+    #[case("1G", 1_000_000_000)]
+    #[case("4Ki", 4_096)]
+    #[case("4kI", 4_096)]
+    #[case("2Mi", 2_097_152)]
+    #[case("1gi", 1_073_741_824)]
+    fn test_cell_count(#[case] input: &str, #[case] expected: usize) {
+        assert_eq!(cell_count(input), Ok(expected));
+    }
+
+    #[rstest]
+    #[case("0", "can't be zero")]
+    #[case("0Mi", "can't be zero")]
+    #[case("10q", "unknown suffix `q`")]
+    #[case("10 k", "unknown suffix ` k`")]
+    #[case("k", "expected a number")]
+    #[case("-1", "expected a number")]
+    #[case("", "expected a number")]
+    #[case("99999999999999999999999", "more than")]
+    #[case("18446744073709551615Ki", "more than")]
+    fn test_cell_count_rejects(#[case] input: &str, #[case] message: &str) {
+        let err = cell_count(input).unwrap_err();
+        assert!(err.contains(message), "{err}");
+    }
+}
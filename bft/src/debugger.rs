@@ -0,0 +1,398 @@
+use std::{
+    fmt,
+    io::{self, BufRead, Write},
+    str::FromStr,
+};
+
+use bft::{BftError, CellSize, MachineSettings};
+use bft_interp::{
+    CellKind, DumpFormat, InterpretError, Machine, MachineConfigError, RunOutcome, SparseTape,
+    StepOutcome, Tape, TapeKind,
+};
+use bft_types::{Program, SourceLocation};
+
+use crate::setup::Prepared;
+
+/// Run an interactive debugging session over the prepared program
+///
+/// `prepared`: The validated settings and loaded program
+/// `commands`: where the debugger commands are read from, one per line
+/// `log`: where the debugger reports the state of the program
+/// `output`: where the program writes its output to
+pub fn debug_program(
+    prepared: &Prepared,
+    commands: impl BufRead,
+    log: impl Write,
+    output: impl Write,
+) -> Result<(), BftError> {
+    match prepared.machine.cell_size {
+        CellSize::U8 => debug_sized::<u8>(prepared, commands, log, output),
+        CellSize::U16 => debug_sized::<u16>(prepared, commands, log, output),
+        CellSize::U32 => debug_sized::<u32>(prepared, commands, log, output),
+        CellSize::U64 => debug_sized::<u64>(prepared, commands, log, output),
+        #[cfg(feature = "bignum")]
+        CellSize::Big => debug_sized::<num_bigint::BigUint>(prepared, commands, log, output),
+    }
+}
+
+/// Debug the prepared program with cells of type `Cell`, stored however the tape kind requires
+fn debug_sized<Cell: CellKind + fmt::Display>(
+    prepared: &Prepared,
+    commands: impl BufRead,
+    log: impl Write,
+    output: impl Write,
+) -> Result<(), BftError> {
+    if prepared.machine.tape_kind == TapeKind::Sparse {
+        Debugger::<Cell, SparseTape<Cell>>::new(prepared)?
+            .run(commands, log, output)
+            .map_err(BftError::Session)
+    } else {
+        Debugger::<Cell, Vec<Cell>>::new(prepared)?
+            .run(commands, log, output)
+            .map_err(BftError::Session)
+    }
+}
+
+/// A command understood by the debugger
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DebugCommand {
+    /// Stop whenever the given instruction is about to be executed
+    Break(BreakTarget),
+    /// Execute the given number of instructions
+    Step(u64),
+    /// Execute until a breakpoint is reached or the program stops
+    Continue,
+    /// Print the value of a cell
+    Print(isize),
+    /// Print a range of cells from the tape
+    Tape {
+        /// The index of the first cell to print
+        start: isize,
+        /// The number of cells to print
+        len: usize,
+    },
+    /// Print the current location in the program
+    Where,
+    /// Stop debugging
+    Quit,
+}
+
+/// The ways a breakpoint's instruction can be specified
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BreakTarget {
+    /// An instruction index
+    Ip(usize),
+    /// A 1-based line and column in the source file
+    Source {
+        /// The line of the instruction
+        line: usize,
+        /// The column of the instruction
+        column: usize,
+    },
+}
+
+impl fmt::Display for BreakTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Ip(ip) => write!(f, "ip={ip}"),
+            Self::Source { line, column } => write!(f, "line {line} column {column}"),
+        }
+    }
+}
+
+impl FromStr for DebugCommand {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut words = s.split_whitespace();
+        let Some(command) = words.next() else {
+            return Err("no command given".to_owned());
+        };
+        let args: Vec<&str> = words.collect();
+
+        let number = |arg: &str| {
+            arg.parse::<usize>()
+                .map_err(|_| format!("expected a number but found {arg:?}"))
+        };
+        // cells left of where the head started have negative indices
+        let cell_index = |arg: &str| {
+            arg.parse::<isize>()
+                .map_err(|_| format!("expected a cell index but found {arg:?}"))
+        };
+
+        let parsed = match (command, args.as_slice()) {
+            ("break" | "b", [target]) => Self::Break(match target.split_once(':') {
+                Some((line, column)) => BreakTarget::Source {
+                    line: number(line)?,
+                    column: number(column)?,
+                },
+                None => BreakTarget::Ip(number(target)?),
+            }),
+            ("step" | "s", []) => Self::Step(1),
+            ("step" | "s", [n]) => Self::Step(number(n)? as u64),
+            ("continue" | "c", []) => Self::Continue,
+            ("print" | "p", [cell]) => Self::Print(cell_index(cell)?),
+            ("tape" | "t", [start, len]) => Self::Tape {
+                start: cell_index(start)?,
+                len: number(len)?,
+            },
+            ("where" | "w", []) => Self::Where,
+            ("quit" | "q", []) => Self::Quit,
+            (
+                "break" | "b" | "step" | "s" | "continue" | "c" | "print" | "p" | "tape" | "t"
+                | "where" | "w" | "quit" | "q",
+                _,
+            ) => return Err(format!("wrong number of arguments for {command}")),
+            _ => return Err(format!("unknown command {command:?}")),
+        };
+
+        Ok(parsed)
+    }
+}
+
+/// The state of a debugging session
+struct Debugger<'a, Cell: CellKind, T: Tape<Cell>> {
+    /// The program being debugged
+    program: &'a Program,
+
+    /// The virtual machine running the program, which holds the breakpoints
+    machine: Machine<'a, Cell, T>,
+
+    /// The limit on the number of instructions the machine executes, which single steps are
+    /// held to as well
+    max_steps: Option<u64>,
+
+    /// Should the tape be dumped once debugging finishes
+    dump_tape: bool,
+}
+
+/// Why the debugger stopped executing instructions
+enum Stop {
+    /// The requested number of instructions were executed
+    Done,
+    /// The program has finished
+    Halted,
+    /// The program can't continue, for the given reason
+    Failed(String),
+}
+
+impl<'a, Cell: CellKind + fmt::Display, T: Tape<Cell>> Debugger<'a, Cell, T> {
+    /// Create a debugger for the prepared program
+    fn new(prepared: &'a Prepared) -> Result<Self, MachineConfigError> {
+        let settings = MachineSettings {
+            // every instruction of the program can be stepped through
+            multiply_loops: false,
+            ..prepared.machine.clone()
+        };
+        let machine = settings.builder().build(&prepared.program)?;
+        Ok(Self {
+            program: &prepared.program,
+            machine,
+            max_steps: prepared.machine.max_steps,
+            dump_tape: prepared.machine.dump_tape,
+        })
+    }
+
+    /// Read and execute commands until told to quit or the commands run out
+    fn run(
+        mut self,
+        commands: impl BufRead,
+        mut log: impl Write,
+        mut output: impl Write,
+    ) -> io::Result<()> {
+        let mut quit = false;
+        write!(log, "(bft) ")?;
+        log.flush()?;
+        for line in commands.lines() {
+            let line = line?;
+            if !line.trim().is_empty() {
+                match line.parse::<DebugCommand>() {
+                    Ok(DebugCommand::Quit) => {
+                        quit = true;
+                        break;
+                    }
+                    Ok(command) => self.execute(command, &mut log, &mut output)?,
+                    Err(e) => writeln!(log, "error: {e}")?,
+                }
+            }
+            write!(log, "(bft) ")?;
+            log.flush()?;
+        }
+
+        // finish the line the last prompt was written on when the commands ran out
+        if !quit {
+            writeln!(log)?;
+        }
+
+        output.flush()?;
+        if self.dump_tape {
+            self.machine.dump_tape(&mut log, DumpFormat::Hex)?;
+        }
+
+        Ok(())
+    }
+
+    /// Execute a single debugger command
+    fn execute(
+        &mut self,
+        command: DebugCommand,
+        log: &mut impl Write,
+        output: &mut impl Write,
+    ) -> io::Result<()> {
+        match command {
+            DebugCommand::Break(target) => match self.resolve(target) {
+                Some(ip) => {
+                    self.machine.add_breakpoint(ip);
+                    writeln!(log, "breakpoint set at ip={ip} ({})", self.location(ip))?;
+                }
+                None => writeln!(log, "error: no instruction at {target}")?,
+            },
+            DebugCommand::Step(n) => {
+                let stop = self.step(n, output);
+                self.report(stop, log)?;
+            }
+            DebugCommand::Continue => {
+                let stop = self.resume(output);
+                if matches!(stop, Stop::Done) {
+                    writeln!(log, "hit breakpoint at ip={}", self.machine.ip())?;
+                }
+                self.report(stop, log)?;
+            }
+            DebugCommand::Print(index) => match self.machine.cell_at(index) {
+                Some(cell) => writeln!(log, "cell[{index}] = {cell}")?,
+                None => writeln!(log, "error: cell {index} is beyond the end of the tape")?,
+            },
+            DebugCommand::Tape { start, len } => {
+                let tape_end = self.machine.tape_start() + self.machine.tape().len() as isize;
+                let end = start.saturating_add_unsigned(len).min(tape_end);
+                let values: Vec<String> = (start..end)
+                    .filter_map(|i| self.machine.cell_at(i))
+                    .map(ToString::to_string)
+                    .collect();
+                writeln!(log, "tape[{start}..{end}] = [{}]", values.join(", "))?;
+            }
+            DebugCommand::Where => self.report(Stop::Done, log)?,
+            DebugCommand::Quit => {}
+        }
+
+        Ok(())
+    }
+
+    /// Execute up to `n` instructions, ignoring breakpoints
+    fn step(&mut self, n: u64, output: &mut impl Write) -> Stop {
+        for _ in 0..n {
+            if self.machine.ip() >= self.program.instructions().len() {
+                return Stop::Halted;
+            }
+            if let Some(max) = self.max_steps.filter(|&max| self.machine.steps() >= max) {
+                return Stop::Failed(step_limit_message(max));
+            }
+
+            match self.machine.step(&mut io::empty(), output) {
+                Ok(StepOutcome::Running) => {}
+                Ok(StepOutcome::Halted) => return Stop::Halted,
+                Err(e) => return Stop::Failed(e.to_string()),
+            }
+        }
+
+        Stop::Done
+    }
+
+    /// Execute until the next breakpoint or the program stops
+    ///
+    /// The instruction at the head is always executed first, so a breakpoint there, whether
+    /// stopped at or stepped onto, is passed over.
+    fn resume(&mut self, output: &mut impl Write) -> Stop {
+        if let stop @ (Stop::Halted | Stop::Failed(_)) = self.step(1, output) {
+            return stop;
+        }
+
+        match self.machine.run_until_break(io::empty(), output) {
+            Ok(RunOutcome::Breakpoint { .. }) => Stop::Done,
+            Ok(RunOutcome::Halted) => Stop::Halted,
+            Ok(RunOutcome::Watchpoint { .. }) => unreachable!("the debugger sets no watchpoints"),
+            Err(InterpretError::StepLimitExceeded { steps, .. }) => {
+                Stop::Failed(step_limit_message(steps))
+            }
+            Err(e) => Stop::Failed(e.to_string()),
+        }
+    }
+
+    /// Report why execution stopped along with the current state of the machine
+    fn report(&self, stop: Stop, log: &mut impl Write) -> io::Result<()> {
+        let (ip, dp) = (self.machine.ip(), self.machine.dp());
+        match stop {
+            Stop::Done => writeln!(log, "ip={ip} dp={dp} at {}", self.location(ip)),
+            Stop::Halted => writeln!(log, "ip={ip} dp={dp} program halted"),
+            Stop::Failed(reason) => writeln!(log, "ip={ip} dp={dp} error: {reason}"),
+        }
+    }
+
+    /// Find the instruction a breakpoint target refers to
+    fn resolve(&self, target: BreakTarget) -> Option<usize> {
+        match target {
+            BreakTarget::Ip(ip) => (ip < self.program.instructions().len()).then_some(ip),
+            BreakTarget::Source { line, column } => {
+                let wanted = SourceLocation {
+                    line: line.checked_sub(1)?,
+                    column: column.checked_sub(1)?,
+                };
+                self.program
+                    .source_map()
+                    .iter()
+                    .position(|&loc| loc == wanted)
+            }
+        }
+    }
+
+    /// Describe the source location of the instruction at `ip`
+    fn location(&self, ip: usize) -> String {
+        match (
+            self.program.source_map().get(ip),
+            self.program.instructions().get(ip),
+        ) {
+            (Some(loc), Some(instr)) => format!(
+                "{}:{}:{} '{}'",
+                self.program.filename().display(),
+                loc.line + 1,
+                loc.column + 1,
+                instr.as_char()
+            ),
+            _ => "the end of the program".to_owned(),
+        }
+    }
+}
+
+/// Describe the program reaching the limit on the number of instructions executed
+fn step_limit_message(max_steps: u64) -> String {
+    format!("reached the step limit of {max_steps}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_commands() {
+        assert_eq!("step".parse(), Ok(DebugCommand::Step(1)));
+        assert_eq!("s 10".parse(), Ok(DebugCommand::Step(10)));
+        assert_eq!(
+            "break 12".parse(),
+            Ok(DebugCommand::Break(BreakTarget::Ip(12)))
+        );
+        assert_eq!(
+            "break 3:7".parse(),
+            Ok(DebugCommand::Break(BreakTarget::Source {
+                line: 3,
+                column: 7
+            }))
+        );
+        assert_eq!(
+            "  tape 0   4 ".parse(),
+            Ok(DebugCommand::Tape { start: 0, len: 4 })
+        );
+        assert!("print".parse::<DebugCommand>().is_err());
+        assert!("print x".parse::<DebugCommand>().is_err());
+        assert!("frobnicate".parse::<DebugCommand>().is_err());
+    }
+}
@@ -0,0 +1,161 @@
+use std::{fmt, io, path::PathBuf};
+
+use bft_interp::{InterpretError, MachineConfigError};
+use bft_types::BfParseError;
+
+/// A reason a program couldn't be run to completion
+#[derive(Debug)]
+pub enum BftError {
+    /// The program's file couldn't be read
+    Io {
+        /// The path of the file which couldn't be read
+        path: PathBuf,
+        /// The underlying IO error
+        inner: io::Error,
+    },
+
+    /// The program failed to parse
+    Parse {
+        /// The underlying parse error
+        inner: BfParseError,
+        /// The source of the program which failed to parse, if it was kept to point out where the
+        /// problems are
+        source: Option<String>,
+    },
+
+    /// A file couldn't be written
+    WriteFile {
+        /// The path of the file which couldn't be written
+        path: PathBuf,
+        /// The underlying IO error
+        inner: io::Error,
+    },
+
+    /// Full-cell input was asked for with cells which have no fixed width to read
+    UnboundedCellInput,
+
+    /// The virtual machine couldn't be created with the options given
+    Config(MachineConfigError),
+
+    /// The program stopped with an error while running
+    Interpret(InterpretError),
+
+    /// The program's output couldn't be written
+    Output(io::Error),
+
+    /// An interactive session's commands couldn't be read or its replies written
+    Session(io::Error),
+}
+
+impl BftError {
+    /// Exit status for invalid command line arguments
+    pub const EXIT_USAGE: u8 = 2;
+    /// Exit status for a program which failed to parse
+    pub const EXIT_PARSE: u8 = 3;
+    /// Exit status for a program which stopped with an error while running
+    pub const EXIT_RUNTIME: u8 = 4;
+    /// Exit status for a file which couldn't be read or output which couldn't be written
+    pub const EXIT_IO: u8 = 5;
+
+    /// Wrap a parse error along with the source it was found in
+    ///
+    /// `inner`: the parse error
+    /// `source`: the source of the program which failed to parse
+    pub fn parse(inner: BfParseError, source: &str) -> Self {
+        Self::Parse {
+            inner,
+            source: Some(source.to_owned()),
+        }
+    }
+
+    /// A short name for the category of this error
+    pub fn category(&self) -> &'static str {
+        match self {
+            Self::Io { .. } | Self::WriteFile { .. } | Self::Output(_) | Self::Session(_) => "io",
+            Self::Parse { .. } => "parse",
+            Self::UnboundedCellInput | Self::Config(_) => "usage",
+            Self::Interpret(_) => "runtime",
+        }
+    }
+
+    /// The status the interpreter exits with when stopped by this error
+    ///
+    /// | status | category |
+    /// |--------|----------|
+    /// | 2      | usage    |
+    /// | 3      | parse    |
+    /// | 4      | runtime  |
+    /// | 5      | io       |
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            Self::Io { .. } | Self::WriteFile { .. } | Self::Output(_) | Self::Session(_) => {
+                Self::EXIT_IO
+            }
+            Self::Parse { .. } => Self::EXIT_PARSE,
+            Self::UnboundedCellInput | Self::Config(_) => Self::EXIT_USAGE,
+            Self::Interpret(_) => Self::EXIT_RUNTIME,
+        }
+    }
+}
+
+impl fmt::Display for BftError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io { path, inner } => write!(f, "failed to read {}: {inner}", path.display()),
+            Self::WriteFile { path, inner } => {
+                write!(f, "failed to write {}: {inner}", path.display())
+            }
+            Self::Parse {
+                inner,
+                source: Some(source),
+            } => f.write_str(&inner.render(source)),
+            Self::Parse {
+                inner,
+                source: None,
+            } => write!(f, "{inner}"),
+            Self::UnboundedCellInput => f.write_str("full-cell input needs cells of a fixed width"),
+            Self::Config(e) => write!(f, "{e}"),
+            Self::Interpret(e) => write!(f, "{e}"),
+            Self::Output(e) => write!(f, "failed to write the program's output: {e}"),
+            Self::Session(e) => write!(f, "the interactive session failed: {e}"),
+        }
+    }
+}
+
+/// The source of an error is the error it wraps, if any
+///
+/// ```
+/// # use std::{error::Error, io, path::PathBuf};
+/// # use bft::BftError;
+/// let err = BftError::Io {
+///     path: PathBuf::from("missing.bf"),
+///     inner: io::Error::from(io::ErrorKind::NotFound),
+/// };
+/// let inner = err.source().unwrap().downcast_ref::<io::Error>().unwrap();
+/// assert_eq!(inner.kind(), io::ErrorKind::NotFound);
+/// assert!(BftError::UnboundedCellInput.source().is_none());
+/// ```
+impl std::error::Error for BftError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io { inner, .. } | Self::WriteFile { inner, .. } => Some(inner),
+            Self::Parse { inner, .. } => Some(inner),
+            Self::UnboundedCellInput => None,
+            Self::Config(e) => Some(e),
+            Self::Interpret(e) => Some(e),
+            Self::Output(e) | Self::Session(e) => Some(e),
+        }
+    }
+}
+
+impl From<MachineConfigError> for BftError {
+    fn from(value: MachineConfigError) -> Self {
+        Self::Config(value)
+    }
+}
+
+impl From<InterpretError> for BftError {
+    fn from(value: InterpretError) -> Self {
+        Self::Interpret(value)
+    }
+}
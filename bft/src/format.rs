@@ -0,0 +1,107 @@
+use std::path::Path;
+
+use bft_types::{Program, SourceLayout, SourceToken};
+
+use crate::BftError;
+
+/// Rewrite brainfuck source in the canonical style used by `bft fmt`
+///
+/// The code is laid out as [`Program::to_source_pretty`] lays it out, see [`SourceLayout`].
+/// Each comment goes on its own line at the indentation of the code around it, with the
+/// whitespace at either end trimmed, and a run of blank lines is kept as a single blank line. Formatting never changes the program's instructions, and formatting
+/// the result again changes nothing.
+///
+/// `filename`: the name to report the source as having come from in errors
+/// `source`: the brainfuck source to format
+/// `width`: the column to wrap code at
+/// `indent`: the number of spaces to indent each loop body by
+///
+/// ```
+/// # use std::path::Path;
+/// let source = "add two +\n+ then  loop [\n->+<] done\n";
+/// let formatted = bft::format_source(Path::new("-"), source, 80, 2).unwrap();
+/// assert_eq!(formatted, "add two\n++\nthen  loop\n[\n  ->+<\n]\ndone\n");
+/// ```
+pub fn format_source(
+    filename: &Path,
+    source: &str,
+    width: usize,
+    indent: usize,
+) -> Result<String, BftError> {
+    let (program, tokens) =
+        Program::parse_lossless(filename, source).map_err(|e| BftError::parse(e, source))?;
+
+    let mut layout = SourceLayout::new(width, indent);
+    // was the last token the end of a line with nothing but whitespace on it
+    let mut line_blank = true;
+
+    for token in tokens {
+        match token {
+            SourceToken::Instruction(i) => {
+                line_blank = false;
+                layout.push_instruction(program.instructions()[i]);
+            }
+            SourceToken::Comment { text, .. } => {
+                let text = text.trim();
+                if !text.is_empty() {
+                    line_blank = false;
+                    layout.push_line(text);
+                }
+            }
+            SourceToken::Newline => {
+                if line_blank {
+                    layout.push_blank();
+                }
+                line_blank = true;
+            }
+        }
+    }
+
+    let formatted = layout.finish();
+    if formatted.is_empty() {
+        // an empty file still ends with a newline
+        return Ok("\n".to_owned());
+    }
+    Ok(formatted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("programs/example.bf")]
+    #[case("programs/rot13.bf")]
+    #[case("programs/numwarp.bf")]
+    #[case("programs/tic_tac_toe.bf")]
+    fn test_format_keeps_instructions_and_is_idempotent(#[case] path: &str) {
+        let source = std::fs::read_to_string(path).unwrap();
+        let original = Program::try_new(Path::new(path), &source).unwrap();
+
+        for (width, indent) in [(80, 4), (1, 0), (12, 8)] {
+            let formatted = format_source(Path::new(path), &source, width, indent).unwrap();
+            let reparsed = Program::try_new(Path::new(path), &formatted).unwrap();
+            assert_eq!(reparsed.instructions(), original.instructions());
+
+            let reformatted = format_source(Path::new(path), &formatted, width, indent).unwrap();
+            assert_eq!(reformatted, formatted);
+        }
+    }
+
+    #[test]
+    fn test_format_layout() {
+        let source = "\n\ncomment\n\n\n+++++ +++++ +++++ [ a\n>\n\n++ <- ] >  .  \n\n";
+        let formatted = format_source(Path::new("-"), source, 8, 2).unwrap();
+        assert_eq!(
+            formatted,
+            "comment\n\n++++++++\n+++++++\n[\n  a\n  >\n\n  ++<-\n]\n>.\n"
+        );
+    }
+
+    #[test]
+    fn test_format_parse_error() {
+        let err = format_source(Path::new("-"), "[", 80, 4).unwrap_err();
+        assert!(matches!(err, BftError::Parse { .. }));
+    }
+}
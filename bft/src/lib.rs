@@ -0,0 +1,38 @@
+#![deny(missing_docs)]
+#![deny(clippy::missing_docs_in_private_items)]
+
+//! Running brainfuck programs with the same options as the bft interpreter
+//!
+//! ```
+//! use bft::{run, ProgramSource, RunSettings};
+//! use std::io;
+//!
+//! let settings = RunSettings::new(ProgramSource::Source {
+//!     name: "add.bf".into(),
+//!     source: "++++++++[>++++++++<-]>+.".to_owned(),
+//! });
+//! let mut output = Vec::new();
+//! let report = run(settings, io::empty(), &mut output).unwrap();
+//! assert_eq!(output, b"A");
+//! assert_eq!(report.steps, 108);
+//! ```
+
+/// The settings describing which program to run and how
+mod settings;
+pub use settings::{CellSize, MachineSettings, ProgramSource, RunSettings};
+
+/// Everything that can go wrong running a program
+mod error;
+pub use error::BftError;
+
+/// Assembling a virtual machine from the settings and running it
+mod run;
+pub use run::{run, RunReport};
+
+/// Saving and loading transcripts of a program's IO
+mod transcript;
+pub use transcript::{load_transcript, save_transcript, write_transcript, RecordedRun};
+
+/// Rewriting brainfuck source in a canonical style
+mod format;
+pub use format::format_source;
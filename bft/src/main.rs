@@ -3,43 +3,393 @@
 
 //! An interpreter for the brainfuck programming language
 
-use std::{error::Error, io, process::ExitCode};
+use std::{
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
 
-use bft_interp::{Machine, NewlineWrap, TapeKind};
-use bft_types::Program;
+use bft::{format_source, run, BftError, ProgramSource, RunSettings};
+use bft_interp::{CellType, Codegen, NewlineWrap};
+use bft_types::{Dialect, ParseOptions};
 use clap::Parser;
 
 /// The CLI for the interpreter
 mod cli;
-use cli::Args;
+use cli::{Args, Command, CompileArgs, MachineArgs};
+
+/// An interactive debugger for brainfuck programs
+mod debugger;
+use debugger::debug_program;
+
+/// Running brainfuck a line at a time
+mod repl;
+use repl::{repl, SNIPPET_NAME};
+
+/// Machine-readable reporting of the features supported by this build
+mod capabilities;
+use capabilities::Capabilities;
+
+/// Validation and loading of everything the interpreter needs before it runs
+mod setup;
+use setup::{prepare, prepare_run, OutputFile, Prepared, PreparedRun};
+
+/// The name a program given with `--eval` is referred to by
+const EVAL_NAME: &str = "<eval>";
 
 fn main() -> ExitCode {
     let args = Args::parse();
 
-    match run_bft(&args) {
-        Err(e) => {
-            eprintln!("Encountered error in {}: {e}", args.program.display());
-            ExitCode::FAILURE
+    if args.capabilities {
+        println!("{}", Capabilities::current().to_json());
+        return ExitCode::SUCCESS;
+    }
+
+    match &args.command {
+        Some(Command::Debug { program, machine }) => {
+            let prepared = match prepare_or_report(ProgramSource::from_arg(program), machine) {
+                Ok(prepared) => prepared,
+                Err(code) => return code,
+            };
+            let stdout = io::stdout().lock();
+            match debug_program(&prepared, io::stdin().lock(), io::stderr().lock(), stdout) {
+                Err(e) => {
+                    eprintln!("Debugger failed: {e}");
+                    ExitCode::from(e.exit_code())
+                }
+                Ok(_) => ExitCode::SUCCESS,
+            }
+        }
+        Some(Command::Repl { machine }) => {
+            let start = ProgramSource::Source {
+                name: PathBuf::from(SNIPPET_NAME),
+                source: String::new(),
+            };
+            let prepared = match prepare_or_report(start, machine) {
+                Ok(prepared) => prepared,
+                Err(code) => return code,
+            };
+            let options = ParseOptions::default()
+                .with_debug_chars(machine.enable_debug_chars)
+                .with_dialect(machine.dialect.clone());
+            let stdout = io::stdout().lock();
+            match repl(
+                &prepared,
+                options,
+                io::stdin().lock(),
+                io::stderr().lock(),
+                stdout,
+            ) {
+                Err(e) => {
+                    eprintln!("REPL failed: {e}");
+                    ExitCode::from(e.exit_code())
+                }
+                Ok(_) => ExitCode::SUCCESS,
+            }
+        }
+        Some(Command::Inspect { program, optimize }) => {
+            match ProgramSource::File(program.clone()).load() {
+                Ok(program) => {
+                    let program = if *optimize {
+                        program.optimized()
+                    } else {
+                        program
+                    };
+                    println!("{}", program.stats());
+                    ExitCode::SUCCESS
+                }
+                Err(e) => {
+                    eprintln!("error: {e}");
+                    ExitCode::from(e.exit_code())
+                }
+            }
+        }
+        Some(Command::Minify {
+            program,
+            output,
+            cancel,
+        }) => match minify(program, output.as_deref(), *cancel) {
+            Err(e) => {
+                eprintln!("error: {e}");
+                ExitCode::from(e.exit_code())
+            }
+            Ok(_) => ExitCode::SUCCESS,
+        },
+        Some(Command::Translate {
+            program,
+            from,
+            to,
+            output,
+        }) => match translate(program, from, to, output.as_deref()) {
+            Err(e) => {
+                eprintln!("error: {e}");
+                ExitCode::from(e.exit_code())
+            }
+            Ok(_) => ExitCode::SUCCESS,
+        },
+        Some(Command::Fmt {
+            program,
+            width,
+            indent,
+            check,
+        }) => match fmt(program, *width, *indent, *check) {
+            Err(e) => {
+                eprintln!("error: {e}");
+                ExitCode::from(e.exit_code())
+            }
+            Ok(true) => ExitCode::SUCCESS,
+            Ok(false) => {
+                eprintln!("{} is not formatted", program.display());
+                ExitCode::FAILURE
+            }
+        },
+        Some(Command::Check { programs }) => check(programs),
+        Some(Command::Compile(args)) => {
+            let Some(cell_type) = args.cell_size.bits().and_then(CellType::from_bits) else {
+                eprintln!("error: compiled programs need cells of a fixed width");
+                return ExitCode::from(BftError::EXIT_USAGE);
+            };
+            match compile(args, cell_type) {
+                Err(e) => {
+                    eprintln!("error: {e}");
+                    ExitCode::from(e.exit_code())
+                }
+                Ok(_) => ExitCode::SUCCESS,
+            }
+        }
+        None => {
+            let program = match (args.program.as_deref(), &args.eval) {
+                (Some(path), _) => ProgramSource::from_arg(path),
+                (None, Some(source)) => ProgramSource::Source {
+                    name: PathBuf::from(EVAL_NAME),
+                    source: source.clone(),
+                },
+                (None, None) => {
+                    unreachable!("clap requires a program unless --capabilities is given")
+                }
+            };
+            let name = program.name().to_path_buf();
+            let run = match prepare_run(program, &args) {
+                Ok(run) => run,
+                Err(errors) => return report_errors(&name, &errors),
+            };
+            match run_bft(run, !args.no_trailing_newline) {
+                Err(e) => {
+                    eprintln!("error: {e}");
+                    ExitCode::from(e.exit_code())
+                }
+                Ok(_) => ExitCode::SUCCESS,
+            }
         }
-        Ok(_) => ExitCode::SUCCESS,
     }
 }
 
-/// Run the brainfuck interpreter using the settings parsed from the CLI arguments
+/// Prepare to run a program, reporting every problem found to stderr
+///
+/// On failure the exit code of the first problem found is returned.
 ///
-/// `args`: The CLI arguments
-fn run_bft(args: &Args) -> Result<(), Box<dyn Error>> {
-    let tape_kind = if args.extensible {
-        TapeKind::Growable
+/// `program`: Where to load the brainfuck program to run from
+/// `machine`: The options for the interpreter
+fn prepare_or_report(program: ProgramSource, machine: &MachineArgs) -> Result<Prepared, ExitCode> {
+    let name = program.name().to_path_buf();
+    prepare(program, machine).map_err(|errors| report_errors(&name, &errors))
+}
+
+/// Report every problem found while preparing to run a program to stderr, returning the exit
+/// code of the first
+///
+/// `name`: The name of the program which was to be run
+/// `errors`: The problems found
+fn report_errors(name: &Path, errors: &[BftError]) -> ExitCode {
+    eprintln!(
+        "Encountered {} error(s) while preparing to run {}:",
+        errors.len(),
+        name.display()
+    );
+    for e in errors {
+        // keep any source snippet indented beneath its error
+        let message = e.to_string().replace('\n', "\n    ");
+        eprintln!("  [{}] {message}", e.category());
+    }
+    ExitCode::from(errors.first().map_or(1, BftError::exit_code))
+}
+
+/// Write a program with its comments removed to `output`, or stdout if it isn't given
+///
+/// `program_path`: The path to the brainfuck program to minify
+/// `output`: The path to write the minified program to
+/// `cancel`: Should adjacent instructions which undo each other be removed too
+fn minify(program_path: &Path, output: Option<&Path>, cancel: bool) -> Result<(), BftError> {
+    let program = ProgramSource::File(program_path.to_path_buf()).load()?;
+    let minified = program.minified(cancel);
+    match output {
+        Some(path) => std::fs::write(path, minified + "\n").map_err(|inner| BftError::WriteFile {
+            path: path.to_path_buf(),
+            inner,
+        }),
+        None => {
+            println!("{minified}");
+            Ok(())
+        }
+    }
+}
+
+/// Rewrite a program in another dialect, writing it to `output`, or stdout if it isn't given
+///
+/// `program_path`: The path to the program to translate
+/// `from`: The dialect the program is written in
+/// `to`: The dialect to rewrite the program in
+/// `output`: The path to write the translated program to
+fn translate(
+    program_path: &Path,
+    from: &Dialect,
+    to: &Dialect,
+    output: Option<&Path>,
+) -> Result<(), BftError> {
+    let options = ParseOptions::default().with_dialect(from.clone());
+    let program = ProgramSource::File(program_path.to_path_buf()).load_with(options)?;
+    let translated = to.translate(&program);
+    match output {
+        Some(path) => {
+            std::fs::write(path, translated + "\n").map_err(|inner| BftError::WriteFile {
+                path: path.to_path_buf(),
+                inner,
+            })
+        }
+        None => {
+            println!("{translated}");
+            Ok(())
+        }
+    }
+}
+
+/// Translate a program into another language, writing it to a file or stdout
+///
+/// `args`: Which program to translate and how
+/// `cell_type`: The width of the translated program's cells
+fn compile(args: &CompileArgs, cell_type: CellType) -> Result<(), BftError> {
+    let program = ProgramSource::File(args.program.clone()).load()?;
+    let program = if args.optimize {
+        program.optimized()
     } else {
-        TapeKind::FixedSize
+        program
     };
-    let program = Program::from_file(&args.program)?;
+    let source = Codegen::new(args.emit.into())
+        .with_cell_type(cell_type)
+        .with_cells(args.cells)
+        .with_growable_tape(args.extensible)
+        .with_eof_behaviour(args.on_eof.into())
+        .generate(&program);
+    match &args.output {
+        Some(path) => std::fs::write(path, source).map_err(|inner| BftError::WriteFile {
+            path: path.clone(),
+            inner,
+        }),
+        None => {
+            print!("{source}");
+            Ok(())
+        }
+    }
+}
+
+/// Check that each program parses, printing a line describing each to stdout
+///
+/// Returns the exit code of the first program which failed to load, if any did.
+///
+/// `paths`: The paths to the brainfuck programs to check, `-` for stdin
+fn check(paths: &[PathBuf]) -> ExitCode {
+    let mut first_failure = None;
+    for path in paths {
+        match ProgramSource::from_arg(path).load() {
+            Ok(program) => {
+                let stats = program.stats();
+                println!(
+                    "{}: OK, {} instructions, max depth {}",
+                    path.display(),
+                    stats.instructions,
+                    stats.max_depth
+                );
+            }
+            Err(e) => {
+                // without the source snippet, every problem fits on one line
+                let message = match &e {
+                    BftError::Parse { inner, .. } => inner.to_string(),
+                    e => e.to_string(),
+                };
+                println!("{}: {}", path.display(), message.replace('\n', "; "));
+                first_failure.get_or_insert(e.exit_code());
+            }
+        }
+    }
+    first_failure.map_or(ExitCode::SUCCESS, ExitCode::from)
+}
 
-    let stdin = io::stdin().lock();
-    let stdout = NewlineWrap::new(io::stdout().lock());
-    let mut machine = Machine::<u8>::new(args.cells, tape_kind, &program);
-    machine.run(stdin, stdout)?;
+/// Format a program in place, or with `check` only compare it with its formatted source
+///
+/// Returns whether the file was already formatted.
+///
+/// `program_path`: The path to the brainfuck program to format
+/// `width`: The column to wrap code at
+/// `indent`: The number of spaces to indent each loop body by
+/// `check`: Should the file be left unchanged
+fn fmt(program_path: &Path, width: usize, indent: usize, check: bool) -> Result<bool, BftError> {
+    let source = std::fs::read_to_string(program_path).map_err(|inner| BftError::Io {
+        path: program_path.to_path_buf(),
+        inner,
+    })?;
+    let formatted = format_source(program_path, &source, width, indent)?;
+    let unchanged = formatted == source;
+    if !check && !unchanged {
+        std::fs::write(program_path, formatted).map_err(|inner| BftError::WriteFile {
+            path: program_path.to_path_buf(),
+            inner,
+        })?;
+    }
+    Ok(unchanged || !check)
+}
 
-    Ok(())
+/// Run the brainfuck interpreter using the settings prepared from the CLI arguments
+///
+/// `prepared_run`: The validated settings and loaded program, with its input and output opened
+/// `trailing_newline`: Should output to stdout be ended with a newline if it doesn't have one,
+/// output written to a file is always left exactly as the program wrote it
+fn run_bft(prepared_run: PreparedRun, trailing_newline: bool) -> Result<(), BftError> {
+    let PreparedRun {
+        prepared,
+        input,
+        output,
+    } = prepared_run;
+    let settings = RunSettings {
+        program: ProgramSource::Parsed(prepared.program),
+        parse_options: ParseOptions::default(),
+        // the program was already optimized while preparing, if asked to
+        optimize: false,
+        machine: prepared.machine,
+    };
+    match output {
+        Some(OutputFile { path, file }) => {
+            let write_error = |inner| BftError::WriteFile {
+                path: path.clone(),
+                inner,
+            };
+            // the file is only truncated once the run is about to start
+            file.set_len(0).map_err(write_error)?;
+            let mut file = BufWriter::new(file);
+            run(settings, input, &mut file)?;
+            file.flush().map_err(write_error)
+        }
+        None if trailing_newline => {
+            let mut stdout = NewlineWrap::new(io::stdout().lock());
+            run(settings, input, &mut stdout)?;
+            // unlike dropping the wrapper, finishing reports a failure to write the last of the
+            // output
+            let _ = stdout.finish().map_err(BftError::Output)?;
+            Ok(())
+        }
+        None => {
+            let mut stdout = io::stdout().lock();
+            run(settings, input, &mut stdout)?;
+            stdout.flush().map_err(BftError::Output)
+        }
+    }
 }
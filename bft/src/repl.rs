@@ -0,0 +1,238 @@
+use std::{
+    fmt,
+    io::{self, BufRead, Write},
+    path::PathBuf,
+    str::FromStr,
+};
+
+use bft::{BftError, CellSize, ProgramSource};
+use bft_interp::{CellKind, DumpFormat, Machine, MachineConfigError, SparseTape, Tape, TapeKind};
+use bft_types::{ParseOptions, Program};
+
+use crate::setup::Prepared;
+
+/// The name snippets typed into the REPL are referred to by
+pub const SNIPPET_NAME: &str = "<repl>";
+
+/// Run snippets of brainfuck read a line at a time, all on the same tape
+///
+/// `prepared`: The validated settings, along with the empty program the machine starts with
+/// `options`: The extensions to accept when parsing snippets
+/// `commands`: where snippets and commands are read from, one per line
+/// `log`: where the REPL reports the state of the machine
+/// `output`: where the snippets' output is written to
+pub fn repl(
+    prepared: &Prepared,
+    options: ParseOptions,
+    commands: impl BufRead,
+    log: impl Write,
+    output: impl Write,
+) -> Result<(), BftError> {
+    match prepared.machine.cell_size {
+        CellSize::U8 => repl_sized::<u8>(prepared, options, commands, log, output),
+        CellSize::U16 => repl_sized::<u16>(prepared, options, commands, log, output),
+        CellSize::U32 => repl_sized::<u32>(prepared, options, commands, log, output),
+        CellSize::U64 => repl_sized::<u64>(prepared, options, commands, log, output),
+        #[cfg(feature = "bignum")]
+        CellSize::Big => {
+            repl_sized::<num_bigint::BigUint>(prepared, options, commands, log, output)
+        }
+    }
+}
+
+/// Run the REPL on a machine with cells of type `Cell`, stored however the tape kind requires
+fn repl_sized<Cell: CellKind + fmt::Display>(
+    prepared: &Prepared,
+    options: ParseOptions,
+    commands: impl BufRead,
+    log: impl Write,
+    output: impl Write,
+) -> Result<(), BftError> {
+    if prepared.machine.tape_kind == TapeKind::Sparse {
+        Repl::<Cell, SparseTape<Cell>>::new(prepared, options)?
+            .run(commands, log, output)
+            .map_err(BftError::Session)
+    } else {
+        Repl::<Cell, Vec<Cell>>::new(prepared, options)?
+            .run(commands, log, output)
+            .map_err(BftError::Session)
+    }
+}
+
+/// A line typed into the REPL
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum ReplCommand {
+    /// Run a snippet of brainfuck
+    Run(String),
+    /// Show the cells of the tape
+    Tape,
+    /// Clear the tape and move the head back to where it started
+    Reset,
+    /// Run the program in a file
+    Load(PathBuf),
+    /// Stop the REPL
+    Quit,
+}
+
+impl FromStr for ReplCommand {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let Some(command) = s.trim().strip_prefix(':') else {
+            return Ok(Self::Run(s.to_owned()));
+        };
+        let (name, arg) = command
+            .split_once(char::is_whitespace)
+            .map_or((command, ""), |(name, arg)| (name, arg.trim()));
+
+        let parsed = match (name, arg) {
+            ("tape" | "t", "") => Self::Tape,
+            ("reset" | "r", "") => Self::Reset,
+            ("quit" | "q", "") => Self::Quit,
+            ("load" | "l", "") => return Err(format!(":{name} needs a file to load")),
+            ("load" | "l", path) => Self::Load(PathBuf::from(path)),
+            ("tape" | "t" | "reset" | "r" | "quit" | "q", _) => {
+                return Err(format!(":{name} takes no arguments"))
+            }
+            _ => return Err(format!("unknown command :{name}")),
+        };
+
+        Ok(parsed)
+    }
+}
+
+/// The state of a REPL session
+struct Repl<'a, Cell: CellKind, T: Tape<Cell>> {
+    /// The virtual machine whose tape every snippet runs on
+    machine: Machine<'a, Cell, T>,
+
+    /// The extensions to accept when parsing snippets
+    options: ParseOptions,
+}
+
+impl<'a, Cell: CellKind + fmt::Display, T: Tape<Cell>> Repl<'a, Cell, T> {
+    /// Create a REPL whose machine starts with the prepared program and settings
+    fn new(prepared: &'a Prepared, options: ParseOptions) -> Result<Self, MachineConfigError> {
+        // snippets are run as they are typed, so are never optimised
+        let machine = prepared
+            .machine
+            .builder()
+            .multiply_loops(false)
+            .build(&prepared.program)?;
+        Ok(Self { machine, options })
+    }
+
+    /// Read and execute lines until told to quit or the lines run out
+    fn run(
+        mut self,
+        commands: impl BufRead,
+        mut log: impl Write,
+        mut output: impl Write,
+    ) -> io::Result<()> {
+        let mut quit = false;
+        write!(log, "bf> ")?;
+        log.flush()?;
+        for line in commands.lines() {
+            let line = line?;
+            if !line.trim().is_empty() {
+                match line.parse::<ReplCommand>() {
+                    Ok(ReplCommand::Quit) => {
+                        quit = true;
+                        break;
+                    }
+                    Ok(command) => self.execute(command, &mut log, &mut output)?,
+                    Err(e) => writeln!(log, "error: {e}")?,
+                }
+            }
+            write!(log, "bf> ")?;
+            log.flush()?;
+        }
+
+        // finish the line the last prompt was written on when the lines ran out
+        if !quit {
+            writeln!(log)?;
+        }
+
+        output.flush()
+    }
+
+    /// Execute a single line
+    fn execute(
+        &mut self,
+        command: ReplCommand,
+        log: &mut impl Write,
+        output: &mut impl Write,
+    ) -> io::Result<()> {
+        let source = match command {
+            ReplCommand::Run(source) => ProgramSource::Source {
+                name: PathBuf::from(SNIPPET_NAME),
+                source,
+            },
+            ReplCommand::Load(path) => ProgramSource::File(path),
+            ReplCommand::Tape => return self.machine.dump_tape(log, DumpFormat::Hex),
+            ReplCommand::Reset => {
+                self.machine.reset();
+                return self.summarise(0, log);
+            }
+            ReplCommand::Quit => return Ok(()),
+        };
+
+        match source.load_with(self.options.clone()) {
+            Ok(program) => self.run_program(program, log, output),
+            Err(e) => writeln!(log, "error: {e}"),
+        }
+    }
+
+    /// Run `program` on the tape as the previous snippet left it
+    fn run_program(
+        &mut self,
+        program: Program,
+        log: &mut impl Write,
+        output: &mut impl Write,
+    ) -> io::Result<()> {
+        let steps = self.machine.steps();
+        self.machine.continue_with(program);
+        let res = self.machine.run(io::empty(), &mut *output);
+        output.flush()?;
+        if let Err(e) = res {
+            writeln!(log, "error: {e}")?;
+        }
+        self.summarise(self.machine.steps() - steps, log)
+    }
+
+    /// Write a line describing the head and the cell under it, after running `steps`
+    /// instructions
+    fn summarise(&self, steps: u64, log: &mut impl Write) -> io::Result<()> {
+        let dp = self.machine.dp();
+        match self.machine.cell_at(dp) {
+            Some(cell) => writeln!(log, "dp={dp} cell={cell} steps={steps}"),
+            None => writeln!(log, "dp={dp} steps={steps}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("+++>.", ReplCommand::Run("+++>.".to_owned()))]
+    #[case("  :tape ", ReplCommand::Tape)]
+    #[case(":t", ReplCommand::Tape)]
+    #[case(":reset", ReplCommand::Reset)]
+    #[case(":quit", ReplCommand::Quit)]
+    #[case(":load programs/hello world.bf", ReplCommand::Load("programs/hello world.bf".into()))]
+    fn test_parse_commands(#[case] line: &str, #[case] expected: ReplCommand) {
+        assert_eq!(line.parse(), Ok(expected));
+    }
+
+    #[rstest]
+    #[case(":load", ":load needs a file to load")]
+    #[case(":load   ", ":load needs a file to load")]
+    #[case(":tape 3", ":tape takes no arguments")]
+    #[case(":frobnicate", "unknown command :frobnicate")]
+    fn test_parse_commands_rejects(#[case] line: &str, #[case] expected: &str) {
+        assert_eq!(line.parse::<ReplCommand>(), Err(expected.to_owned()));
+    }
+}
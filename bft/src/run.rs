@@ -0,0 +1,220 @@
+use std::{
+    fmt,
+    io::{self, Read, Write},
+    time::{Duration, Instant},
+};
+
+use bft_interp::{CellKind, DumpFormat, Machine, RunStats, SparseTape, Tape, TapeKind};
+use bft_types::Program;
+
+use crate::{save_transcript, BftError, CellSize, MachineSettings, RunSettings};
+
+/// A summary of a program which ran to completion
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RunReport {
+    /// The number of instructions executed
+    pub steps: u64,
+}
+
+/// Load the program described by `settings` and run it to completion
+///
+/// A traced run, dump of the tape, statistics about the run or its coverage are written to
+/// stderr. A transcript of the program's IO is saved even if it stops with an error. A replayed
+/// run which stops without repeating everything recorded is an error.
+///
+/// `settings`: which program to run and how
+/// `input`: where the program reads its input from
+/// `output`: where the program writes its output to
+pub fn run(
+    settings: RunSettings,
+    input: impl Read,
+    output: impl Write,
+) -> Result<RunReport, BftError> {
+    let program = settings.program.load_with(settings.parse_options)?;
+    let program = if settings.optimize {
+        program.optimized()
+    } else {
+        program
+    };
+
+    let steps = match settings.machine.cell_size {
+        CellSize::U8 => run_sized::<u8>(&program, &settings.machine, input, output),
+        CellSize::U16 => run_sized::<u16>(&program, &settings.machine, input, output),
+        CellSize::U32 => run_sized::<u32>(&program, &settings.machine, input, output),
+        CellSize::U64 => run_sized::<u64>(&program, &settings.machine, input, output),
+        #[cfg(feature = "bignum")]
+        CellSize::Big => {
+            run_sized::<num_bigint::BigUint>(&program, &settings.machine, input, output)
+        }
+    }?;
+
+    Ok(RunReport { steps })
+}
+
+/// Run `program` on a machine with cells of type `Cell`, stored however the tape kind
+/// requires, returning the number of instructions executed
+///
+/// `program`: The program to run
+/// `settings`: How to configure the virtual machine
+/// `input`: where the program reads its input from
+/// `output`: where the program writes its output to
+fn run_sized<Cell: CellKind + fmt::Display>(
+    program: &Program,
+    settings: &MachineSettings,
+    input: impl Read,
+    output: impl Write,
+) -> Result<u64, BftError> {
+    if settings.tape_kind == TapeKind::Sparse {
+        run_machine::<Cell, SparseTape<Cell>>(program, settings, input, output)
+    } else {
+        run_machine::<Cell, Vec<Cell>>(program, settings, input, output)
+    }
+}
+
+/// Run `program` on a machine with cells of type `Cell` stored in a `T`, returning the number
+/// of instructions executed
+///
+/// `program`: The program to run
+/// `settings`: How to configure the virtual machine
+/// `input`: where the program reads its input from
+/// `output`: where the program writes its output to
+fn run_machine<Cell: CellKind + fmt::Display, T: Tape<Cell>>(
+    program: &Program,
+    settings: &MachineSettings,
+    input: impl Read,
+    output: impl Write,
+) -> Result<u64, BftError> {
+    let mut machine: Machine<Cell, T> = settings
+        .builder()
+        .profiling(settings.coverage)
+        .recording(settings.record.is_some())
+        .build(program)?;
+    let start = Instant::now();
+    let res = if settings.trace {
+        let mut stderr = io::stderr().lock();
+        machine.run_traced(input, output, |event| {
+            writeln!(stderr, "{event}").ok();
+        })
+    } else {
+        machine.run(input, output)
+    };
+
+    let elapsed = start.elapsed();
+
+    if settings.dump_tape {
+        machine.dump_tape(io::stderr().lock(), DumpFormat::Hex).ok();
+    }
+    if settings.stats {
+        write_stats(io::stderr().lock(), machine.stats(), elapsed).ok();
+    }
+    if let Some(coverage) = machine.coverage() {
+        eprintln!("{coverage}");
+    }
+
+    let saved = match (&settings.record, machine.transcript()) {
+        (Some(path), Some(transcript)) => save_transcript(path, transcript),
+        _ => Ok(()),
+    };
+    let steps = res.map(|()| machine.steps())?;
+    saved?;
+    if let (Some(recorded), Some(replay)) = (&settings.replay, machine.replay()) {
+        replay.finish().map_err(|inner| BftError::Io {
+            path: recorded.path.clone(),
+            inner,
+        })?;
+    }
+    Ok(steps)
+}
+
+/// Write a table of statistics about a run which took `elapsed`
+fn write_stats(mut writer: impl Write, stats: RunStats, elapsed: Duration) -> io::Result<()> {
+    let seconds = elapsed.as_secs_f64();
+    writeln!(writer, "{:<20}{elapsed:?}", "time:")?;
+    writeln!(writer, "{:<20}{}", "instructions:", stats.steps)?;
+    if seconds > 0.0 {
+        let per_second = stats.steps as f64 / seconds;
+        writeln!(writer, "{:<20}{per_second:.0}", "instructions/sec:")?;
+    }
+    writeln!(writer, "{:<20}{}", "peak tape length:", stats.peak_tape_len)?;
+    writeln!(writer, "{:<20}{}", "data pointer:", stats.dp)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ProgramSource;
+    use bft_interp::{InterpretError, MachineConfigError, OutputMode};
+
+    fn run_source(source: &str, cell_size: CellSize) -> Vec<u8> {
+        run_source_with(source, cell_size, OutputMode::FullCell)
+    }
+
+    fn run_source_with(source: &str, cell_size: CellSize, output_mode: OutputMode) -> Vec<u8> {
+        let mut settings = RunSettings::new(ProgramSource::Source {
+            name: "-".into(),
+            source: source.to_owned(),
+        });
+        settings.machine.cell_size = cell_size;
+        settings.machine.output_mode = output_mode;
+        let mut output = Vec::new();
+        run(settings, io::empty(), &mut output).unwrap();
+        output
+    }
+
+    #[test]
+    fn test_cell_size_8_wraps_at_255() {
+        assert_eq!(run_source("-.", CellSize::U8), [0xFF]);
+    }
+
+    #[test]
+    fn test_cell_size_16_wraps_at_65535() {
+        assert_eq!(run_source("-.", CellSize::U16), 65535_u16.to_be_bytes());
+        assert_eq!(run_source("-+.", CellSize::U16), [0, 0]);
+    }
+
+    #[test]
+    fn test_low_byte_output() {
+        assert_eq!(
+            run_source_with("-.", CellSize::U32, OutputMode::LowByte),
+            [0xFF]
+        );
+        assert_eq!(
+            run_source_with("-.", CellSize::U8, OutputMode::LowByte),
+            [0xFF]
+        );
+    }
+
+    #[test]
+    fn test_cell_size_64_wraps() {
+        assert_eq!(run_source("-.", CellSize::U64), u64::MAX.to_be_bytes());
+    }
+
+    #[test]
+    fn test_invalid_machine_settings() {
+        let mut settings = RunSettings::new(ProgramSource::Source {
+            name: "-".into(),
+            source: "+".to_owned(),
+        });
+        settings.machine.max_cells = Some(100_000);
+        let err = run(settings, io::empty(), io::sink()).unwrap_err();
+        assert!(matches!(
+            err,
+            BftError::Config(MachineConfigError::MaxCellsWithoutGrowth { .. })
+        ));
+        assert_eq!(err.exit_code(), BftError::EXIT_USAGE);
+    }
+
+    #[test]
+    fn test_max_steps() {
+        let mut settings = RunSettings::new(ProgramSource::Source {
+            name: "-".into(),
+            source: "+[]".to_owned(),
+        });
+        settings.machine.max_steps = Some(5);
+        let err = run(settings, io::empty(), io::sink()).unwrap_err();
+        assert!(matches!(
+            err,
+            BftError::Interpret(InterpretError::StepLimitExceeded { steps: 5, .. })
+        ));
+    }
+}
@@ -0,0 +1,281 @@
+use std::{
+    error::Error,
+    io,
+    path::{Path, PathBuf},
+};
+
+use bft_interp::{
+    CellOverflow, EofBehaviour, InputMode, MachineBuilder, OutputMode, Replay, TapeKind,
+    DEFAULT_TAPE_SIZE,
+};
+use bft_types::{BfParseError, ParseOptions, Program};
+use clap::ValueEnum;
+
+use crate::{BftError, RecordedRun};
+
+/// Everything needed to run a program, mirroring the interpreter's command line arguments
+#[derive(Debug)]
+pub struct RunSettings {
+    /// Where to find the program to run
+    pub program: ProgramSource,
+
+    /// Which extensions to the language to accept when parsing the program
+    pub parse_options: ParseOptions,
+
+    /// Should runs of repeated instructions be folded together before running the program
+    pub optimize: bool,
+
+    /// How to configure the virtual machine
+    pub machine: MachineSettings,
+}
+
+impl RunSettings {
+    /// Settings to run `program` with the interpreter's default options
+    pub fn new(program: ProgramSource) -> Self {
+        Self {
+            program,
+            parse_options: ParseOptions::default(),
+            optimize: false,
+            machine: MachineSettings::default(),
+        }
+    }
+}
+
+/// The places a program can be loaded from
+#[derive(Debug)]
+pub enum ProgramSource {
+    /// A file containing the program's source
+    File(PathBuf),
+
+    /// The program's source, held in memory
+    Source {
+        /// The name to refer to the program by in errors
+        name: PathBuf,
+        /// The program's source
+        source: String,
+    },
+
+    /// The program's source, read from stdin and referred to as `<stdin>`
+    Stdin,
+
+    /// A program which has already been parsed
+    Parsed(Program),
+}
+
+impl ProgramSource {
+    /// The name a program read from stdin is referred to by
+    pub const STDIN_NAME: &'static str = "<stdin>";
+
+    /// The source for a path given on the command line, where `-` means stdin
+    pub fn from_arg(path: &Path) -> Self {
+        if path == Path::new("-") {
+            Self::Stdin
+        } else {
+            Self::File(path.to_path_buf())
+        }
+    }
+
+    /// The name the program is referred to by in errors
+    pub fn name(&self) -> &Path {
+        match self {
+            Self::File(path) => path,
+            Self::Source { name, .. } => name,
+            Self::Stdin => Path::new(Self::STDIN_NAME),
+            Self::Parsed(program) => program.filename(),
+        }
+    }
+
+    /// Load and parse the program
+    pub fn load(self) -> Result<Program, BftError> {
+        self.load_with(ParseOptions::default())
+    }
+
+    /// Load and parse the program, accepting the extensions enabled in `options`
+    pub fn load_with(self, options: ParseOptions) -> Result<Program, BftError> {
+        match self {
+            Self::File(path) => {
+                let source = std::fs::read_to_string(&path).map_err(|inner| BftError::Io {
+                    path: path.clone(),
+                    inner,
+                })?;
+                Self::Source { name: path, source }.load_with(options)
+            }
+            Self::Source { name, source } => Program::try_new_with_options(&name, &source, options)
+                .map_err(|e| BftError::parse(e, &source)),
+            Self::Stdin => {
+                Program::from_reader_with_options(Self::STDIN_NAME, io::stdin().lock(), options)
+                    .map_err(stdin_error)
+            }
+            Self::Parsed(program) => Ok(program),
+        }
+    }
+}
+
+/// Sort a failure to read and parse a program from stdin into the kind of error it was
+///
+/// The source read is gone by the time a parse error is seen, so it can't be pointed into.
+fn stdin_error(e: Box<dyn Error>) -> BftError {
+    let e = match e.downcast::<BfParseError>() {
+        Ok(inner) => {
+            return BftError::Parse {
+                inner: *inner,
+                source: None,
+            }
+        }
+        Err(e) => e,
+    };
+    let inner = match e.downcast::<io::Error>() {
+        Ok(inner) => *inner,
+        Err(e) => io::Error::other(e.to_string()),
+    };
+    BftError::Io {
+        path: PathBuf::from(ProgramSource::STDIN_NAME),
+        inner,
+    }
+}
+
+/// How to configure the virtual machine running a program
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MachineSettings {
+    /// The kind of tape to give the virtual machine
+    pub tape_kind: TapeKind,
+
+    /// The number of cells to allocate for the tape
+    pub cells: usize,
+
+    /// The most cells the tape can grow to, if limited
+    pub max_cells: Option<usize>,
+
+    /// The width of the tape's cells
+    pub cell_size: CellSize,
+
+    /// What `,` does once the input is exhausted
+    pub eof_behaviour: EofBehaviour,
+
+    /// What `+` and `-` do when a cell would overflow
+    pub overflow: CellOverflow,
+
+    /// How much of a cell `.` writes
+    pub output_mode: OutputMode,
+
+    /// How much of a cell `,` reads
+    pub input_mode: InputMode,
+
+    /// The maximum number of instructions to execute, if any
+    pub max_steps: Option<u64>,
+
+    /// Should a loop which keeps repeating without changing anything stop the program
+    pub detect_hangs: bool,
+
+    /// Should loops which only multiply their counter into nearby cells run as multiply-adds
+    pub multiply_loops: bool,
+
+    /// Should the tape be dumped to stderr once the program stops
+    pub dump_tape: bool,
+
+    /// Should each instruction executed be traced to stderr
+    pub trace: bool,
+
+    /// Should timings and statistics about the run be printed to stderr once the program stops
+    pub stats: bool,
+
+    /// Should the instructions which were never executed be listed on stderr once the program
+    /// stops
+    pub coverage: bool,
+
+    /// Where to write a transcript of every byte the program reads or writes once it stops, if
+    /// anywhere
+    pub record: Option<PathBuf>,
+
+    /// The recorded run the program must repeat, reading its input from the transcript rather
+    /// than the input it is given, if any
+    pub replay: Option<RecordedRun>,
+}
+
+impl Default for MachineSettings {
+    fn default() -> Self {
+        Self {
+            tape_kind: TapeKind::FixedSize,
+            cells: DEFAULT_TAPE_SIZE,
+            max_cells: None,
+            cell_size: CellSize::U8,
+            eof_behaviour: EofBehaviour::Error,
+            overflow: CellOverflow::Wrapping,
+            // with 8-bit cells both modes write the same single byte
+            output_mode: OutputMode::LowByte,
+            input_mode: InputMode::Byte,
+            max_steps: None,
+            detect_hangs: false,
+            multiply_loops: false,
+            dump_tape: false,
+            trace: false,
+            stats: false,
+            coverage: false,
+            record: None,
+            replay: None,
+        }
+    }
+}
+
+impl MachineSettings {
+    /// A builder for virtual machines configured by these settings
+    ///
+    /// Only the options the machine itself knows about are set, the rest control how the
+    /// interpreter reports on the run.
+    pub fn builder(&self) -> MachineBuilder {
+        let mut builder = MachineBuilder::new()
+            .tape_size(self.cells)
+            .tape_kind(self.tape_kind)
+            .eof(self.eof_behaviour)
+            .overflow(self.overflow)
+            .output_mode(self.output_mode)
+            .input_mode(self.input_mode)
+            .hang_detection(self.detect_hangs)
+            .multiply_loops(self.multiply_loops);
+        if let Some(max_cells) = self.max_cells {
+            builder = builder.max_cells(max_cells);
+        }
+        if let Some(max_steps) = self.max_steps {
+            builder = builder.max_steps(max_steps);
+        }
+        if let Some(replay) = &self.replay {
+            builder = builder.replay(Replay::new(replay.events.iter().copied()));
+        }
+        builder
+    }
+}
+
+/// The cell widths the interpreter can be run with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum CellSize {
+    /// 8-bit cells
+    #[value(name = "8")]
+    U8,
+    /// 16-bit cells
+    #[value(name = "16")]
+    U16,
+    /// 32-bit cells
+    #[value(name = "32")]
+    U32,
+    /// 64-bit cells
+    #[value(name = "64")]
+    U64,
+    /// Cells holding arbitrarily large numbers, which stay at zero when decremented from zero
+    #[cfg(feature = "bignum")]
+    #[value(name = "big")]
+    Big,
+}
+
+impl CellSize {
+    /// The width of the cell in bits, None for cells which grow without bound
+    pub fn bits(self) -> Option<u32> {
+        match self {
+            Self::U8 => Some(u8::BITS),
+            Self::U16 => Some(u16::BITS),
+            Self::U32 => Some(u32::BITS),
+            Self::U64 => Some(u64::BITS),
+            #[cfg(feature = "bignum")]
+            Self::Big => None,
+        }
+    }
+}
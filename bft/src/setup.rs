@@ -0,0 +1,496 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, BufReader, Cursor, Read},
+    path::{Path, PathBuf},
+};
+
+use bft::{load_transcript, BftError, MachineSettings, ProgramSource, RecordedRun};
+use bft_interp::{GrowthStrategy, InputMode, MachineConfigError, OutputMode, TapeKind};
+use bft_types::{ParseOptions, Program};
+
+use crate::cli::{Args, MachineArgs};
+
+/// Everything the interpreter needs to start executing, validated and loaded up front
+#[derive(Debug)]
+pub struct Prepared {
+    /// The parsed brainfuck program
+    pub program: Program,
+
+    /// How to configure the virtual machine
+    pub machine: MachineSettings,
+}
+
+/// Everything a run from the command line needs, with the files it reads and writes opened
+#[derive(Debug)]
+pub struct PreparedRun {
+    /// The loaded program and settings for its virtual machine
+    pub prepared: Prepared,
+
+    /// Where the program's `,` instructions read from
+    pub input: ProgramInput,
+
+    /// The file to write the program's output to, rather than stdout
+    pub output: Option<OutputFile>,
+}
+
+/// Where a program run from the command line reads its input from
+#[derive(Debug)]
+pub enum ProgramInput {
+    /// A file given with `--input`
+    File(BufReader<File>),
+
+    /// Text given with `--input-str` or embedded in the program, empty when replaying a
+    /// transcript
+    Text(Cursor<Vec<u8>>),
+
+    /// The interpreter's stdin
+    Stdin,
+}
+
+impl Read for ProgramInput {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Self::File(file) => file.read(buf),
+            Self::Text(text) => text.read(buf),
+            Self::Stdin => io::stdin().read(buf),
+        }
+    }
+}
+
+/// A file opened for a program's output
+///
+/// It is opened without being truncated, so that a run which never starts leaves it as it was,
+/// and a file created for a run which never starts is removed again.
+#[derive(Debug)]
+pub struct OutputFile {
+    /// The path the file was opened at, which errors writing to it are reported against
+    pub path: PathBuf,
+
+    /// The open file
+    pub file: File,
+}
+
+/// Validate the interpreter's options and load every file they reference
+///
+/// Rather than stopping at the first problem, every problem that can be detected before
+/// execution starts is collected so they can all be reported at once.
+///
+/// `program`: Where to load the brainfuck program to run from
+/// `args`: The options for the interpreter
+pub fn prepare(program: ProgramSource, args: &MachineArgs) -> Result<Prepared, Vec<BftError>> {
+    let mut errors = Vec::new();
+    match prepare_into(program, args, &mut errors) {
+        Some(prepared) if errors.is_empty() => Ok(prepared),
+        _ => Err(errors),
+    }
+}
+
+/// Prepare to run a program as [`prepare`] does, also loading the transcript to replay and
+/// opening the files the program reads its input from and writes its output to
+///
+/// `program`: Where to load the brainfuck program to run from
+/// `args`: The interpreter's command line arguments
+pub fn prepare_run(program: ProgramSource, args: &Args) -> Result<PreparedRun, Vec<BftError>> {
+    let mut errors = Vec::new();
+    let prepared = prepare_into(program, &args.machine, &mut errors);
+
+    let replay = args.replay.as_ref().and_then(|path| {
+        load_transcript(path)
+            .map(|events| RecordedRun {
+                path: path.clone(),
+                events,
+            })
+            .map_err(|e| errors.push(e))
+            .ok()
+    });
+
+    let input = match (&args.input, &args.input_str) {
+        (Some(path), _) => File::open(path)
+            .map(|file| ProgramInput::File(BufReader::new(file)))
+            .map_err(|inner| {
+                errors.push(BftError::Io {
+                    path: path.clone(),
+                    inner,
+                })
+            })
+            .ok(),
+        (None, Some(text)) => Some(ProgramInput::Text(Cursor::new(text.clone().into_bytes()))),
+        // the program's input comes from the transcript
+        (None, None) if args.replay.is_some() => Some(ProgramInput::Text(Cursor::default())),
+        (None, None) => match prepared.as_ref().and_then(|p| p.program.embedded_input()) {
+            Some(text) => Some(ProgramInput::Text(Cursor::new(text.as_bytes().to_vec()))),
+            // a program read from stdin has already used it up, leaving `,` at the end of the
+            // input
+            None => Some(ProgramInput::Stdin),
+        },
+    };
+
+    // was the output file created here, so that it can be removed again if preparing fails
+    let mut output_created = false;
+    let output = match &args.output {
+        Some(path) => open_output(path)
+            .map(|(file, created)| {
+                output_created = created;
+                Some(OutputFile {
+                    path: path.clone(),
+                    file,
+                })
+            })
+            .map_err(|inner| {
+                errors.push(BftError::WriteFile {
+                    path: path.clone(),
+                    inner,
+                })
+            })
+            .ok(),
+        None => Some(None),
+    };
+
+    match (prepared, input, output) {
+        (Some(mut prepared), Some(input), Some(output)) if errors.is_empty() => {
+            prepared.machine.record = args.record.clone();
+            prepared.machine.replay = replay;
+            Ok(PreparedRun {
+                prepared,
+                input,
+                output,
+            })
+        }
+        _ => {
+            if let (true, Some(path)) = (output_created, &args.output) {
+                // the run never starts, so don't leave behind an empty file it didn't write
+                let _ = std::fs::remove_file(path);
+            }
+            Err(errors)
+        }
+    }
+}
+
+/// Open a file to write a program's output to without truncating it, creating it if it doesn't
+/// exist
+///
+/// Returns the file and whether it was created.
+fn open_output(path: &Path) -> io::Result<(File, bool)> {
+    match OpenOptions::new().write(true).create_new(true).open(path) {
+        Ok(file) => Ok((file, true)),
+        Err(e) if e.kind() == io::ErrorKind::AlreadyExists => OpenOptions::new()
+            .write(true)
+            .open(path)
+            .map(|file| (file, false)),
+        Err(e) => Err(e),
+    }
+}
+
+/// Validate the options for the virtual machine and load the program, adding every problem
+/// found to `errors`
+///
+/// Returns the loaded program and settings if they could be made, even if problems were found.
+///
+/// `program`: Where to load the brainfuck program to run from
+/// `args`: The options for the interpreter
+/// `errors`: Where to add the problems found
+fn prepare_into(
+    program: ProgramSource,
+    args: &MachineArgs,
+    errors: &mut Vec<BftError>,
+) -> Option<Prepared> {
+    let tape_kind = if args.grow_left {
+        TapeKind::GrowableBothEnds {
+            growth: GrowthStrategy::default(),
+        }
+    } else if args.sparse_tape {
+        TapeKind::Sparse
+    } else if args.wrap_tape {
+        TapeKind::Wrapping
+    } else if args.extensible || args.max_cells.is_some() {
+        TapeKind::Growable {
+            growth: GrowthStrategy::default(),
+        }
+    } else {
+        TapeKind::FixedSize
+    };
+
+    if let Some(max_cells) = args.max_cells.filter(|&max| args.cells > max) {
+        errors.push(BftError::Config(MachineConfigError::TapeAboveMaxCells {
+            tape_size: args.cells,
+            max_cells,
+        }));
+    }
+
+    // there is no width to read a cell holding an arbitrarily large number with
+    if args.full_cell_input && args.cell_size.bits().is_none() {
+        errors.push(BftError::UnboundedCellInput);
+    }
+
+    let parse_options = ParseOptions::default()
+        .with_debug_chars(args.enable_debug_chars)
+        .with_input_separator(args.input_separator)
+        .with_dialect(args.dialect.clone());
+    let program = match program.load_with(parse_options) {
+        Ok(program) if args.optimize => Some(program.optimized()),
+        Ok(program) => Some(program),
+        Err(e) => {
+            errors.push(e);
+            None
+        }
+    };
+
+    program.map(|program| Prepared {
+        program,
+        machine: MachineSettings {
+            tape_kind,
+            cells: args.cells,
+            max_cells: args.max_cells,
+            cell_size: args.cell_size,
+            eof_behaviour: args.on_eof.into(),
+            overflow: args.overflow.into(),
+            // with 8-bit cells both modes write the same single byte
+            output_mode: if args.numeric_output {
+                OutputMode::Decimal
+            } else if args.full_cell_output {
+                OutputMode::FullCell
+            } else {
+                OutputMode::LowByte
+            },
+            input_mode: if args.full_cell_input {
+                InputMode::FullCell
+            } else {
+                InputMode::Byte
+            },
+            max_steps: args.max_steps,
+            detect_hangs: args.detect_hangs,
+            multiply_loops: args.optimize,
+            dump_tape: args.dump_tape,
+            trace: args.trace,
+            stats: args.stats,
+            coverage: args.coverage,
+            // only a run from the command line records or replays, see `prepare_run`
+            record: None,
+            replay: None,
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::Args;
+    use bft::CellSize;
+    use bft_interp::{EofBehaviour, DEFAULT_TAPE_SIZE};
+    use clap::Parser;
+    use std::path::Path;
+
+    fn parse_args(argv: &[&str]) -> Args {
+        Args::parse_from(std::iter::once("bft").chain(argv.iter().copied()))
+    }
+
+    fn prepare_with(argv: &[&str]) -> Result<Prepared, Vec<BftError>> {
+        let args = parse_args(argv);
+        let program = ProgramSource::from_arg(args.program.as_deref().unwrap());
+        prepare(program, &args.machine)
+    }
+
+    fn prepare_run_with(argv: &[&str]) -> Result<PreparedRun, Vec<BftError>> {
+        let args = parse_args(argv);
+        let program = ProgramSource::from_arg(args.program.as_deref().unwrap());
+        prepare_run(program, &args)
+    }
+
+    #[test]
+    fn test_prepare_ok() {
+        let prepared = prepare_with(&["programs/example.bf", "-e", "-c", "10"]).unwrap();
+        assert!(matches!(
+            prepared.machine.tape_kind,
+            TapeKind::Growable { .. }
+        ));
+        assert_eq!(prepared.machine.cells, 10);
+        assert_eq!(prepared.machine.cell_size, CellSize::U8);
+        assert_eq!(prepared.machine.eof_behaviour, EofBehaviour::Error);
+
+        assert_eq!(prepared.machine.output_mode, OutputMode::LowByte);
+
+        let prepared = prepare_with(&["programs/example.bf", "--grow-left"]).unwrap();
+        assert!(matches!(
+            prepared.machine.tape_kind,
+            TapeKind::GrowableBothEnds { .. }
+        ));
+
+        let prepared = prepare_with(&["programs/example.bf", "--wrap-tape"]).unwrap();
+        assert_eq!(prepared.machine.tape_kind, TapeKind::Wrapping);
+
+        let prepared = prepare_with(&["programs/example.bf", "--sparse-tape"]).unwrap();
+        assert_eq!(prepared.machine.tape_kind, TapeKind::Sparse);
+
+        let prepared = prepare_with(&["programs/example.bf", "--cell-size", "16"]).unwrap();
+        assert_eq!(prepared.machine.cell_size, CellSize::U16);
+
+        let prepared = prepare_with(&["programs/example.bf", "--full-cell-output"]).unwrap();
+        assert_eq!(prepared.machine.output_mode, OutputMode::FullCell);
+        assert_eq!(prepared.machine.input_mode, InputMode::Byte);
+
+        let prepared = prepare_with(&["programs/example.bf", "--numeric-output"]).unwrap();
+        assert_eq!(prepared.machine.output_mode, OutputMode::Decimal);
+
+        let prepared = prepare_with(&["programs/example.bf", "--full-cell-input"]).unwrap();
+        assert_eq!(prepared.machine.input_mode, InputMode::FullCell);
+
+        let prepared = prepare_with(&["programs/example.bf", "--on-eof", "minus-one"]).unwrap();
+        assert_eq!(prepared.machine.eof_behaviour, EofBehaviour::MinusOne);
+        assert_eq!(
+            prepared.program.filename(),
+            Path::new("programs/example.bf")
+        );
+    }
+
+    #[test]
+    fn test_prepare_optimize() {
+        let plain = prepare_with(&["programs/example.bf"]).unwrap();
+        let optimized = prepare_with(&["programs/example.bf", "--optimize"]).unwrap();
+        assert_eq!(
+            optimized.program.instructions(),
+            plain.program.optimized().instructions()
+        );
+        assert!(optimized.program.instructions().len() < plain.program.instructions().len());
+        assert!(optimized.machine.multiply_loops);
+        assert!(!plain.machine.multiply_loops);
+    }
+
+    #[test]
+    fn test_wrap_tape_conflicts_with_extensible() {
+        let argv = ["bft", "programs/example.bf", "--wrap-tape", "-e"];
+        assert!(Args::try_parse_from(argv).is_err());
+    }
+
+    #[test]
+    fn test_prepare_max_cells() {
+        let argv = ["programs/example.bf", "--max-cells", "100", "-c", "10"];
+        let prepared = prepare_with(&argv).unwrap();
+        assert!(matches!(
+            prepared.machine.tape_kind,
+            TapeKind::Growable { .. }
+        ));
+        assert_eq!(prepared.machine.max_cells, Some(100));
+
+        let errors = prepare_with(&["programs/example.bf", "--max-cells", "10"]).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(
+            errors[0],
+            BftError::Config(MachineConfigError::TapeAboveMaxCells {
+                tape_size: DEFAULT_TAPE_SIZE,
+                max_cells: 10
+            })
+        ));
+        assert_eq!(errors[0].category(), "usage");
+    }
+
+    #[test]
+    fn test_prepare_missing_program() {
+        let errors = prepare_with(&["programs/does_not_exist.bf"]).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(
+            &errors[0],
+            BftError::Io { path, .. } if path == Path::new("programs/does_not_exist.bf")
+        ));
+        assert_eq!(errors[0].category(), "io");
+    }
+
+    #[test]
+    fn test_prepare_parse_error() {
+        let errors = prepare_with(&["programs/unclosed_bracket.bf"]).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(errors[0], BftError::Parse { .. }));
+        assert_eq!(errors[0].category(), "parse");
+    }
+
+    #[test]
+    fn test_prepare_reports_every_error() {
+        let argv = [
+            "programs/unclosed_bracket.bf",
+            "--cells",
+            "20",
+            "--max-cells",
+            "10",
+        ];
+        let errors = prepare_with(&argv).unwrap_err();
+        assert_eq!(errors.len(), 2);
+        assert!(matches!(
+            errors[0],
+            BftError::Config(MachineConfigError::TapeAboveMaxCells {
+                tape_size: 20,
+                max_cells: 10
+            })
+        ));
+        assert!(matches!(errors[1], BftError::Parse { .. }));
+    }
+
+    #[test]
+    fn test_prepare_run() {
+        let argv = [
+            "programs/example.bf",
+            "--input-str",
+            "hi",
+            "--record",
+            "out.jsonl",
+        ];
+        let run = prepare_run_with(&argv).unwrap();
+        assert!(matches!(run.input, ProgramInput::Text(_)));
+        assert!(run.output.is_none());
+        assert_eq!(
+            run.prepared.machine.record,
+            Some(PathBuf::from("out.jsonl"))
+        );
+        assert_eq!(run.prepared.machine.replay, None);
+
+        let run = prepare_run_with(&["programs/example.bf"]).unwrap();
+        assert!(matches!(run.input, ProgramInput::Stdin));
+        assert_eq!(run.prepared.machine.record, None);
+    }
+
+    #[test]
+    fn test_prepare_run_reports_every_file() {
+        let argv = [
+            "programs/unclosed_bracket.bf",
+            "--input",
+            "does_not_exist.txt",
+            "--output",
+            "does/not/exist.out",
+        ];
+        let errors = prepare_run_with(&argv).unwrap_err();
+        assert_eq!(errors.len(), 3);
+        assert!(matches!(errors[0], BftError::Parse { .. }));
+        assert!(matches!(
+            &errors[1],
+            BftError::Io { path, .. } if path == Path::new("does_not_exist.txt")
+        ));
+        assert!(matches!(
+            &errors[2],
+            BftError::WriteFile { path, .. } if path == Path::new("does/not/exist.out")
+        ));
+
+        let argv = ["programs/example.bf", "--replay", "does_not_exist.jsonl"];
+        let errors = prepare_run_with(&argv).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(
+            &errors[0],
+            BftError::Io { path, .. } if path == Path::new("does_not_exist.jsonl")
+        ));
+    }
+
+    #[cfg(feature = "bignum")]
+    #[test]
+    fn test_prepare_big_cells_full_cell_input() {
+        let argv = ["programs/example.bf", "--cell-size", "big"];
+        assert!(prepare_with(&argv).is_ok());
+
+        let argv = [
+            "programs/example.bf",
+            "--cell-size",
+            "big",
+            "--full-cell-input",
+        ];
+        let errors = prepare_with(&argv).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(errors[0], BftError::UnboundedCellInput));
+        assert_eq!(errors[0].category(), "usage");
+    }
+}
@@ -0,0 +1,82 @@
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use bft_interp::IoEvent;
+
+use crate::BftError;
+
+/// A transcript loaded for a run to repeat
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordedRun {
+    /// The file the transcript was loaded from, which a run stopping early is reported against
+    pub path: PathBuf,
+
+    /// The events recorded during the original run
+    pub events: Vec<IoEvent>,
+}
+
+/// Write a transcript of a program's IO to `path`, one JSON object per line
+///
+/// `path`: the file to write the transcript to
+/// `transcript`: the events recorded while the program ran
+pub fn save_transcript(path: &Path, transcript: &[IoEvent]) -> Result<(), BftError> {
+    let write_error = |inner| BftError::WriteFile {
+        path: path.to_path_buf(),
+        inner,
+    };
+    let mut writer = BufWriter::new(File::create(path).map_err(write_error)?);
+    write_transcript(&mut writer, transcript).map_err(write_error)?;
+    writer.flush().map_err(write_error)
+}
+
+/// Write a transcript of a program's IO as JSON lines
+///
+/// ```
+/// use bft_interp::{IoDirection, IoEvent};
+///
+/// let event = IoEvent {
+///     direction: IoDirection::Input,
+///     byte: Some(b'a'),
+///     ip: 0,
+/// };
+/// let mut output = Vec::new();
+/// bft::write_transcript(&mut output, &[event]).unwrap();
+/// assert_eq!(output, b"{\"direction\":\"input\",\"byte\":97,\"ip\":0}\n");
+/// ```
+///
+/// `writer`: where to write the transcript
+/// `transcript`: the events recorded while the program ran
+pub fn write_transcript(mut writer: impl Write, transcript: &[IoEvent]) -> io::Result<()> {
+    for event in transcript {
+        serde_json::to_writer(&mut writer, event)?;
+        writeln!(writer)?;
+    }
+    Ok(())
+}
+
+/// Load a transcript written by [`save_transcript`], ignoring blank lines
+///
+/// `path`: the file to read the transcript from
+pub fn load_transcript(path: &Path) -> Result<Vec<IoEvent>, BftError> {
+    let read_error = |inner| BftError::Io {
+        path: path.to_path_buf(),
+        inner,
+    };
+    let reader = BufReader::new(File::open(path).map_err(read_error)?);
+    let mut transcript = Vec::new();
+    for (i, line) in reader.lines().enumerate() {
+        let line = line.map_err(read_error)?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let event = serde_json::from_str(&line).map_err(|e| {
+            let message = format!("line {}: {e}", i + 1);
+            read_error(io::Error::new(io::ErrorKind::InvalidData, message))
+        })?;
+        transcript.push(event);
+    }
+    Ok(transcript)
+}
@@ -0,0 +1,41 @@
+#![cfg(feature = "bignum")]
+
+use assert_cmd::Command;
+
+#[test]
+fn test_big_cells_grow_past_u64() {
+    // doubling one 64 times, with each loop run as a single multiply-add, reaches 2^64
+    let doublings = "[->++<]>[-<++>]<".repeat(32);
+    Command::cargo_bin("bft")
+        .unwrap()
+        .arg(format!("--eval=+{doublings}+."))
+        .args(["--cell-size", "big", "--numeric-output", "--optimize"])
+        .assert()
+        .success()
+        .stdout("18446744073709551617 \n");
+}
+
+#[test]
+fn test_big_cells_reject_full_cell_input() {
+    let output = Command::cargo_bin("bft")
+        .unwrap()
+        .args(["--eval", ",.", "--cell-size", "big", "--full-cell-input"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(2));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains("[usage] full-cell input needs cells of a fixed width"),
+        "{stderr}"
+    );
+}
+
+#[test]
+fn test_big_cells_stop_at_zero() {
+    Command::cargo_bin("bft")
+        .unwrap()
+        .args(["--eval=--+.", "--cell-size", "big", "--numeric-output"])
+        .assert()
+        .success()
+        .stdout("1 \n");
+}
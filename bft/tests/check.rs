@@ -0,0 +1,51 @@
+use assert_cmd::Command;
+
+#[test]
+fn test_check_reports_each_file_in_order() {
+    let output = Command::cargo_bin("bft")
+        .unwrap()
+        .args([
+            "check",
+            "programs/example.bf",
+            "programs/unclosed_bracket.bf",
+            "-",
+        ])
+        .write_stdin("+[>.<-]")
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(3));
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "\
+programs/example.bf: OK, 62 instructions, max depth 4
+programs/unclosed_bracket.bf: Error in input file programs/unclosed_bracket.bf, dangling open bracket found at line 1 column 12
+-: OK, 7 instructions, max depth 1
+"
+    );
+}
+
+#[test]
+fn test_check_all_ok() {
+    Command::cargo_bin("bft")
+        .unwrap()
+        .args(["check", "programs/example.bf", "programs/rot13.bf"])
+        .assert()
+        .success()
+        .stdout(
+            "programs/example.bf: OK, 62 instructions, max depth 4\n\
+             programs/rot13.bf: OK, 190 instructions, max depth 5\n",
+        );
+}
+
+#[test]
+fn test_check_missing_file() {
+    Command::cargo_bin("bft")
+        .unwrap()
+        .args([
+            "check",
+            "programs/does_not_exist.bf",
+            "programs/unopened_bracket.bf",
+        ])
+        .assert()
+        .code(5);
+}
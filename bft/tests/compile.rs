@@ -0,0 +1,144 @@
+use assert_cmd::Command;
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+    process::{self, Stdio},
+};
+
+/// Set to skip the tests which build the translated programs
+const SKIP_VAR: &str = "BFT_SKIP_COMPILE_TESTS";
+
+/// Translate a program with `bft compile`, returning the path of the generated source
+fn translate(program: &str, extension: &str, args: &[&str]) -> PathBuf {
+    let name = Path::new(program).file_stem().unwrap().to_str().unwrap();
+    let source = Path::new(env!("CARGO_TARGET_TMPDIR")).join(format!("{name}.{extension}"));
+    Command::cargo_bin("bft")
+        .unwrap()
+        .args(["compile", program, "-o"])
+        .arg(&source)
+        .args(args)
+        .assert()
+        .success();
+    source
+}
+
+/// Build a translated program with `compiler`, returning the path of the executable
+///
+/// Returns None if the tests are skipped or the compiler isn't installed.
+fn build(compiler: &str, source: &Path) -> Option<PathBuf> {
+    if std::env::var_os(SKIP_VAR).is_some() {
+        return None;
+    }
+    let executable = source.with_extension(format!("{compiler}.out"));
+    let status = process::Command::new(compiler)
+        .arg("-o")
+        .arg(&executable)
+        .arg(source)
+        .status();
+    match status {
+        Err(e) => {
+            eprintln!("skipping, {compiler} couldn't be run: {e}");
+            None
+        }
+        Ok(status) => {
+            assert!(status.success(), "{compiler} failed to build {source:?}");
+            Some(executable)
+        }
+    }
+}
+
+/// Run a program with the interpreter and a built translation, checking they agree
+fn compare(program: &str, executable: &Path, input: &[u8], args: &[&str]) {
+    let expected = Command::cargo_bin("bft")
+        .unwrap()
+        .arg(program)
+        .arg("--no-trailing-newline")
+        .args(args)
+        .write_stdin(input)
+        .output()
+        .unwrap();
+    assert!(expected.status.success());
+
+    let mut child = process::Command::new(executable)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(input).unwrap();
+    let actual = child.wait_with_output().unwrap();
+    assert!(actual.status.success());
+    assert_eq!(actual.stdout, expected.stdout);
+}
+
+#[test]
+fn test_compile_hello_world_rust() {
+    let source = translate("programs/example.bf", "rs", &["--optimize"]);
+    if let Some(executable) = build("rustc", &source) {
+        compare("programs/example.bf", &executable, b"", &[]);
+    }
+}
+
+#[test]
+fn test_compile_hello_world_c() {
+    let source = translate("programs/example.bf", "c", &["--emit", "c"]);
+    if let Some(executable) = build("cc", &source) {
+        compare("programs/example.bf", &executable, b"", &[]);
+    }
+}
+
+#[test]
+fn test_compile_input_and_wide_cells() {
+    let args = [
+        "--on-eof",
+        "minus-one",
+        "--cell-size",
+        "16",
+        "-e",
+        "-c",
+        "8",
+    ];
+    let source = translate(
+        "programs/rot13.bf",
+        "c",
+        &[&["--emit", "c"][..], &args].concat(),
+    );
+    if let Some(executable) = build("cc", &source) {
+        compare("programs/rot13.bf", &executable, b"Hello, World!", &args);
+    }
+}
+
+#[test]
+fn test_compile_input_rust() {
+    let args = ["--on-eof", "zero", "--cell-size", "32", "-e"];
+    let source = translate("programs/numwarp.bf", "rs", &args);
+    if let Some(executable) = build("rustc", &source) {
+        compare("programs/numwarp.bf", &executable, b"123-45\n", &args);
+    }
+}
+
+#[test]
+fn test_compile_to_stdout() {
+    let output = Command::cargo_bin("bft")
+        .unwrap()
+        .args(["compile", "programs/example.bf", "--emit", "c"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let source = String::from_utf8(output.stdout).unwrap();
+    assert!(source.starts_with("// Generated by bft from programs/example.bf\n"));
+    assert!(source.contains("while (tape[p] != 0) {"));
+}
+
+#[cfg(feature = "bignum")]
+#[test]
+fn test_compile_rejects_big_cells() {
+    let output = Command::cargo_bin("bft")
+        .unwrap()
+        .args(["compile", "programs/example.bf", "--cell-size", "big"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .contains("fixed width"));
+}
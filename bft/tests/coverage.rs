@@ -0,0 +1,43 @@
+use assert_cmd::Command;
+
+#[test]
+fn test_coverage() {
+    let output = Command::cargo_bin("bft")
+        .unwrap()
+        .args([
+            "--eval",
+            "++[-][>+++<-]>.",
+            "--coverage",
+            "--numeric-output",
+        ])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "0 \n");
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        "coverage: 8 of 15 instructions executed\n  <eval>:1:7: >+++<-]\n"
+    );
+}
+
+#[test]
+fn test_full_coverage() {
+    Command::cargo_bin("bft")
+        .unwrap()
+        .args(["programs/example.bf", "--coverage"])
+        .assert()
+        .success()
+        .stderr("coverage: 62 of 62 instructions executed\n");
+}
+
+#[test]
+fn test_optimized_coverage() {
+    Command::cargo_bin("bft")
+        .unwrap()
+        .args(["--eval", "++[->+++<]>.", "--optimize", "--coverage"])
+        .args(["--numeric-output"])
+        .assert()
+        .success()
+        .stdout("6 \n")
+        .stderr("coverage: 9 of 9 instructions executed\n");
+}
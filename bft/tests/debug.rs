@@ -0,0 +1,95 @@
+use assert_cmd::Command;
+
+#[test]
+fn test_debug_session() {
+    let script = "\
+where
+break 19:6
+continue
+print 3
+tape 2 3
+step 2
+where
+break 999
+continue
+quit
+";
+    let output = Command::cargo_bin("bft")
+        .unwrap()
+        .args(["debug", "programs/example.bf"])
+        .write_stdin(script)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    // the program's output goes to stdout, leaving the debugger's reports on stderr
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "hello world");
+
+    let log = String::from_utf8(output.stderr).unwrap();
+    let lines: Vec<&str> = log.split("(bft) ").filter(|l| !l.is_empty()).collect();
+    assert_eq!(
+        lines,
+        [
+            "ip=0 dp=0 at programs/example.bf:8:4 '+'\n",
+            "breakpoint set at ip=29 (programs/example.bf:19:6 '.')\n",
+            "hit breakpoint at ip=29\nip=29 dp=6 at programs/example.bf:19:6 '.'\n",
+            "cell[3] = 118\n",
+            "tape[2..5] = [0, 118, 33]\n",
+            "ip=31 dp=6 at programs/example.bf:19:8 '-'\n",
+            "ip=31 dp=6 at programs/example.bf:19:8 '-'\n",
+            "error: no instruction at ip=999\n",
+            "ip=62 dp=6 program halted\n",
+        ]
+    );
+}
+
+#[test]
+fn test_debug_missing_program() {
+    Command::cargo_bin("bft")
+        .unwrap()
+        .args(["debug", "programs/does_not_exist.bf"])
+        .write_stdin("quit\n")
+        .assert()
+        .code(5);
+}
+
+#[test]
+fn test_debug_invalid_machine_settings() {
+    Command::cargo_bin("bft")
+        .unwrap()
+        .args([
+            "debug",
+            "programs/example.bf",
+            "--cells",
+            "20",
+            "--max-cells",
+            "10",
+        ])
+        .write_stdin("quit\n")
+        .assert()
+        .code(2);
+}
+
+#[test]
+fn test_debug_step_limit() {
+    let output = Command::cargo_bin("bft")
+        .unwrap()
+        .args(["debug", "programs/example.bf", "--max-steps", "5"])
+        .write_stdin("break 2\ncontinue\ncontinue\nstep\nquit\n")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    // steps taken before the breakpoint count towards the limit
+    let log = String::from_utf8(output.stderr).unwrap();
+    let lines: Vec<&str> = log.split("(bft) ").filter(|l| !l.is_empty()).collect();
+    assert_eq!(
+        lines,
+        [
+            "breakpoint set at ip=2 (programs/example.bf:8:6 '-')\n",
+            "hit breakpoint at ip=2\nip=2 dp=0 at programs/example.bf:8:6 '-'\n",
+            "ip=23 dp=1 error: reached the step limit of 5\n",
+            "ip=23 dp=1 error: reached the step limit of 5\n",
+        ]
+    );
+}
@@ -0,0 +1,28 @@
+use assert_cmd::Command;
+
+#[test]
+fn test_debug_chars() {
+    let output = Command::cargo_bin("bft")
+        .unwrap()
+        .args(["--eval", "+++#>++#", "--enable-debug-chars"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        "\
+<eval>:1:4: ip 3, dp 0, cells 0..8: [3] 0 0 0 0 0 0 0
+<eval>:1:8: ip 7, dp 1, cells 0..9: 3 [2] 0 0 0 0 0 0 0
+"
+    );
+}
+
+#[test]
+fn test_debug_chars_are_comments_by_default() {
+    Command::cargo_bin("bft")
+        .unwrap()
+        .args(["--eval", "+++#>++#"])
+        .assert()
+        .success()
+        .stderr("");
+}
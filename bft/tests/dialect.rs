@@ -0,0 +1,68 @@
+use assert_cmd::Command;
+
+#[test]
+fn test_ook_hello_world() {
+    let output = Command::cargo_bin("bft")
+        .unwrap()
+        .args(["--dialect", "ook", "programs/hello_world.ook"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let expected = std::fs::read("programs/hello_world.out").unwrap();
+    assert_eq!(output.stdout, expected);
+}
+
+#[test]
+fn test_translate_round_trip() {
+    let custom = "right left inc dec out in loop end";
+    let translated = format!("{}/example.custom", env!("CARGO_TARGET_TMPDIR"));
+    Command::cargo_bin("bft")
+        .unwrap()
+        .args(["translate", "programs/example.bf", "--to", custom])
+        .args(["-o", &translated])
+        .assert()
+        .success();
+
+    let output = Command::cargo_bin("bft")
+        .unwrap()
+        .args(["translate", &translated, "--from", custom, "--to", "bf"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "+[-[<<[+[--->]-[<<<]]]>>>-]>-.---.>..>.<<<<-.<+.>>>>>.>.<<.<-.\n"
+    );
+}
+
+#[test]
+fn test_translate_to_ook() {
+    let output = Command::cargo_bin("bft")
+        .unwrap()
+        .args(["translate", "programs/hello_world.bf", "--to", "ook"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let ook = String::from_utf8(output.stdout).unwrap();
+    assert!(ook.starts_with("Ook. Ook. Ook. Ook."));
+}
+
+#[test]
+fn test_invalid_ook_is_a_parse_error() {
+    Command::cargo_bin("bft")
+        .unwrap()
+        .args(["--dialect", "ook", "--eval", "Ook? Ook?"])
+        .assert()
+        .code(3);
+}
+
+#[test]
+fn test_invalid_token_map() {
+    for tokens in ["a b c d e f g a", "a b c d e f g"] {
+        Command::cargo_bin("bft")
+            .unwrap()
+            .args(["translate", "programs/example.bf", "--to", tokens])
+            .assert()
+            .code(2);
+    }
+}
@@ -0,0 +1,112 @@
+use assert_cmd::Command;
+
+fn bft(args: &[&str]) -> std::process::Output {
+    Command::cargo_bin("bft")
+        .unwrap()
+        .args(args)
+        .write_stdin("")
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn test_usage_error_exit_code() {
+    let output = bft(&["programs/example.bf", "--wrap-tape", "-e"]);
+    assert_eq!(output.status.code(), Some(2));
+
+    let output = bft(&["programs/example.bf", "--max-cells", "10"]);
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn test_parse_error_exit_code_and_caret() {
+    let output = bft(&["programs/unopened_bracket.bf"]);
+    assert_eq!(output.status.code(), Some(3));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert_eq!(
+        stderr,
+        "Encountered 1 error(s) while preparing to run programs/unopened_bracket.bf:
+  [parse] Error in input file programs/unopened_bracket.bf, dangling close bracket found at line 1 column 20
+    1 | +++++[[[]]][][][][]]
+      |                    ^
+"
+    );
+}
+
+#[test]
+fn test_runtime_error_exit_code() {
+    let output = bft(&["programs/example.bf", "--max-steps", "10"]);
+    assert_eq!(output.status.code(), Some(4));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.starts_with("error: programs/example.bf:"));
+}
+
+#[test]
+fn test_io_error_exit_code() {
+    let output = bft(&["programs/does_not_exist.bf"]);
+    assert_eq!(output.status.code(), Some(5));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("failed to read programs/does_not_exist.bf"));
+}
+
+#[test]
+fn test_file_errors_reported_together() {
+    let output = bft(&[
+        "programs/unclosed_bracket.bf",
+        "--input",
+        "does_not_exist.txt",
+        "--output",
+        "does/not/exist.out",
+    ]);
+    assert_eq!(output.status.code(), Some(3));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.starts_with("Encountered 3 error(s)"), "{stderr}");
+    assert!(
+        stderr.contains("failed to read does_not_exist.txt"),
+        "{stderr}"
+    );
+    assert!(stderr.contains("does/not/exist.out"), "{stderr}");
+}
+
+#[test]
+fn test_output_kept_when_preparing_fails() {
+    let path = format!("{}/kept.out", env!("CARGO_TARGET_TMPDIR"));
+    std::fs::write(&path, "unchanged").unwrap();
+    let output = bft(&["programs/unclosed_bracket.bf", "--output", &path]);
+    assert_eq!(output.status.code(), Some(3));
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "unchanged");
+}
+
+#[test]
+fn test_output_not_created_when_preparing_fails() {
+    let path = format!("{}/not_created.out", env!("CARGO_TARGET_TMPDIR"));
+    let _ = std::fs::remove_file(&path);
+    let output = bft(&["programs/unclosed_bracket.bf", "--output", &path]);
+    assert_eq!(output.status.code(), Some(3));
+    assert!(!std::path::Path::new(&path).exists());
+}
+
+#[test]
+fn test_every_error_reported() {
+    let output = bft(&[
+        "programs/unclosed_bracket.bf",
+        "--cells",
+        "20",
+        "--max-cells",
+        "10",
+    ]);
+    // the exit status is that of the first error
+    assert_eq!(output.status.code(), Some(2));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.starts_with(
+            "Encountered 2 error(s) while preparing to run programs/unclosed_bracket.bf:\n"
+        ),
+        "{stderr}"
+    );
+    assert!(
+        stderr.contains("\n  [usage] the tape can't start with 20 cells"),
+        "{stderr}"
+    );
+    assert!(stderr.contains("\n  [parse] "), "{stderr}");
+}
@@ -0,0 +1,38 @@
+use assert_cmd::Command;
+
+#[test]
+fn test_eval() {
+    Command::cargo_bin("bft")
+        .unwrap()
+        .args(["--eval", "++++++++[>++++++++<-]>+."])
+        .assert()
+        .success()
+        .stdout("A\n");
+}
+
+#[test]
+fn test_eval_parse_error() {
+    let output = Command::cargo_bin("bft")
+        .unwrap()
+        .args(["--eval", "+++[>+<-]]"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(3));
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        "Encountered 1 error(s) while preparing to run <eval>:
+  [parse] Error in input file <eval>, dangling close bracket found at line 1 column 10
+    1 | +++[>+<-]]
+      |          ^
+"
+    );
+}
+
+#[test]
+fn test_eval_conflicts_with_program() {
+    Command::cargo_bin("bft")
+        .unwrap()
+        .args(["programs/example.bf", "--eval", "+"])
+        .assert()
+        .code(2);
+}
@@ -0,0 +1,37 @@
+use assert_cmd::Command;
+use bft_types::Program;
+
+fn bft_fmt(args: &[&str]) -> assert_cmd::assert::Assert {
+    Command::cargo_bin("bft")
+        .unwrap()
+        .arg("fmt")
+        .args(args)
+        .assert()
+}
+
+#[test]
+fn test_fmt_in_place_then_check() {
+    let path = format!("{}/example.fmt.bf", env!("CARGO_TARGET_TMPDIR"));
+    std::fs::copy("programs/example.bf", &path).unwrap();
+
+    bft_fmt(&["--check", &path]).code(1);
+    bft_fmt(&["--width", "20", &path]).success();
+    bft_fmt(&["--check", "--width", "20", &path]).success();
+    bft_fmt(&["--check", "--width", "40", &path]).code(1);
+
+    let original = Program::from_file("programs/example.bf").unwrap();
+    let formatted = Program::from_file(&path).unwrap();
+    assert_eq!(formatted.instructions(), original.instructions());
+
+    let source = std::fs::read_to_string(&path).unwrap();
+    assert!(source.starts_with("This is your input file\n\nAnything"));
+    assert!(source.lines().any(|line| line == "        <<"));
+}
+
+#[test]
+fn test_fmt_parse_error_leaves_file() {
+    bft_fmt(&["programs/unclosed_bracket.bf"]).code(3);
+    assert!(std::fs::read_to_string("programs/unclosed_bracket.bf")
+        .unwrap()
+        .contains('['));
+}
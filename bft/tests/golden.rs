@@ -0,0 +1,87 @@
+//! Runs every program under `programs/` which has a sibling `.out` file, checking it writes
+//! exactly what that file holds
+//!
+//! A sibling `.in` file is used as the program's input, and `,` sets the cell to minus one once
+//! the input runs out. Set `UPDATE_GOLDEN=1` to write each program's output to its `.out` file
+//! rather than checking it.
+
+use bft::{run, ProgramSource, RunSettings};
+use bft_interp::EofBehaviour;
+use std::{fs, path::Path};
+
+/// The directory the programs are discovered in
+const PROGRAMS: &str = "programs";
+
+/// The most instructions a program may execute, so that a broken interpreter can't hang the test
+const MAX_STEPS: u64 = 100_000_000;
+
+/// Run a program with the golden settings, returning what it wrote
+fn run_program(path: &Path, input: &[u8], optimize: bool) -> Result<Vec<u8>, String> {
+    let mut settings = RunSettings::new(ProgramSource::File(path.to_path_buf()));
+    settings.optimize = optimize;
+    settings.machine.multiply_loops = optimize;
+    settings.machine.eof_behaviour = EofBehaviour::MinusOne;
+    settings.machine.max_steps = Some(MAX_STEPS);
+    let mut output = Vec::new();
+    run(settings, input, &mut output)
+        .map(|_| output)
+        .map_err(|e| e.to_string())
+}
+
+#[test]
+fn test_golden_outputs() {
+    let update = std::env::var_os("UPDATE_GOLDEN").is_some_and(|v| v == "1");
+    let mut programs: Vec<_> = fs::read_dir(PROGRAMS)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "bf"))
+        .collect();
+    programs.sort();
+
+    let mut checked = 0;
+    let mut failures = Vec::new();
+    for path in &programs {
+        let expected_path = path.with_extension("out");
+        let expected = fs::read(&expected_path).ok();
+        if expected.is_none() && !update {
+            eprintln!("skipped {}: no {}", path.display(), expected_path.display());
+            continue;
+        }
+        let input = fs::read(path.with_extension("in")).unwrap_or_default();
+
+        let output = match run_program(path, &input, false) {
+            Ok(output) => output,
+            Err(e) if expected.is_none() => {
+                eprintln!("skipped {}: {e}", path.display());
+                continue;
+            }
+            Err(e) => {
+                failures.push(format!("{}: {e}", path.display()));
+                continue;
+            }
+        };
+        if update {
+            fs::write(&expected_path, &output).unwrap();
+        } else if expected.as_ref() != Some(&output) {
+            failures.push(format!(
+                "{} wrote {:?}",
+                path.display(),
+                String::from_utf8_lossy(&output)
+            ));
+        }
+
+        match run_program(path, &input, true) {
+            Ok(optimized) if optimized == output => {}
+            Ok(optimized) => failures.push(format!(
+                "{} wrote {:?} when optimized",
+                path.display(),
+                String::from_utf8_lossy(&optimized)
+            )),
+            Err(e) => failures.push(format!("{} when optimized: {e}", path.display())),
+        }
+        checked += 1;
+    }
+
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+    assert!(checked >= 6, "only {checked} programs have expected output");
+}
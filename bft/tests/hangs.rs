@@ -0,0 +1,24 @@
+use assert_cmd::Command;
+
+#[test]
+fn test_detect_hangs() {
+    Command::cargo_bin("bft")
+        .unwrap()
+        .args(["--eval", "+[]", "--detect-hangs"])
+        .assert()
+        .code(4)
+        .stderr(
+            "error: <eval>:1:3: loop repeated 1000 times without changing anything, stopped at \
+             ']'\n",
+        );
+}
+
+#[test]
+fn test_detect_hangs_allows_slow_programs() {
+    Command::cargo_bin("bft")
+        .unwrap()
+        .args(["programs/example.bf", "--detect-hangs"])
+        .assert()
+        .success()
+        .stdout("hello world\n");
+}
@@ -0,0 +1,58 @@
+use assert_cmd::Command;
+
+#[test]
+fn test_input_str() {
+    Command::cargo_bin("bft")
+        .unwrap()
+        .args(["programs/rot13.bf", "--on-eof", "minus-one"])
+        .args(["--input-str", "Hello, World!"])
+        .write_stdin("ignored")
+        .assert()
+        .success()
+        .stdout("Uryyb, Jbeyq!\n");
+}
+
+#[test]
+fn test_input_file() {
+    let path = format!("{}/rot13.input", env!("CARGO_TARGET_TMPDIR"));
+    std::fs::write(&path, "Uryyb\n").unwrap();
+    Command::cargo_bin("bft")
+        .unwrap()
+        .args([
+            "programs/rot13.bf",
+            "--on-eof",
+            "minus-one",
+            "--input",
+            &path,
+        ])
+        .assert()
+        .success()
+        .stdout("Hello\n");
+}
+
+#[test]
+fn test_input_follows_on_eof() {
+    Command::cargo_bin("bft")
+        .unwrap()
+        .args(["--eval", ",,.", "--input-str", "x"])
+        .assert()
+        .code(4);
+}
+
+#[test]
+fn test_input_missing_file() {
+    Command::cargo_bin("bft")
+        .unwrap()
+        .args(["programs/example.bf", "--input", "does_not_exist.txt"])
+        .assert()
+        .code(5);
+}
+
+#[test]
+fn test_input_conflicts_with_input_str() {
+    Command::cargo_bin("bft")
+        .unwrap()
+        .args(["programs/example.bf", "--input", "a", "--input-str", "b"])
+        .assert()
+        .code(2);
+}
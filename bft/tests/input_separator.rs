@@ -0,0 +1,43 @@
+use assert_cmd::Command;
+
+fn bft(args: &[&str]) -> assert_cmd::assert::Assert {
+    Command::cargo_bin("bft")
+        .unwrap()
+        .args(args)
+        .write_stdin("from stdin")
+        .assert()
+}
+
+#[test]
+fn test_embedded_input() {
+    bft(&[
+        "--eval",
+        ",[.,]!hello",
+        "--input-separator",
+        "--on-eof",
+        "zero",
+    ])
+    .success()
+    .stdout("hello\n");
+}
+
+#[test]
+fn test_input_str_overrides_embedded_input() {
+    let args = [
+        "--eval",
+        ",[.,]!hello",
+        "--input-separator",
+        "--on-eof",
+        "zero",
+    ];
+    bft(&[&args[..], &["--input-str", "bye"]].concat())
+        .success()
+        .stdout("bye\n");
+}
+
+#[test]
+fn test_separator_is_opt_in() {
+    bft(&["--eval", ",[.,]!hello", "--on-eof", "zero"])
+        .success()
+        .stdout("from stdin\n");
+}
@@ -0,0 +1,37 @@
+use assert_cmd::Command;
+
+#[test]
+fn test_inspect_table() {
+    let output = Command::cargo_bin("bft")
+        .unwrap()
+        .args(["inspect", "programs/example.bf"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "\
+instructions        62
+loops                5
+max depth            4
+io                  11
+'+'                  3
+','                  0
+'-'                 12
+'.'                 11
+'<'                 13
+'>'                 13
+'['                  5
+']'                  5
+"
+    );
+}
+
+#[test]
+fn test_inspect_parse_error() {
+    Command::cargo_bin("bft")
+        .unwrap()
+        .args(["inspect", "programs/unclosed_bracket.bf"])
+        .assert()
+        .code(3);
+}
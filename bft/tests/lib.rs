@@ -0,0 +1,63 @@
+use std::io;
+
+use bft::{run, BftError, CellSize, ProgramSource, RunSettings};
+use bft_interp::{InterpretError, OutputMode, TapeKind};
+
+#[test]
+fn test_run_file() {
+    let settings = RunSettings::new(ProgramSource::File("programs/example.bf".into()));
+    let mut output = Vec::new();
+    let report = run(settings, io::empty(), &mut output).unwrap();
+    assert_eq!(output, b"hello world");
+    assert_eq!(report.steps, 10623);
+}
+
+#[test]
+fn test_run_source_with_input() {
+    let mut settings = RunSettings::new(ProgramSource::File("programs/rot13.bf".into()));
+    settings.optimize = true;
+    settings.machine.eof_behaviour = bft_interp::EofBehaviour::MinusOne;
+    let mut output = Vec::new();
+    run(settings, &b"Hello, World!"[..], &mut output).unwrap();
+    assert_eq!(output, b"Uryyb, Jbeyq!");
+}
+
+#[test]
+fn test_run_wide_cells() {
+    let mut settings = RunSettings::new(ProgramSource::Source {
+        name: "<test>".into(),
+        source: "->-<.>.".to_owned(),
+    });
+    settings.machine.cell_size = CellSize::U16;
+    settings.machine.output_mode = OutputMode::FullCell;
+    settings.machine.tape_kind = TapeKind::Sparse;
+    let mut output = Vec::new();
+    let report = run(settings, io::empty(), &mut output).unwrap();
+    assert_eq!(output, [0xFF; 4]);
+    assert_eq!(report.steps, 7);
+}
+
+#[test]
+fn test_run_errors() {
+    let settings = RunSettings::new(ProgramSource::File("programs/does_not_exist.bf".into()));
+    let err = run(settings, io::empty(), io::sink()).unwrap_err();
+    assert!(matches!(err, BftError::Io { .. }));
+
+    let settings = RunSettings::new(ProgramSource::File("programs/unclosed_bracket.bf".into()));
+    let err = run(settings, io::empty(), io::sink()).unwrap_err();
+    assert!(matches!(err, BftError::Parse { .. }));
+
+    let settings = RunSettings::new(ProgramSource::Source {
+        name: "<test>".into(),
+        source: "<".to_owned(),
+    });
+    let err = run(settings, io::empty(), io::sink()).unwrap_err();
+    assert!(matches!(
+        err,
+        BftError::Interpret(InterpretError::TapeRunOffError { .. })
+    ));
+    assert_eq!(
+        err.to_string(),
+        "<test>:1:1: tape run off while executing '<'"
+    );
+}
@@ -0,0 +1,76 @@
+use assert_cmd::Command;
+
+#[test]
+fn test_minify_still_runs() {
+    let minified = format!("{}/example.min.bf", env!("CARGO_TARGET_TMPDIR"));
+    Command::cargo_bin("bft")
+        .unwrap()
+        .args(["minify", "programs/example.bf", "--cancel", "-o", &minified])
+        .assert()
+        .success();
+
+    let original_len = std::fs::metadata("programs/example.bf").unwrap().len();
+    let minified_len = std::fs::metadata(&minified).unwrap().len();
+    assert!(minified_len < original_len);
+
+    let output = Command::cargo_bin("bft")
+        .unwrap()
+        .arg(&minified)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "hello world\n");
+}
+
+#[test]
+fn test_minify_to_stdout() {
+    let output = Command::cargo_bin("bft")
+        .unwrap()
+        .args(["minify", "programs/example.bf"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "+[-[<<[+[--->]-[<<<]]]>>>-]>-.---.>..>.<<<<-.<+.>>>>>.>.<<.<-.\n"
+    );
+}
+
+#[test]
+fn test_minify_parse_error() {
+    Command::cargo_bin("bft")
+        .unwrap()
+        .args(["minify", "programs/unopened_bracket.bf"])
+        .assert()
+        .code(3);
+}
+
+#[test]
+fn test_cancel_assumes_wrapping_cells() {
+    let program = format!("{}/overflow.bf", env!("CARGO_TARGET_TMPDIR"));
+    std::fs::write(&program, "-+.").unwrap();
+    let output = Command::cargo_bin("bft")
+        .unwrap()
+        .args(["minify", &program, "--cancel"])
+        .output()
+        .unwrap();
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), ".\n");
+
+    let run = |source: &str, overflow: &str| {
+        Command::cargo_bin("bft")
+            .unwrap()
+            .arg(format!("--eval={source}"))
+            .args(["--overflow", overflow, "--no-trailing-newline"])
+            .output()
+            .unwrap()
+    };
+
+    // with wrapping cells the cancelled pair made no difference
+    assert_eq!(run("-+.", "wrap").stdout, run(".", "wrap").stdout);
+
+    // but without them it did
+    assert_eq!(run("-+.", "saturate").stdout, [1]);
+    assert_eq!(run(".", "saturate").stdout, [0]);
+    assert_eq!(run("-+.", "error").status.code(), Some(4));
+    assert!(run(".", "error").status.success());
+}
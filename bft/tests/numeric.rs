@@ -0,0 +1,28 @@
+use assert_cmd::Command;
+
+fn bft(args: &[&str]) -> assert_cmd::assert::Assert {
+    Command::cargo_bin("bft").unwrap().args(args).assert()
+}
+
+#[test]
+fn test_numeric_output() {
+    let source = format!(".>{}.>-.", "+".repeat(65));
+    bft(&["--eval", &source, "--numeric-output"])
+        .success()
+        .stdout("0 65 255 \n");
+    bft(&["--eval", &source])
+        .success()
+        .stdout(&b"\0A\xFF\n"[..]);
+}
+
+#[test]
+fn test_numeric_output_wide_cells() {
+    bft(&["--eval=-.", "--numeric-output", "--cell-size", "64"])
+        .success()
+        .stdout("18446744073709551615 \n");
+}
+
+#[test]
+fn test_numeric_output_conflicts_with_full_cell_output() {
+    bft(&["--eval", ".", "--numeric-output", "--full-cell-output"]).code(2);
+}
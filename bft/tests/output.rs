@@ -0,0 +1,61 @@
+use assert_cmd::Command;
+
+/// Writes "AB" with no newline, then a zero byte and a 0xFF byte
+const BYTES: &str = "++++++++[>++++++++<-]>+.+.[-].-.";
+
+#[test]
+fn test_output_file_is_raw() {
+    let path = format!("{}/bytes.out", env!("CARGO_TARGET_TMPDIR"));
+    Command::cargo_bin("bft")
+        .unwrap()
+        .args(["--eval", BYTES, "--output", &path])
+        .assert()
+        .success()
+        .stdout("");
+    assert_eq!(std::fs::read(&path).unwrap(), b"AB\0\xFF");
+}
+
+#[test]
+fn test_stdout_gets_trailing_newline() {
+    Command::cargo_bin("bft")
+        .unwrap()
+        .args(["--eval", BYTES])
+        .assert()
+        .success()
+        .stdout(&b"AB\0\xFF\n"[..]);
+}
+
+#[test]
+fn test_no_trailing_newline() {
+    Command::cargo_bin("bft")
+        .unwrap()
+        .args(["--eval", BYTES, "--no-trailing-newline"])
+        .assert()
+        .success()
+        .stdout(&b"AB\0\xFF"[..]);
+}
+
+#[test]
+fn test_no_trailing_newline_with_output_file() {
+    let path = format!("{}/bytes_no_newline.out", env!("CARGO_TARGET_TMPDIR"));
+    for extra in [None, Some("--no-trailing-newline")] {
+        Command::cargo_bin("bft")
+            .unwrap()
+            .args(["--eval", BYTES, "--output", &path])
+            .args(extra)
+            .assert()
+            .success()
+            .stdout("");
+        // the file is raw whether or not the flag is given
+        assert_eq!(std::fs::read(&path).unwrap(), b"AB\0\xFF");
+    }
+}
+
+#[test]
+fn test_output_unwritable() {
+    Command::cargo_bin("bft")
+        .unwrap()
+        .args(["--eval", BYTES, "--output", "does/not/exist.out"])
+        .assert()
+        .code(5);
+}
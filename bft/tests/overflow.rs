@@ -0,0 +1,26 @@
+use assert_cmd::Command;
+
+fn run_with_overflow(mode: &str) -> assert_cmd::assert::Assert {
+    Command::cargo_bin("bft")
+        .unwrap()
+        .args(["--eval=-.", "--numeric-output", "--overflow", mode])
+        .assert()
+}
+
+#[test]
+fn test_overflow_wrap() {
+    run_with_overflow("wrap").success().stdout("255 \n");
+}
+
+#[test]
+fn test_overflow_saturate() {
+    run_with_overflow("saturate").success().stdout("0 \n");
+}
+
+#[test]
+fn test_overflow_error() {
+    run_with_overflow("error")
+        .code(4)
+        .stdout("")
+        .stderr("error: <eval>:1:1: cell 0 overflowed while executing '-'\n");
+}
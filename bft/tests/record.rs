@@ -0,0 +1,131 @@
+use std::{fs, path::PathBuf};
+
+use assert_cmd::Command;
+
+fn transcript_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(name)
+}
+
+#[test]
+fn test_record_then_replay_rot13() {
+    let transcript = transcript_path("rot13.jsonl");
+    let recorded = Command::cargo_bin("bft")
+        .unwrap()
+        .args([
+            "programs/rot13.bf",
+            "--on-eof",
+            "minus-one",
+            "--input-str",
+            "Hello",
+        ])
+        .arg("--record")
+        .arg(&transcript)
+        .output()
+        .unwrap();
+    assert!(recorded.status.success());
+    assert_eq!(String::from_utf8(recorded.stdout).unwrap(), "Uryyb\n");
+
+    let lines = fs::read_to_string(&transcript).unwrap();
+    let lines: Vec<&str> = lines.lines().collect();
+    // five characters in and out, then the end of the input
+    assert_eq!(lines.len(), 11);
+    assert_eq!(lines[0], r#"{"direction":"input","byte":72,"ip":1}"#);
+    assert!(lines[1].starts_with(r#"{"direction":"output","byte":85,"ip":"#));
+    assert_eq!(lines[10], r#"{"direction":"input","byte":null,"ip":187}"#);
+
+    // stdin is ignored, the input comes from the transcript
+    Command::cargo_bin("bft")
+        .unwrap()
+        .args(["programs/rot13.bf", "--on-eof", "minus-one"])
+        .arg("--replay")
+        .arg(&transcript)
+        .write_stdin("something else")
+        .assert()
+        .success()
+        .stdout("Uryyb\n");
+}
+
+#[test]
+fn test_replay_mismatch() {
+    let transcript = transcript_path("short.jsonl");
+    fs::write(
+        &transcript,
+        r#"{"direction":"input","byte":104,"ip":0}
+{"direction":"output","byte":104,"ip":1}
+"#,
+    )
+    .unwrap();
+
+    // reading more than was recorded is a runtime error at the `,` which tried
+    Command::cargo_bin("bft")
+        .unwrap()
+        .args(["--eval", ",.,."])
+        .arg("--replay")
+        .arg(&transcript)
+        .assert()
+        .code(4);
+
+    // as is writing something other than was recorded
+    let output = Command::cargo_bin("bft")
+        .unwrap()
+        .args(["--eval", ",+."])
+        .arg("--replay")
+        .arg(&transcript)
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(4));
+    assert!(output.stdout.is_empty());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains("wrote 0x69 by ip 2 where the recording has a write of 0x68 by ip 1"),
+        "{stderr}"
+    );
+
+    // while stopping before repeating everything which was is an error with the transcript
+    let output = Command::cargo_bin("bft")
+        .unwrap()
+        .args(["--eval", ","])
+        .arg("--replay")
+        .arg(&transcript)
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(5));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains("stopped with 1 recorded event(s) left, the first a write of 0x68 by ip 1"),
+        "{stderr}"
+    );
+}
+
+#[test]
+fn test_replay_changed_program() {
+    let transcript = transcript_path("changed.jsonl");
+    Command::cargo_bin("bft")
+        .unwrap()
+        .args([
+            "programs/rot13.bf",
+            "--on-eof",
+            "minus-one",
+            "--input-str",
+            "Hi",
+        ])
+        .arg("--record")
+        .arg(&transcript)
+        .assert()
+        .success();
+
+    // rot13 which forgets to rotate reads the same input but writes something else
+    let output = Command::cargo_bin("bft")
+        .unwrap()
+        .args(["--eval=-,+[-.,+]", "--on-eof", "minus-one"])
+        .arg("--replay")
+        .arg(&transcript)
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(4));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains("wrote 0x48 by ip 5 where the recording has a write of 0x55"),
+        "{stderr}"
+    );
+}
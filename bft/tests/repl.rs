@@ -0,0 +1,79 @@
+use assert_cmd::Command;
+
+#[test]
+fn test_repl_session() {
+    let script = "\
+++++++++[>++++++++<-]>+.
+>++
+:tape
+<<<
+[
+:reset
+:tape
+:load programs/example.bf
+:frobnicate
+:quit
++.
+";
+    let output = Command::cargo_bin("bft")
+        .unwrap()
+        .arg("repl")
+        .write_stdin(script)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    // each line's output goes to stdout and nothing runs after :quit
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "Ahello world");
+
+    let log = String::from_utf8(output.stderr).unwrap();
+    let lines: Vec<&str> = log.split("bf> ").filter(|l| !l.is_empty()).collect();
+    assert_eq!(
+        lines,
+        [
+            "dp=1 cell=65 steps=108\n",
+            "dp=2 cell=2 steps=3\n",
+            "00000000:  00  41 [02]\n",
+            "error: <repl>:1:3: tape run off while executing '<'\ndp=0 cell=0 steps=2\n",
+            "error: Error in input file <repl>, dangling open bracket found at line 1 column 1\n\
+             1 | [\n  | ^\n",
+            "dp=0 cell=0 steps=0\n",
+            "00000000: [00]\n",
+            "dp=6 cell=100 steps=10623\n",
+            "error: unknown command :frobnicate\n",
+        ]
+    );
+}
+
+#[test]
+fn test_repl_settings() {
+    let output = Command::cargo_bin("bft")
+        .unwrap()
+        .args(["repl", "--cell-size", "16", "--max-steps", "10"])
+        .write_stdin("-\n[]\n")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let log = String::from_utf8(output.stderr).unwrap();
+    assert!(log.contains("dp=0 cell=65535 steps=1\n"), "{log}");
+    assert!(log.contains("didn't finish within 10 steps"), "{log}");
+}
+
+#[test]
+fn test_repl_missing_file() {
+    let output = Command::cargo_bin("bft")
+        .unwrap()
+        .arg("repl")
+        .write_stdin(":load programs/does_not_exist.bf\n")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let log = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        log.starts_with("bf> error: failed to read programs/does_not_exist.bf: "),
+        "{log}"
+    );
+    assert!(log.ends_with("bf> \n"), "{log}");
+}
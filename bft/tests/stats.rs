@@ -0,0 +1,41 @@
+use assert_cmd::Command;
+
+/// The value printed for a statistic in the `--stats` table
+fn stat<'a>(stderr: &'a str, name: &str) -> Option<&'a str> {
+    stderr
+        .lines()
+        .find_map(|line| line.strip_prefix(name)?.strip_prefix(':'))
+        .map(str::trim)
+}
+
+#[test]
+fn test_stats() {
+    let output = Command::cargo_bin("bft")
+        .unwrap()
+        .args(["programs/example.bf", "--stats"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "hello world\n");
+
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert_eq!(stat(&stderr, "instructions"), Some("10623"));
+    assert_eq!(stat(&stderr, "peak tape length"), Some("57"));
+    assert_eq!(stat(&stderr, "data pointer"), Some("6"));
+    assert!(stat(&stderr, "time").is_some());
+}
+
+#[test]
+fn test_stats_after_error() {
+    let output = Command::cargo_bin("bft")
+        .unwrap()
+        .args(["--eval", "+++[>+]", "-c", "10", "--stats"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(4));
+
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert_eq!(stat(&stderr, "instructions"), Some("31"));
+    assert_eq!(stat(&stderr, "peak tape length"), Some("10"));
+    assert_eq!(stat(&stderr, "data pointer"), Some("9"));
+}
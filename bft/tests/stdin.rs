@@ -0,0 +1,61 @@
+use assert_cmd::Command;
+
+#[test]
+fn test_program_from_stdin() {
+    Command::cargo_bin("bft")
+        .unwrap()
+        .arg("-")
+        .write_stdin("++++++++[>++++++++<-]>+.")
+        .assert()
+        .success()
+        .stdout("A\n");
+}
+
+#[test]
+fn test_program_from_stdin_has_no_input() {
+    Command::cargo_bin("bft")
+        .unwrap()
+        .args(["-", "--on-eof", "zero"])
+        .write_stdin("+++++, add one to the zero read >++++++[<++++++++>-]<+.")
+        .assert()
+        .success()
+        .stdout("1\n");
+}
+
+#[test]
+fn test_program_from_stdin_parse_error() {
+    let output = Command::cargo_bin("bft")
+        .unwrap()
+        .arg("-")
+        .write_stdin("+[")
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(3));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr
+        .contains("Error in input file <stdin>, dangling open bracket found at line 1 column 2"));
+}
+
+#[test]
+fn test_program_from_stdin_not_utf8() {
+    let output = Command::cargo_bin("bft")
+        .unwrap()
+        .arg("-")
+        .write_stdin(&b"+\xFF"[..])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(5));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("[io] failed to read <stdin>: "), "{stderr}");
+}
+
+#[test]
+fn test_program_from_stdin_with_extensions() {
+    Command::cargo_bin("bft")
+        .unwrap()
+        .args(["-", "--dialect", "ook"])
+        .write_stdin("Ook. Ook. Ook! Ook.")
+        .assert()
+        .success()
+        .stdout(&b"\x01\n"[..]);
+}
@@ -0,0 +1,55 @@
+use assert_cmd::Command;
+
+#[test]
+fn test_trace() {
+    Command::cargo_bin("bft")
+        .unwrap()
+        .args(["--eval", "+[-]", "--trace"])
+        .assert()
+        .success()
+        .stderr(
+            "ip=0 '+' dp=0 cell=0 -> ip=1 cell=1\n\
+             ip=1 '[' dp=0 cell=1 -> ip=2 cell=1\n\
+             ip=2 '-' dp=0 cell=1 -> ip=3 cell=0\n\
+             ip=3 ']' dp=0 cell=0 -> ip=4 cell=0\n",
+        );
+}
+
+#[test]
+fn test_optimized_trace() {
+    Command::cargo_bin("bft")
+        .unwrap()
+        .args(["--eval", "++[->+++<]>.", "--optimize", "--trace"])
+        .args(["--numeric-output"])
+        .assert()
+        .success()
+        .stdout("6 \n")
+        .stderr(
+            "ip=0 '+' dp=0 cell=0 -> ip=1 cell=2\n\
+             ip=1 '[' as mul-add(+1, 3) dp=0 cell=2 -> ip=2 cell=2\n\
+             ip=2 '-' as set-zero dp=0 cell=2 -> ip=3 cell=0\n\
+             ip=3 '>' as skip-loop dp=0 cell=0 -> ip=7 cell=0\n\
+             ip=7 '>' dp=0 cell=0 -> ip=8 cell=0\n\
+             ip=8 '.' dp=1 cell=6 -> ip=9 cell=6\n",
+        );
+}
+
+#[test]
+fn test_trace_step_limit() {
+    let output = Command::cargo_bin("bft")
+        .unwrap()
+        .args(["--eval", "+[]", "--trace", "--max-steps", "3"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(4));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.starts_with(
+            "ip=0 '+' dp=0 cell=0 -> ip=1 cell=1\n\
+             ip=1 '[' dp=0 cell=1 -> ip=2 cell=1\n\
+             ip=2 ']' dp=0 cell=1 -> ip=2 cell=1\n\
+             error: "
+        ),
+        "{stderr}"
+    );
+}
@@ -1,14 +1,595 @@
-use std::error::Error;
+use clap::Parser;
+use std::{
+    error::Error,
+    fmt,
+    fs::File,
+    io::{self, BufRead, BufReader},
+    num::IntErrorKind,
+    path::{Path, PathBuf},
+};
 
-fn main() -> Result<(), Box<dyn Error>> {
-    let if_name = std::env::args().nth(1)
-        .ok_or_else(|| String::from("Insufficient number of arguments, please provide a filename."))?;
+/// Sum the numbers in a file, separated by whitespace, with `#` starting a comment
+///
+/// Numbers may be written in hex, octal or binary with a `0x`, `0o` or `0b` prefix, and may use
+/// `_` as a separator.
+#[derive(Parser)]
+struct Args {
+    /// The files of numbers to sum, with `-` for stdin
+    #[arg(required = true)]
+    filenames: Vec<PathBuf>,
 
-    let contents = std::fs::read_to_string(if_name)?;
-    let parsed_nums: Vec<i32> = contents.lines().map(str::parse).collect::<Result<_, _>>()?;
-    let sum: i32 = parsed_nums.into_iter().sum();
+    /// Warn about anything which isn't a number and sum the rest, rather than stopping
+    #[arg(long)]
+    skip_bad_lines: bool,
 
-    println!("The numbers in the file had a sum of: {sum}");
+    /// Also print how many numbers there were, the smallest, the largest and their mean
+    #[arg(long)]
+    stats: bool,
 
-    Ok(())
+    /// Warn about files which can't be opened and sum the rest, rather than stopping
+    #[arg(long)]
+    keep_going: bool,
+}
+
+/// Running statistics about the numbers in a file
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+struct Stats {
+    count: usize,
+    sum: i64,
+    min: Option<i64>,
+    max: Option<i64>,
+}
+
+impl Stats {
+    /// Add a number, returning None and leaving the stats unchanged if the sum would overflow
+    fn push(&mut self, n: i64) -> Option<()> {
+        self.sum = self.sum.checked_add(n)?;
+        self.count += 1;
+        self.min = Some(self.min.map_or(n, |min| min.min(n)));
+        self.max = self.max.max(Some(n));
+        Some(())
+    }
+
+    /// Add all the numbers counted in `other`, returning None and leaving the stats unchanged if
+    /// the sum would overflow
+    fn merge(&mut self, other: &Stats) -> Option<()> {
+        self.sum = self.sum.checked_add(other.sum)?;
+        self.count += other.count;
+        self.min = self.min.into_iter().chain(other.min).min();
+        self.max = self.max.max(other.max);
+        Some(())
+    }
+
+    /// The mean of the numbers, if there were any
+    fn mean(&self) -> Option<f64> {
+        (self.count > 0).then(|| self.sum as f64 / self.count as f64)
+    }
+}
+
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.min, self.max, self.mean()) {
+            (Some(min), Some(max), Some(mean)) => write!(
+                f,
+                "count: {}\nmin: {min}\nmax: {max}\nmean: {mean:.2}",
+                self.count
+            ),
+            _ => write!(f, "no numbers found"),
+        }
+    }
+}
+
+/// The reasons the numbers in a file can't be summed
+#[derive(Debug, PartialEq, Eq)]
+enum SumError {
+    /// Something which isn't a number
+    NotANumber { line_number: usize, content: String },
+    /// A number too large or small to sum
+    OutOfRange { line_number: usize, content: String },
+    /// The sum grew too large or small after adding a line
+    Overflow { line_number: usize },
+    /// A line which couldn't be read, such as one which isn't UTF-8
+    Unreadable { line_number: usize, reason: String },
+    /// The grand total grew too large or small after adding a file
+    TotalOverflow { name: String },
+}
+
+impl fmt::Display for SumError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotANumber {
+                line_number,
+                content,
+            } => write!(
+                f,
+                "line {line_number}: could not parse {content:?} as a number"
+            ),
+            Self::OutOfRange {
+                line_number,
+                content,
+            } => write!(
+                f,
+                "line {line_number}: {content:?} is outside the supported range of {} to {}",
+                i64::MIN,
+                i64::MAX
+            ),
+            Self::Overflow { line_number } => write!(
+                f,
+                "sum overflowed at line {line_number}, it must stay within {} to {}",
+                i64::MIN,
+                i64::MAX
+            ),
+            Self::Unreadable {
+                line_number,
+                reason,
+            } => write!(f, "line {line_number}: could not be read: {reason}"),
+            Self::TotalOverflow { name } => write!(
+                f,
+                "grand total overflowed adding {name}, it must stay within {} to {}",
+                i64::MIN,
+                i64::MAX
+            ),
+        }
+    }
+}
+
+impl Error for SumError {}
+
+fn main() {
+    let args = Args::parse();
+
+    let mut summed = vec![];
+    let mut failed = false;
+    for filename in &args.filenames {
+        let name = filename.display().to_string();
+        let reader: Box<dyn BufRead> = if filename == Path::new("-") {
+            Box::new(io::stdin().lock())
+        } else {
+            match File::open(filename) {
+                Ok(file) => Box::new(BufReader::new(file)),
+                Err(e) if args.keep_going => {
+                    eprintln!("warning: skipping {name}: {e}");
+                    failed = true;
+                    continue;
+                }
+                Err(e) => {
+                    eprintln!("Error: {name}: {e}");
+                    std::process::exit(1);
+                }
+            }
+        };
+
+        match sum_numbers(reader, args.skip_bad_lines) {
+            Ok((stats, skipped)) => {
+                for bad in skipped {
+                    eprintln!("warning: skipping {name}: {bad}");
+                }
+                summed.push((name, stats));
+            }
+            Err(e) => {
+                eprintln!("Error: {name}: {e}");
+                std::process::exit(1);
+            }
+        }
+    }
+
+    match report(&summed, args.stats) {
+        Ok(lines) => lines.iter().for_each(|line| println!("{line}")),
+        Err(e) => {
+            eprintln!("Error: {e}");
+            std::process::exit(1);
+        }
+    }
+
+    if failed {
+        std::process::exit(1);
+    }
+}
+
+/// The lines describing the sum of each file, followed by the grand total
+///
+/// A single file is described just by its sum, as before multiple files were supported.
+fn report(summed: &[(String, Stats)], show_stats: bool) -> Result<Vec<String>, SumError> {
+    let mut lines = vec![];
+    let mut total = Stats::default();
+    for (name, stats) in summed {
+        if summed.len() > 1 {
+            lines.push(format!("{name}: {}", stats.sum));
+        }
+        total
+            .merge(stats)
+            .ok_or_else(|| SumError::TotalOverflow { name: name.clone() })?;
+    }
+
+    let files = if summed.len() == 1 { "file" } else { "files" };
+    lines.push(format!(
+        "The numbers in the {files} had a sum of: {}",
+        total.sum
+    ));
+    if show_stats {
+        lines.extend(total.to_string().lines().map(str::to_owned));
+    }
+
+    Ok(lines)
+}
+
+/// Parse a number, which may have a `0x`, `0o` or `0b` prefix and `_` separators
+fn parse_number(token: &str) -> Result<i64, IntErrorKind> {
+    let (sign, unsigned) = match token.strip_prefix('-') {
+        Some(unsigned) => ("-", unsigned),
+        None => ("", token.strip_prefix('+').unwrap_or(token)),
+    };
+    let prefix = unsigned.get(..2).map(str::to_ascii_lowercase);
+    let (radix, digits) = match prefix.as_deref() {
+        Some("0x") => (16, &unsigned[2..]),
+        Some("0o") => (8, &unsigned[2..]),
+        Some("0b") => (2, &unsigned[2..]),
+        _ => (10, unsigned),
+    };
+
+    let digits = digits.replace('_', "");
+    // from_str_radix allows its own sign, which would let through `--5` and `-0x-5`
+    if digits.is_empty() || digits.starts_with(['+', '-']) {
+        return Err(IntErrorKind::InvalidDigit);
+    }
+    i64::from_str_radix(&format!("{sign}{digits}"), radix).map_err(|e| *e.kind())
+}
+
+/// Sum the whitespace separated numbers on each line, ignoring anything after a `#`
+///
+/// Stops at the first thing which isn't a number, unless `skip_bad_lines` is set, in which case
+/// those are returned alongside the sum. The sum always stops if it overflows.
+fn sum_numbers(
+    reader: impl BufRead,
+    skip_bad_lines: bool,
+) -> Result<(Stats, Vec<SumError>), SumError> {
+    let mut stats = Stats::default();
+    let mut skipped = vec![];
+    for (i, line) in reader.lines().enumerate() {
+        let line_number = i + 1;
+        let line = line.map_err(|e| SumError::Unreadable {
+            line_number,
+            reason: e.to_string(),
+        })?;
+        let numbers = line
+            .split_once('#')
+            .map_or(line.as_str(), |(numbers, _comment)| numbers);
+        for token in numbers.split_whitespace() {
+            match parse_number(token) {
+                Ok(n) => {
+                    stats.push(n).ok_or(SumError::Overflow { line_number })?;
+                }
+                Err(e) => {
+                    let content = token.to_owned();
+                    let bad = match e {
+                        IntErrorKind::PosOverflow | IntErrorKind::NegOverflow => {
+                            SumError::OutOfRange {
+                                line_number,
+                                content,
+                            }
+                        }
+                        _ => SumError::NotANumber {
+                            line_number,
+                            content,
+                        },
+                    };
+                    if !skip_bad_lines {
+                        return Err(bad);
+                    }
+                    skipped.push(bad);
+                }
+            }
+        }
+    }
+
+    Ok((stats, skipped))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+    use std::io::Cursor;
+
+    /// Sum the numbers, keeping just the sum from the stats
+    fn summed(contents: &str, skip_bad_lines: bool) -> Result<(i64, Vec<SumError>), SumError> {
+        sum_numbers(Cursor::new(contents), skip_bad_lines)
+            .map(|(stats, skipped)| (stats.sum, skipped))
+    }
+
+    fn fixture(name: &str) -> String {
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("test_data")
+            .join(name);
+        std::fs::read_to_string(path).unwrap()
+    }
+
+    #[test]
+    fn test_stats_empty() {
+        let stats = Stats::default();
+        assert_eq!(stats.mean(), None);
+        assert_eq!(stats.to_string(), "no numbers found");
+        assert_eq!(
+            sum_numbers(Cursor::new("# nothing here\n\n"), false)
+                .unwrap()
+                .0,
+            stats
+        );
+    }
+
+    #[test]
+    fn test_stats_single() {
+        let mut stats = Stats::default();
+        stats.push(7).unwrap();
+        assert_eq!(
+            stats,
+            Stats {
+                count: 1,
+                sum: 7,
+                min: Some(7),
+                max: Some(7)
+            }
+        );
+        assert_eq!(stats.to_string(), "count: 1\nmin: 7\nmax: 7\nmean: 7.00");
+    }
+
+    #[test]
+    fn test_stats_negative() {
+        let mut stats = Stats::default();
+        for n in [-3, -10, -4] {
+            stats.push(n).unwrap();
+        }
+        assert_eq!(
+            (stats.min, stats.max, stats.sum),
+            (Some(-10), Some(-3), -17)
+        );
+        assert_eq!(
+            stats.to_string(),
+            "count: 3\nmin: -10\nmax: -3\nmean: -5.67"
+        );
+    }
+
+    #[test]
+    fn test_stats_overflow_unchanged() {
+        let mut stats = Stats::default();
+        stats.push(i64::MAX).unwrap();
+        let before = stats;
+        assert_eq!(stats.push(1), None);
+        assert_eq!(stats, before);
+    }
+
+    #[test]
+    fn test_stats_from_file() {
+        let (stats, _) = sum_numbers(Cursor::new(fixture("empty_lines")), false).unwrap();
+        assert_eq!(stats.to_string(), "count: 5\nmin: 2\nmax: 32\nmean: 8.40");
+    }
+
+    #[test]
+    fn test_unreadable() {
+        let err = sum_numbers(Cursor::new(b"1\n2\n\xff\n"), false).unwrap_err();
+        assert!(matches!(err, SumError::Unreadable { line_number: 3, .. }));
+    }
+
+    fn summed_file(name: &str) -> (String, Stats) {
+        let (stats, skipped) = sum_numbers(Cursor::new(fixture(name)), false).unwrap();
+        assert!(skipped.is_empty());
+        (name.to_owned(), stats)
+    }
+
+    #[test]
+    fn test_report_single_file() {
+        let summed = [summed_file("numbers")];
+        assert_eq!(
+            report(&summed, false).unwrap(),
+            ["The numbers in the file had a sum of: 4896"]
+        );
+    }
+
+    #[test]
+    fn test_report_multiple_files() {
+        let summed = [summed_file("numbers"), summed_file("empty_lines")];
+        assert_eq!(
+            report(&summed, false).unwrap(),
+            [
+                "numbers: 4896",
+                "empty_lines: 42",
+                "The numbers in the files had a sum of: 4938"
+            ]
+        );
+        assert_eq!(
+            report(&summed, true).unwrap()[3..],
+            ["count: 22", "min: -75623", "max: 65536", "mean: 224.45"]
+        );
+    }
+
+    #[test]
+    fn test_report_total_overflow() {
+        let mut big = Stats::default();
+        big.push(i64::MAX).unwrap();
+        let summed = [summed_file("numbers"), ("big".to_owned(), big)];
+        assert_eq!(
+            report(&summed, false),
+            Err(SumError::TotalOverflow {
+                name: "big".to_owned()
+            })
+        );
+    }
+
+    #[test]
+    fn test_sum() {
+        assert_eq!(summed(&fixture("numbers"), false), Ok((4896, vec![])));
+    }
+
+    #[test]
+    fn test_blank_lines_ignored() {
+        assert_eq!(summed(&fixture("empty_lines"), false), Ok((42, vec![])));
+        assert_eq!(summed("", false), Ok((0, vec![])));
+        assert_eq!(summed("1\n   \n\t\n2\n", false), Ok((3, vec![])));
+    }
+
+    #[test]
+    fn test_bad_line() {
+        let err = summed(&fixture("letters"), false).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "line 1: could not parse \"asbc\" as a number"
+        );
+
+        let err = summed("1\n2\n\n7O\n3", false).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "line 4: could not parse \"7O\" as a number"
+        );
+    }
+
+    #[rstest]
+    #[case("14\n9\n-7\n", 16)]
+    #[case("1 2 3\n4\t5\n", 15)]
+    #[case("  1   -2  \n\n3", 2)]
+    #[case("# totals for march\n#\n10\n", 10)]
+    #[case("10 # the first\n20#the second\n30 40 # and the rest 50\n", 100)]
+    #[case("1\n# 2\n3 4 # 5 6\n   # 7\n8", 16)]
+    fn test_layouts(#[case] contents: &str, #[case] expected: i64) {
+        assert_eq!(summed(contents, false), Ok((expected, vec![])));
+    }
+
+    #[rstest]
+    #[case("42", 42)]
+    #[case("+42", 42)]
+    #[case("-42", -42)]
+    #[case("0x1F", 31)]
+    #[case("0X1f", 31)]
+    #[case("-0x10", -16)]
+    #[case("0b1010", 10)]
+    #[case("-0B1", -1)]
+    #[case("0o17", 15)]
+    #[case("0O17", 15)]
+    #[case("1_000_000", 1_000_000)]
+    #[case("0xFF_FF", 0xffff)]
+    #[case("0b_1000_0000", 128)]
+    #[case("-0x8000_0000_0000_0000", i64::MIN)]
+    #[case("007", 7)]
+    fn test_parse_number(#[case] token: &str, #[case] expected: i64) {
+        assert_eq!(parse_number(token), Ok(expected));
+    }
+
+    #[rstest]
+    #[case("0x", IntErrorKind::InvalidDigit)]
+    #[case("-0b", IntErrorKind::InvalidDigit)]
+    #[case("0x_", IntErrorKind::InvalidDigit)]
+    #[case("--5", IntErrorKind::InvalidDigit)]
+    #[case("-+5", IntErrorKind::InvalidDigit)]
+    #[case("0x-5", IntErrorKind::InvalidDigit)]
+    #[case("0b102", IntErrorKind::InvalidDigit)]
+    #[case("0o8", IntErrorKind::InvalidDigit)]
+    #[case("0xG", IntErrorKind::InvalidDigit)]
+    #[case("1e3", IntErrorKind::InvalidDigit)]
+    #[case("-", IntErrorKind::InvalidDigit)]
+    #[case("0x8000_0000_0000_0000", IntErrorKind::PosOverflow)]
+    #[case(
+        "-0b1_0000000000000000000000000000000000000000000000000000000000000001",
+        IntErrorKind::NegOverflow
+    )]
+    fn test_parse_number_malformed(#[case] token: &str, #[case] expected: IntErrorKind) {
+        assert_eq!(parse_number(token), Err(expected));
+    }
+
+    #[test]
+    fn test_sum_literals() {
+        assert_eq!(
+            summed("0x10 0b11 0o7\n1_000 -0x1\n", false),
+            Ok((1025, vec![]))
+        );
+        let err = summed("1\n2 0x\n", false).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "line 2: could not parse \"0x\" as a number"
+        );
+        let err = summed("--5\n", false).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "line 1: could not parse \"--5\" as a number"
+        );
+    }
+
+    #[test]
+    fn test_bad_token() {
+        let err = summed("1 2\n3 four 5 # six\n", false).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "line 2: could not parse \"four\" as a number"
+        );
+
+        let (sum, skipped) = summed("1 2\n3 four 5 # six\n7x 8", true).unwrap();
+        assert_eq!(sum, 19);
+        let skipped: Vec<_> = skipped.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            skipped,
+            [
+                "line 2: could not parse \"four\" as a number",
+                "line 3: could not parse \"7x\" as a number"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_large_numbers() {
+        assert_eq!(
+            summed(&fixture("out_of_range"), false),
+            Ok((2341245235126154, vec![]))
+        );
+        let near_max = format!("{}\n{}\n-5\n", i64::MAX - 1, i64::MIN + 10);
+        assert_eq!(summed(&near_max, false), Ok((3, vec![])));
+        let near_min = format!("{}\n-1\n", i64::MIN + 1);
+        assert_eq!(summed(&near_min, false), Ok((i64::MIN, vec![])));
+    }
+
+    #[test]
+    fn test_overflow() {
+        let err = summed(&format!("1\n\n{}\n3\n", i64::MAX), false).unwrap_err();
+        assert_eq!(err, SumError::Overflow { line_number: 3 });
+        assert_eq!(
+            err.to_string(),
+            "sum overflowed at line 3, it must stay within -9223372036854775808 to 9223372036854775807"
+        );
+
+        // overflowing isn't a bad line, so skipping doesn't help
+        let err = summed(&format!("{}\n-2\n", i64::MIN + 1), true).unwrap_err();
+        assert_eq!(err, SumError::Overflow { line_number: 2 });
+    }
+
+    #[test]
+    fn test_out_of_range() {
+        let err = summed("1\n9223372036854775808\n", false).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "line 2: \"9223372036854775808\" is outside the supported range of -9223372036854775808 to 9223372036854775807"
+        );
+
+        let (sum, skipped) = summed("1\n-9223372036854775809\n2\n", true).unwrap();
+        assert_eq!(sum, 3);
+        assert_eq!(
+            skipped,
+            [SumError::OutOfRange {
+                line_number: 2,
+                content: "-9223372036854775809".to_owned()
+            }]
+        );
+    }
+
+    #[test]
+    fn test_skip_bad_lines() {
+        let (sum, skipped) = summed("1\n2\n\n7O\n3\nx", true).unwrap();
+        assert_eq!(sum, 6);
+        let skipped: Vec<_> = skipped.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            skipped,
+            [
+                "line 4: could not parse \"7O\" as a number",
+                "line 6: could not parse \"x\" as a number"
+            ]
+        );
+
+        assert_eq!(summed(&fixture("letters"), true).unwrap().0, 278);
+    }
 }
@@ -1,15 +1,337 @@
-use std::error::Error;
+use clap::Parser;
+use std::{
+    error::Error,
+    fmt,
+    fs::{self, File},
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    num::NonZeroUsize,
+    path::{Path, PathBuf},
+};
 
 const BF_ALPHABET: [char; 8] = ['>', '<', '+', '-', '.', ',', '[', ']'];
 
+/// Strip everything but the brainfuck instructions from a program
+#[derive(Parser)]
+struct Args {
+    /// The program to strip, with `-` for stdin
+    filename: PathBuf,
+
+    /// Write the stripped program to this file rather than stdout
+    #[arg(short, long, conflicts_with = "in_place")]
+    output: Option<PathBuf>,
+
+    /// Replace the program with the stripped version
+    #[arg(long)]
+    in_place: bool,
+
+    /// Keep a line for each line of the program holding instructions, rather than joining them
+    #[arg(long, conflicts_with = "wrap")]
+    keep_lines: bool,
+
+    /// Wrap the stripped program onto lines of at most this many instructions
+    #[arg(long, value_name = "N")]
+    wrap: Option<NonZeroUsize>,
+
+    /// Print statistics about what was stripped to stderr
+    #[arg(long)]
+    stats: bool,
+}
+
+/// A count of the characters read while stripping a program
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+struct Tally {
+    /// Every character read, including line endings
+    read: usize,
+    /// How many of each instruction were kept, in the order of `BF_ALPHABET`
+    instructions: [usize; BF_ALPHABET.len()],
+}
+
+impl Tally {
+    /// The number of instructions kept
+    fn kept(&self) -> usize {
+        self.instructions.iter().sum()
+    }
+
+    /// The number of comment characters removed
+    fn removed(&self) -> usize {
+        self.read - self.kept()
+    }
+}
+
+impl fmt::Display for Tally {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "characters read: {}", self.read)?;
+        writeln!(f, "instructions kept: {}", self.kept())?;
+        writeln!(f, "comment characters removed: {}", self.removed())?;
+        for (op, count) in BF_ALPHABET.iter().zip(self.instructions) {
+            writeln!(f, "  {op} {count}")?;
+        }
+        let reduction = match self.read {
+            0 => 0.0,
+            read => self.removed() as f64 / read as f64 * 100.0,
+        };
+        write!(f, "size reduction: {reduction:.1}%")
+    }
+}
+
+/// How the stripped instructions are laid out
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Layout {
+    /// All on a single line
+    Joined,
+    /// A line for each line of the program holding instructions
+    Lines,
+    /// Wrapped onto lines of at most this many instructions
+    Wrapped(NonZeroUsize),
+}
+
 fn main() -> Result<(), Box<dyn Error>> {
-    let if_name = std::env::args().nth(1)
-        .ok_or_else(|| String::from("Insufficient number of arguments, please provide a filename."))?;
+    let args = Args::parse();
+    let from_stdin = args.filename == Path::new("-");
+    let layout = match (args.keep_lines, args.wrap) {
+        (true, _) => Layout::Lines,
+        (false, Some(width)) => Layout::Wrapped(width),
+        (false, None) => Layout::Joined,
+    };
 
-    let contents = std::fs::read_to_string(if_name)?;
-    let prog: String = contents.chars().filter(|c| BF_ALPHABET.contains(c)).collect();
+    if args.in_place {
+        if from_stdin {
+            return Err("can't edit stdin in place".into());
+        }
+        let tally = strip_in_place(&args.filename, layout)?;
+        if args.stats {
+            eprintln!("{tally}");
+        }
+        return Ok(());
+    }
 
-    println!("{}", prog);
+    let input: Box<dyn BufRead> = if from_stdin {
+        Box::new(io::stdin().lock())
+    } else {
+        Box::new(BufReader::new(File::open(&args.filename)?))
+    };
+
+    let tally = match args.output {
+        Some(output) => {
+            if !from_stdin && is_same_file(&args.filename, &output) {
+                return Err(format!(
+                    "refusing to overwrite the input file {}, use --in-place to edit it",
+                    output.display()
+                )
+                .into());
+            }
+            let mut writer = BufWriter::new(File::create(output)?);
+            let tally = strip(input, &mut writer, layout)?;
+            writer.flush()?;
+            tally
+        }
+        None => strip(input, io::stdout().lock(), layout)?,
+    };
+    if args.stats {
+        eprintln!("{tally}");
+    }
 
     Ok(())
 }
+
+/// Whether two paths lead to the same existing file
+fn is_same_file(a: &Path, b: &Path) -> bool {
+    match (fs::canonicalize(a), fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+/// Copy the brainfuck instructions from `input` to `output`, laid out according to `layout`
+///
+/// Every line written, including the last, ends in a newline.
+fn strip(mut input: impl BufRead, mut output: impl Write, layout: Layout) -> io::Result<Tally> {
+    let mut tally = Tally::default();
+    let mut column = 0;
+    let mut line = String::new();
+    while input.read_line(&mut line)? != 0 {
+        let mut prog = String::new();
+        for c in line.chars() {
+            tally.read += 1;
+            if let Some(i) = BF_ALPHABET.iter().position(|&op| op == c) {
+                tally.instructions[i] += 1;
+                prog.push(c);
+            }
+        }
+        line.clear();
+
+        match layout {
+            Layout::Joined => output.write_all(prog.as_bytes())?,
+            Layout::Lines if prog.is_empty() => {}
+            Layout::Lines => writeln!(output, "{prog}")?,
+            Layout::Wrapped(width) => {
+                for c in prog.chars() {
+                    if column == width.get() {
+                        writeln!(output)?;
+                        column = 0;
+                    }
+                    write!(output, "{c}")?;
+                    column += 1;
+                }
+            }
+        }
+    }
+
+    match layout {
+        Layout::Lines => {}
+        Layout::Joined | Layout::Wrapped(_) => writeln!(output)?,
+    }
+    Ok(tally)
+}
+
+/// Strip a program in place, writing to a temporary file next to it which then replaces it
+///
+/// The program is left as it was if stripping fails.
+fn strip_in_place(path: &Path, layout: Layout) -> io::Result<Tally> {
+    let name = path
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "the path isn't a file"))?;
+    let temp = path.with_file_name(format!(".{}.tmp", name.to_string_lossy()));
+
+    let stripped = (|| {
+        let input = BufReader::new(File::open(path)?);
+        let mut writer = BufWriter::new(File::create(&temp)?);
+        let tally = strip(input, &mut writer, layout)?;
+        writer.into_inner()?.sync_all()?;
+        Ok(tally)
+    })();
+
+    match stripped {
+        Ok(tally) => fs::rename(&temp, path).map(|()| tally),
+        Err(e) => {
+            let _ = fs::remove_file(&temp);
+            Err(e)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIXTURE: &[u8] = include_bytes!("../test_data/input");
+
+    fn strip_to_string(input: &[u8]) -> io::Result<String> {
+        strip_with_layout(input, Layout::Joined)
+    }
+
+    fn strip_with_layout(input: &[u8], layout: Layout) -> io::Result<String> {
+        let mut output = vec![];
+        strip(input, &mut output, layout)?;
+        Ok(String::from_utf8(output).unwrap())
+    }
+
+    #[test]
+    fn test_strip() {
+        let program = "Add two +[-\n  >+< # move\n]\nand print .\n";
+        assert_eq!(strip_to_string(program.as_bytes()).unwrap(), "+[->+<].\n");
+    }
+
+    #[test]
+    fn test_strip_fixture() {
+        assert_eq!(
+            strip_to_string(FIXTURE).unwrap(),
+            "+[-[<<[+[--->]-[<<<]]]>>>-]>-.---.>..>.<<<<-.<+.>>>>>.>.<<.<-.\n"
+        );
+    }
+
+    #[test]
+    fn test_strip_empty() {
+        assert_eq!(strip_to_string(b"").unwrap(), "\n");
+        assert_eq!(strip_to_string(b"no instructions here").unwrap(), "\n");
+    }
+
+    #[test]
+    fn test_strip_invalid_utf8() {
+        let err = strip_to_string(b"+ + \xff -").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_keep_lines() {
+        let stripped = strip_with_layout(FIXTURE, Layout::Lines).unwrap();
+        let instruction_lines = String::from_utf8_lossy(FIXTURE)
+            .lines()
+            .filter(|line| line.chars().any(|c| BF_ALPHABET.contains(&c)))
+            .count();
+        assert_eq!(stripped.lines().count(), instruction_lines);
+        assert_eq!(
+            stripped,
+            "+[-\n[<<\n[+\n[--->]\n-[<<<]\n]\n]>>>-\n]\n>-.---.>..>.<<<<-.<+.>>>>>.>.<<.<-.\n"
+        );
+    }
+
+    #[test]
+    fn test_wrap() {
+        let joined = strip_to_string(FIXTURE).unwrap();
+        for width in 1..=joined.len() + 1 {
+            let stripped =
+                strip_with_layout(FIXTURE, Layout::Wrapped(NonZeroUsize::new(width).unwrap()))
+                    .unwrap();
+            let lines: Vec<_> = stripped.lines().collect();
+            assert!(lines
+                .iter()
+                .all(|line| !line.is_empty() && line.len() <= width));
+            assert!(lines[..lines.len() - 1]
+                .iter()
+                .all(|line| line.len() == width));
+            assert_eq!(lines.concat() + "\n", joined);
+        }
+    }
+
+    #[test]
+    fn test_wrap_exact() {
+        let stripped = strip_with_layout(
+            b"+-\n<>.,\n[]",
+            Layout::Wrapped(NonZeroUsize::new(4).unwrap()),
+        );
+        assert_eq!(stripped.unwrap(), "+-<>\n.,[]\n");
+    }
+
+    #[test]
+    fn test_tally() {
+        let mut output = vec![];
+        let tally = strip(
+            &include_bytes!("../test_data/tally")[..],
+            &mut output,
+            Layout::Joined,
+        )
+        .unwrap();
+        assert_eq!(output, b"+[->+<],.\n");
+        assert_eq!(
+            tally,
+            Tally {
+                read: 30,
+                instructions: [1, 1, 2, 1, 1, 1, 1, 1],
+            }
+        );
+        assert_eq!((tally.kept(), tally.removed()), (9, 21));
+        assert_eq!(
+            tally.to_string(),
+            "characters read: 30
+instructions kept: 9
+comment characters removed: 21
+  > 1
+  < 1
+  + 2
+  - 1
+  . 1
+  , 1
+  [ 1
+  ] 1
+size reduction: 70.0%"
+        );
+    }
+
+    #[test]
+    fn test_tally_empty() {
+        let tally = strip(&b""[..], io::sink(), Layout::Joined).unwrap();
+        assert_eq!(tally, Tally::default());
+        assert!(tally.to_string().ends_with("size reduction: 0.0%"));
+    }
+}
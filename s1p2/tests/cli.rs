@@ -0,0 +1,90 @@
+use std::{
+    fs,
+    io::Write,
+    path::PathBuf,
+    process::{Command, Output, Stdio},
+};
+
+const STRIPPED: &str = "+[-[<<[+[--->]-[<<<]]]>>>-]>-.---.>..>.<<<<-.<+.>>>>>.>.<<.<-.\n";
+
+/// Run s1p2 with `args`, feeding it `stdin`
+fn run(args: &[&str], stdin: &[u8]) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_s1p2"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(stdin).unwrap();
+    child.wait_with_output().unwrap()
+}
+
+/// A copy of the input fixture in a fresh scratch file
+fn scratch(name: &str, contents: &[u8]) -> PathBuf {
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(name);
+    fs::write(&path, contents).unwrap();
+    path
+}
+
+#[test]
+fn test_stdin_to_stdout() {
+    let output = run(&["-"], &fs::read("test_data/input").unwrap());
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), STRIPPED);
+}
+
+#[test]
+fn test_output_to_file() {
+    let out = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("output.bf");
+    let _ = fs::remove_file(&out);
+    let output = run(&["test_data/input", "-o", out.to_str().unwrap()], b"");
+    assert!(output.status.success());
+    assert!(output.stdout.is_empty());
+    assert_eq!(fs::read_to_string(&out).unwrap(), STRIPPED);
+}
+
+#[test]
+fn test_refuses_to_overwrite_input() {
+    let contents = fs::read("test_data/input").unwrap();
+    let path = scratch("overwrite.bf", &contents);
+    let output = run(
+        &[path.to_str().unwrap(), "--output", path.to_str().unwrap()],
+        b"",
+    );
+    assert!(!output.status.success());
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .contains("use --in-place"));
+    assert_eq!(fs::read(&path).unwrap(), contents);
+}
+
+#[test]
+fn test_in_place() {
+    let path = scratch("in_place.bf", &fs::read("test_data/input").unwrap());
+    let output = run(&[path.to_str().unwrap(), "--in-place"], b"");
+    assert!(output.status.success());
+    assert_eq!(fs::read_to_string(&path).unwrap(), STRIPPED);
+}
+
+#[test]
+fn test_in_place_preserves_content_on_error() {
+    let contents = b"+[-]\n\xff not utf-8\n.".as_slice();
+    let path = scratch("in_place_error.bf", contents);
+    let output = run(&[path.to_str().unwrap(), "--in-place"], b"");
+    assert!(!output.status.success());
+    assert_eq!(fs::read(&path).unwrap(), contents);
+    assert!(!path.with_file_name(".in_place_error.bf.tmp").exists());
+}
+
+#[test]
+fn test_keep_lines_conflicts_with_wrap() {
+    let output = run(&["test_data/input", "--keep-lines", "--wrap", "10"], b"");
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn test_wrap_rejects_zero() {
+    let output = run(&["test_data/input", "--wrap", "0"], b"");
+    assert_eq!(output.status.code(), Some(2));
+}
@@ -1,12 +1,199 @@
-use std::{collections::HashMap, error::Error, fmt, path::Path, str::FromStr};
+use clap::{Parser, ValueEnum};
+use serde::Serialize;
+use std::{
+    cmp::Reverse,
+    collections::BTreeMap,
+    error::Error,
+    fmt, io,
+    num::{IntErrorKind, ParseIntError},
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+/// Summarise the test scores of each student in a file
+#[derive(Parser)]
+struct Args {
+    /// The files of test results to read, with `-` for stdin, combining the results for each
+    /// student
+    #[arg(required = true)]
+    filenames: Vec<PathBuf>,
+
+    /// The format of the files, guessed from each extension if not given
+    #[arg(long, value_enum)]
+    format: Option<Format>,
+
+    /// The order to list the students in, highest first when sorting by score
+    #[arg(long, value_enum, default_value_t = SortKey::Name)]
+    sort: SortKey,
+
+    /// How to write out the statistics
+    #[arg(long, value_enum, default_value_t = Output::Text)]
+    output: Output,
+
+    /// Flag any score above this as a mistake, leaving it out of the statistics
+    #[arg(long)]
+    max_score: Option<u32>,
+
+    /// Letter grades to give each student by their average, as `A:90,B:80,...` with the
+    /// thresholds descending, and F for anything lower
+    #[arg(long)]
+    grades: Option<Grades>,
+
+    /// A file holding the letter grades, written the same way as for `--grades`
+    #[arg(long, conflicts_with = "grades")]
+    grades_file: Option<PathBuf>,
+
+    /// Exit with an error if any line of the file couldn't be parsed
+    #[arg(long)]
+    strict: bool,
+}
+
+/// The orders the students can be listed in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum SortKey {
+    /// Alphabetically by name
+    Name,
+    /// By total score, highest first
+    Score,
+    /// By the number of tests missed, fewest first
+    Missed,
+}
+
+/// The ways the statistics can be written out
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Output {
+    /// A line for each student followed by a class summary
+    Text,
+    /// A JSON array with an object for each student
+    Json,
+}
+
+/// The formats a file of test results can be written in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    /// A `name:score` line for each test taken and a `name` line for each test missed
+    Colon,
+    /// `name,score` rows after a header row, with an empty score for each test missed
+    Csv,
+}
+
+impl Format {
+    /// Guess the format of a file from its extension, defaulting to colon separated
+    fn from_extension(path: &Path) -> Self {
+        match path.extension() {
+            Some(ext) if ext.eq_ignore_ascii_case("csv") => Self::Csv,
+            _ => Self::Colon,
+        }
+    }
+}
+
+/// Letter grades, each given to averages at or above its threshold
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Grades {
+    /// The letter and threshold of each grade, highest threshold first
+    bands: Vec<(String, u8)>,
+}
+
+impl Grades {
+    /// The grade for an average, F below the lowest threshold and `-` if there are no scores
+    fn grade(&self, average: Option<f64>) -> &str {
+        let Some(average) = average else {
+            return "-";
+        };
+        self.bands
+            .iter()
+            .find(|(_, threshold)| average >= *threshold as f64)
+            .map_or("F", |(letter, _)| letter)
+    }
+}
+
+impl FromStr for Grades {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut bands: Vec<(String, u8)> = vec![];
+        for band in s.trim().split(',') {
+            let band = band.trim();
+            let (letter, threshold) = band
+                .split_once(':')
+                .ok_or_else(|| format!("grade {band:?} should be written as letter:threshold"))?;
+            let (letter, threshold) = (letter.trim(), threshold.trim());
+            if letter.is_empty() {
+                return Err(format!("grade {band:?} has no letter"));
+            }
+            let threshold = threshold
+                .parse()
+                .ok()
+                .filter(|threshold| *threshold <= 100)
+                .ok_or_else(|| {
+                    format!("threshold {threshold:?} for grade {letter} should be a whole number from 0 to 100")
+                })?;
+            if let Some((above, above_threshold)) = bands.last() {
+                if threshold >= *above_threshold {
+                    return Err(format!(
+                        "threshold {threshold} for grade {letter} should be below {above_threshold}, the threshold for grade {above}"
+                    ));
+                }
+            }
+            bands.push((letter.to_owned(), threshold));
+        }
+
+        Ok(Self { bands })
+    }
+}
 
 fn main() -> Result<(), Box<dyn Error>> {
-    let if_name = std::env::args().nth(1).ok_or_else(|| {
-        String::from("Insufficient number of arguments, please provide a filename.")
-    })?;
+    let args = Args::parse();
+
+    let grades = match (args.grades, &args.grades_file) {
+        (Some(grades), _) => Some(grades),
+        (None, Some(path)) => Some(
+            std::fs::read_to_string(path)
+                .map_err(|e| e.to_string())
+                .and_then(|spec| spec.parse())
+                .map_err(|e| format!("{}: {e}", path.display()))?,
+        ),
+        (None, None) => None,
+    };
+
+    let mut students = vec![];
+    let mut no_issues = 0;
+    for filename in &args.filenames {
+        let format = args
+            .format
+            .unwrap_or_else(|| Format::from_extension(filename));
+        let (file_students, issues) = read_students(filename, format, args.max_score)
+            .map_err(|e| format!("{}: {e}", filename.display()))?;
+        for issue in &issues {
+            eprintln!("{}: {issue}", filename.display());
+        }
+        no_issues += issues.len();
+        students.extend(file_students);
+    }
 
-    let students = read_students(if_name)?;
-    let mut student_stats: HashMap<String, TestStatistics> = Default::default();
+    let student_stats = collect_statistics(students);
+    match args.output {
+        Output::Text => {
+            for line in report(&student_stats, args.sort, grades.as_ref()) {
+                println!("{line}");
+            }
+            println!("{}", class_summary(&student_stats));
+        }
+        Output::Json => println!("{}", json_report(&student_stats, args.sort)?),
+    }
+
+    if args.strict && no_issues > 0 {
+        std::process::exit(1);
+    }
+
+    Ok(())
+}
+
+/// Gather up the tests taken and missed by each student, ordered by name
+fn collect_statistics(
+    students: impl IntoIterator<Item = Student>,
+) -> BTreeMap<String, TestStatistics> {
+    let mut student_stats: BTreeMap<String, TestStatistics> = Default::default();
 
     for student in students {
         match student {
@@ -19,29 +206,155 @@ fn main() -> Result<(), Box<dyn Error>> {
         }
     }
 
-    for (name, stats) in student_stats {
-        println!("{name} took {stats}");
+    student_stats
+}
+
+/// The students in the order given by `sort`
+fn sorted(
+    student_stats: &BTreeMap<String, TestStatistics>,
+    sort: SortKey,
+) -> Vec<(&String, &TestStatistics)> {
+    let mut student_stats: Vec<_> = student_stats.iter().collect();
+    // the students start out in name order, which the stable sorts keep for ties
+    match sort {
+        SortKey::Name => {}
+        SortKey::Score => student_stats.sort_by_key(|(_, stats)| Reverse(stats.total)),
+        SortKey::Missed => student_stats.sort_by_key(|(_, stats)| stats.no_missed),
     }
+    student_stats
+}
 
-    Ok(())
+/// A line describing each student, in the order given by `sort`, ending with their grade if
+/// `grades` are given
+fn report(
+    student_stats: &BTreeMap<String, TestStatistics>,
+    sort: SortKey,
+    grades: Option<&Grades>,
+) -> Vec<String> {
+    sorted(student_stats, sort)
+        .iter()
+        .map(|(name, stats)| match grades {
+            Some(grades) => format!(
+                "{name} took {stats}  Grade {}.",
+                grades.grade(stats.average())
+            ),
+            None => format!("{name} took {stats}"),
+        })
+        .collect()
+}
+
+/// The statistics for a single student, as written out in JSON
+#[derive(Debug, Serialize)]
+struct StudentReport<'a> {
+    name: &'a str,
+    tests_taken: u32,
+    total: u64,
+    average: Option<f64>,
+    median: Option<f64>,
+    stddev: Option<f64>,
+    missed: u32,
+}
+
+impl<'a> StudentReport<'a> {
+    fn new(name: &'a str, stats: &TestStatistics) -> Self {
+        Self {
+            name,
+            tests_taken: stats.no_scores,
+            total: stats.total,
+            average: stats.average(),
+            median: stats.median(),
+            stddev: stats.stddev(),
+            missed: stats.no_missed,
+        }
+    }
+}
+
+/// A JSON array describing each student, in the order given by `sort`
+fn json_report(
+    student_stats: &BTreeMap<String, TestStatistics>,
+    sort: SortKey,
+) -> serde_json::Result<String> {
+    let reports: Vec<_> = sorted(student_stats, sort)
+        .into_iter()
+        .map(|(name, stats)| StudentReport::new(name, stats))
+        .collect();
+    serde_json::to_string_pretty(&reports)
+}
+
+/// A line describing the class as a whole
+fn class_summary(student_stats: &BTreeMap<String, TestStatistics>) -> String {
+    let total: u64 = student_stats.values().map(|stats| stats.total).sum();
+    let no_scores: u32 = student_stats.values().map(|stats| stats.no_scores).sum();
+    // the first student with the highest total, so ties go to the earliest name
+    let best = student_stats
+        .iter()
+        .filter(|(_, stats)| stats.no_scores > 0)
+        .rev()
+        .max_by_key(|(_, stats)| stats.total);
+
+    let no_students = student_stats.len();
+    let pluralise = if no_students == 1 {
+        "student"
+    } else {
+        "students"
+    };
+    match best {
+        Some((name, stats)) => format!(
+            "{no_students} {pluralise}, with an overall average of {:.1}.  {name} scored highest with {}.",
+            total as f64 / no_scores as f64,
+            stats.total
+        ),
+        None => format!("{no_students} {pluralise}, no scores recorded."),
+    }
 }
 
 #[derive(Default, Debug)]
 struct TestStatistics {
-    total: u32,
+    total: u64,
     no_scores: u32,
     no_missed: u32,
+    scores: Vec<u32>,
 }
 
 impl TestStatistics {
-    fn add_score(&mut self, score: u8) {
-        self.total += score as u32;
+    fn add_score(&mut self, score: u32) {
+        self.total += score as u64;
         self.no_scores += 1;
+        self.scores.push(score);
     }
 
     fn missed_test(&mut self) {
         self.no_missed += 1;
     }
+
+    /// The mean score, if any tests were taken
+    fn average(&self) -> Option<f64> {
+        (self.no_scores > 0).then(|| self.total as f64 / self.no_scores as f64)
+    }
+
+    /// The middle score, or the mean of the two middle scores for an even number of tests
+    fn median(&self) -> Option<f64> {
+        let mut scores = self.scores.clone();
+        scores.sort_unstable();
+        let mid = scores.len() / 2;
+        match scores.len() {
+            0 => None,
+            n if n % 2 == 1 => Some(scores[mid] as f64),
+            _ => Some((scores[mid - 1] as f64 + scores[mid] as f64) / 2.0),
+        }
+    }
+
+    /// The population standard deviation of the scores, if any tests were taken
+    fn stddev(&self) -> Option<f64> {
+        let average = self.average()?;
+        let variance = self
+            .scores
+            .iter()
+            .map(|&score| (score as f64 - average).powi(2))
+            .sum::<f64>()
+            / self.scores.len() as f64;
+        Some(variance.sqrt())
+    }
 }
 
 impl fmt::Display for TestStatistics {
@@ -50,34 +363,50 @@ impl fmt::Display for TestStatistics {
             total,
             no_scores,
             no_missed,
+            ref scores,
         } = *self;
         let pluralise = |n: u32| if n == 1 { "test" } else { "tests" };
-        write!(
-            f,
-            "{no_scores} {}, with a total score of {total}.  They missed {no_missed} {}.",
-            pluralise(no_scores),
-            pluralise(no_missed)
-        )
+        let best = scores.iter().max();
+        let worst = scores.iter().min();
+        match (self.average(), best, worst, self.median(), self.stddev()) {
+            (Some(average), Some(best), Some(worst), Some(median), Some(stddev)) => write!(
+                f,
+                "{no_scores} {}, with a total score of {total}, averaging {average:.1} (best {best}, worst {worst}, median {median:.1}, std dev {stddev:.1}).",
+                pluralise(no_scores),
+            )?,
+            _ => write!(f, "{no_scores} tests, no scores recorded.")?,
+        }
+        write!(f, "  They missed {no_missed} {}.", pluralise(no_missed))
     }
 }
 
 #[derive(Debug)]
 pub enum Student {
-    NameAndNumber { name: String, score: u8 },
+    NameAndNumber { name: String, score: u32 },
     Name { name: String },
 }
 
+/// Parses a `name:score` line, or a `name` line for a missed test
+///
+/// Only the last colon separates the score, so names may contain colons, and an empty score
+/// (`name:`) is also a missed test. Whitespace around the name and score is ignored.
 impl TryFrom<&str> for Student {
     type Error = Box<dyn Error>;
 
     fn try_from(value: &str) -> Result<Self, Self::Error> {
-        let student = match value.split_once(':') {
-            Some((name, number)) => Self::NameAndNumber {
-                name: name.to_owned(),
-                score: number.parse()?,
+        let student = match value.rsplit_once(':') {
+            Some((name, number)) if !number.trim().is_empty() => {
+                let number = number.trim();
+                Self::NameAndNumber {
+                    name: name.trim().to_owned(),
+                    score: parse_score(number)?,
+                }
+            }
+            Some((name, _)) => Self::Name {
+                name: name.trim().to_owned(),
             },
             None => Self::Name {
-                name: value.to_owned(),
+                name: value.trim().to_owned(),
             },
         };
 
@@ -85,9 +414,550 @@ impl TryFrom<&str> for Student {
     }
 }
 
-pub fn read_students<P: AsRef<Path>>(filename: P) -> Result<Vec<Student>, Box<dyn Error>> {
-    std::fs::read_to_string(filename)?
+/// Parse a score, explaining what is wrong with it if it can't be
+fn parse_score(score: &str) -> Result<u32, String> {
+    score.parse().map_err(|e: ParseIntError| match e.kind() {
+        IntErrorKind::PosOverflow => {
+            format!(
+                "score {score:?} is too large, the largest allowed is {}",
+                u32::MAX
+            )
+        }
+        _ if score.strip_prefix('-').is_some_and(|digits| {
+            !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit())
+        }) =>
+        {
+            format!("score {score:?} is negative, which is not allowed")
+        }
+        _ => format!("score {score:?} is not a number"),
+    })
+}
+
+/// A line of a file which couldn't be parsed
+#[derive(Debug)]
+pub struct ParseIssue {
+    pub line_number: usize,
+    pub content: String,
+    pub reason: String,
+}
+
+impl fmt::Display for ParseIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ParseIssue {
+            line_number,
+            content,
+            reason,
+        } = self;
+        write!(f, "line {line_number}: {reason}: {content:?}")
+    }
+}
+
+/// Read the students from a file, or stdin for `-`, along with the issues with any lines which
+/// couldn't be parsed
+///
+/// Scores above `max_score` are also treated as issues.
+pub fn read_students<P: AsRef<Path>>(
+    filename: P,
+    format: Format,
+    max_score: Option<u32>,
+) -> Result<(Vec<Student>, Vec<ParseIssue>), Box<dyn Error>> {
+    let filename = filename.as_ref();
+    let contents = if filename == Path::new("-") {
+        io::read_to_string(io::stdin())?
+    } else {
+        std::fs::read_to_string(filename)?
+    };
+    let parsed = match format {
+        Format::Colon => parse_colon(&contents),
+        Format::Csv => parse_csv(contents.as_bytes()),
+    };
+
+    let lines: Vec<_> = contents.lines().collect();
+    let mut students = vec![];
+    let mut issues = vec![];
+    for (line_number, student) in parsed {
+        let student = student.and_then(|student| match (&student, max_score) {
+            (Student::NameAndNumber { score, .. }, Some(max)) if *score > max => {
+                Err(format!("score {score} is above the maximum of {max}"))
+            }
+            _ => Ok(student),
+        });
+        match student {
+            Ok(student) => students.push(student),
+            Err(reason) => issues.push(ParseIssue {
+                line_number,
+                content: line_number
+                    .checked_sub(1)
+                    .and_then(|i| lines.get(i))
+                    .map_or_else(String::new, |line| line.to_string()),
+                reason,
+            }),
+        }
+    }
+
+    Ok((students, issues))
+}
+
+/// Parse `name:score` lines, pairing each with its line number
+///
+/// Blank lines, such as one left at the end of a file, are skipped rather than taken as a
+/// student with no name.
+fn parse_colon(contents: &str) -> Vec<(usize, Result<Student, String>)> {
+    contents
         .lines()
-        .map(Student::try_from)
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| (i + 1, Student::try_from(line).map_err(|e| e.to_string())))
         .collect()
 }
+
+/// Parse `name,score` rows following a header row, pairing each with its line number
+fn parse_csv(data: &[u8]) -> Vec<(usize, Result<Student, String>)> {
+    csv::Reader::from_reader(data)
+        .records()
+        .map(|record| {
+            let record = match record {
+                Ok(record) => record,
+                Err(e) => {
+                    let line = e.position().map_or(0, |pos| pos.line() as usize);
+                    let reason = match e.kind() {
+                        csv::ErrorKind::UnequalLengths {
+                            expected_len, len, ..
+                        } => format!("expected {expected_len} fields, found {len}"),
+                        _ => e.to_string(),
+                    };
+                    return (line, Err(reason));
+                }
+            };
+            let line = record.position().map_or(0, |pos| pos.line() as usize);
+            let [name, score] = record.iter().collect::<Vec<_>>()[..] else {
+                return (
+                    line,
+                    Err(format!("expected 2 fields, found {}", record.len())),
+                );
+            };
+
+            let name = name.trim().to_owned();
+            let student = match score.trim() {
+                "" => Ok(Student::Name { name }),
+                score => parse_score(score).map(|score| Student::NameAndNumber { name, score }),
+            };
+
+            (line, student)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    const ROSTER: &str = "Carol:5\nAlice:7\nBob:9\nAlice\nDave:3\nCarol:4\nDave\nDave\nBob";
+
+    fn statistics(roster: &str) -> BTreeMap<String, TestStatistics> {
+        collect_statistics(roster.lines().map(|line| Student::try_from(line).unwrap()))
+    }
+
+    fn roster_report(sort: SortKey) -> Vec<String> {
+        report(&statistics(ROSTER), sort, None)
+    }
+
+    #[test]
+    fn test_sort_by_name() {
+        assert_eq!(
+            roster_report(SortKey::Name),
+            [
+                "Alice took 1 test, with a total score of 7, averaging 7.0 (best 7, worst 7, median 7.0, std dev 0.0).  They missed 1 test.",
+                "Bob took 1 test, with a total score of 9, averaging 9.0 (best 9, worst 9, median 9.0, std dev 0.0).  They missed 1 test.",
+                "Carol took 2 tests, with a total score of 9, averaging 4.5 (best 5, worst 4, median 4.5, std dev 0.5).  They missed 0 tests.",
+                "Dave took 1 test, with a total score of 3, averaging 3.0 (best 3, worst 3, median 3.0, std dev 0.0).  They missed 2 tests.",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_sort_by_score() {
+        let names: Vec<_> = roster_report(SortKey::Score)
+            .into_iter()
+            .map(|line| line.split_once(" took").unwrap().0.to_owned())
+            .collect();
+        // Bob and Carol tie on 9
+        assert_eq!(names, ["Bob", "Carol", "Alice", "Dave"]);
+    }
+
+    #[test]
+    fn test_sort_by_missed() {
+        let names: Vec<_> = roster_report(SortKey::Missed)
+            .into_iter()
+            .map(|line| line.split_once(" took").unwrap().0.to_owned())
+            .collect();
+        // Alice and Bob tie on 1
+        assert_eq!(names, ["Carol", "Alice", "Bob", "Dave"]);
+    }
+
+    #[rstest]
+    #[case(&[7, 8, 8], "7.7")]
+    #[case(&[1, 1, 2], "1.3")]
+    #[case(&[1, 2], "1.5")]
+    #[case(&[10], "10.0")]
+    fn test_average_rounding(#[case] scores: &[u32], #[case] expected: &str) {
+        let mut stats = TestStatistics::default();
+        for &score in scores {
+            stats.add_score(score);
+        }
+        assert!(stats
+            .to_string()
+            .contains(&format!("averaging {expected} ")));
+    }
+
+    #[rstest]
+    #[case("Smith: John:87", "Smith: John", Some(87))]
+    #[case("a:b:c:1", "a:b:c", Some(1))]
+    #[case("Alice:", "Alice", None)]
+    #[case("Smith: John:", "Smith: John", None)]
+    #[case("Alice: 12 ", "Alice", Some(12))]
+    #[case("  Alice  :\t7", "Alice", Some(7))]
+    #[case(" Alice ", "Alice", None)]
+    fn test_parse_line(#[case] line: &str, #[case] name: &str, #[case] score: Option<u32>) {
+        match Student::try_from(line).unwrap() {
+            Student::NameAndNumber { name: n, score: s } => {
+                assert_eq!((n.as_str(), Some(s)), (name, score))
+            }
+            Student::Name { name: n } => assert_eq!((n.as_str(), None), (name, score)),
+        }
+    }
+
+    #[test]
+    fn test_parse_line_bad_score() {
+        let err = Student::try_from("Smith: John:eighty").unwrap_err();
+        assert_eq!(err.to_string(), "score \"eighty\" is not a number");
+    }
+
+    #[test]
+    fn test_json_report() {
+        let mut student_stats = statistics(ROSTER);
+        student_stats
+            .entry("Eve".to_owned())
+            .or_default()
+            .missed_test();
+
+        let json = json_report(&student_stats, SortKey::Name).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        let students = value.as_array().unwrap();
+        let names: Vec<_> = students
+            .iter()
+            .map(|s| s["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(names, ["Alice", "Bob", "Carol", "Dave", "Eve"]);
+
+        let carol = &students[2];
+        assert_eq!(carol["tests_taken"], 2);
+        assert_eq!(carol["total"], 9);
+        assert_eq!(carol["average"], 4.5);
+        assert_eq!(carol["median"], 4.5);
+        assert_eq!(carol["stddev"], 0.5);
+        assert_eq!(carol["missed"], 0);
+
+        let eve = &students[4];
+        assert_eq!(eve["tests_taken"], 0);
+        assert!(eve["average"].is_null());
+        assert!(eve["median"].is_null());
+        assert!(eve["stddev"].is_null());
+        assert_eq!(eve["missed"], 1);
+    }
+
+    #[rstest]
+    #[case("300", Ok(300))]
+    #[case(" 300 ", Ok(300))]
+    #[case("abc", Err("score \"abc\" is not a number"))]
+    #[case("", Err("score \"\" is not a number"))]
+    #[case("-", Err("score \"-\" is not a number"))]
+    #[case("7O", Err("score \"7O\" is not a number"))]
+    #[case("-5", Err("score \"-5\" is negative, which is not allowed"))]
+    #[case(
+        "4294967296",
+        Err("score \"4294967296\" is too large, the largest allowed is 4294967295")
+    )]
+    fn test_parse_score(#[case] score: &str, #[case] expected: Result<u32, &str>) {
+        assert_eq!(parse_score(score.trim()), expected.map_err(str::to_owned));
+    }
+
+    #[test]
+    fn test_max_score() {
+        let (students, issues) =
+            read_students(fixture("points"), Format::Colon, Some(300)).unwrap();
+        let issues: Vec<_> = issues.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            issues,
+            ["line 3: score 3000 is above the maximum of 300: \"Bob:3000\""]
+        );
+        assert_eq!(
+            report(&collect_statistics(students), SortKey::Name, None),
+            [
+                "Alice took 2 tests, with a total score of 550, averaging 275.0 (best 300, worst 250, median 275.0, std dev 25.0).  They missed 0 tests.",
+                "Bob took 1 test, with a total score of 120, averaging 120.0 (best 120, worst 120, median 120.0, std dev 0.0).  They missed 0 tests.",
+            ]
+        );
+
+        let (_, issues) = read_students(fixture("points"), Format::Colon, None).unwrap();
+        assert!(issues.is_empty());
+    }
+
+    #[test]
+    fn test_merge_files() {
+        let mut students = vec![];
+        for name in ["week1", "week2"] {
+            let (file_students, issues) =
+                read_students(fixture(name), Format::Colon, None).unwrap();
+            assert!(issues.is_empty());
+            students.extend(file_students);
+        }
+
+        let student_stats = collect_statistics(students);
+        let totals: Vec<_> = student_stats
+            .iter()
+            .map(|(name, stats)| (name.as_str(), stats.total, stats.no_scores, stats.no_missed))
+            .collect();
+        assert_eq!(
+            totals,
+            [
+                ("Alice", 17, 2, 0),
+                ("Bob", 6, 1, 1),
+                ("Carol", 0, 0, 2),
+                ("Dave", 4, 1, 0)
+            ]
+        );
+    }
+
+    const GRADES: &str = "A:90,B:80,C:70,D:60";
+
+    #[rstest]
+    #[case(&[90], "A")]
+    #[case(&[100, 80], "A")]
+    #[case(&[89, 90], "B")]
+    #[case(&[80], "B")]
+    #[case(&[70], "C")]
+    #[case(&[60], "D")]
+    #[case(&[59, 60], "F")]
+    #[case(&[0], "F")]
+    #[case(&[], "-")]
+    fn test_grades(#[case] scores: &[u32], #[case] expected: &str) {
+        let mut stats = TestStatistics::default();
+        for &score in scores {
+            stats.add_score(score);
+        }
+        let grades: Grades = GRADES.parse().unwrap();
+        assert_eq!(grades.grade(stats.average()), expected);
+    }
+
+    #[rstest]
+    #[case("", "grade \"\" should be written as letter:threshold")]
+    #[case("A:90,B80", "grade \"B80\" should be written as letter:threshold")]
+    #[case(":90", "grade \":90\" has no letter")]
+    #[case(
+        "A:ninety",
+        "threshold \"ninety\" for grade A should be a whole number from 0 to 100"
+    )]
+    #[case(
+        "A:101",
+        "threshold \"101\" for grade A should be a whole number from 0 to 100"
+    )]
+    #[case(
+        "A:-1",
+        "threshold \"-1\" for grade A should be a whole number from 0 to 100"
+    )]
+    #[case(
+        "A:80,B:90",
+        "threshold 90 for grade B should be below 80, the threshold for grade A"
+    )]
+    #[case(
+        "A:80,B:80",
+        "threshold 80 for grade B should be below 80, the threshold for grade A"
+    )]
+    fn test_invalid_grades(#[case] spec: &str, #[case] expected: &str) {
+        assert_eq!(spec.parse::<Grades>(), Err(expected.to_owned()));
+    }
+
+    #[test]
+    fn test_grades_file() {
+        let grades: Grades = std::fs::read_to_string(fixture("grades"))
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert_eq!(grades, GRADES.parse().unwrap());
+    }
+
+    #[test]
+    fn test_report_with_grades() {
+        let grades: Grades = "A+ : 95, B:5".parse().unwrap();
+        let mut student_stats = statistics(ROSTER);
+        student_stats
+            .entry("Eve".to_owned())
+            .or_default()
+            .missed_test();
+        let grades: Vec<_> = report(&student_stats, SortKey::Name, Some(&grades))
+            .into_iter()
+            .map(|line| line.rsplit_once("  ").unwrap().1.to_owned())
+            .collect();
+        assert_eq!(
+            grades,
+            ["Grade B.", "Grade B.", "Grade F.", "Grade F.", "Grade -."]
+        );
+    }
+
+    fn fixture(name: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("test_data")
+            .join(name)
+    }
+
+    #[test]
+    fn test_read_csv() {
+        let (students, issues) = read_students(fixture("input.csv"), Format::Csv, None).unwrap();
+        assert!(issues.is_empty(), "{issues:?}");
+        let names: Vec<_> = students
+            .iter()
+            .map(|student| match student {
+                Student::NameAndNumber { name, score } => format!("{name}={score}"),
+                Student::Name { name } => name.clone(),
+            })
+            .collect();
+        assert_eq!(
+            names,
+            [
+                "Silverstone, Daniel=9",
+                "Harry=7",
+                "Smith: John=10",
+                "Harry",
+                "Silverstone, Daniel=4",
+            ]
+        );
+    }
+
+    #[rstest]
+    #[case(
+        "bad",
+        Format::Colon,
+        &[
+            "line 4: score \"banana\" is not a number: \"Alice:banana\"",
+            "line 6: score \"-5\" is negative, which is not allowed: \"Bob:-5\"",
+        ],
+    )]
+    #[case(
+        "bad.csv",
+        Format::Csv,
+        &[
+            "line 4: score \"banana\" is not a number: \"Alice,banana\"",
+            "line 6: expected 2 fields, found 3: \"Bob,3,extra\"",
+        ],
+    )]
+    fn test_issues_reported(#[case] name: &str, #[case] format: Format, #[case] expected: &[&str]) {
+        let (students, issues) = read_students(fixture(name), format, None).unwrap();
+        let issues: Vec<_> = issues.iter().map(ToString::to_string).collect();
+        assert_eq!(issues, expected);
+        assert_eq!(
+            report(&collect_statistics(students), SortKey::Name, None),
+            [
+                "Harry took 2 tests, with a total score of 16, averaging 8.0 (best 9, worst 7, median 8.0, std dev 1.0).  They missed 1 test.",
+                "Susan took 1 test, with a total score of 4, averaging 4.0 (best 4, worst 4, median 4.0, std dev 0.0).  They missed 1 test.",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_colon_skips_blank_lines() {
+        let parsed = parse_colon("Harry:7\n\n  \nSusan\n");
+        let lines: Vec<_> = parsed.iter().map(|(line, _)| *line).collect();
+        assert_eq!(lines, [1, 4]);
+        assert!(matches!(
+            &parsed[1].1,
+            Ok(Student::Name { name }) if name == "Susan"
+        ));
+    }
+
+    #[test]
+    fn test_csv_names_trimmed() {
+        let parsed = parse_csv(b"name,score\n  Harry , 7\nSusan ,\n");
+        let names: Vec<_> = parsed
+            .into_iter()
+            .map(|(_, student)| match student.unwrap() {
+                Student::NameAndNumber { name, .. } | Student::Name { name } => name,
+            })
+            .collect();
+        assert_eq!(names, ["Harry", "Susan"]);
+    }
+
+    #[test]
+    fn test_csv_missing_score_column() {
+        let (students, issues) = read_students(fixture("short.csv"), Format::Csv, None).unwrap();
+        assert!(students.is_empty());
+        let issues: Vec<_> = issues.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            issues,
+            [
+                "line 2: expected 2 fields, found 1: \"Harry\"",
+                "line 3: expected 2 fields, found 1: \"Susan\"",
+            ]
+        );
+    }
+
+    #[rstest]
+    #[case("grades.csv", Format::Csv)]
+    #[case("GRADES.CSV", Format::Csv)]
+    #[case("input", Format::Colon)]
+    #[case("input.txt", Format::Colon)]
+    fn test_format_from_extension(#[case] path: &str, #[case] expected: Format) {
+        assert_eq!(Format::from_extension(Path::new(path)), expected);
+    }
+
+    #[rstest]
+    #[case(&[7], 7.0, 0.0)]
+    #[case(&[9, 1, 5], 5.0, 3.265986323710904)]
+    #[case(&[4, 10, 2, 8], 6.0, 3.1622776601683795)]
+    #[case(&[3, 3, 5, 5], 4.0, 1.0)]
+    fn test_median_and_stddev(#[case] scores: &[u32], #[case] median: f64, #[case] stddev: f64) {
+        let mut stats = TestStatistics::default();
+        for &score in scores {
+            stats.add_score(score);
+        }
+        assert_eq!(stats.median(), Some(median));
+        assert!((stats.stddev().unwrap() - stddev).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_no_scores() {
+        let mut stats = TestStatistics::default();
+        stats.missed_test();
+        stats.missed_test();
+        assert_eq!(stats.average(), None);
+        assert_eq!(stats.median(), None);
+        assert_eq!(stats.stddev(), None);
+        assert_eq!(
+            stats.to_string(),
+            "0 tests, no scores recorded.  They missed 2 tests."
+        );
+    }
+
+    #[test]
+    fn test_class_summary() {
+        let mut student_stats = BTreeMap::new();
+        student_stats.insert("Eve".to_owned(), TestStatistics::default());
+        assert_eq!(
+            class_summary(&student_stats),
+            "1 student, no scores recorded."
+        );
+
+        for (name, score) in [("Carol", 5), ("Bob", 9), ("Carol", 4), ("Alice", 2)] {
+            student_stats
+                .entry(name.to_owned())
+                .or_default()
+                .add_score(score);
+        }
+        // Bob and Carol tie on 9, so the earlier name wins
+        assert_eq!(
+            class_summary(&student_stats),
+            "4 students, with an overall average of 5.0.  Bob scored highest with 9."
+        );
+    }
+}
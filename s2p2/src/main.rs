@@ -1,120 +1,476 @@
-use std::{error::Error, fmt, path::Path};
+use bft_types::{Instruction, Program, SourceLocation};
+use clap::{Parser, ValueEnum};
+use serde::Serialize;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    error::Error,
+    num::NonZeroUsize,
+    path::PathBuf,
+    str::FromStr,
+};
 
-fn main() -> Result<(), Box<dyn Error>> {
-    let if_name = std::env::args()
-        .nth(1)
-        .ok_or("Insufficient number of arguments, please provide a filename.")?;
-
-    let prog = read_instructions(&if_name)?;
-    for instr in prog {
-        println!(
-            "[{if_name}:{}:{}] {}",
-            1 + instr.line(),
-            1 + instr.column(),
-            instr.opcode(),
-        );
-    }
+/// List the instructions of a brainfuck program along with where they are
+#[derive(Parser)]
+struct Args {
+    /// The program to list
+    filename: PathBuf,
 
-    Ok(())
-}
+    /// How to write out the listing
+    #[arg(long, value_enum, default_value_t = Format::Plain)]
+    format: Format,
 
-/// The brainfuck language commands
-#[derive(Debug, PartialEq, Eq, Clone, Copy)]
-pub enum Opcode {
-    /// > Increment the data pointer by one (to point to the next cell to the right).
-    Inc,
+    /// How to count the columns of instructions
+    #[arg(long, value_enum, default_value_t = ColumnMode::Chars)]
+    column_mode: ColumnMode,
 
-    /// < Decrement the data pointer by one (to point to the next cell to the left).
-    Dec,
+    /// The width of a tab when counting columns as they're displayed
+    #[arg(long, default_value = "8")]
+    tab_width: NonZeroUsize,
 
-    /// + Increment the byte at the data pointer by one.
-    Succ,
+    /// Only list these instructions, given as their brainfuck characters, such as `.,`
+    #[arg(long, value_name = "OPCODES")]
+    only: Option<Opcodes>,
 
-    /// - Decrement the byte at the data pointer by one.
-    Pred,
+    /// Only list instructions on these lines, given as `start:end` counting from 1 and including
+    /// both ends
+    #[arg(long, value_name = "START:END")]
+    lines: Option<LineRange>,
+}
 
-    /// . Output the byte at the data pointer.
-    Out,
+/// The brainfuck characters of the instructions to list
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Opcodes(BTreeSet<char>);
 
-    /// , Accept one byte of input, storing its value in the byte at the data pointer.
-    In,
+impl FromStr for Opcodes {
+    type Err = String;
 
-    /// [ If the byte at the data pointer is zero, then instead of moving the instruction pointer forward to the next command, jump it forward to the command after the matching ] command.
-    Jz,
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.is_empty() {
+            return Err("no instructions given".to_owned());
+        }
+        s.chars()
+            .map(|c| match c {
+                '>' | '<' | '+' | '-' | '.' | ',' | '[' | ']' => Ok(c),
+                _ => Err(format!(
+                    "{c:?} is not a brainfuck instruction, expected some of ><+-.,[]"
+                )),
+            })
+            .collect::<Result<_, _>>()
+            .map(Self)
+    }
+}
 
-    /// ] If the byte at the data pointer is nonzero, then instead of moving the instruction pointer forward to the next command, jump it back to the command after the matching [ command.
-    Jnz,
+/// An inclusive range of 1-based line numbers
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct LineRange {
+    start: usize,
+    end: usize,
 }
 
-impl Opcode {
-    pub fn from_char(c: char) -> Option<Self> {
-        let instr = match c {
-            '>' => Opcode::Inc,
-            '<' => Opcode::Dec,
-            '+' => Opcode::Succ,
-            '-' => Opcode::Pred,
-            '.' => Opcode::Out,
-            ',' => Opcode::In,
-            '[' => Opcode::Jz,
-            ']' => Opcode::Jnz,
-            _ => return None,
+impl FromStr for LineRange {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (start, end) = s
+            .split_once(':')
+            .ok_or_else(|| format!("{s:?} should be written as start:end"))?;
+        let parse = |n: &str| {
+            n.parse::<usize>()
+                .ok()
+                .filter(|&n| n > 0)
+                .ok_or_else(|| format!("{n:?} is not a line number, they count from 1"))
         };
+        let (start, end) = (parse(start)?, parse(end)?);
+        if start > end {
+            return Err(format!("the range {start}:{end} ends before it starts"));
+        }
+        Ok(Self { start, end })
+    }
+}
+
+/// Which instructions to list
+#[derive(Debug, Clone, Default)]
+struct Filter {
+    /// Only instructions written as one of these characters
+    only: Option<Opcodes>,
+    /// Only instructions on these lines
+    lines: Option<LineRange>,
+}
 
-        Some(instr)
+impl Filter {
+    /// Whether to list an instruction found at `location`
+    fn matches(&self, instr: Instruction, location: SourceLocation) -> bool {
+        let line = 1 + location.line;
+        self.only
+            .as_ref()
+            .is_none_or(|only| only.0.contains(&instr.as_char()))
+            && self
+                .lines
+                .is_none_or(|range| (range.start..=range.end).contains(&line))
     }
 }
 
-impl fmt::Display for Opcode {
-    #[rustfmt::skip]
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            Opcode::Inc  => write!(f, "Increment the data pointer by one"),
-            Opcode::Dec  => write!(f, "Decrement the data pointer by one"),
-            Opcode::Succ => write!(f, "Increment the byte at the data pointer by one"),  
-            Opcode::Pred => write!(f, "Decrement the byte at the data pointer by one"),                                               
-            Opcode::Out  => write!(f, "Output the byte at the data pointer"),                                               
-            Opcode::In   => write!(f, "Accept one byte of input"),  
-            Opcode::Jz   => write!(f, "Jump if zero"),
-            Opcode::Jnz  => write!(f, "Jump if not zero"),
+/// The ways the column of an instruction can be counted
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum ColumnMode {
+    /// Each character is one column
+    Chars,
+    /// Each UTF-8 byte is one column
+    Bytes,
+    /// Each character is one column, except tabs which reach the next tab stop
+    Display,
+}
+
+/// Works out the line and column of instructions in the source of a program
+struct Locator<'a> {
+    /// The lines of the source, split the same way as when parsing
+    lines: Vec<&'a str>,
+    /// How to count columns
+    mode: ColumnMode,
+    /// The width of a tab in [`ColumnMode::Display`]
+    tab_width: usize,
+}
+
+impl<'a> Locator<'a> {
+    /// A locator for instructions in `source`, counting columns with `mode`
+    fn new(source: &'a str, mode: ColumnMode, tab_width: NonZeroUsize) -> Self {
+        Self {
+            // `lines` drops the `\r` of `\r\n` endings, so they don't count as a column
+            lines: source.lines().collect(),
+            mode,
+            tab_width: tab_width.get(),
         }
     }
+
+    /// The 1-based line and column of a location in the source
+    fn locate(&self, location: SourceLocation) -> (usize, usize) {
+        let before = self.lines[location.line].chars().take(location.column);
+        let column = match self.mode {
+            ColumnMode::Chars => location.column,
+            ColumnMode::Bytes => before.map(char::len_utf8).sum(),
+            ColumnMode::Display => before.fold(0, |column, c| match c {
+                '\t' => (column / self.tab_width + 1) * self.tab_width,
+                _ => column + 1,
+            }),
+        };
+        (1 + location.line, 1 + column)
+    }
 }
 
-#[derive(Debug, Clone, Copy)]
-struct Instruction {
-    op: Opcode,
-    line: usize,
-    column: usize,
+/// The ways the listing can be written out
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Format {
+    /// A line for each instruction, describing it and where it is
+    Plain,
+    /// A JSON array with an object for each instruction
+    Json,
+    /// The number of each instruction and the total
+    Summary,
 }
 
-impl Instruction {
-    fn opcode(&self) -> &Opcode {
-        &self.op
-    }
+fn main() -> Result<(), Box<dyn Error>> {
+    let args = Args::parse();
 
-    fn line(&self) -> usize {
-        self.line
+    let source = std::fs::read_to_string(&args.filename)?;
+    let prog = match Program::from_source(&args.filename, &source) {
+        Ok(prog) => prog,
+        Err(e) => {
+            eprintln!("{}", e.render(&source));
+            std::process::exit(1);
+        }
+    };
+
+    let locator = Locator::new(&source, args.column_mode, args.tab_width);
+    let filter = Filter {
+        only: args.only,
+        lines: args.lines,
+    };
+    match args.format {
+        Format::Plain => listing(&prog, &locator, &filter)
+            .iter()
+            .for_each(|line| println!("{line}")),
+        Format::Json => println!("{}", json_listing(&prog, &locator, &filter)?),
+        Format::Summary => summary(&prog, &filter)
+            .iter()
+            .for_each(|line| println!("{line}")),
     }
 
-    fn column(&self) -> usize {
-        self.column
+    Ok(())
+}
+
+/// A line describing each instruction of `prog` picked by `filter` and where it is, with brackets
+/// also pointing at their partner
+fn listing(prog: &Program, locator: &Locator, filter: &Filter) -> Vec<String> {
+    let name = prog.filename().display();
+    prog.instructions_with_locations()
+        .filter(|&(instr, location)| filter.matches(instr, location))
+        .map(|(instr, location)| {
+            let (line_no, column) = locator.locate(location);
+            let mut line = format!("[{name}:{line_no}:{column}] {}", instr.description());
+            if let Instruction::Jz { dest } | Instruction::Jnz { dest } = instr {
+                // jumps land just after their partner
+                let partner = dest - 1;
+                let (partner_line, partner_column) = locator.locate(prog.source_map()[partner]);
+                line += &format!(
+                    " -> matches {} at {partner_line}:{partner_column}",
+                    prog.instructions()[partner].as_char(),
+                );
+            }
+            line
+        })
+        .collect()
+}
+
+/// An instruction and where it is, as written out in JSON
+#[derive(Debug, Serialize)]
+struct InstructionRecord {
+    opcode: &'static str,
+    char: char,
+    line: usize,
+    column: usize,
+}
+
+/// The name of the opcode of an instruction
+fn opcode_name(instr: Instruction) -> &'static str {
+    match instr {
+        Instruction::Inc => "Inc",
+        Instruction::Dec => "Dec",
+        Instruction::Succ => "Succ",
+        Instruction::Pred => "Pred",
+        Instruction::Out => "Out",
+        Instruction::In => "In",
+        Instruction::Jz { .. } => "Jz",
+        Instruction::Jnz { .. } => "Jnz",
+        Instruction::Add(_) => "Add",
+        Instruction::Sub(_) => "Sub",
+        Instruction::Right(_) => "Right",
+        Instruction::Left(_) => "Left",
+        Instruction::SetZero => "SetZero",
+        Instruction::Debug => "Debug",
     }
 }
 
-fn read_instructions<P: AsRef<Path>>(filename: P) -> Result<Vec<Instruction>, Box<dyn Error>> {
-    let instrs = std::fs::read_to_string(filename)?
-        .lines()
-        .enumerate()
-        .flat_map(|(line_no, line)| {
-            line.chars().enumerate().filter_map(move |(column, c)| {
-                Opcode::from_char(c).map(|op| Instruction {
-                    op,
-                    line: line_no,
-                    column,
-                })
-            })
+/// A JSON array describing each instruction of `prog` picked by `filter` and where it is
+fn json_listing(prog: &Program, locator: &Locator, filter: &Filter) -> serde_json::Result<String> {
+    let records: Vec<_> = prog
+        .instructions_with_locations()
+        .filter(|&(instr, location)| filter.matches(instr, location))
+        .map(|(instr, location)| {
+            let (line, column) = locator.locate(location);
+            InstructionRecord {
+                opcode: opcode_name(instr),
+                char: instr.as_char(),
+                line,
+                column,
+            }
         })
         .collect();
+    serde_json::to_string_pretty(&records)
+}
+
+/// A line with the number of each instruction in `prog` picked by `filter`, followed by the
+/// total
+fn summary(prog: &Program, filter: &Filter) -> Vec<String> {
+    let mut per_opcode: BTreeMap<char, usize> = "><+-.,[]".chars().map(|c| (c, 0)).collect();
+    let mut total = 0;
+    for (instr, _) in prog
+        .instructions_with_locations()
+        .filter(|&(instr, location)| filter.matches(instr, location))
+    {
+        *per_opcode.entry(instr.as_char()).or_default() += 1;
+        total += 1;
+    }
+
+    per_opcode
+        .iter()
+        .map(|(opcode, count)| format!("{opcode} {count}"))
+        .chain([format!("total {total}")])
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    const TAB_WIDTH: NonZeroUsize = NonZeroUsize::new(8).unwrap();
+
+    /// Parse `source` and list it, counting columns by character
+    fn list_source(name: &str, source: &str) -> Vec<String> {
+        let prog = Program::from_source(name, source).unwrap();
+        listing(
+            &prog,
+            &Locator::new(source, ColumnMode::Chars, TAB_WIDTH),
+            &Filter::default(),
+        )
+    }
+
+    #[rstest]
+    #[case(ColumnMode::Chars, 8, 3)]
+    #[case(ColumnMode::Bytes, 8, 4)]
+    #[case(ColumnMode::Display, 8, 10)]
+    #[case(ColumnMode::Display, 4, 6)]
+    #[case(ColumnMode::Display, 1, 3)]
+    fn test_column_modes(
+        #[case] mode: ColumnMode,
+        #[case] tab_width: usize,
+        #[case] column: usize,
+    ) {
+        let source = std::fs::read_to_string("test_data/columns.bf").unwrap();
+        let prog = Program::from_source("columns.bf", &source).unwrap();
+        let locator = Locator::new(&source, mode, NonZeroUsize::new(tab_width).unwrap());
+        let first = listing(&prog, &locator, &Filter::default()).swap_remove(0);
+        assert_eq!(
+            first,
+            format!("[columns.bf:1:{column}] Increment the byte at the data pointer by one")
+        );
+    }
+
+    #[rstest]
+    #[case(ColumnMode::Chars)]
+    #[case(ColumnMode::Bytes)]
+    #[case(ColumnMode::Display)]
+    fn test_crlf_columns(#[case] mode: ColumnMode) {
+        let unix = "a\t+\n\u{e9}[-]\n";
+        let windows = unix.replace('\n', "\r\n");
+        let list = |source: &str| {
+            let prog = Program::from_source("-", source).unwrap();
+            listing(
+                &prog,
+                &Locator::new(source, mode, TAB_WIDTH),
+                &Filter::default(),
+            )
+        };
+        assert_eq!(list(&windows), list(unix));
+    }
+
+    #[test]
+    fn test_display_tab_stops() {
+        let source = "ab\tc\t\t+";
+        let locator = Locator::new(source, ColumnMode::Display, NonZeroUsize::new(4).unwrap());
+        let location = SourceLocation { line: 0, column: 6 };
+        // `ab` then a tab to 4, `c` then tabs to 8 and 12
+        assert_eq!(locator.locate(location), (1, 13));
+    }
+
+    fn example_listing(only: Option<&str>, lines: Option<&str>) -> Vec<String> {
+        let source = std::fs::read_to_string("../bft/programs/example.bf").unwrap();
+        let prog = Program::from_source("example.bf", &source).unwrap();
+        let filter = Filter {
+            only: only.map(|only| only.parse().unwrap()),
+            lines: lines.map(|lines| lines.parse().unwrap()),
+        };
+        listing(
+            &prog,
+            &Locator::new(&source, ColumnMode::Chars, TAB_WIDTH),
+            &filter,
+        )
+    }
 
-    Ok(instrs)
+    #[rstest]
+    #[case(None, None, 62)]
+    #[case(Some("."), None, 11)]
+    #[case(Some("."), Some("1:18"), 0)]
+    #[case(Some(".["), None, 16)]
+    #[case(Some(".["), Some("9:19"), 15)]
+    #[case(Some("[.["), Some("9:9"), 1)]
+    #[case(None, Some("11:11"), 6)]
+    fn test_filter(
+        #[case] only: Option<&str>,
+        #[case] lines: Option<&str>,
+        #[case] expected: usize,
+    ) {
+        let listed = example_listing(only, lines);
+        assert_eq!(listed.len(), expected);
+        if let Some(only) = only {
+            assert!(listed.iter().all(|line| {
+                let description = line.split_once("] ").unwrap().1;
+                (only.contains('.') && description == "Output the byte at the data pointer")
+                    || (only.contains('[') && description.starts_with("Jump if zero"))
+            }));
+        }
+    }
+
+    #[rstest]
+    #[case("", "no instructions given")]
+    #[case(".x", "'x' is not a brainfuck instruction, expected some of ><+-.,[]")]
+    #[case("#", "'#' is not a brainfuck instruction, expected some of ><+-.,[]")]
+    fn test_invalid_opcodes(#[case] only: &str, #[case] expected: &str) {
+        assert_eq!(only.parse::<Opcodes>(), Err(expected.to_owned()));
+    }
+
+    #[rstest]
+    #[case("3", "\"3\" should be written as start:end")]
+    #[case("0:3", "\"0\" is not a line number, they count from 1")]
+    #[case("a:3", "\"a\" is not a line number, they count from 1")]
+    #[case("3:", "\"\" is not a line number, they count from 1")]
+    #[case("5:3", "the range 5:3 ends before it starts")]
+    fn test_invalid_line_range(#[case] lines: &str, #[case] expected: &str) {
+        assert_eq!(lines.parse::<LineRange>(), Err(expected.to_owned()));
+    }
+
+    #[test]
+    fn test_filtered_summary() {
+        let source = std::fs::read_to_string("../bft/programs/example.bf").unwrap();
+        let prog = Program::from_source("example.bf", &source).unwrap();
+        let filter = Filter {
+            only: Some(".[".parse().unwrap()),
+            lines: Some("9:19".parse().unwrap()),
+        };
+        assert_eq!(
+            summary(&prog, &filter),
+            ["+ 0", ", 0", "- 0", ". 11", "< 0", "> 0", "[ 4", "] 0", "total 15"]
+        );
+    }
+
+    #[test]
+    fn test_json_matches_summary() {
+        let source = std::fs::read_to_string("../bft/programs/example.bf").unwrap();
+        let prog = Program::from_source("example.bf", &source).unwrap();
+        let locator = Locator::new(&source, ColumnMode::Chars, TAB_WIDTH);
+        let json: serde_json::Value =
+            serde_json::from_str(&json_listing(&prog, &locator, &Filter::default()).unwrap())
+                .unwrap();
+        let records = json.as_array().unwrap();
+
+        let mut counts: BTreeMap<String, usize> = "><+-.,[]"
+            .chars()
+            .map(|opcode| (opcode.to_string(), 0))
+            .collect();
+        for record in records {
+            *counts.get_mut(record["char"].as_str().unwrap()).unwrap() += 1;
+        }
+        let expected: Vec<_> = counts
+            .iter()
+            .map(|(opcode, count)| format!("{opcode} {count}"))
+            .chain([format!("total {}", records.len())])
+            .collect();
+        assert_eq!(summary(&prog, &Filter::default()), expected);
+        assert_eq!(
+            summary(&prog, &Filter::default()).last().unwrap(),
+            "total 62"
+        );
+
+        assert_eq!(records[1]["opcode"], "Jz");
+        assert_eq!(records[1]["char"], "[");
+        assert_eq!(records[1]["line"], 8);
+        assert_eq!(records[1]["column"], 5);
+    }
+
+    #[test]
+    fn test_nested_partners() {
+        assert_eq!(
+            list_source("nested.bf", "+[\n  >[-]\n<]\n"),
+            [
+                "[nested.bf:1:1] Increment the byte at the data pointer by one",
+                "[nested.bf:1:2] Jump if zero -> matches ] at 3:2",
+                "[nested.bf:2:3] Increment the data pointer by one",
+                "[nested.bf:2:4] Jump if zero -> matches ] at 2:6",
+                "[nested.bf:2:5] Decrement the byte at the data pointer by one",
+                "[nested.bf:2:6] Jump if not zero -> matches [ at 2:4",
+                "[nested.bf:3:1] Decrement the data pointer by one",
+                "[nested.bf:3:2] Jump if not zero -> matches [ at 1:2",
+            ]
+        );
+    }
 }
@@ -0,0 +1,44 @@
+use std::process::Command;
+
+/// Set to rewrite the golden listings rather than checking against them
+const UPDATE_VAR: &str = "UPDATE_GOLDEN";
+
+/// List `program` and compare it against the golden listing `name`
+fn check_listing(program: &str, name: &str) {
+    let output = Command::new(env!("CARGO_BIN_EXE_s2p2"))
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .arg(program)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let golden = format!("{}/tests/golden/{name}", env!("CARGO_MANIFEST_DIR"));
+    if std::env::var_os(UPDATE_VAR).is_some() {
+        std::fs::write(&golden, &output.stdout).unwrap();
+    }
+    let expected = std::fs::read_to_string(&golden).unwrap();
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), expected);
+}
+
+#[test]
+fn test_example_listing() {
+    check_listing("../bft/programs/example.bf", "example.listing");
+}
+
+#[test]
+fn test_unbalanced() {
+    let output = Command::new(env!("CARGO_BIN_EXE_s2p2"))
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .arg("../bft/programs/unclosed_bracket.bf")
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    assert!(output.stdout.is_empty());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.starts_with(
+            "Error in input file ../bft/programs/unclosed_bracket.bf, dangling open bracket found at line "
+        ),
+        "{stderr}"
+    );
+}